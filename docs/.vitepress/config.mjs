@@ -28,6 +28,8 @@ export default withMermaid(
             text: 'Features',
             items: [
               { text: 'Fan Control', link: '/guide/fan-control' },
+              { text: 'Background Daemon', link: '/guide/daemon' },
+              { text: 'Command Line', link: '/guide/cli' },
               { text: 'Battery Management', link: '/guide/battery' },
               { text: 'Performance Tuning', link: '/guide/performance' },
               { text: 'System Monitor', link: '/guide/monitor' },
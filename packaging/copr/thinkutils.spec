@@ -22,12 +22,11 @@ BuildRequires:  pkgconfig(ayatana-appindicator3-0.1)
 BuildRequires:  pkgconfig(librsvg-2.0)
 BuildRequires:  pkgconfig(openssl)
 BuildRequires:  desktop-file-utils
+BuildRequires:  systemd-rpm-macros
 
 Requires:       webkit2gtk4.1
 Requires:       polkit
 
-Recommends:     lm_sensors
-
 # Fan control needs thinkpad_acpi, which is x86-only. Everything else -- battery
 # thresholds via the generic power_supply API, CPU governor, system monitor --
 # is architecture neutral and works on an aarch64 ThinkPad such as the X13s.
@@ -44,8 +43,8 @@ providing manual and temperature-curve fan control via thinkpad_acpi, battery
 charge threshold management, CPU governor and turbo-boost control, and live
 system monitoring.
 
-Privileged operations go through a dedicated, package-owned helper authorised by
-a narrow polkit rule; the application itself runs unprivileged.
+Privileged operations go through a dedicated, package-owned helper, with a
+separate polkit action for each kind of change; the application itself runs unprivileged.
 
 %prep
 %autosetup -n ThinkUtils-%{version}
@@ -57,27 +56,56 @@ cd src-tauri && cargo build --release
 
 %install
 install -Dpm0755 src-tauri/target/release/thinkutils %{buildroot}%{_bindir}/thinkutils
+install -Dpm0755 src-tauri/target/release/thinkutils-daemon %{buildroot}%{_bindir}/thinkutils-daemon
+
+# Shipped disabled: no %systemd_post, so the user opts in with systemctl.
+install -Dpm0644 packaging/systemd/thinkutils-daemon.service \
+    %{buildroot}%{_unitdir}/thinkutils-daemon.service
+# The group that may use the daemon's socket.
+install -Dpm0644 packaging/systemd/thinkutils.sysusers \
+    %{buildroot}%{_sysusersdir}/thinkutils.conf
 
 # %{_libexecdir} is the canonical Fedora home for an internal helper that must
 # not be on $PATH. NEVER /usr/local -- forbidden by the packaging guidelines.
-# Matches HELPER_CANDIDATES[1] in src-tauri/src/fan_control.rs.
-install -Dpm0755 packaging/helper/thinkutils-fan-control \
-    %{buildroot}%{_libexecdir}/thinkutils/thinkutils-fan-control
+# Matches HELPER_CANDIDATES[1] in src-tauri/src/helper.rs.
+install -Dpm0755 src-tauri/target/release/thinkutils-helper \
+    %{buildroot}%{_libexecdir}/thinkutils/thinkutils-helper
 
 install -Dpm0644 packaging/polkit/50-thinkutils.rules \
     %{buildroot}%{_datadir}/polkit-1/rules.d/50-thinkutils.rules
+# The actions the rule grants by ID, naming the %{_libexecdir} helper.
+install -Dpm0644 packaging/polkit/libexec/com.thinkutils.helper.policy \
+    %{buildroot}%{_datadir}/polkit-1/actions/com.thinkutils.helper.policy
+
+# The helper's D-Bus service: started on first call, never enabled.
+install -Dpm0644 packaging/dbus/org.thinkutils.Hardware1.conf \
+    %{buildroot}%{_datadir}/dbus-1/system.d/org.thinkutils.Hardware1.conf
+install -Dpm0644 packaging/dbus/libexec/org.thinkutils.Hardware1.service \
+    %{buildroot}%{_datadir}/dbus-1/system-services/org.thinkutils.Hardware1.service
+install -Dpm0644 packaging/dbus/libexec/thinkutils-hardware.service \
+    %{buildroot}%{_unitdir}/thinkutils-hardware.service
 
 desktop-file-install --dir=%{buildroot}%{_datadir}/applications thinkutils.desktop
 install -Dpm0644 src-tauri/icons/128x128.png \
     %{buildroot}%{_datadir}/icons/hicolor/128x128/apps/thinkutils.png
 
+%post
+systemd-sysusers %{_sysusersdir}/thinkutils.conf >/dev/null 2>&1 || :
+
 %files
 %license LICENSE
 %doc README.md
 %{_bindir}/thinkutils
+%{_bindir}/thinkutils-daemon
+%{_unitdir}/thinkutils-daemon.service
+%{_sysusersdir}/thinkutils.conf
 %dir %{_libexecdir}/thinkutils
-%{_libexecdir}/thinkutils/thinkutils-fan-control
+%{_libexecdir}/thinkutils/thinkutils-helper
 %{_datadir}/polkit-1/rules.d/50-thinkutils.rules
+%{_datadir}/polkit-1/actions/com.thinkutils.helper.policy
+%{_datadir}/dbus-1/system.d/org.thinkutils.Hardware1.conf
+%{_datadir}/dbus-1/system-services/org.thinkutils.Hardware1.service
+%{_unitdir}/thinkutils-hardware.service
 %{_datadir}/applications/thinkutils.desktop
 %{_datadir}/icons/hicolor/128x128/apps/thinkutils.png
 
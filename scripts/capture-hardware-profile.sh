@@ -94,6 +94,18 @@ capture_dir() {
     done
 }
 
+# Mirror a /sys/class entry as the relative symlink the kernel has, pointing
+# into the captured devices tree. Code that discovers devices by class --
+# every hwmon chip, every power supply -- then finds them in the fixture too.
+link_class() {
+    local entry="$1"
+    local real
+    real=$(readlink -f "$entry")
+    [ -e "$DEST$real" ] || return 0
+    mkdir -p "$DEST$(dirname "$entry")"
+    ln -sfn "$(realpath --relative-to="$(dirname "$entry")" "$real")" "$DEST$entry"
+}
+
 # --- ThinkPad fan (the control interface) ---
 capture /proc/acpi/ibm/fan
 
@@ -110,12 +122,14 @@ for h in /sys/class/hwmon/hwmon*; do
     done
     # Record the symlink mapping; tests need to find chips by name.
     printf '%s -> %s\n' "$h" "$real" >> "$DEST/hwmon-map.txt"
+    link_class "$h"
 done
 
 # --- Batteries and AC ---
 for ps in /sys/class/power_supply/*; do
     [ -e "$ps/type" ] || continue
     capture_dir "$(readlink -f "$ps")"
+    link_class "$ps"
 done
 
 # --- CPU frequency scaling and turbo ---
@@ -123,15 +137,23 @@ capture_dir /sys/devices/system/cpu/cpu0/cpufreq
 capture /sys/devices/system/cpu/intel_pstate/no_turbo
 capture /sys/devices/system/cpu/intel_pstate/status
 capture /sys/devices/system/cpu/amd_pstate/status
+capture /sys/firmware/acpi/platform_profile
+capture /sys/firmware/acpi/platform_profile_choices
 
 # --- Thermal zones ---
 for tz in /sys/class/thermal/thermal_zone*; do
     [ -e "$tz/type" ] || continue
     capture "$(readlink -f "$tz")/type"
     capture "$(readlink -f "$tz")/temp"
+    link_class "$tz"
 done
 
 # --- Model identification, minus anything machine-unique ---
+# The model names are also read at runtime: per-machine data such as the fan
+# calibration table is keyed on them.
+capture /sys/class/dmi/id/product_version
+capture /sys/class/dmi/id/product_name
+capture /sys/class/dmi/id/sys_vendor
 {
     printf 'product_version=%s\n' "$(cat /sys/class/dmi/id/product_version 2>/dev/null || echo unknown)"
     printf 'product_name=%s\n'    "$(cat /sys/class/dmi/id/product_name 2>/dev/null || echo unknown)"
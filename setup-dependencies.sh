@@ -38,14 +38,6 @@ echo ""
 
 MISSING_DEPS=()
 
-# Check lm-sensors
-if ! command -v sensors &> /dev/null; then
-    echo -e "${YELLOW}⚠ lm-sensors not found${NC}"
-    MISSING_DEPS+=("lm-sensors")
-else
-    echo -e "${GREEN}✓ lm-sensors installed${NC}"
-fi
-
 # Check pkexec (polkit)
 if ! command -v pkexec &> /dev/null; then
     echo -e "${YELLOW}⚠ pkexec (polkit) not found${NC}"
@@ -128,22 +120,6 @@ fi
 
 echo ""
 
-# Configure sensors if just installed
-if command -v sensors &> /dev/null; then
-    if [ ! -f /etc/sensors3.conf ] || ! sensors &> /dev/null; then
-        echo -e "${BLUE}Configuring sensors...${NC}"
-        read -p "Run sensors-detect? (y/n) " -n 1 -r
-        echo ""
-        
-        if [[ $REPLY =~ ^[Yy]$ ]]; then
-            sudo sensors-detect --auto
-            echo -e "${GREEN}✓ Sensors configured${NC}"
-        fi
-    fi
-fi
-
-echo ""
-
 # Setup ThinkPad fan control
 if lsmod | grep -q thinkpad_acpi; then
     if [ ! -f /etc/modprobe.d/thinkpad_acpi.conf ]; then
@@ -16,46 +16,39 @@ else
     exit 1
 fi
 
-# Install dedicated fan control helper (validates commands before applying)
-echo "Installing fan control helper..."
-sudo tee /usr/local/bin/thinkutils-fan-control > /dev/null << 'EOF'
-#!/bin/bash
-set -e
-FAN="/proc/acpi/ibm/fan"
-case "$1" in
-    "level auto"|"level full-speed"|"level 0"|"level 1"|"level 2"|"level 3"|"level 4"|"level 5"|"level 6"|"level 7")
-        echo "$1" > "$FAN"
-        ;;
-    *)
-        echo "Invalid command" >&2
-        exit 1
-        ;;
-esac
-EOF
-sudo chmod 755 /usr/local/bin/thinkutils-fan-control
-echo "✓ Helper installed at /usr/local/bin/thinkutils-fan-control"
+SCRIPT_DIR="$(cd "$(dirname "$0")" && pwd)"
+HELPER_SRC="${1:-$SCRIPT_DIR/src-tauri/target/release/thinkutils-helper}"
+
+if [ ! -x "$HELPER_SRC" ]; then
+    echo "✗ No helper binary at $HELPER_SRC"
+    echo "  Build it first (cd src-tauri && cargo build --release), or pass its path"
+    exit 1
+fi
+
+# Install the compiled helper (validates every operation before writing)
+echo "Installing privileged helper..."
+sudo install -D -m 0755 "$HELPER_SRC" /usr/local/bin/thinkutils-helper
+# The bash helper older versions installed is superseded.
+sudo rm -f /usr/local/bin/thinkutils-fan-control
+echo "✓ Helper installed at /usr/local/bin/thinkutils-helper"
 
 # Create polkit rules directory if it doesn't exist
 sudo mkdir -p /etc/polkit-1/rules.d
 
-# Create the rule file (only allows the dedicated helper, not arbitrary bash)
+# The rule is generated from the Rust source; it only allows the helper, not
+# arbitrary bash.
 echo "Creating polkit rule..."
-sudo tee /etc/polkit-1/rules.d/50-thinkutils.rules > /dev/null << 'EOF'
-/* ThinkUtils: Allow passwordless fan control via dedicated helper only */
-polkit.addRule(function(action, subject) {
-    if (action.id == "org.freedesktop.policykit.exec") {
-        var program = action.lookup("program");
-        if (program == "/usr/local/bin/thinkutils-fan-control") {
-            if (subject.isInGroup("wheel") || subject.isInGroup("sudo")) {
-                polkit.log("ThinkUtils: Allowing passwordless fan control for " + subject.user);
-                return polkit.Result.YES;
-            }
-        }
-    }
-});
-EOF
+sudo install -m 0644 "$SCRIPT_DIR/packaging/polkit/50-thinkutils.rules" \
+    /etc/polkit-1/rules.d/50-thinkutils.rules
 
 echo "✓ Polkit rule created at /etc/polkit-1/rules.d/50-thinkutils.rules"
+
+# The rule grants by action ID; the actions name the helper installed above.
+echo "Installing polkit actions..."
+sed 's|/usr/lib/thinkutils/thinkutils-helper|/usr/local/bin/thinkutils-helper|' \
+    "$SCRIPT_DIR/packaging/polkit/lib/com.thinkutils.helper.policy" |
+    sudo install -D -m 0644 /dev/stdin /usr/share/polkit-1/actions/com.thinkutils.helper.policy
+echo "✓ Polkit actions installed at /usr/share/polkit-1/actions/com.thinkutils.helper.policy"
 echo ""
 echo "Reloading polkit..."
 sudo systemctl reload polkit 2>/dev/null || sudo killall -HUP polkitd 2>/dev/null || true
@@ -63,5 +56,5 @@ sudo systemctl reload polkit 2>/dev/null || sudo killall -HUP polkitd 2>/dev/nul
 echo ""
 echo "✓ Setup complete!"
 echo ""
-echo "ThinkUtils fan control will now work without asking for a password."
+echo "ThinkUtils fan control and battery thresholds will now work without a password."
 echo "Restart the app to test."
@@ -1,10 +1,13 @@
 //! Emit the packaging artifacts that must stay in lockstep with the Rust source.
 //!
-//! The polkit rule and the fan helper are installed by three different package
-//! formats, and the app searches for them at paths defined in `fan_control`. If a
-//! packaged copy drifts from the constants, the rule grants access to a path the
-//! helper is not at — which fails silently and looks exactly like a permissions
-//! problem.
+//! The polkit actions, the polkit rule and its .pkla counterpart, the D-Bus service files and the
+//! daemon's systemd unit are installed by three different package formats, and
+//! the actions and service files name the helper at paths defined in `helper`.
+//! If a packaged copy drifts from the constants, polkit authorises a path the
+//! helper is not at — which fails silently and looks exactly like a
+//! permissions problem. The helper itself is a compiled binary
+//! (src/bin/thinkutils-helper.rs) that the package recipes install straight
+//! from the build.
 //!
 //! Regenerate with:
 //!
@@ -15,7 +18,7 @@
 //! `packaging_matches_source` in tests/packaging.rs fails if the committed files
 //! and these outputs disagree, so the two cannot diverge unnoticed.
 
-use std::io::Write;
+use thinkutils_lib::hardware_service;
 
 fn main() {
     let out_dir = std::env::args()
@@ -23,24 +26,57 @@ fn main() {
         .unwrap_or_else(|| "../packaging".to_string());
 
     let polkit_dir = format!("{}/polkit", out_dir);
-    let helper_dir = format!("{}/helper", out_dir);
     std::fs::create_dir_all(&polkit_dir).expect("create polkit dir");
-    std::fs::create_dir_all(&helper_dir).expect("create helper dir");
 
     let rule_path = format!("{}/50-thinkutils.rules", polkit_dir);
-    std::fs::write(&rule_path, thinkutils_lib::fan_control::polkit_rule()).expect("write rule");
+    std::fs::write(&rule_path, thinkutils_lib::helper::polkit_rule()).expect("write rule");
     println!("wrote {}", rule_path);
+    let pkla_path = format!("{}/50-thinkutils.pkla", polkit_dir);
+    std::fs::write(&pkla_path, thinkutils_lib::helper::polkit_pkla()).expect("write pkla");
+    println!("wrote {}", pkla_path);
 
-    let helper_path = format!("{}/thinkutils-fan-control", helper_dir);
-    let mut f = std::fs::File::create(&helper_path).expect("create helper");
-    f.write_all(thinkutils_lib::fan_control::HELPER_SCRIPT.as_bytes())
-        .expect("write helper");
+    // One policy per helper location, since exec.path takes a single path.
+    for (dir, helper) in thinkutils_lib::helper::PACKAGED_POLICIES {
+        let policy_dir = format!("{}/{}", polkit_dir, dir);
+        std::fs::create_dir_all(&policy_dir).expect("create policy dir");
+        let policy_path = format!("{}/com.thinkutils.helper.policy", policy_dir);
+        std::fs::write(&policy_path, thinkutils_lib::helper::polkit_policy(helper))
+            .expect("write policy");
+        println!("wrote {}", policy_path);
+    }
+
+    let dbus_dir = format!("{}/dbus", out_dir);
+    std::fs::create_dir_all(&dbus_dir).expect("create dbus dir");
+    let bus_policy_path = format!("{}/org.thinkutils.Hardware1.conf", dbus_dir);
+    std::fs::write(&bus_policy_path, hardware_service::BUS_POLICY).expect("write bus policy");
+    println!("wrote {}", bus_policy_path);
 
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        std::fs::set_permissions(&helper_path, std::fs::Permissions::from_mode(0o755))
-            .expect("chmod helper");
+    // Activation and its unit both name the helper, so one pair per location.
+    for (dir, helper) in thinkutils_lib::helper::PACKAGED_POLICIES {
+        let service_dir = format!("{}/{}", dbus_dir, dir);
+        std::fs::create_dir_all(&service_dir).expect("create dbus service dir");
+        for (name, content) in [
+            (
+                "org.thinkutils.Hardware1.service",
+                hardware_service::activation_file(helper),
+            ),
+            (
+                hardware_service::SYSTEMD_SERVICE,
+                hardware_service::systemd_unit(helper),
+            ),
+        ] {
+            let path = format!("{}/{}", service_dir, name);
+            std::fs::write(&path, content).expect("write dbus service file");
+            println!("wrote {}", path);
+        }
     }
-    println!("wrote {}", helper_path);
+
+    let systemd_dir = format!("{}/systemd", out_dir);
+    std::fs::create_dir_all(&systemd_dir).expect("create systemd dir");
+    let unit_path = format!("{}/thinkutils-daemon.service", systemd_dir);
+    std::fs::write(&unit_path, thinkutils_lib::daemon::SYSTEMD_UNIT).expect("write unit");
+    println!("wrote {}", unit_path);
+    let sysusers_path = format!("{}/thinkutils.sysusers", systemd_dir);
+    std::fs::write(&sysusers_path, thinkutils_lib::daemon::SYSUSERS).expect("write sysusers");
+    println!("wrote {}", sysusers_path);
 }
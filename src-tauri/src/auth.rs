@@ -1,11 +1,4 @@
-use serde::{Deserialize, Serialize};
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ApiResponse<T> {
-    pub success: bool,
-    pub data: Option<T>,
-    pub error: Option<String>,
-}
+use crate::error::{ApiResponse, Error};
 
 #[tauri::command]
 pub async fn authenticate_once() -> ApiResponse<String> {
@@ -21,28 +14,19 @@ pub async fn authenticate_once() -> ApiResponse<String> {
         Ok(output) => {
             if output.status.success() {
                 println!("[Auth] ✓ Authentication successful");
-                ApiResponse {
-                    success: true,
-                    data: Some("Authenticated".to_string()),
-                    error: None,
-                }
+                ApiResponse::ok("Authenticated".to_string())
             } else {
                 let stderr = String::from_utf8_lossy(&output.stderr);
                 println!("[Auth] ✗ Authentication failed: {}", stderr);
-                ApiResponse {
-                    success: false,
-                    data: None,
-                    error: Some(format!("Authentication failed: {}", stderr)),
-                }
+                ApiResponse::err(Error::permission_denied(format!(
+                    "Authentication failed: {}",
+                    stderr
+                )))
             }
         }
         Err(e) => {
             println!("[Auth] ✗ Failed to execute pkexec: {}", e);
-            ApiResponse {
-                success: false,
-                data: None,
-                error: Some(format!("Failed to execute authentication: {}", e)),
-            }
+            ApiResponse::err(format!("Failed to execute authentication: {}", e))
         }
     }
 }
@@ -1,9 +1,16 @@
+use crate::error::{ApiResponse, Error};
+use crate::hardware_root;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-const BAT0_PATH: &str = "/sys/class/power_supply/BAT0";
-const BAT1_PATH: &str = "/sys/class/power_supply/BAT1";
+/// Where the kernel lists every power supply: the system batteries, the AC
+/// adapter, USB-C sources, and the batteries inside wireless peripherals.
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BatteryInfo {
@@ -20,52 +27,100 @@ pub struct BatteryInfo {
     pub energy_design: f32,
     pub technology: String,
     pub manufacturer: String,
+    /// This battery's own charge thresholds, where it has them.
+    pub thresholds: Option<BatteryThresholds>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BatteryThresholds {
     pub start: u8,
     pub stop: u8,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ApiResponse<T> {
-    pub success: bool,
-    pub data: Option<T>,
-    pub error: Option<String>,
+/// Every battery taken together, the way the machine runs on them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatterySummary {
+    /// How many batteries were read.
+    pub batteries: usize,
+    /// `Charging` or `Discharging` if any battery is, otherwise the first
+    /// battery's status.
+    pub status: String,
+    /// Combined charge, as a percentage of the combined full capacity.
+    pub capacity: u8,
+    /// Combined full capacity as a percentage of the combined design capacity.
+    pub health: u8,
+    /// Watt-hours.
+    pub energy_now: f32,
+    pub energy_full: f32,
+    pub energy_design: f32,
+    /// Watts flowing in or out of all batteries.
+    pub power: f32,
+    /// `power` smoothed over about a minute: the rate the time estimates use,
+    /// so a burst of load does not swing them by hours.
+    pub rate: f32,
+    /// Seconds until empty at the smoothed rate, while discharging.
+    pub time_to_empty: Option<u64>,
+    /// Seconds until charging stops at the smoothed rate, while charging:
+    /// at each battery's stop threshold, or full without one.
+    pub time_to_full: Option<u64>,
+}
+
+/// The batteries that power this machine, as sysfs directories in name order.
+///
+/// Found by `type` rather than by name: the primary battery is not always
+/// BAT0, and a second or slice battery can be BAT1 or BAT2. A `scope` of
+/// `Device` is a mouse or headset reporting its own charge, so it is left out.
+///
+/// The paths are the real sysfs ones, rebuilt from each entry's name, so they
+/// can be written to.
+pub fn battery_dirs() -> Vec<String> {
+    let Ok(entries) = hardware_root::read_dir(POWER_SUPPLY_DIR) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<String> = entries
+        .flatten()
+        .map(|e| format!("{}/{}", POWER_SUPPLY_DIR, e.file_name().to_string_lossy()))
+        .filter(|dir| is_system_battery(dir))
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+fn is_system_battery(dir: &str) -> bool {
+    let attr = |name: &str| {
+        hardware_root::read_to_string(&format!("{}/{}", dir, name))
+            .map(|s| s.trim().to_string())
+            .ok()
+    };
+    attr("type").as_deref() == Some("Battery") && attr("scope").as_deref() != Some("Device")
+}
+
+/// The sysfs name of a battery directory, e.g. `BAT0`.
+fn battery_name(dir: &str) -> &str {
+    dir.rsplit('/').next().unwrap_or(dir)
 }
 
 #[tauri::command]
 pub fn get_battery_info() -> ApiResponse<Vec<BatteryInfo>> {
     let mut batteries = Vec::new();
 
-    for (index, bat_path) in [BAT0_PATH, BAT1_PATH].iter().enumerate() {
-        if Path::new(bat_path).exists() {
-            match read_battery_info(bat_path, index) {
-                Ok(info) => batteries.push(info),
-                Err(e) => println!("[Battery] Error reading {}: {}", bat_path, e),
-            }
+    for dir in battery_dirs() {
+        match read_battery_info(&dir) {
+            Ok(info) => batteries.push(info),
+            Err(e) => println!("[Battery] Error reading {}: {}", dir, e),
         }
     }
 
     if batteries.is_empty() {
-        return ApiResponse {
-            success: false,
-            data: None,
-            error: Some("No batteries found".to_string()),
-        };
+        return ApiResponse::err(Error::hardware_absent("No batteries found"));
     }
 
-    ApiResponse {
-        success: true,
-        data: Some(batteries),
-        error: None,
-    }
+    ApiResponse::ok(batteries)
 }
 
-fn read_battery_info(path: &str, index: usize) -> Result<BatteryInfo, String> {
+fn read_battery_info(path: &str) -> Result<BatteryInfo, String> {
     let read_file = |file: &str| -> Result<String, String> {
-        fs::read_to_string(format!("{}/{}", path, file))
+        hardware_root::read_to_string(&format!("{}/{}", path, file))
             .map(|s| s.trim().to_string())
             .map_err(|e| e.to_string())
     };
@@ -77,18 +132,16 @@ fn read_battery_info(path: &str, index: usize) -> Result<BatteryInfo, String> {
             .unwrap_or(0)
     };
 
-    let read_f32 = |file: &str| -> f32 {
-        read_file(file)
-            .ok()
-            .and_then(|s| s.parse::<f32>().ok())
-            .map(|v| v / 1_000_000.0) // Convert from µV/µA to V/A
-            .unwrap_or(0.0)
-    };
+    let read_f32 = |file: &str| -> f32 { read_micro(path, file).unwrap_or(0.0) };
 
-    let capacity = read_u32("capacity") as u8;
-    let energy_now = read_f32("energy_now");
-    let energy_full = read_f32("energy_full");
-    let energy_design = read_f32("energy_full_design");
+    let energy = read_energy(path);
+    let (energy_now, energy_full, energy_design) = (energy.now, energy.full, energy.design);
+
+    let capacity = match read_file("capacity").ok().and_then(|s| s.parse().ok()) {
+        Some(capacity) => capacity,
+        None if energy_full > 0.0 => ((energy_now / energy_full) * 100.0).round().min(100.0) as u8,
+        None => 0,
+    };
 
     let health = if energy_design > 0.0 {
         ((energy_full / energy_design) * 100.0) as u8
@@ -98,10 +151,10 @@ fn read_battery_info(path: &str, index: usize) -> Result<BatteryInfo, String> {
 
     let voltage = read_f32("voltage_now");
     let current = read_f32("current_now");
-    let power = voltage * current;
+    let power = battery_power(path);
 
     Ok(BatteryInfo {
-        name: format!("BAT{}", index),
+        name: battery_name(path).to_string(),
         status: read_file("status").unwrap_or_else(|_| "Unknown".to_string()),
         capacity,
         health,
@@ -114,9 +167,223 @@ fn read_battery_info(path: &str, index: usize) -> Result<BatteryInfo, String> {
         energy_design,
         technology: read_file("technology").unwrap_or_else(|_| "Unknown".to_string()),
         manufacturer: read_file("manufacturer").unwrap_or_else(|_| "Unknown".to_string()),
+        thresholds: thresholds_in(path).map(|paths| read_thresholds(&paths)),
     })
 }
 
+/// What a battery holds, in watt-hours.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Energy {
+    now: f32,
+    full: f32,
+    design: f32,
+}
+
+/// One sysfs attribute, from micro-units (µWh, µAh, µW, µA, µV) to base units.
+fn read_micro(dir: &str, file: &str) -> Option<f32> {
+    hardware_root::read_to_string(&format!("{}/{}", dir, file))
+        .ok()
+        .and_then(|s| s.trim().parse::<f32>().ok())
+        .map(|v| v / 1_000_000.0)
+}
+
+/// A battery reports what it holds either as energy (`energy_*`, µWh), as
+/// ThinkPads do, or as charge (`charge_*`, µAh), as many other laptops do.
+/// Charge is turned into energy at the design voltage, not the present one:
+/// that moves with load and charge, and full capacity would move with it on
+/// every read, as if the battery had been swapped.
+fn read_energy(dir: &str) -> Energy {
+    let volts = nominal_volts(dir).unwrap_or(0.0);
+    let wh = |energy: &str, charge: &str| {
+        read_micro(dir, energy)
+            .or_else(|| read_micro(dir, charge).map(|ah| ah * volts))
+            .unwrap_or(0.0)
+    };
+    Energy {
+        now: wh("energy_now", "charge_now"),
+        full: wh("energy_full", "charge_full"),
+        design: wh("energy_full_design", "charge_full_design"),
+    }
+}
+
+/// The voltage a battery is rated at, or where it does not say, the present
+/// one.
+fn nominal_volts(dir: &str) -> Option<f32> {
+    read_micro(dir, "voltage_min_design")
+        .filter(|v| *v > 0.0)
+        .or_else(|| read_micro(dir, "voltage_now").filter(|v| *v > 0.0))
+}
+
+/// Watts through one battery: `power_now` where the battery reports it,
+/// otherwise voltage times current, at the rated voltage if the present one
+/// is not reported. Some drivers sign these by direction, so only the size is
+/// kept.
+fn battery_power(dir: &str) -> f32 {
+    match read_micro(dir, "power_now") {
+        Some(watts) => watts,
+        None => {
+            let volts = read_micro(dir, "voltage_now")
+                .filter(|v| *v > 0.0)
+                .or_else(|| nominal_volts(dir))
+                .unwrap_or(0.0);
+            let amps = read_micro(dir, "current_now").unwrap_or(0.0);
+            volts * amps
+        }
+    }
+    .abs()
+}
+
+/// Add up what each battery reports.
+fn summarize(batteries: &[BatteryInfo]) -> BatterySummary {
+    let energy_now: f32 = batteries.iter().map(|b| b.energy_now).sum();
+    let energy_full: f32 = batteries.iter().map(|b| b.energy_full).sum();
+    let energy_design: f32 = batteries.iter().map(|b| b.energy_design).sum();
+    let power: f32 = batteries.iter().map(|b| b.power).sum();
+
+    let any = |status: &str| batteries.iter().any(|b| b.status == status);
+    let status = if any("Charging") {
+        "Charging".to_string()
+    } else if any("Discharging") {
+        "Discharging".to_string()
+    } else {
+        batteries
+            .first()
+            .map_or_else(|| "Unknown".to_string(), |b| b.status.clone())
+    };
+
+    let percent = |part: f32, whole: f32| {
+        if whole > 0.0 {
+            ((part / whole) * 100.0).round().min(100.0) as u8
+        } else {
+            0
+        }
+    };
+
+    let mut summary = BatterySummary {
+        batteries: batteries.len(),
+        capacity: percent(energy_now, energy_full),
+        health: if energy_design > 0.0 {
+            percent(energy_full, energy_design)
+        } else {
+            100
+        },
+        status,
+        energy_now,
+        energy_full,
+        energy_design,
+        power,
+        rate: power,
+        time_to_empty: None,
+        time_to_full: None,
+    };
+    estimate(&mut summary, batteries, power);
+    summary
+}
+
+/// Fill in the time estimates at `rate` watts.
+fn estimate(summary: &mut BatterySummary, batteries: &[BatteryInfo], rate: f32) {
+    // Hours at the rate; no estimate without a measurable flow.
+    let seconds = |wh: f32| (rate > 0.0).then(|| (wh / rate * 3600.0) as u64);
+    // Charging stops at the stop threshold, so that is where "full" is.
+    let target: f32 = batteries
+        .iter()
+        .map(|b| b.energy_full * b.thresholds.map_or(100, |t| t.stop.min(100)) as f32 / 100.0)
+        .sum();
+
+    summary.rate = rate;
+    summary.time_to_empty = match summary.status.as_str() {
+        "Discharging" => seconds(summary.energy_now),
+        _ => None,
+    };
+    summary.time_to_full = match summary.status.as_str() {
+        "Charging" => seconds((target - summary.energy_now).max(0.0)),
+        _ => None,
+    };
+}
+
+/// The time constant of the smoothing: a change in load is about two-thirds
+/// of the way into the estimate after this long.
+const RATE_SMOOTHING: Duration = Duration::from_secs(60);
+
+/// Readings further apart than this, as across a suspend, start the
+/// smoothing over rather than averaging across the gap.
+const RATE_RESET_GAP: Duration = Duration::from_secs(5 * 60);
+
+/// The smoothed rate as of the last reading, and what it was read from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SmoothedRate {
+    watts: f32,
+    status_charging: bool,
+    batteries: usize,
+    energy_now: f32,
+    energy_full: f32,
+    at: Instant,
+}
+
+static SMOOTHED_RATE: Mutex<Option<SmoothedRate>> = Mutex::new(None);
+
+/// Fold a reading into the smoothed rate: an exponential moving average,
+/// weighted by the time since the last reading so it does not matter how
+/// often the summary is asked for.
+///
+/// A change of direction, a battery added or removed, or a long gap starts it
+/// over from the reading. Where the firmware reports no flow at all while
+/// charging or discharging, the change in stored energy stands in for it.
+fn smooth_rate(
+    previous: Option<&SmoothedRate>,
+    summary: &BatterySummary,
+    at: Instant,
+) -> SmoothedRate {
+    let reading = SmoothedRate {
+        watts: summary.power,
+        status_charging: summary.status == "Charging",
+        batteries: summary.batteries,
+        energy_now: summary.energy_now,
+        energy_full: summary.energy_full,
+        at,
+    };
+    let Some(previous) = previous.filter(|p| {
+        p.status_charging == reading.status_charging
+            && p.batteries == reading.batteries
+            && p.energy_full == reading.energy_full
+            && at > p.at
+            && at - p.at <= RATE_RESET_GAP
+    }) else {
+        return reading;
+    };
+    let dt = (at - previous.at).as_secs_f32();
+    let measured = if summary.power > 0.0 {
+        summary.power
+    } else {
+        (previous.energy_now - summary.energy_now).abs() / dt * 3600.0
+    };
+    let weight = 1.0 - (-dt / RATE_SMOOTHING.as_secs_f32()).exp();
+    SmoothedRate {
+        watts: previous.watts + weight * (measured - previous.watts),
+        ..reading
+    }
+}
+
+#[tauri::command]
+pub fn get_battery_summary() -> ApiResponse<BatterySummary> {
+    let batteries = match get_battery_info().data {
+        Some(batteries) => batteries,
+        None => return ApiResponse::err(Error::hardware_absent("No batteries found")),
+    };
+    let mut summary = summarize(&batteries);
+    let mut smoothed = SMOOTHED_RATE.lock().unwrap_or_else(|e| e.into_inner());
+    *smoothed = match summary.status.as_str() {
+        "Charging" | "Discharging" => {
+            Some(smooth_rate(smoothed.as_ref(), &summary, Instant::now()))
+        }
+        _ => None,
+    };
+    if let Some(rate) = *smoothed {
+        estimate(&mut summary, &batteries, rate.watts);
+    }
+    ApiResponse::ok(summary)
+}
+
 /// Attribute names for the charge thresholds, most-standard first.
 ///
 /// `charge_control_*` is the generic kernel power-supply API and works beyond
@@ -135,51 +402,197 @@ const THRESHOLD_ATTRS: &[(&str, &str)] = &[
     ("charge_start_threshold", "charge_stop_threshold"),
 ];
 
-/// The threshold file pair this machine actually exposes.
+/// One battery's charge threshold files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThresholdPaths {
+    /// The battery's sysfs name, e.g. `BAT0`.
+    pub battery: String,
+    pub start: String,
+    pub stop: String,
+}
+
+/// The threshold file pair a battery directory actually exposes: the first
+/// pair where both files exist.
 ///
-/// Returns the first pair where both files exist. Every caller must go through
-/// here — the duplication between modules is what allowed them to disagree.
-pub fn threshold_paths() -> Option<(String, String)> {
+/// Existence is checked through the hardware root, but the paths returned are
+/// the real ones: callers write to them.
+fn thresholds_in(dir: &str) -> Option<ThresholdPaths> {
     THRESHOLD_ATTRS.iter().find_map(|(start, stop)| {
-        let start_path = format!("{}/{}", BAT0_PATH, start);
-        let stop_path = format!("{}/{}", BAT0_PATH, stop);
-        (Path::new(&start_path).exists() && Path::new(&stop_path).exists())
-            .then_some((start_path, stop_path))
+        let start_path = format!("{}/{}", dir, start);
+        let stop_path = format!("{}/{}", dir, stop);
+        (hardware_root::exists(&start_path) && hardware_root::exists(&stop_path)).then(|| {
+            ThresholdPaths {
+                battery: battery_name(dir).to_string(),
+                start: start_path,
+                stop: stop_path,
+            }
+        })
     })
 }
 
-#[tauri::command]
-pub fn get_battery_thresholds() -> ApiResponse<BatteryThresholds> {
-    let (start_path, stop_path) = match threshold_paths() {
-        Some(pair) => pair,
-        None => {
-            // Preserve the previous defaults so callers that ignore `success`
-            // keep behaving as before.
-            return ApiResponse {
-                success: true,
-                data: Some(BatteryThresholds {
-                    start: 0,
-                    stop: 100,
-                }),
-                error: None,
-            };
-        }
+/// Every battery with charge thresholds, each with its own pair: the firmware
+/// keeps a separate setting per battery.
+///
+/// Every caller must go through here or [`threshold_paths`] — the duplication
+/// between modules is what allowed them to disagree.
+pub fn all_threshold_paths() -> Vec<ThresholdPaths> {
+    battery_dirs()
+        .iter()
+        .filter_map(|dir| thresholds_in(dir))
+        .collect()
+}
+
+/// The primary battery's threshold pair: the first battery that has one.
+pub fn threshold_paths() -> Option<(String, String)> {
+    all_threshold_paths()
+        .into_iter()
+        .next()
+        .map(|paths| (paths.start, paths.stop))
+}
+
+/// A battery's current thresholds. A failed read substitutes the defaults,
+/// 0 and 100.
+pub(crate) fn read_thresholds(paths: &ThresholdPaths) -> BatteryThresholds {
+    let read = |path: &str| {
+        hardware_root::read_to_string(path)
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
     };
+    BatteryThresholds {
+        start: read(&paths.start).unwrap_or(0),
+        stop: read(&paths.stop).unwrap_or(100),
+    }
+}
 
-    let start = fs::read_to_string(&start_path)
-        .ok()
-        .and_then(|s| s.trim().parse().ok())
-        .unwrap_or(0);
+/// The batteries a threshold change applies to: the one named, or every
+/// battery with thresholds when none is.
+fn threshold_targets(battery: Option<&str>) -> Result<Vec<ThresholdPaths>, Error> {
+    let all = all_threshold_paths();
+    if all.is_empty() {
+        return Err(Error::hardware_absent(
+            "This machine exposes no battery charge threshold controls.",
+        ));
+    }
+    let Some(name) = battery else {
+        return Ok(all);
+    };
+    let named: Vec<ThresholdPaths> = all.into_iter().filter(|p| p.battery == name).collect();
+    if named.is_empty() {
+        return Err(Error::hardware_absent(format!(
+            "{} has no charge threshold controls.",
+            name
+        )));
+    }
+    Ok(named)
+}
+
+/// Check a battery name from outside: it must be one of this machine's
+/// batteries with thresholds. A name is only ever matched against what
+/// discovery found, never used to build a path.
+pub(crate) fn validate_battery(name: &str) -> Result<(), String> {
+    threshold_targets(Some(name))
+        .map(|_| ())
+        .map_err(String::from)
+}
+
+/// How the firmware answers a threshold or charge behaviour write, for a
+/// simulated hardware root. None for any other path.
+///
+/// Enforces the rule [`write_start_first`] exists for: a start at or above the
+/// stop already on the battery is refused, and so is a stop at or below the
+/// start. Both spellings are one setting in the firmware, so an accepted write
+/// shows up under the other name too.
+pub(crate) fn simulate_write(path: &str, value: &str) -> Option<std::io::Result<()>> {
+    let (dir, attr) = path.rsplit_once('/')?;
+    if attr == CHARGE_BEHAVIOUR_ATTR {
+        return Some(simulate_behaviour_write(path, value));
+    }
+    let (pair, is_start) = THRESHOLD_ATTRS.iter().find_map(|pair| {
+        (attr == pair.0)
+            .then_some((pair, true))
+            .or((attr == pair.1).then_some((pair, false)))
+    })?;
 
-    let stop = fs::read_to_string(&stop_path)
+    let invalid = |message: String| Some(Err(hardware_root::invalid_value(message)));
+    let Ok(new) = value.parse::<u8>() else {
+        return invalid(format!("{} is not a percentage", value));
+    };
+    if new > 100 {
+        return invalid(format!("{} is above 100", new));
+    }
+
+    let sibling = if is_start { pair.1 } else { pair.0 };
+    let other = hardware_root::read_to_string(&format!("{}/{}", dir, sibling))
         .ok()
-        .and_then(|s| s.trim().parse().ok())
-        .unwrap_or(100);
+        .and_then(|s| s.trim().parse::<u8>().ok());
+    match other {
+        Some(stop) if is_start && new >= stop => {
+            return invalid(format!(
+                "start {} is not below the current stop {}",
+                new, stop
+            ))
+        }
+        Some(start) if !is_start && new <= start => {
+            return invalid(format!(
+                "stop {} is not above the current start {}",
+                new, start
+            ))
+        }
+        _ => {}
+    }
+
+    for &(start_attr, stop_attr) in THRESHOLD_ATTRS {
+        let name = if is_start { start_attr } else { stop_attr };
+        let target = hardware_root::resolve(&format!("{}/{}", dir, name));
+        if target.is_file() {
+            if let Err(e) = fs::write(target, format!("{}\n", new)) {
+                return Some(Err(e));
+            }
+        }
+    }
+    Some(Ok(()))
+}
+
+/// A charge behaviour write: one of the listed choices becomes the bracketed
+/// one, and anything else is refused.
+fn simulate_behaviour_write(path: &str, value: &str) -> std::io::Result<()> {
+    let text = hardware_root::read_to_string(path)?;
+    let (_, choices) = parse_choices(&text);
+    if !choices.contains(&value) {
+        return Err(hardware_root::invalid_value(format!(
+            "{} is not one of: {}",
+            value,
+            choices.join(" ")
+        )));
+    }
+    let listed: Vec<String> = choices
+        .iter()
+        .map(|c| {
+            if *c == value {
+                format!("[{}]", c)
+            } else {
+                c.to_string()
+            }
+        })
+        .collect();
+    fs::write(
+        hardware_root::resolve(path),
+        format!("{}\n", listed.join(" ")),
+    )
+}
 
-    ApiResponse {
-        success: true,
-        data: Some(BatteryThresholds { start, stop }),
-        error: None,
+/// The primary battery's thresholds. Each battery's own are in
+/// [`BatteryInfo::thresholds`].
+#[tauri::command]
+pub fn get_battery_thresholds() -> ApiResponse<BatteryThresholds> {
+    match all_threshold_paths().first() {
+        Some(paths) => ApiResponse::ok(read_thresholds(paths)),
+        // Preserve the previous defaults so callers that ignore `success`
+        // keep behaving as before.
+        None => ApiResponse::ok(BatteryThresholds {
+            start: 0,
+            stop: 100,
+        }),
     }
 }
 
@@ -197,46 +610,102 @@ fn write_start_first(current_start: u8, new_stop: u8) -> bool {
     new_stop <= current_start
 }
 
-#[tauri::command]
-pub async fn set_battery_thresholds(start: u8, stop: u8) -> ApiResponse<String> {
+/// The checks every threshold change passes before anything is written.
+pub(crate) fn validate_thresholds(start: u8, stop: u8) -> Result<(), String> {
     if start >= stop {
-        return ApiResponse {
-            success: false,
-            data: None,
-            error: Some("Start threshold must be less than stop threshold".to_string()),
-        };
+        return Err("Start threshold must be less than stop threshold".to_string());
     }
-
     if start > 100 || stop > 100 {
-        return ApiResponse {
-            success: false,
-            data: None,
-            error: Some("Thresholds must be between 0 and 100".to_string()),
+        return Err("Thresholds must be between 0 and 100".to_string());
+    }
+    Ok(())
+}
+
+/// Set the thresholds of one battery, named as in [`BatteryInfo::name`], or of
+/// every battery that has them when `battery` is omitted.
+#[tauri::command]
+pub async fn set_battery_thresholds(
+    start: u8,
+    stop: u8,
+    battery: Option<String>,
+) -> ApiResponse<String> {
+    if let Err(e) = validate_thresholds(start, stop) {
+        return ApiResponse::err(Error::validation(e));
+    }
+    if calibration_running() {
+        return ApiResponse::err(Error::validation(CALIBRATION_HOLDS_CHARGING));
+    }
+    apply_thresholds(start, stop, battery).await
+}
+
+/// Write validated thresholds: through the daemon, directly, or through the
+/// helper.
+async fn apply_thresholds(start: u8, stop: u8, battery: Option<String>) -> ApiResponse<String> {
+    // The daemon runs as root and re-applies thresholds the firmware drops, so
+    // with one attached it takes the change instead of a privilege prompt. It
+    // keeps each battery's own setting.
+    if let Some(socket) = crate::daemon::attached() {
+        let request = crate::daemon::Request::SetBatteryThresholds {
+            start,
+            stop,
+            battery,
+        };
+        return match crate::daemon::call(socket, request).await {
+            Ok(_) => ApiResponse::ok(format!("Thresholds set: {}%-{}%", start, stop)),
+            Err(e) => ApiResponse::err(e),
         };
     }
 
-    let (start_path, stop_path) = match threshold_paths() {
-        Some(pair) => pair,
-        None => {
-            return ApiResponse {
-                success: false,
-                data: None,
-                error: Some(
-                    "This machine exposes no battery charge threshold controls.".to_string(),
-                ),
-            }
+    if let Err(e) = threshold_targets(battery.as_deref()) {
+        return ApiResponse::err(e);
+    }
+
+    // A simulated profile has nothing to escalate for: the writes land in its
+    // scratch copy, in the same order, or fail.
+    match write_thresholds(start, stop, battery.as_deref()) {
+        Ok(()) => return ApiResponse::ok(format!("Thresholds set: {}%-{}%", start, stop)),
+        Err(e) if hardware_root::is_simulated() => {
+            return ApiResponse::err(Error::simulated(format!(
+                "Simulated hardware rejected the change: {}",
+                e
+            )))
         }
+        Err(_) => {}
+    }
+
+    // Need elevated permissions. The helper writes in the same order.
+    let operation = crate::helper::Operation::BatteryThresholds {
+        start,
+        stop,
+        battery,
     };
+    match crate::helper::run(&operation).await {
+        Ok(_) => ApiResponse::ok(format!("Thresholds set: {}%-{}%", start, stop)),
+        Err(e) => ApiResponse::err(e),
+    }
+}
+
+/// Write both thresholds of the named battery, or of every battery with
+/// thresholds. Shared by the app and the privileged helper, so a write that
+/// needs root is ordered exactly like one that does not.
+pub(crate) fn write_thresholds(start: u8, stop: u8, battery: Option<&str>) -> Result<(), String> {
+    for paths in threshold_targets(battery)? {
+        write_thresholds_to(&paths, start, stop)
+            .map_err(|e| format!("{}: {}", paths.battery, e))?;
+    }
+    Ok(())
+}
 
-    // get_battery_thresholds() has no failure path — it substitutes defaults on a
+/// Write one battery's pair, in the order the firmware accepts from where
+/// they are now.
+fn write_thresholds_to(paths: &ThresholdPaths, start: u8, stop: u8) -> Result<(), String> {
+    // read_thresholds() has no failure path — it substitutes defaults on a
     // failed read — so there is nothing to match on. Note the substituted default
     // for start is 0, which makes write_start_first() return false and yields the
     // stop-first order. That is the safe fallback: it is the order that was in use
     // before this ordering logic existed.
-    let current = get_battery_thresholds().data.unwrap_or(BatteryThresholds {
-        start: 0,
-        stop: 100,
-    });
+    let current = read_thresholds(paths);
+    let (start_path, stop_path) = (paths.start.clone(), paths.stop.clone());
 
     let (first_path, first_value, second_path, second_value) =
         if write_start_first(current.start, stop) {
@@ -245,117 +714,1240 @@ pub async fn set_battery_thresholds(start: u8, stop: u8) -> ApiResponse<String>
             (stop_path, stop.to_string(), start_path, start.to_string())
         };
 
-    // Try direct write first
-    if fs::write(&first_path, &first_value).is_ok()
-        && fs::write(&second_path, &second_value).is_ok()
-    {
-        return ApiResponse {
-            success: true,
-            data: Some(format!("Thresholds set: {}%-{}%", start, stop)),
-            error: None,
-        };
+    hardware_root::write(&first_path, &first_value)
+        .and_then(|()| hardware_root::write(&second_path, &second_value))
+        .map_err(|e| e.to_string())
+}
+
+/// Watts through every battery together.
+#[tauri::command]
+pub fn get_power_consumption() -> ApiResponse<f32> {
+    ApiResponse::ok(battery_dirs().iter().map(|dir| battery_power(dir)).sum())
+}
+
+/// The kernel's charging override, next to the thresholds in each battery
+/// directory. It lists what the firmware offers with the one in force in
+/// brackets, `[auto] inhibit-charge force-discharge`, and takes one name.
+const CHARGE_BEHAVIOUR_ATTR: &str = "charge_behaviour";
+
+/// What the charger does with a battery, whatever its thresholds say.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChargeBehaviour {
+    /// Charge between the thresholds.
+    Auto,
+    /// Run from AC without charging: a docked laptop kept off the charger.
+    InhibitCharge,
+    /// Run from the battery even on AC, to calibrate it or bring it down to
+    /// storage charge.
+    ForceDischarge,
+}
+
+impl ChargeBehaviour {
+    pub const ALL: [ChargeBehaviour; 3] = [
+        ChargeBehaviour::Auto,
+        ChargeBehaviour::InhibitCharge,
+        ChargeBehaviour::ForceDischarge,
+    ];
+}
+
+impl std::fmt::Display for ChargeBehaviour {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ChargeBehaviour::Auto => "auto",
+            ChargeBehaviour::InhibitCharge => "inhibit-charge",
+            ChargeBehaviour::ForceDischarge => "force-discharge",
+        })
     }
+}
 
-    // Need elevated permissions. Writes stay in the order chosen above.
-    let script_content = format!(
-        "#!/bin/bash\nset -e\necho {} > {}\necho {} > {}\nexit 0\n",
-        first_value, first_path, second_value, second_path
-    );
+impl std::str::FromStr for ChargeBehaviour {
+    type Err = String;
 
-    match crate::privileged::run_script(&script_content).await {
-        Ok(output) => {
-            if output.status.success() {
-                ApiResponse {
-                    success: true,
-                    data: Some(format!("Thresholds set: {}%-{}%", start, stop)),
-                    error: None,
-                }
-            } else {
-                ApiResponse {
-                    success: false,
-                    data: None,
-                    error: Some("Permission denied".to_string()),
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ChargeBehaviour::ALL
+            .into_iter()
+            .find(|b| b.to_string() == s)
+            .ok_or_else(|| format!("Invalid charge behaviour: {:?}", s))
+    }
+}
+
+/// One battery's charge behaviour and the ones its firmware offers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChargeBehaviourState {
+    pub battery: String,
+    /// None when the kernel reports one this app does not know.
+    pub current: Option<ChargeBehaviour>,
+    pub available: Vec<ChargeBehaviour>,
+    /// Seconds until a timed change goes back to `auto`.
+    pub reverts_in: Option<u64>,
+}
+
+/// Split a sysfs choice list into the entry in force and every entry:
+/// `[auto] inhibit-charge force-discharge` is `auto` and all three.
+fn parse_choices(text: &str) -> (Option<&str>, Vec<&str>) {
+    let mut active = None;
+    let choices: Vec<&str> = text
+        .split_whitespace()
+        .map(
+            |word| match word.strip_prefix('[').and_then(|w| w.strip_suffix(']')) {
+                Some(name) => {
+                    active = Some(name);
+                    name
                 }
+                None => word,
+            },
+        )
+        .collect();
+    (active, choices)
+}
+
+/// A battery's charge behaviour file, where it has one. As with the
+/// thresholds, existence is checked in the hardware root and the real path
+/// returned.
+fn charge_behaviour_in(dir: &str) -> Option<String> {
+    let path = format!("{}/{}", dir, CHARGE_BEHAVIOUR_ATTR);
+    hardware_root::exists(&path).then_some(path)
+}
+
+/// Every battery's charge behaviour file.
+pub fn charge_behaviour_paths() -> Vec<String> {
+    battery_dirs()
+        .iter()
+        .filter_map(|dir| charge_behaviour_in(dir))
+        .collect()
+}
+
+/// The batteries a behaviour change applies to, as name and file: the one
+/// named, or every battery with the control when none is.
+fn behaviour_targets(battery: Option<&str>) -> Result<Vec<(String, String)>, Error> {
+    let all: Vec<(String, String)> = battery_dirs()
+        .iter()
+        .filter_map(|dir| {
+            charge_behaviour_in(dir).map(|path| (battery_name(dir).to_string(), path))
+        })
+        .collect();
+    if all.is_empty() {
+        return Err(Error::hardware_absent(
+            "This machine exposes no charge behaviour control. It needs Linux 5.17 or later.",
+        ));
+    }
+    let Some(name) = battery else {
+        return Ok(all);
+    };
+    let named: Vec<(String, String)> = all.into_iter().filter(|(b, _)| b == name).collect();
+    if named.is_empty() {
+        return Err(Error::hardware_absent(format!(
+            "{} has no charge behaviour control.",
+            name
+        )));
+    }
+    Ok(named)
+}
+
+/// The behaviour in force on one battery, and the ones its firmware offers.
+/// Names this app does not know are left out.
+fn read_behaviour(path: &str) -> (Option<ChargeBehaviour>, Vec<ChargeBehaviour>) {
+    let text = hardware_root::read_to_string(path).unwrap_or_default();
+    let (active, choices) = parse_choices(&text);
+    (
+        active.and_then(|a| a.parse().ok()),
+        choices.into_iter().filter_map(|c| c.parse().ok()).collect(),
+    )
+}
+
+#[tauri::command]
+pub fn get_charge_behaviour() -> ApiResponse<Vec<ChargeBehaviourState>> {
+    let targets = match behaviour_targets(None) {
+        Ok(targets) => targets,
+        Err(e) => return ApiResponse::err(e),
+    };
+    let pending = lock_pending();
+    let states = targets
+        .into_iter()
+        .map(|(battery, path)| {
+            let (current, available) = read_behaviour(&path);
+            let reverts_in = pending
+                .get(&battery)
+                .filter(|p| current == Some(p.behaviour))
+                .map(|p| p.at.saturating_duration_since(Instant::now()).as_secs());
+            ChargeBehaviourState {
+                battery,
+                current,
+                available,
+                reverts_in,
             }
+        })
+        .collect();
+    ApiResponse::ok(states)
+}
+
+/// Check a behaviour against the batteries it would apply to: each must
+/// offer it.
+pub(crate) fn validate_charge_behaviour(
+    behaviour: ChargeBehaviour,
+    battery: Option<&str>,
+) -> Result<(), String> {
+    for (name, path) in behaviour_targets(battery)? {
+        if !read_behaviour(&path).1.contains(&behaviour) {
+            return Err(format!("{} does not offer {}", name, behaviour));
         }
-        Err(e) => ApiResponse {
-            success: false,
-            data: None,
-            error: Some(format!("Failed to execute: {}", e)),
-        },
     }
+    Ok(())
 }
 
-#[tauri::command]
-pub fn get_power_consumption() -> ApiResponse<f32> {
-    let power_path = format!("{}/power_now", BAT0_PATH);
+/// Write a behaviour to the named battery, or to every battery with the
+/// control. Shared by the app and the privileged helper.
+pub(crate) fn write_charge_behaviour(
+    behaviour: ChargeBehaviour,
+    battery: Option<&str>,
+) -> Result<(), String> {
+    for (name, path) in behaviour_targets(battery)? {
+        hardware_root::write(&path, &behaviour.to_string())
+            .map_err(|e| format!("{}: {}", name, e))?;
+    }
+    Ok(())
+}
 
-    let power = fs::read_to_string(&power_path)
-        .ok()
-        .and_then(|s| s.trim().parse::<f32>().ok())
-        .map(|w| w / 1_000_000.0) // Convert µW to W
-        .unwrap_or(0.0);
+/// The longest time limit a behaviour change accepts.
+pub const MAX_CHARGE_BEHAVIOUR_SECS: u64 = 24 * 60 * 60;
 
-    ApiResponse {
-        success: true,
-        data: Some(power),
-        error: None,
+/// The checks a behaviour change passes before anything is written: the name
+/// is one this app knows, and a time limit is in range. `auto` takes no limit,
+/// since it is what a limit returns to.
+pub(crate) fn validate_behaviour_request(
+    behaviour: &str,
+    limit_secs: Option<u64>,
+) -> Result<ChargeBehaviour, String> {
+    let behaviour: ChargeBehaviour = behaviour.parse()?;
+    match limit_secs {
+        Some(_) if behaviour == ChargeBehaviour::Auto => {
+            Err("auto is what a time limit returns to; it takes none".to_string())
+        }
+        Some(secs) if !(1..=MAX_CHARGE_BEHAVIOUR_SECS).contains(&secs) => Err(format!(
+            "A time limit of {}s is out of range (1-{}s)",
+            secs, MAX_CHARGE_BEHAVIOUR_SECS
+        )),
+        _ => Ok(behaviour),
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// One battery's timed behaviour, waiting to go back to `auto`.
+struct PendingRevert {
+    /// The change that set it. A timer only acts on its own.
+    change: u64,
+    behaviour: ChargeBehaviour,
+    battery: String,
+    at: Instant,
+    /// Where it is kept on disk until it ends, if anywhere.
+    state_path: Option<PathBuf>,
+}
 
-    /// Model the firmware constraint: a write is rejected if it would leave
-    /// start >= stop, even for an instant. Replays the two writes in the chosen
-    /// order and reports whether every intermediate state stayed legal.
-    fn ordering_is_safe(current: (u8, u8), target: (u8, u8)) -> bool {
-        let (cur_start, cur_stop) = current;
-        let (new_start, new_stop) = target;
+const REVERT_STATE_FILE: &str = "charge_behaviour_revert.json";
 
-        let states = if write_start_first(cur_start, new_stop) {
-            // write start, then stop
-            [(new_start, cur_stop), (new_start, new_stop)]
-        } else {
-            // write stop, then start
-            [(cur_start, new_stop), (new_start, new_stop)]
-        };
+/// A timed behaviour as kept on disk: enough to end it after a crash or a
+/// reboot, when the timer that would have is gone. The file holds one per
+/// battery counting down.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SavedRevert {
+    behaviour: ChargeBehaviour,
+    battery: String,
+    /// Unix seconds.
+    until: u64,
+}
 
-        states.iter().all(|(s, e)| s < e)
+/// Kept on disk for as long as a timed behaviour runs. A simulated profile
+/// keeps none: its timer must never end a real battery's behaviour.
+fn revert_state_path() -> Option<PathBuf> {
+    if hardware_root::is_simulated() {
+        return None;
     }
+    crate::sync::get_config_dir()
+        .ok()
+        .map(|dir| dir.join(REVERT_STATE_FILE))
+}
 
-    /// The case the original ordering logic missed. With `new_stop < current_start`
-    /// this picks stop-first, producing the intermediate state (60, 60) — start
-    /// equal to stop, which the firmware rejects with the same "Invalid argument"
-    /// the ordering was introduced to prevent.
-    #[test]
-    fn boundary_new_stop_equals_current_start() {
-        assert!(
-            write_start_first(60, 60),
-            "new_stop == current_start must write start first"
-        );
-        assert!(ordering_is_safe((60, 80), (40, 60)));
+fn load_reverts(path: &Path) -> Option<Vec<SavedRevert>> {
+    let text = fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&text) {
+        Ok(saved) => Some(saved),
+        Err(e) => {
+            eprintln!("[Battery] Ignoring unreadable {}: {}", path.display(), e);
+            let _ = fs::remove_file(path);
+            None
+        }
     }
+}
 
-    #[test]
-    fn lowering_thresholds_writes_start_first() {
-        assert!(write_start_first(60, 50));
-        assert!(ordering_is_safe((60, 80), (30, 50)));
-    }
+/// Counts behaviour changes, so a timer can tell it has been overtaken.
+static BEHAVIOUR_CHANGES: AtomicU64 = AtomicU64::new(0);
 
-    #[test]
-    fn raising_thresholds_writes_stop_first() {
-        assert!(!write_start_first(40, 90));
-        assert!(ordering_is_safe((40, 60), (70, 90)));
-    }
+/// At most one timed behaviour per battery: a later change to that battery
+/// replaces it, and a change to another leaves it alone.
+static PENDING_REVERTS: Mutex<BTreeMap<String, PendingRevert>> = Mutex::new(BTreeMap::new());
 
-    /// Exhaustive sweep over every legal current/target pair. Any ordering rule
-    /// that admits an illegal intermediate state fails here.
-    #[test]
-    fn no_transition_passes_through_an_illegal_state() {
-        for cur_start in 0..=100u8 {
-            for cur_stop in (cur_start + 1)..=100u8 {
+fn lock_pending() -> std::sync::MutexGuard<'static, BTreeMap<String, PendingRevert>> {
+    PENDING_REVERTS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn next_change() -> u64 {
+    BEHAVIOUR_CHANGES.fetch_add(1, Ordering::SeqCst) + 1
+}
+
+/// Bring the file at `path` in line with the reverts kept there, removing it
+/// once there are none.
+fn save_reverts(path: &Path, pending: &BTreeMap<String, PendingRevert>) {
+    let now = Instant::now();
+    let saved: Vec<SavedRevert> = pending
+        .values()
+        .filter(|p| p.state_path.as_deref() == Some(path))
+        .map(|p| SavedRevert {
+            behaviour: p.behaviour,
+            battery: p.battery.clone(),
+            until: now_secs() + p.at.saturating_duration_since(now).as_secs(),
+        })
+        .collect();
+    let result = if saved.is_empty() {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    } else {
+        serde_json::to_string_pretty(&saved)
+            .map_err(std::io::Error::other)
+            .and_then(|json| fs::write(path, json))
+    };
+    if let Err(e) = result {
+        eprintln!("[Battery] Could not record {}: {}", path.display(), e);
+    }
+}
+
+/// Take the countdowns of `batteries` out, on disk too, and hand them back:
+/// they have been made, or overtaken.
+fn take_reverts(batteries: &[String]) -> Vec<PendingRevert> {
+    let mut pending = lock_pending();
+    let taken: Vec<PendingRevert> = batteries
+        .iter()
+        .filter_map(|battery| pending.remove(battery))
+        .collect();
+    let paths: BTreeSet<&PathBuf> = taken.iter().filter_map(|p| p.state_path.as_ref()).collect();
+    for path in paths {
+        save_reverts(path, &pending);
+    }
+    taken
+}
+
+/// Take one battery's revert, if it is still the one `change` set.
+fn take_pending(battery: &str, change: u64) -> Option<PendingRevert> {
+    if lock_pending().get(battery).map(|p| p.change) != Some(change) {
+        return None;
+    }
+    take_reverts(&[battery.to_string()]).pop()
+}
+
+/// Count down again what a change took out but never got to replace.
+fn restore_reverts(taken: Vec<PendingRevert>) {
+    for pending in taken {
+        let left = pending.at.saturating_duration_since(Instant::now());
+        revert_after(left, pending);
+    }
+}
+
+/// Whether a revert still applies: the battery is still in the behaviour it
+/// set. One that something else has moved on is left alone.
+fn still_in(pending: &PendingRevert) -> bool {
+    behaviour_targets(Some(&pending.battery))
+        .unwrap_or_default()
+        .iter()
+        .any(|(_, path)| read_behaviour(path).0 == Some(pending.behaviour))
+}
+
+/// Set how the charger treats the named battery, or every battery with the
+/// control when `battery` is omitted.
+///
+/// `limit_secs` makes the change temporary: the battery goes back to `auto`
+/// when it runs out or when the app exits, whichever is first. A later change
+/// to the same battery cancels its countdown once written; one that fails
+/// leaves it running. There is no firmware watchdog for this, so
+/// the app is what ends it; the deadline is kept on disk, so a crash or a
+/// reboot in between ends it at the next start.
+#[tauri::command]
+pub async fn set_charge_behaviour(
+    behaviour: String,
+    battery: Option<String>,
+    limit_secs: Option<u64>,
+) -> ApiResponse<String> {
+    let behaviour = match validate_behaviour_request(&behaviour, limit_secs) {
+        Ok(behaviour) => behaviour,
+        Err(e) => return ApiResponse::err(Error::validation(e)),
+    };
+    if calibration_running() {
+        return ApiResponse::err(Error::validation(CALIBRATION_HOLDS_CHARGING));
+    }
+    let targets: Vec<String> = match behaviour_targets(battery.as_deref()) {
+        Ok(targets) => targets.into_iter().map(|(name, _)| name).collect(),
+        Err(e) => return ApiResponse::err(e),
+    };
+    if let Err(e) = validate_charge_behaviour(behaviour, battery.as_deref()) {
+        return ApiResponse::err(Error::validation(e));
+    }
+
+    // Taken out first so none runs out mid-write, and put back if the write
+    // does not happen: the old behaviour is still in place and still timed.
+    let overtaken = take_reverts(&targets);
+    if let Err(e) = apply_charge_behaviour(behaviour, battery).await {
+        restore_reverts(overtaken);
+        return ApiResponse::err(e);
+    }
+    match limit_secs {
+        Some(secs) => {
+            let limit = Duration::from_secs(secs);
+            for battery in targets {
+                revert_after(
+                    limit,
+                    PendingRevert {
+                        change: next_change(),
+                        behaviour,
+                        battery,
+                        at: Instant::now() + limit,
+                        state_path: revert_state_path(),
+                    },
+                );
+            }
+            ApiResponse::ok(format!(
+                "Charge behaviour {} for {}s, then back to auto",
+                behaviour, secs
+            ))
+        }
+        None => ApiResponse::ok(format!("Charge behaviour set to {}", behaviour)),
+    }
+}
+
+/// Write a validated behaviour, elevating if needed.
+async fn apply_charge_behaviour(
+    behaviour: ChargeBehaviour,
+    battery: Option<String>,
+) -> Result<(), Error> {
+    match write_charge_behaviour(behaviour, battery.as_deref()) {
+        Ok(()) => return Ok(()),
+        Err(e) if hardware_root::is_simulated() => {
+            return Err(Error::simulated(format!(
+                "Simulated hardware rejected the change: {}",
+                e
+            )))
+        }
+        Err(_) => {}
+    }
+    let operation = crate::helper::Operation::ChargeBehaviour { behaviour, battery };
+    crate::helper::run(&operation).await.map(|_| ())
+}
+
+/// Put a battery back to `auto` once its timed behaviour runs out, unless
+/// another change has been made to it since.
+fn revert_after(limit: Duration, pending: PendingRevert) {
+    let change = pending.change;
+    let battery = pending.battery.clone();
+    {
+        let mut reverts = lock_pending();
+        let path = pending.state_path.clone();
+        reverts.insert(battery.clone(), pending);
+        if let Some(path) = path {
+            save_reverts(&path, &reverts);
+        }
+    }
+    tokio::spawn(async move {
+        tokio::time::sleep(limit).await;
+        let Some(pending) = take_pending(&battery, change) else {
+            return;
+        };
+        if !still_in(&pending) {
+            return;
+        }
+        match apply_charge_behaviour(ChargeBehaviour::Auto, Some(battery.clone())).await {
+            Ok(()) => println!(
+                "[Battery] {} time limit reached; {} back to auto",
+                pending.behaviour, battery
+            ),
+            Err(e) => eprintln!("[Battery] FAILED to return {} to auto: {}", battery, e),
+        }
+    });
+}
+
+/// Pick up a timed behaviour that a crash or a reboot left running: end it
+/// now if its time is up, otherwise count down what is left. Called once at
+/// startup.
+///
+/// An interrupted calibration takes precedence, as it would have if started
+/// while the countdown ran: it holds the behaviour itself.
+pub fn resume_timed_charge_behaviour() {
+    let Some(state_path) = revert_state_path() else {
+        return;
+    };
+    if calibration_state_path().is_some_and(|path| path.exists()) {
+        let _ = fs::remove_file(&state_path);
+        return;
+    }
+    tauri::async_runtime::spawn(async move { resume_revert(state_path) });
+}
+
+/// [`resume_timed_charge_behaviour`], from a given file.
+fn resume_revert(state_path: PathBuf) {
+    let Some(reverts) = load_reverts(&state_path) else {
+        return;
+    };
+    for saved in reverts {
+        let left = Duration::from_secs(saved.until.saturating_sub(now_secs()));
+        println!(
+            "[Battery] Resuming a timed {} on {}: {}s left",
+            saved.behaviour,
+            saved.battery,
+            left.as_secs()
+        );
+        revert_after(
+            left,
+            PendingRevert {
+                change: next_change(),
+                behaviour: saved.behaviour,
+                battery: saved.battery,
+                at: Instant::now() + left,
+                state_path: Some(state_path.clone()),
+            },
+        );
+    }
+}
+
+/// [`apply_charge_behaviour`] for one battery, for the exit handler.
+fn write_charge_behaviour_blocking(
+    behaviour: ChargeBehaviour,
+    battery: &str,
+) -> Result<(), String> {
+    match write_charge_behaviour(behaviour, Some(battery)) {
+        Err(_) if !hardware_root::is_simulated() => {
+            crate::helper::run_blocking(&crate::helper::Operation::ChargeBehaviour {
+                behaviour,
+                battery: Some(battery.to_string()),
+            })
+            .map(|_| ())
+            .map_err(String::from)
+        }
+        result => result,
+    }
+}
+
+/// End a timed behaviour now, for the app exit handler. Once this process is
+/// gone nothing is left to end it, and a battery left inhibited would not
+/// charge again until someone noticed.
+pub fn end_timed_charge_behaviour_blocking() {
+    let batteries: Vec<String> = lock_pending().keys().cloned().collect();
+    for pending in take_reverts(&batteries) {
+        if !still_in(&pending) {
+            continue;
+        }
+        let battery = &pending.battery;
+        match write_charge_behaviour_blocking(ChargeBehaviour::Auto, battery) {
+            Ok(()) => println!("[Battery] {} returned to auto on exit", battery),
+            Err(e) => eprintln!(
+                "[Battery] Could not return {} to auto on exit: {}",
+                battery, e
+            ),
+        }
+    }
+}
+
+/// Told to anyone who tries to change the thresholds or the charge behaviour
+/// while a calibration holds them.
+const CALIBRATION_HOLDS_CHARGING: &str =
+    "A battery calibration is running. Cancel it first; it puts your thresholds back when it stops.";
+
+/// The charge a calibration discharges down to unless told otherwise.
+pub const DEFAULT_CALIBRATION_FLOOR: u8 = 5;
+
+/// The floors a calibration accepts. Much lower and the machine can shut down
+/// before the discharge is stopped; much higher and the gauge sees too little
+/// of the range to learn anything.
+pub const CALIBRATION_FLOORS: std::ops::RangeInclusive<u8> = 3..=20;
+
+/// Held for the whole calibration, so the recharge runs all the way to full.
+const CALIBRATION_THRESHOLDS: BatteryThresholds = BatteryThresholds {
+    start: 96,
+    stop: 100,
+};
+
+/// How often a running calibration reads the battery. A discharge takes hours.
+const CALIBRATION_POLL: Duration = Duration::from_secs(30);
+
+/// A calibration found on disk that started longer ago than this is ended
+/// rather than resumed: too much may have happened to the battery since.
+const CALIBRATION_RESUME_LIMIT: Duration = Duration::from_secs(2 * 24 * 60 * 60);
+
+const CALIBRATION_STATE_FILE: &str = "battery_calibration.json";
+
+/// Where a calibration stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CalibrationPhase {
+    /// On AC, forced to discharge down to the floor.
+    Discharging,
+    /// Charging back to full.
+    Charging,
+    Complete,
+    Cancelled,
+    /// Stopped by an error; `message` says which.
+    Failed,
+}
+
+impl CalibrationPhase {
+    pub fn is_finished(self) -> bool {
+        !matches!(self, Self::Discharging | Self::Charging)
+    }
+}
+
+/// A calibration, as persisted and as sent with each
+/// `battery-calibration-progress` event.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatteryCalibration {
+    pub battery: String,
+    pub phase: CalibrationPhase,
+    /// The charge, in percent, the discharge runs down to.
+    pub floor: u8,
+    /// The user's thresholds, put back when the calibration ends.
+    pub saved: BatteryThresholds,
+    /// The charge at the last reading.
+    pub capacity: u8,
+    /// Unix seconds.
+    pub started_at: u64,
+    pub message: Option<String>,
+    /// Unix seconds the current phase began; see [`phase_limit`]. Files
+    /// written before phases were timed count from `started_at`.
+    #[serde(default)]
+    pub phase_started_at: u64,
+    /// The process running it, so another -- the CLI, a second instance -- can
+    /// tell from the file that the battery is held.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<ProcessIdentity>,
+}
+
+/// A process, told apart from whatever is given its pid later: after a
+/// reboot the boot differs, and within one boot the start time does.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessIdentity {
+    pub pid: u32,
+    pub boot_id: String,
+    /// Clock ticks after boot that it started.
+    pub start_time: u64,
+}
+
+impl ProcessIdentity {
+    fn of(pid: u32) -> Option<Self> {
+        let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        let boot_id = fs::read_to_string("/proc/sys/kernel/random/boot_id").ok()?;
+        Some(Self {
+            pid,
+            boot_id: boot_id.trim().to_string(),
+            start_time: parse_start_time(&stat)?,
+        })
+    }
+
+    fn current() -> Option<Self> {
+        Self::of(std::process::id())
+    }
+
+    /// Whether it still runs: the pid is taken, in this boot, by a process
+    /// that started when it did.
+    fn is_alive(&self) -> bool {
+        Self::of(self.pid).is_some_and(|now| now == *self)
+    }
+}
+
+/// The start time in a `/proc/<pid>/stat` line: field 22. The command name,
+/// field 2, is in parentheses and may hold spaces and parentheses itself, so
+/// fields are counted from the last `)`.
+fn parse_start_time(stat: &str) -> Option<u64> {
+    let after_name = &stat[stat.rfind(')')? + 1..];
+    after_name.split_whitespace().nth(22 - 3)?.parse().ok()
+}
+
+/// The phase a reading moves a calibration to.
+fn next_phase(phase: CalibrationPhase, floor: u8, capacity: u8, status: &str) -> CalibrationPhase {
+    match phase {
+        CalibrationPhase::Discharging if capacity <= floor => CalibrationPhase::Charging,
+        CalibrationPhase::Charging if status == "Full" || capacity >= 100 => {
+            CalibrationPhase::Complete
+        }
+        phase => phase,
+    }
+}
+
+/// How long a phase may take before the calibration gives up on it. A forced
+/// discharge draws what the machine does, so even a large battery at idle
+/// empties within the first; the second is several full charges' worth. Past
+/// either, the firmware is not doing what it was told.
+fn phase_limit(phase: CalibrationPhase) -> Option<Duration> {
+    match phase {
+        CalibrationPhase::Discharging => Some(Duration::from_secs(12 * 60 * 60)),
+        CalibrationPhase::Charging => Some(Duration::from_secs(6 * 60 * 60)),
+        _ => None,
+    }
+}
+
+static CALIBRATING: AtomicBool = AtomicBool::new(false);
+static CALIBRATION_CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// The running calibration, or the last one to end, as last reported.
+static CALIBRATION: Mutex<Option<BatteryCalibration>> = Mutex::new(None);
+
+fn lock_calibration() -> std::sync::MutexGuard<'static, Option<BatteryCalibration>> {
+    CALIBRATION.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Whether a calibration is running, in this process or another. The CLI
+/// asks too, and must not change what the app's calibration holds.
+pub fn calibration_running() -> bool {
+    CALIBRATING.load(Ordering::SeqCst)
+        || calibration_state_path().is_some_and(|path| running_elsewhere(&path))
+}
+
+/// Whether the calibration recorded at `path` is held by another live
+/// process. One whose process is gone was interrupted, and is the next
+/// start's to resume -- including after a reboot or a crash that left its
+/// pid to something else.
+fn running_elsewhere(path: &Path) -> bool {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|text| serde_json::from_str::<BatteryCalibration>(&text).ok())
+        .filter(|calibration| !calibration.phase.is_finished())
+        .and_then(|calibration| calibration.owner)
+        .is_some_and(|owner| owner.pid != std::process::id() && owner.is_alive())
+}
+
+/// Holds [`CALIBRATING`] for as long as a calibration runs, so only one can.
+struct CalibrationRun;
+
+impl CalibrationRun {
+    fn claim() -> Option<Self> {
+        // Not `then_some`: a guard built for a failed claim would release the
+        // running calibration's flag as it dropped.
+        if CALIBRATING.swap(true, Ordering::SeqCst) {
+            None
+        } else {
+            Some(CalibrationRun)
+        }
+    }
+}
+
+impl Drop for CalibrationRun {
+    fn drop(&mut self) {
+        CALIBRATING.store(false, Ordering::SeqCst);
+    }
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Kept on disk for as long as a calibration runs, so one interrupted by a
+/// crash or a reboot is picked up at the next start.
+fn calibration_state_path() -> Option<PathBuf> {
+    crate::sync::get_config_dir()
+        .ok()
+        .map(|dir| dir.join(CALIBRATION_STATE_FILE))
+}
+
+fn load_calibration(path: &Path) -> Option<BatteryCalibration> {
+    let text = fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&text) {
+        Ok(calibration) => Some(calibration),
+        Err(e) => {
+            eprintln!("[Battery] Ignoring unreadable {}: {}", path.display(), e);
+            let _ = fs::remove_file(path);
+            None
+        }
+    }
+}
+
+/// Record where a calibration stands: in memory, on disk while it runs, and
+/// to whoever is listening.
+fn report(
+    calibration: &BatteryCalibration,
+    state_path: Option<&Path>,
+    on_progress: &impl Fn(&BatteryCalibration),
+) {
+    *lock_calibration() = Some(calibration.clone());
+    if let Some(path) = state_path {
+        let saved = if calibration.phase.is_finished() {
+            fs::remove_file(path).or_else(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => Ok(()),
+                _ => Err(e),
+            })
+        } else {
+            serde_json::to_string_pretty(calibration)
+                .map_err(std::io::Error::other)
+                .and_then(|json| fs::write(path, json))
+        };
+        if let Err(e) = saved {
+            eprintln!("[Battery] Could not update {}: {}", path.display(), e);
+        }
+    }
+    on_progress(calibration);
+}
+
+/// Whether a mains adapter is plugged in and supplying power.
+fn on_ac() -> bool {
+    let Ok(entries) = hardware_root::read_dir(POWER_SUPPLY_DIR) else {
+        return false;
+    };
+    entries.flatten().any(|e| {
+        let dir = format!("{}/{}", POWER_SUPPLY_DIR, e.file_name().to_string_lossy());
+        let attr = |name: &str| {
+            hardware_root::read_to_string(&format!("{}/{}", dir, name))
+                .map(|s| s.trim().to_string())
+                .unwrap_or_default()
+        };
+        attr("type") == "Mains" && attr("online") == "1"
+    })
+}
+
+/// Check a calibration can run on the named battery, or on the first that can
+/// be calibrated, and describe it. Changes nothing.
+fn plan_calibration(battery: Option<&str>, floor: u8) -> Result<BatteryCalibration, Error> {
+    if !CALIBRATION_FLOORS.contains(&floor) {
+        return Err(Error::validation(format!(
+            "Calibration floor must be between {}% and {}%",
+            CALIBRATION_FLOORS.start(),
+            CALIBRATION_FLOORS.end()
+        )));
+    }
+    // Needs both halves: thresholds to lift, and a charger that can be told
+    // to discharge while plugged in.
+    let target = threshold_targets(battery)?
+        .into_iter()
+        .find(|paths| {
+            validate_charge_behaviour(ChargeBehaviour::ForceDischarge, Some(&paths.battery)).is_ok()
+        })
+        .ok_or_else(|| {
+            Error::hardware_absent(
+                "Calibration needs a battery that can be forced to discharge (Linux 5.17 or later)",
+            )
+        })?;
+    if !on_ac() {
+        return Err(Error::validation(
+            "Plug in the AC adapter first: the calibration discharges on AC, then recharges",
+        ));
+    }
+    let dir = format!("{}/{}", POWER_SUPPLY_DIR, target.battery);
+    let capacity = read_battery_info(&dir).map(|info| info.capacity)?;
+    Ok(BatteryCalibration {
+        saved: read_thresholds(&target),
+        battery: target.battery,
+        phase: CalibrationPhase::Discharging,
+        floor,
+        capacity,
+        started_at: now_secs(),
+        message: None,
+        phase_started_at: now_secs(),
+        owner: ProcessIdentity::current(),
+    })
+}
+
+/// Thresholds for the calibrated battery. With the daemon attached they go
+/// through it, so it does not put the old ones back a minute later.
+async fn calibration_thresholds(thresholds: BatteryThresholds, battery: &str) -> Result<(), Error> {
+    match apply_thresholds(thresholds.start, thresholds.stop, Some(battery.to_string()))
+        .await
+        .failure()
+    {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Put the battery in the state the calibration's phase holds it in. A
+/// finished phase puts back what the calibration changed.
+async fn enter_phase(calibration: &BatteryCalibration) -> Result<(), Error> {
+    // The battery's timed behaviour, if any, is overridden from here on.
+    take_reverts(std::slice::from_ref(&calibration.battery));
+    let battery = Some(calibration.battery.clone());
+    let behaviour = match calibration.phase {
+        CalibrationPhase::Discharging => ChargeBehaviour::ForceDischarge,
+        CalibrationPhase::Charging => ChargeBehaviour::Auto,
+        _ => {
+            // Both are tried even if one fails.
+            let behaviour = apply_charge_behaviour(ChargeBehaviour::Auto, battery).await;
+            let thresholds = calibration_thresholds(calibration.saved, &calibration.battery).await;
+            return behaviour.and(thresholds);
+        }
+    };
+    calibration_thresholds(CALIBRATION_THRESHOLDS, &calibration.battery).await?;
+    apply_charge_behaviour(behaviour, battery).await
+}
+
+/// End a calibration in `phase`, putting back what it changed.
+async fn finish(
+    mut calibration: BatteryCalibration,
+    phase: CalibrationPhase,
+    message: Option<String>,
+) -> BatteryCalibration {
+    calibration.phase = phase;
+    calibration.message = message;
+    if let Err(e) = enter_phase(&calibration).await {
+        eprintln!(
+            "[Battery] FAILED to restore {} after calibration: {}",
+            calibration.battery, e
+        );
+        calibration.phase = CalibrationPhase::Failed;
+        calibration.message = Some(format!("Could not restore the thresholds: {}", e));
+    }
+    calibration
+}
+
+/// Sleep for `poll`, waking early for a cancel.
+async fn calibration_wait(poll: Duration) {
+    let step = Duration::from_millis(250).min(poll);
+    let until = Instant::now() + poll;
+    while Instant::now() < until && !CALIBRATION_CANCELLED.load(Ordering::SeqCst) {
+        tokio::time::sleep(step).await;
+    }
+}
+
+/// Claim the calibration, record it and put the battery into its first phase.
+async fn begin_calibration(
+    battery: Option<&str>,
+    floor: u8,
+    state_path: Option<&Path>,
+) -> Result<(BatteryCalibration, CalibrationRun), Error> {
+    let already = || Error::validation("A battery calibration is already running");
+    let run = CalibrationRun::claim().ok_or_else(already)?;
+    if state_path.is_some_and(running_elsewhere) {
+        return Err(already());
+    }
+    let calibration = plan_calibration(battery, floor)?;
+    CALIBRATION_CANCELLED.store(false, Ordering::SeqCst);
+    // Recorded before anything is changed, so a crash from here on is undone
+    // at the next start.
+    report(&calibration, state_path, &|_: &BatteryCalibration| {});
+    if let Err(e) = enter_phase(&calibration).await {
+        let message = Some(format!("Could not start: {}", e));
+        report(
+            &finish(calibration, CalibrationPhase::Failed, message).await,
+            state_path,
+            &|_: &BatteryCalibration| {},
+        );
+        return Err(e);
+    }
+    println!(
+        "[Battery] Calibrating {}: discharging from {}% to {}%",
+        calibration.battery, calibration.capacity, floor
+    );
+    Ok((calibration, run))
+}
+
+/// Run a calibration from the phase it is in until it ends, reading the
+/// battery every `poll`. The battery must already be in that phase's state.
+async fn run_calibration(
+    mut calibration: BatteryCalibration,
+    _run: CalibrationRun,
+    poll: Duration,
+    state_path: Option<PathBuf>,
+    on_progress: impl Fn(&BatteryCalibration),
+) -> BatteryCalibration {
+    let state_path = state_path.as_deref();
+    report(&calibration, state_path, &on_progress);
+    while !calibration.phase.is_finished() {
+        calibration_wait(poll).await;
+        if CALIBRATION_CANCELLED.load(Ordering::SeqCst) {
+            calibration = finish(calibration, CalibrationPhase::Cancelled, None).await;
+        } else {
+            let info = battery_dirs()
+                .into_iter()
+                .find(|dir| battery_name(dir) == calibration.battery)
+                .and_then(|dir| read_battery_info(&dir).ok());
+            calibration = match info {
+                None => {
+                    let message = Some(format!("{} is gone", calibration.battery));
+                    finish(calibration, CalibrationPhase::Failed, message).await
+                }
+                Some(info) => step(calibration, &info).await,
+            };
+        }
+        report(&calibration, state_path, &on_progress);
+    }
+    println!(
+        "[Battery] Calibration of {} ended: {:?}",
+        calibration.battery, calibration.phase
+    );
+    calibration
+}
+
+/// Move a calibration on by one reading of its battery.
+async fn step(mut calibration: BatteryCalibration, info: &BatteryInfo) -> BatteryCalibration {
+    calibration.capacity = info.capacity;
+    let next = next_phase(
+        calibration.phase,
+        calibration.floor,
+        info.capacity,
+        &info.status,
+    );
+    if next == calibration.phase {
+        let began = match calibration.phase_started_at {
+            0 => calibration.started_at,
+            at => at,
+        };
+        let elapsed = Duration::from_secs(now_secs().saturating_sub(began));
+        return match phase_limit(next).filter(|limit| elapsed > *limit) {
+            Some(limit) => {
+                let message = Some(format!(
+                    "Still {} after {} hours",
+                    if next == CalibrationPhase::Discharging {
+                        "discharging"
+                    } else {
+                        "charging"
+                    },
+                    limit.as_secs() / 3600
+                ));
+                finish(calibration, CalibrationPhase::Failed, message).await
+            }
+            None => calibration,
+        };
+    }
+    if next.is_finished() {
+        return finish(calibration, next, None).await;
+    }
+    calibration.phase = next;
+    calibration.phase_started_at = now_secs();
+    println!(
+        "[Battery] Calibrating {}: {:?} at {}%",
+        calibration.battery, next, info.capacity
+    );
+    match enter_phase(&calibration).await {
+        Ok(()) => calibration,
+        Err(e) => {
+            let message = Some(e.to_string());
+            finish(calibration, CalibrationPhase::Failed, message).await
+        }
+    }
+}
+
+/// Pick a persisted calibration back up where it left off, or end it if it is
+/// too old or its battery is gone.
+async fn resume_calibration(
+    mut calibration: BatteryCalibration,
+    poll: Duration,
+    state_path: PathBuf,
+    on_progress: impl Fn(&BatteryCalibration),
+) -> Option<BatteryCalibration> {
+    let run = CalibrationRun::claim()?;
+    // Another instance has it; this one only watches.
+    if running_elsewhere(&state_path) {
+        return None;
+    }
+    calibration.owner = ProcessIdentity::current();
+    CALIBRATION_CANCELLED.store(false, Ordering::SeqCst);
+    let age = Duration::from_secs(now_secs().saturating_sub(calibration.started_at));
+    let present = battery_dirs()
+        .iter()
+        .any(|dir| battery_name(dir) == calibration.battery);
+    let stopped = if calibration.phase.is_finished() {
+        None
+    } else if age > CALIBRATION_RESUME_LIMIT {
+        Some("Too old to resume".to_string())
+    } else if !present {
+        Some(format!("{} is gone", calibration.battery))
+    } else {
+        match enter_phase(&calibration).await {
+            Ok(()) => {
+                println!(
+                    "[Battery] Resuming the calibration of {}: {:?}",
+                    calibration.battery, calibration.phase
+                );
+                return Some(
+                    run_calibration(calibration, run, poll, Some(state_path), on_progress).await,
+                );
+            }
+            Err(e) => Some(format!("Could not resume: {}", e)),
+        }
+    };
+    let calibration = finish(calibration, CalibrationPhase::Cancelled, stopped).await;
+    report(&calibration, Some(&state_path), &on_progress);
+    Some(calibration)
+}
+
+/// Calibrate a battery's charge gauge: on AC, discharge it to `floor` percent,
+/// charge it back to full, then put the user's thresholds back. It takes
+/// hours, and each step is sent as a `battery-calibration-progress` event.
+///
+/// Thresholds and charge behaviour are held until it ends, by completing,
+/// failing, being cancelled, or the app exiting. A phase that outlasts its
+/// [`phase_limit`] fails it. A reboot in between resumes it at the next start.
+#[tauri::command]
+pub async fn start_battery_calibration(
+    app: tauri::AppHandle,
+    battery: Option<String>,
+    floor: Option<u8>,
+) -> ApiResponse<BatteryCalibration> {
+    let floor = floor.unwrap_or(DEFAULT_CALIBRATION_FLOOR);
+    let state_path = calibration_state_path();
+    match begin_calibration(battery.as_deref(), floor, state_path.as_deref()).await {
+        Ok((calibration, run)) => {
+            let started = calibration.clone();
+            tauri::async_runtime::spawn(async move {
+                run_calibration(calibration, run, CALIBRATION_POLL, state_path, |c| {
+                    emit_calibration(&app, c)
+                })
+                .await;
+            });
+            ApiResponse::ok(started)
+        }
+        Err(e) => ApiResponse::err(e),
+    }
+}
+
+fn emit_calibration(app: &tauri::AppHandle, calibration: &BatteryCalibration) {
+    use tauri::Emitter;
+    let _ = app.emit_to("main", "battery-calibration-progress", calibration);
+}
+
+/// Stop a running calibration and put the thresholds back.
+#[tauri::command]
+pub async fn cancel_battery_calibration() {
+    CALIBRATION_CANCELLED.store(true, Ordering::SeqCst);
+}
+
+/// The running calibration, or the last one to end since the app started.
+#[tauri::command]
+pub fn get_battery_calibration() -> ApiResponse<Option<BatteryCalibration>> {
+    ApiResponse::ok(lock_calibration().clone())
+}
+
+/// Pick up a calibration that a crash or a reboot interrupted. Called once at
+/// startup.
+pub fn resume_battery_calibration(app: tauri::AppHandle) {
+    let Some(state_path) = calibration_state_path() else {
+        return;
+    };
+    let Some(calibration) = load_calibration(&state_path) else {
+        return;
+    };
+    tauri::async_runtime::spawn(async move {
+        resume_calibration(calibration, CALIBRATION_POLL, state_path, |c| {
+            emit_calibration(&app, c)
+        })
+        .await;
+    });
+}
+
+/// [`calibration_thresholds`], for the exit handler.
+fn write_thresholds_blocking(thresholds: BatteryThresholds, battery: &str) -> Result<(), String> {
+    let BatteryThresholds { start, stop } = thresholds;
+    if let Some(socket) = crate::daemon::attached() {
+        let request = crate::daemon::Request::SetBatteryThresholds {
+            start,
+            stop,
+            battery: Some(battery.to_string()),
+        };
+        return crate::daemon::request(socket, &request)
+            .map(|_| ())
+            .map_err(String::from);
+    }
+    match write_thresholds(start, stop, Some(battery)) {
+        Err(_) if !hardware_root::is_simulated() => {
+            crate::helper::run_blocking(&crate::helper::Operation::BatteryThresholds {
+                start,
+                stop,
+                battery: Some(battery.to_string()),
+            })
+            .map(|_| ())
+            .map_err(String::from)
+        }
+        result => result,
+    }
+}
+
+/// End a running calibration now, for the app exit handler. Unlike a reboot,
+/// quitting is taken as a choice to stop, and a battery left forced to
+/// discharge would run flat on AC.
+pub fn end_battery_calibration_blocking() {
+    if !CALIBRATING.load(Ordering::SeqCst) {
+        return;
+    }
+    CALIBRATION_CANCELLED.store(true, Ordering::SeqCst);
+    // Exit is signalled more than once; the first ends it.
+    let Some(mut calibration) = lock_calibration()
+        .clone()
+        .filter(|c| !c.phase.is_finished())
+    else {
+        return;
+    };
+    calibration.phase = CalibrationPhase::Cancelled;
+    calibration.message = Some("Ended on exit".to_string());
+    *lock_calibration() = Some(calibration.clone());
+    let battery = &calibration.battery;
+    let behaviour = write_charge_behaviour_blocking(ChargeBehaviour::Auto, battery);
+    let thresholds = write_thresholds_blocking(calibration.saved, battery);
+    match behaviour.and(thresholds) {
+        Ok(()) => println!("[Battery] Calibration of {} ended on exit", battery),
+        Err(e) => eprintln!("[Battery] Could not restore {} on exit: {}", battery, e),
+    }
+    if let Some(path) = calibration_state_path() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Model the firmware constraint: a write is rejected if it would leave
+    /// start >= stop, even for an instant. Replays the two writes in the chosen
+    /// order and reports whether every intermediate state stayed legal.
+    fn ordering_is_safe(current: (u8, u8), target: (u8, u8)) -> bool {
+        let (cur_start, cur_stop) = current;
+        let (new_start, new_stop) = target;
+
+        let states = if write_start_first(cur_start, new_stop) {
+            // write start, then stop
+            [(new_start, cur_stop), (new_start, new_stop)]
+        } else {
+            // write stop, then start
+            [(cur_start, new_stop), (new_start, new_stop)]
+        };
+
+        states.iter().all(|(s, e)| s < e)
+    }
+
+    /// The case the original ordering logic missed. With `new_stop < current_start`
+    /// this picks stop-first, producing the intermediate state (60, 60) — start
+    /// equal to stop, which the firmware rejects with the same "Invalid argument"
+    /// the ordering was introduced to prevent.
+    #[test]
+    fn boundary_new_stop_equals_current_start() {
+        assert!(
+            write_start_first(60, 60),
+            "new_stop == current_start must write start first"
+        );
+        assert!(ordering_is_safe((60, 80), (40, 60)));
+    }
+
+    #[test]
+    fn lowering_thresholds_writes_start_first() {
+        assert!(write_start_first(60, 50));
+        assert!(ordering_is_safe((60, 80), (30, 50)));
+    }
+
+    #[test]
+    fn raising_thresholds_writes_stop_first() {
+        assert!(!write_start_first(40, 90));
+        assert!(ordering_is_safe((40, 60), (70, 90)));
+    }
+
+    /// Exhaustive sweep over every legal current/target pair. Any ordering rule
+    /// that admits an illegal intermediate state fails here.
+    #[test]
+    fn no_transition_passes_through_an_illegal_state() {
+        for cur_start in 0..=100u8 {
+            for cur_stop in (cur_start + 1)..=100u8 {
                 for new_start in 0..=100u8 {
                     for new_stop in (new_start + 1)..=100u8 {
                         assert!(
@@ -424,3 +2016,988 @@ mod threshold_path_tests {
         }
     }
 }
+
+/// The battery page end to end, against a captured P1 Gen 4i.
+#[cfg(test)]
+mod fixture_tests {
+    use super::*;
+    use crate::hardware_root::testing::with_profile;
+
+    const P1: &str = "thinkpad-p1-gen-4i";
+    const BAT0_PATH: &str = "/sys/class/power_supply/BAT0";
+
+    #[test]
+    fn reads_the_captured_battery() {
+        let info = with_profile(P1, get_battery_info)
+            .data
+            .expect("fixture has BAT0");
+        assert_eq!(info.len(), 1, "the P1 has a single battery");
+
+        let bat = &info[0];
+        assert_eq!(bat.name, "BAT0");
+        assert_eq!(bat.status, "Not charging");
+        assert_eq!(bat.capacity, 79);
+        assert_eq!(bat.cycles, 100);
+        assert_eq!(bat.technology, "Li-poly");
+        // 75.09 Wh of a 90.09 Wh design.
+        assert_eq!(bat.health, 83);
+        assert!((bat.energy_full - 75.09).abs() < 0.01);
+    }
+
+    /// Existence is probed in the fixture, but the paths handed back are the
+    /// real sysfs ones: they are what gets written.
+    #[test]
+    fn threshold_paths_are_found_in_the_profile_but_point_at_real_sysfs() {
+        let (start, stop) = with_profile(P1, threshold_paths).expect("fixture has thresholds");
+        assert_eq!(start, format!("{}/{}", BAT0_PATH, THRESHOLD_ATTRS[0].0));
+        assert_eq!(stop, format!("{}/{}", BAT0_PATH, THRESHOLD_ATTRS[0].1));
+    }
+
+    #[test]
+    fn reads_the_captured_thresholds_and_power() {
+        let thresholds = with_profile(P1, get_battery_thresholds).data.unwrap();
+        assert_eq!((thresholds.start, thresholds.stop), (75, 80));
+
+        assert_eq!(with_profile(P1, get_power_consumption).data, Some(0.0));
+    }
+
+    /// Both spellings are one firmware setting, so a write to one must show up
+    /// in the other -- otherwise a reader that picked the other pair would see
+    /// a stale value the hardware never reports.
+    #[test]
+    fn simulated_threshold_write_shows_under_both_spellings() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |root| {
+            let (_, stop) = threshold_paths().unwrap();
+            hardware_root::write(&stop, "90").unwrap();
+
+            let bat = root.join(BAT0_PATH.trim_start_matches('/'));
+            for (_, stop_attr) in THRESHOLD_ATTRS {
+                let value = fs::read_to_string(bat.join(stop_attr)).unwrap();
+                assert_eq!(value.trim(), "90", "{}", stop_attr);
+            }
+        });
+    }
+
+    #[test]
+    fn simulated_thresholds_refuse_start_at_or_above_stop() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |_| {
+            let (start, stop) = threshold_paths().unwrap();
+            assert!(hardware_root::write(&start, "80").is_err());
+            assert!(hardware_root::write(&stop, "75").is_err());
+            assert!(hardware_root::write(&stop, "101").is_err());
+            hardware_root::write(&start, "79").unwrap();
+        });
+    }
+
+    /// Write a power supply directory into a scratch profile.
+    fn add_supply(root: &std::path::Path, name: &str, attrs: &[(&str, &str)]) {
+        let dir = root.join("sys/class/power_supply").join(name);
+        fs::create_dir_all(&dir).unwrap();
+        for (attr, value) in attrs {
+            fs::write(dir.join(attr), format!("{}\n", value)).unwrap();
+        }
+    }
+
+    /// A second battery next to BAT0, plus a wireless mouse reporting its own
+    /// charge -- a Battery too, but not one of the machine's.
+    fn with_second_battery(root: &std::path::Path) {
+        add_supply(
+            root,
+            "BAT1",
+            &[
+                ("type", "Battery"),
+                ("status", "Discharging"),
+                ("capacity", "50"),
+                ("energy_now", "10000000"),
+                ("energy_full", "20000000"),
+                ("energy_full_design", "24000000"),
+                ("power_now", "5000000"),
+                ("charge_control_start_threshold", "40"),
+                ("charge_control_end_threshold", "60"),
+            ],
+        );
+        add_supply(
+            root,
+            "hid-00:11:22:33:44:55-battery",
+            &[("type", "Battery"), ("scope", "Device"), ("capacity", "90")],
+        );
+    }
+
+    #[test]
+    fn discovery_skips_mains_usb_and_peripheral_batteries() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |root| {
+            assert_eq!(battery_dirs(), vec![BAT0_PATH.to_string()]);
+            with_second_battery(root);
+            assert_eq!(
+                battery_dirs(),
+                vec![
+                    BAT0_PATH.to_string(),
+                    "/sys/class/power_supply/BAT1".to_string()
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn each_battery_reports_its_own_thresholds() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |root| {
+            with_second_battery(root);
+            let info = get_battery_info().data.unwrap();
+            let thresholds: Vec<_> = info
+                .iter()
+                .map(|b| (b.name.as_str(), b.thresholds.map(|t| (t.start, t.stop))))
+                .collect();
+            assert_eq!(
+                thresholds,
+                vec![("BAT0", Some((75, 80))), ("BAT1", Some((40, 60)))]
+            );
+            // The primary battery is still the one the single-pair API reports.
+            let primary = get_battery_thresholds().data.unwrap();
+            assert_eq!((primary.start, primary.stop), (75, 80));
+        });
+    }
+
+    #[test]
+    fn a_named_write_touches_only_that_battery() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |root| {
+            with_second_battery(root);
+            let current = || -> Vec<(u8, u8)> {
+                all_threshold_paths()
+                    .iter()
+                    .map(|p| {
+                        let t = read_thresholds(p);
+                        (t.start, t.stop)
+                    })
+                    .collect()
+            };
+
+            write_thresholds(20, 30, Some("BAT1")).unwrap();
+            assert_eq!(current(), vec![(75, 80), (20, 30)]);
+
+            write_thresholds(50, 90, None).unwrap();
+            assert_eq!(current(), vec![(50, 90), (50, 90)]);
+
+            assert!(write_thresholds(50, 90, Some("BAT7")).is_err());
+            assert!(validate_battery("hid-00:11:22:33:44:55-battery").is_err());
+        });
+    }
+
+    /// The only battery is not always BAT0.
+    #[test]
+    fn a_machine_whose_only_battery_is_bat1_has_thresholds() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |root| {
+            fs::remove_file(root.join(BAT0_PATH.trim_start_matches('/'))).unwrap();
+            with_second_battery(root);
+            let (start, stop) = threshold_paths().expect("BAT1 has thresholds");
+            assert!(start.starts_with("/sys/class/power_supply/BAT1/"));
+            assert!(stop.starts_with("/sys/class/power_supply/BAT1/"));
+        });
+    }
+
+    #[test]
+    fn the_summary_adds_up_every_battery() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |root| {
+            with_second_battery(root);
+            let summary = get_battery_summary().data.unwrap();
+            assert_eq!(summary.batteries, 2);
+            assert_eq!(summary.status, "Discharging");
+            // 59.49 + 10 Wh of 75.09 + 20 Wh.
+            assert!((summary.energy_now - 69.49).abs() < 0.01);
+            assert!((summary.energy_full - 95.09).abs() < 0.01);
+            assert_eq!(summary.capacity, 73);
+            // BAT0 draws nothing; BAT1 draws 5 W.
+            assert!((summary.power - 5.0).abs() < 0.01);
+            assert!((get_power_consumption().data.unwrap() - 5.0).abs() < 0.01);
+            // 69.49 Wh at 5 W is just under 14 hours.
+            let seconds = summary.time_to_empty.expect("discharging");
+            assert!((50_000..50_100).contains(&seconds), "{}", seconds);
+            assert_eq!(summary.time_to_full, None);
+        });
+    }
+
+    #[test]
+    fn choices_split_into_the_active_one_and_all() {
+        assert_eq!(
+            parse_choices("[auto] inhibit-charge force-discharge\n"),
+            (
+                Some("auto"),
+                vec!["auto", "inhibit-charge", "force-discharge"]
+            )
+        );
+        assert_eq!(
+            parse_choices("auto [inhibit-charge]"),
+            (Some("inhibit-charge"), vec!["auto", "inhibit-charge"])
+        );
+        assert_eq!(parse_choices(""), (None, vec![]));
+    }
+
+    #[test]
+    fn reads_the_captured_charge_behaviour() {
+        let states = with_profile(P1, get_charge_behaviour).data.unwrap();
+        assert_eq!(states.len(), 1);
+        assert_eq!(states[0].battery, "BAT0");
+        assert_eq!(states[0].current, Some(ChargeBehaviour::Auto));
+        assert_eq!(states[0].available, ChargeBehaviour::ALL.to_vec());
+        assert_eq!(states[0].reverts_in, None);
+    }
+
+    #[test]
+    fn simulated_behaviour_write_moves_the_brackets() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |_| {
+            write_charge_behaviour(ChargeBehaviour::ForceDischarge, None).unwrap();
+            let path = format!("{}/{}", BAT0_PATH, CHARGE_BEHAVIOUR_ATTR);
+            assert_eq!(
+                hardware_root::read_to_string(&path).unwrap().trim(),
+                "auto inhibit-charge [force-discharge]"
+            );
+            assert!(hardware_root::write(&path, "discharge").is_err());
+        });
+    }
+
+    /// A battery is only offered what its firmware lists, and one without the
+    /// control is not offered anything.
+    #[test]
+    fn a_behaviour_must_be_one_the_battery_lists() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |root| {
+            with_second_battery(root);
+            add_supply(
+                root,
+                "BAT2",
+                &[
+                    ("type", "Battery"),
+                    ("charge_behaviour", "[auto] inhibit-charge"),
+                ],
+            );
+            assert!(validate_charge_behaviour(ChargeBehaviour::InhibitCharge, None).is_ok());
+            assert!(validate_charge_behaviour(ChargeBehaviour::ForceDischarge, None).is_err());
+            assert!(
+                validate_charge_behaviour(ChargeBehaviour::ForceDischarge, Some("BAT0")).is_ok()
+            );
+            assert!(validate_charge_behaviour(ChargeBehaviour::Auto, Some("BAT1")).is_err());
+        });
+    }
+
+    #[test]
+    fn calibration_moves_on_at_the_floor_and_at_full() {
+        use CalibrationPhase::*;
+        assert_eq!(next_phase(Discharging, 5, 6, "Discharging"), Discharging);
+        assert_eq!(next_phase(Discharging, 5, 5, "Discharging"), Charging);
+        assert_eq!(next_phase(Charging, 5, 99, "Charging"), Charging);
+        assert_eq!(next_phase(Charging, 5, 98, "Full"), Complete);
+        assert_eq!(next_phase(Charging, 5, 100, "Charging"), Complete);
+        assert_eq!(next_phase(Cancelled, 5, 0, "Discharging"), Cancelled);
+    }
+
+    #[test]
+    fn only_an_override_takes_a_time_limit() {
+        assert_eq!(
+            validate_behaviour_request("inhibit-charge", Some(3600)),
+            Ok(ChargeBehaviour::InhibitCharge)
+        );
+        assert_eq!(
+            validate_behaviour_request("auto", None),
+            Ok(ChargeBehaviour::Auto)
+        );
+        assert!(validate_behaviour_request("auto", Some(60)).is_err());
+        assert!(validate_behaviour_request("inhibit-charge", Some(0)).is_err());
+        assert!(
+            validate_behaviour_request("force-discharge", Some(MAX_CHARGE_BEHAVIOUR_SECS + 1))
+                .is_err()
+        );
+        assert!(validate_behaviour_request("[auto]", None).is_err());
+    }
+
+    /// A timed override ends on its own, and a later change cancels it.
+    #[test]
+    fn a_timed_behaviour_goes_back_to_auto() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |_| {
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            let current = || get_charge_behaviour().data.unwrap()[0].current;
+            let timed = || PendingRevert {
+                change: next_change(),
+                behaviour: ChargeBehaviour::InhibitCharge,
+                battery: "BAT0".into(),
+                at: Instant::now(),
+                state_path: None,
+            };
+            runtime.block_on(async {
+                write_charge_behaviour(ChargeBehaviour::InhibitCharge, None).unwrap();
+                revert_after(Duration::from_millis(50), timed());
+                assert_eq!(current(), Some(ChargeBehaviour::InhibitCharge));
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                assert_eq!(current(), Some(ChargeBehaviour::Auto));
+
+                write_charge_behaviour(ChargeBehaviour::InhibitCharge, None).unwrap();
+                revert_after(Duration::from_millis(50), timed());
+                take_reverts(&["BAT0".into()]);
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                assert_eq!(current(), Some(ChargeBehaviour::InhibitCharge));
+            });
+        });
+    }
+
+    /// Quitting ends a timed override that has not run out.
+    #[test]
+    fn exit_ends_a_timed_behaviour() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |_| {
+            write_charge_behaviour(ChargeBehaviour::ForceDischarge, None).unwrap();
+            lock_pending().insert(
+                "BAT0".into(),
+                PendingRevert {
+                    change: next_change(),
+                    behaviour: ChargeBehaviour::ForceDischarge,
+                    battery: "BAT0".into(),
+                    at: Instant::now() + Duration::from_secs(3600),
+                    state_path: None,
+                },
+            );
+            let state = &get_charge_behaviour().data.unwrap()[0];
+            assert!(state.reverts_in.is_some_and(|s| s > 3500));
+
+            end_timed_charge_behaviour_blocking();
+            let state = &get_charge_behaviour().data.unwrap()[0];
+            assert_eq!(state.current, Some(ChargeBehaviour::Auto));
+            assert_eq!(state.reverts_in, None);
+        });
+    }
+
+    /// A timed override outlives a crash on disk: one whose time is up ends
+    /// at the next start, and one with time left counts down the rest.
+    #[test]
+    fn a_persisted_timed_behaviour_ends_after_a_restart() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |root| {
+            let state = root.join(REVERT_STATE_FILE);
+            let save = |until| {
+                let saved = vec![SavedRevert {
+                    behaviour: ChargeBehaviour::InhibitCharge,
+                    battery: "BAT0".into(),
+                    until,
+                }];
+                fs::write(&state, serde_json::to_string(&saved).unwrap()).unwrap();
+            };
+            let current = || get_charge_behaviour().data.unwrap()[0].clone();
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime.block_on(async {
+                write_charge_behaviour(ChargeBehaviour::InhibitCharge, None).unwrap();
+                save(now_secs() - 60);
+                resume_revert(state.clone());
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                assert_eq!(current().current, Some(ChargeBehaviour::Auto));
+                assert!(!state.exists());
+
+                write_charge_behaviour(ChargeBehaviour::InhibitCharge, None).unwrap();
+                save(now_secs() + 3600);
+                resume_revert(state.clone());
+                assert!(current().reverts_in.is_some_and(|s| s > 3500));
+                assert!(state.exists());
+
+                // A later change overtakes it, on disk too.
+                take_reverts(&["BAT0".into()]);
+                assert!(!state.exists());
+            });
+        });
+    }
+
+    /// Each battery counts down on its own: changing one leaves the other's
+    /// timed behaviour running, in memory and on disk.
+    #[test]
+    fn a_change_to_one_battery_leaves_the_other_counting_down() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |root| {
+            with_second_battery(root);
+            let bat1 = root.join("sys/class/power_supply/BAT1/charge_behaviour");
+            fs::write(&bat1, "[auto] inhibit-charge force-discharge\n").unwrap();
+            let state = root.join(REVERT_STATE_FILE);
+            let reverts_in = |battery: &str| {
+                get_charge_behaviour()
+                    .data
+                    .unwrap()
+                    .into_iter()
+                    .find(|s| s.battery == battery)
+                    .and_then(|s| s.reverts_in)
+            };
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime.block_on(async {
+                for battery in ["BAT0", "BAT1"] {
+                    write_charge_behaviour(ChargeBehaviour::ForceDischarge, Some(battery)).unwrap();
+                    revert_after(
+                        Duration::from_secs(3600),
+                        PendingRevert {
+                            change: next_change(),
+                            behaviour: ChargeBehaviour::ForceDischarge,
+                            battery: battery.into(),
+                            at: Instant::now() + Duration::from_secs(3600),
+                            state_path: Some(state.clone()),
+                        },
+                    );
+                }
+                assert_eq!(load_reverts(&state).unwrap().len(), 2);
+
+                let response =
+                    set_charge_behaviour("inhibit-charge".into(), Some("BAT1".into()), None).await;
+                assert!(response.success, "{:?}", response.error);
+                assert!(reverts_in("BAT0").is_some_and(|s| s > 3500));
+                assert_eq!(reverts_in("BAT1"), None);
+                let saved = load_reverts(&state).unwrap();
+                assert_eq!(saved.len(), 1);
+                assert_eq!(saved[0].battery, "BAT0");
+
+                // Every battery at once cancels both.
+                let response = set_charge_behaviour("auto".into(), None, None).await;
+                assert!(response.success, "{:?}", response.error);
+                assert_eq!(reverts_in("BAT0"), None);
+                assert!(!state.exists());
+            });
+        });
+    }
+
+    /// A change that is never written leaves the timed behaviour it would
+    /// have replaced counting down, on disk too.
+    #[test]
+    fn a_failed_change_keeps_the_countdown() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |root| {
+            let state = root.join(REVERT_STATE_FILE);
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime.block_on(async {
+                write_charge_behaviour(ChargeBehaviour::ForceDischarge, None).unwrap();
+                revert_after(
+                    Duration::from_secs(3600),
+                    PendingRevert {
+                        change: next_change(),
+                        behaviour: ChargeBehaviour::ForceDischarge,
+                        battery: "BAT0".into(),
+                        at: Instant::now() + Duration::from_secs(3600),
+                        state_path: Some(state.clone()),
+                    },
+                );
+
+                // Without the marker, the scratch profile refuses every write.
+                fs::remove_file(root.join(crate::hardware_root::SCRATCH_MARKER)).unwrap();
+                let response =
+                    set_charge_behaviour("inhibit-charge".into(), Some("BAT0".into()), None).await;
+                assert!(!response.success);
+
+                let bat0 = &get_charge_behaviour().data.unwrap()[0];
+                assert_eq!(bat0.current, Some(ChargeBehaviour::ForceDischarge));
+                assert!(bat0.reverts_in.is_some_and(|s| s > 3500));
+                assert_eq!(load_reverts(&state).unwrap().len(), 1);
+                take_reverts(&["BAT0".into()]);
+            });
+        });
+    }
+
+    fn set_attr(root: &std::path::Path, attr: &str, value: &str) {
+        let path = root.join(BAT0_PATH.trim_start_matches('/')).join(attr);
+        fs::write(path, format!("{}\n", value)).unwrap();
+    }
+
+    fn current_thresholds() -> (u8, u8) {
+        let t = get_battery_thresholds().data.unwrap();
+        (t.start, t.stop)
+    }
+
+    /// Polls until the running calibration reaches `phase`.
+    async fn reach(phase: CalibrationPhase) {
+        for _ in 0..200 {
+            if lock_calibration().as_ref().map(|c| c.phase) == Some(phase) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("calibration never reached {:?}", phase);
+    }
+
+    /// Down to the floor with the thresholds lifted, back up to full, then the
+    /// user's thresholds put back and the state file gone.
+    #[test]
+    fn a_calibration_discharges_recharges_and_restores() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |root| {
+            let state = root.join(CALIBRATION_STATE_FILE);
+            let behaviour = || get_charge_behaviour().data.unwrap()[0].current;
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            let ended = runtime.block_on(async {
+                let (calibration, run) = begin_calibration(None, 5, Some(&state)).await.unwrap();
+                assert_eq!(calibration.battery, "BAT0");
+                assert_eq!(
+                    calibration.saved,
+                    BatteryThresholds {
+                        start: 75,
+                        stop: 80
+                    }
+                );
+                assert!(calibration_running());
+                assert!(state.exists(), "persisted before the first change");
+                assert_eq!(current_thresholds(), (96, 100));
+                assert_eq!(behaviour(), Some(ChargeBehaviour::ForceDischarge));
+
+                let seen = std::sync::Arc::new(Mutex::new(Vec::new()));
+                let log = seen.clone();
+                let task = tokio::spawn(run_calibration(
+                    calibration,
+                    run,
+                    Duration::from_millis(10),
+                    Some(state.clone()),
+                    move |c| log.lock().unwrap().push(c.phase),
+                ));
+
+                set_attr(root, "capacity", "4");
+                reach(CalibrationPhase::Charging).await;
+                assert_eq!(behaviour(), Some(ChargeBehaviour::Auto));
+                assert_eq!(current_thresholds(), (96, 100));
+                let persisted = load_calibration(&state).unwrap();
+                assert_eq!(persisted.phase, CalibrationPhase::Charging);
+
+                set_attr(root, "capacity", "100");
+                set_attr(root, "status", "Full");
+                let ended = task.await.unwrap();
+                assert!(seen.lock().unwrap().contains(&CalibrationPhase::Charging));
+                ended
+            });
+            assert_eq!(ended.phase, CalibrationPhase::Complete);
+            assert_eq!(ended.capacity, 100);
+            assert_eq!(current_thresholds(), (75, 80));
+            assert_eq!(behaviour(), Some(ChargeBehaviour::Auto));
+            assert!(!state.exists());
+            assert!(!calibration_running());
+        });
+    }
+
+    /// While one runs, the settings it holds cannot be changed underneath it;
+    /// a cancel puts them back.
+    #[test]
+    fn a_cancelled_calibration_restores_and_holds_until_then() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |root| {
+            let state = root.join(CALIBRATION_STATE_FILE);
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            let ended = runtime.block_on(async {
+                let (calibration, run) = begin_calibration(Some("BAT0"), 10, Some(&state))
+                    .await
+                    .unwrap();
+                assert!(begin_calibration(None, 10, None).await.is_err());
+                let held = set_battery_thresholds(40, 60, None).await;
+                assert_eq!(
+                    held.failure().unwrap().code,
+                    crate::error::ErrorCode::Validation
+                );
+                assert!(set_charge_behaviour("auto".into(), None, None)
+                    .await
+                    .failure()
+                    .is_some());
+
+                let task = tokio::spawn(run_calibration(
+                    calibration,
+                    run,
+                    Duration::from_secs(3600),
+                    Some(state.clone()),
+                    |_| {},
+                ));
+                cancel_battery_calibration().await;
+                task.await.unwrap()
+            });
+            assert_eq!(ended.phase, CalibrationPhase::Cancelled);
+            assert_eq!(current_thresholds(), (75, 80));
+            assert_eq!(
+                get_charge_behaviour().data.unwrap()[0].current,
+                Some(ChargeBehaviour::Auto)
+            );
+            assert!(!state.exists());
+            assert!(!calibration_running());
+        });
+    }
+
+    #[test]
+    fn a_calibration_needs_ac_and_a_sane_floor() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |root| {
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            for floor in [0, 2, 21, 100] {
+                let refused = runtime.block_on(begin_calibration(None, floor, None));
+                assert!(refused.is_err(), "floor {}", floor);
+            }
+            assert!(runtime
+                .block_on(begin_calibration(Some("BAT9"), 5, None))
+                .is_err());
+
+            let ac = root.join("sys/class/power_supply/AC/online");
+            fs::write(ac, "0\n").unwrap();
+            let refused = runtime.block_on(begin_calibration(None, 5, None));
+            assert_eq!(
+                refused.err().unwrap().code,
+                crate::error::ErrorCode::Validation
+            );
+            // Nothing was touched, and nothing is left claimed.
+            assert_eq!(current_thresholds(), (75, 80));
+            assert!(!calibration_running());
+        });
+    }
+
+    /// A calibration left on disk by a reboot carries on; one that is too old
+    /// is ended and the saved thresholds put back.
+    #[test]
+    fn a_persisted_calibration_resumes_or_restores() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |root| {
+            let state = root.join(CALIBRATION_STATE_FILE);
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            write_thresholds(96, 100, None).unwrap();
+            let interrupted = BatteryCalibration {
+                battery: "BAT0".into(),
+                phase: CalibrationPhase::Discharging,
+                floor: 5,
+                saved: BatteryThresholds {
+                    start: 50,
+                    stop: 70,
+                },
+                capacity: 60,
+                started_at: now_secs(),
+                message: None,
+                phase_started_at: now_secs(),
+                owner: None,
+            };
+
+            *lock_calibration() = None;
+            let resumed = runtime.block_on(async {
+                let task = tokio::spawn(resume_calibration(
+                    interrupted.clone(),
+                    Duration::from_millis(10),
+                    state.clone(),
+                    |_| {},
+                ));
+                reach(CalibrationPhase::Discharging).await;
+                assert_eq!(
+                    get_charge_behaviour().data.unwrap()[0].current,
+                    Some(ChargeBehaviour::ForceDischarge)
+                );
+                cancel_battery_calibration().await;
+                task.await.unwrap().unwrap()
+            });
+            assert_eq!(resumed.phase, CalibrationPhase::Cancelled);
+            assert_eq!(current_thresholds(), (50, 70));
+
+            write_thresholds(96, 100, None).unwrap();
+            let stale = BatteryCalibration {
+                started_at: now_secs() - CALIBRATION_RESUME_LIMIT.as_secs() - 1,
+                ..interrupted
+            };
+            let ended = runtime
+                .block_on(resume_calibration(
+                    stale,
+                    Duration::from_millis(10),
+                    state.clone(),
+                    |_| {},
+                ))
+                .unwrap();
+            assert_eq!(ended.phase, CalibrationPhase::Cancelled);
+            assert!(ended.message.is_some());
+            assert_eq!(current_thresholds(), (50, 70));
+            assert!(!state.exists());
+        });
+    }
+
+    /// A phase the firmware never finishes ends the calibration as failed,
+    /// with the user's thresholds back.
+    #[test]
+    fn a_phase_that_runs_too_long_fails_and_restores() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |_| {
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            let ended = runtime.block_on(async {
+                let (mut calibration, run) = begin_calibration(None, 5, None).await.unwrap();
+                let info = read_battery_info(BAT0_PATH).unwrap();
+                assert!(info.capacity > 5);
+
+                let within = step(calibration.clone(), &info).await;
+                assert_eq!(within.phase, CalibrationPhase::Discharging);
+
+                calibration.phase_started_at = now_secs() - 13 * 60 * 60;
+                let ended = step(calibration, &info).await;
+                drop(run);
+                ended
+            });
+            assert_eq!(ended.phase, CalibrationPhase::Failed);
+            assert!(ended.message.unwrap().contains("12 hours"));
+            assert_eq!(current_thresholds(), (75, 80));
+            assert_eq!(
+                get_charge_behaviour().data.unwrap()[0].current,
+                Some(ChargeBehaviour::Auto)
+            );
+        });
+    }
+
+    /// The state file says who runs the calibration, so a second process is
+    /// held off while that one lives, and only then.
+    #[test]
+    fn a_calibration_in_another_process_is_seen_from_its_file() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |root| {
+            let state = root.join(CALIBRATION_STATE_FILE);
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            let mut calibration = runtime.block_on(async {
+                let (calibration, run) = begin_calibration(None, 5, None).await.unwrap();
+                drop(run);
+                calibration
+            });
+            let record = |calibration: &BatteryCalibration| {
+                fs::write(&state, serde_json::to_string(calibration).unwrap()).unwrap()
+            };
+
+            // pid 1 always lives, and is never this test.
+            let init = ProcessIdentity::of(1).unwrap();
+            calibration.owner = Some(init.clone());
+            record(&calibration);
+            assert!(running_elsewhere(&state));
+            assert!(runtime
+                .block_on(begin_calibration(None, 5, Some(&state)))
+                .is_err());
+
+            calibration.owner = ProcessIdentity::current();
+            record(&calibration);
+            assert!(!running_elsewhere(&state));
+
+            calibration.owner = Some(ProcessIdentity {
+                pid: u32::MAX,
+                ..init.clone()
+            });
+            record(&calibration);
+            assert!(!running_elsewhere(&state), "its process is gone");
+
+            // The pid lives on, but in another process: after a reboot, or
+            // handed on within this boot.
+            for reused in [
+                ProcessIdentity {
+                    boot_id: "00000000-0000-0000-0000-000000000000".into(),
+                    ..init.clone()
+                },
+                ProcessIdentity {
+                    start_time: init.start_time + 1,
+                    ..init.clone()
+                },
+            ] {
+                calibration.owner = Some(reused);
+                record(&calibration);
+                assert!(!running_elsewhere(&state), "{:?}", calibration.owner);
+            }
+
+            calibration.owner = Some(init);
+            calibration.phase = CalibrationPhase::Complete;
+            record(&calibration);
+            assert!(!running_elsewhere(&state));
+        });
+    }
+
+    #[test]
+    fn the_start_time_is_found_past_any_command_name() {
+        let fields = "S 1 1 1 0 -1 4194560 100 0 0 0 1 2 0 0 20 0 1 0 4242 1000 10";
+        assert_eq!(
+            parse_start_time(&format!("1 (systemd) {}", fields)),
+            Some(4242)
+        );
+        assert_eq!(
+            parse_start_time(&format!("77 (a (b) c) {}", fields)),
+            Some(4242)
+        );
+        assert_eq!(parse_start_time("77 (truncated"), None);
+    }
+
+    /// Quitting mid-calibration puts everything back at once.
+    #[test]
+    fn exit_ends_a_calibration() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |_| {
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            let (_, run) = runtime.block_on(begin_calibration(None, 5, None)).unwrap();
+            assert_eq!(current_thresholds(), (96, 100));
+
+            end_battery_calibration_blocking();
+            drop(run);
+            assert_eq!(current_thresholds(), (75, 80));
+            assert_eq!(
+                get_charge_behaviour().data.unwrap()[0].current,
+                Some(ChargeBehaviour::Auto)
+            );
+        });
+    }
+
+    /// Replace the profile's battery with one unpacked from a uevent fixture
+    /// in tests/fixtures/power_supply.
+    fn install_uevent(root: &std::path::Path, fixture: &str) {
+        let text = fs::read_to_string(format!(
+            "{}/tests/fixtures/power_supply/{}.uevent",
+            env!("CARGO_MANIFEST_DIR"),
+            fixture
+        ))
+        .unwrap();
+        let attrs: Vec<(String, String)> = text
+            .lines()
+            .filter_map(|line| line.strip_prefix("POWER_SUPPLY_")?.split_once('='))
+            .map(|(attr, value)| (attr.to_lowercase(), value.to_string()))
+            .collect();
+        let attrs: Vec<(&str, &str)> = attrs
+            .iter()
+            .map(|(attr, value)| (attr.as_str(), value.as_str()))
+            .collect();
+        fs::remove_file(root.join("sys/class/power_supply/BAT0")).unwrap();
+        add_supply(root, "BAT0", &attrs);
+    }
+
+    /// Energy and charge flavours both come out in watt-hours and watts.
+    #[test]
+    fn each_flavour_reads_in_watt_hours() {
+        // fixture, (now, full, design) Wh, health, W, capacity
+        let cases = [
+            ("energy-discharging", (25.65, 51.3, 57.0), 90, 9.5, 50),
+            ("energy-charging", (20.0, 50.0, 57.0), 87, 30.0, 40),
+            // 2 of 4 Ah at the rated 11.1 V, 1 A at 11.5 V; the current is
+            // signed negative.
+            ("charge-discharging", (22.2, 44.4, 49.95), 88, 11.5, 50),
+            // No capacity file: 1 of 4 Ah is 25%.
+            ("charge-charging", (11.1, 44.4, 49.95), 88, 24.0, 25),
+        ];
+        for (fixture, (now, full, design), health, watts, capacity) in cases {
+            crate::hardware_root::testing::with_scratch_profile(P1, |root| {
+                install_uevent(root, fixture);
+                let info = &get_battery_info().data.unwrap()[0];
+                assert!((info.energy_now - now).abs() < 0.01, "{}", fixture);
+                assert!((info.energy_full - full).abs() < 0.01, "{}", fixture);
+                assert!((info.energy_design - design).abs() < 0.01, "{}", fixture);
+                assert_eq!(info.health, health, "{}", fixture);
+                assert!((info.power - watts).abs() < 0.01, "{}", fixture);
+                assert_eq!(info.capacity, capacity, "{}", fixture);
+            });
+        }
+    }
+
+    /// Whatever the flavour, the estimate is what is left over the rate.
+    #[test]
+    fn each_flavour_estimates_its_time() {
+        let cases = [
+            // 25.65 Wh at 9.5 W.
+            ("energy-discharging", Some(9720), None),
+            // 30 Wh to go at 30 W.
+            ("energy-charging", None, Some(3600)),
+            // 22.2 Wh at 11.5 W.
+            ("charge-discharging", Some(6950), None),
+            // 33.3 Wh to go at 24 W.
+            ("charge-charging", None, Some(4995)),
+        ];
+        for (fixture, empty, full) in cases {
+            crate::hardware_root::testing::with_scratch_profile(P1, |root| {
+                install_uevent(root, fixture);
+                let summary = summarize(&get_battery_info().data.unwrap());
+                let near = |got: Option<u64>, want: Option<u64>| match (got, want) {
+                    (Some(got), Some(want)) => got.abs_diff(want) <= 1,
+                    (got, want) => got == want,
+                };
+                assert!(
+                    near(summary.time_to_empty, empty),
+                    "{}: {:?}",
+                    fixture,
+                    summary
+                );
+                assert!(
+                    near(summary.time_to_full, full),
+                    "{}: {:?}",
+                    fixture,
+                    summary
+                );
+            });
+        }
+    }
+
+    /// The present voltage moves from one read to the next; what a battery
+    /// that reports charge holds must not move with it, or the smoothed rate
+    /// would take every read for a new battery and start over.
+    #[test]
+    fn a_changing_voltage_leaves_the_capacity_alone() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |root| {
+            install_uevent(root, "charge-discharging");
+            let start = Instant::now();
+            let first = summarize(&get_battery_info().data.unwrap());
+            let rate = smooth_rate(None, &first, start);
+
+            set_attr(root, "voltage_now", "11000000");
+            let second = summarize(&get_battery_info().data.unwrap());
+            assert_eq!(second.energy_full, first.energy_full);
+            assert_eq!(second.energy_now, first.energy_now);
+            assert!((second.power - 11.0).abs() < 0.01);
+
+            let rate = smooth_rate(Some(&rate), &second, start + Duration::from_secs(5));
+            assert!(rate.watts > 11.0 && rate.watts < 11.5, "{}", rate.watts);
+
+            // Without a present voltage, the rated one gives the power.
+            fs::remove_file(
+                root.join(BAT0_PATH.trim_start_matches('/'))
+                    .join("voltage_now"),
+            )
+            .unwrap();
+            let info = &get_battery_info().data.unwrap()[0];
+            assert!((info.power - 11.1).abs() < 0.01, "{}", info.power);
+            assert!((info.energy_full - 44.4).abs() < 0.01);
+        });
+    }
+
+    /// Charging ends at the stop threshold, not at 100%.
+    #[test]
+    fn time_to_full_runs_to_the_stop_threshold() {
+        crate::hardware_root::testing::with_scratch_profile(P1, |root| {
+            install_uevent(root, "energy-charging");
+            let bat = root.join(BAT0_PATH.trim_start_matches('/'));
+            fs::write(bat.join("charge_control_start_threshold"), "75\n").unwrap();
+            fs::write(bat.join("charge_control_end_threshold"), "80\n").unwrap();
+            // 40 Wh is 80% of 50; 20 Wh to go at 30 W.
+            let summary = summarize(&get_battery_info().data.unwrap());
+            assert_eq!(summary.time_to_full, Some(2400));
+        });
+    }
+
+    fn reading(status: &str, power: f32, energy_now: f32) -> BatterySummary {
+        let mut summary = summarize(&[]);
+        summary.batteries = 1;
+        summary.status = status.to_string();
+        summary.power = power;
+        summary.energy_now = energy_now;
+        summary.energy_full = 50.0;
+        summary
+    }
+
+    #[test]
+    fn the_rate_is_smoothed_over_time() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let first = smooth_rate(None, &reading("Discharging", 10.0, 40.0), at(0));
+        assert_eq!(first.watts, 10.0);
+
+        // A one-second burst to 40 W barely moves it.
+        let burst = smooth_rate(Some(&first), &reading("Discharging", 40.0, 40.0), at(1));
+        assert!(burst.watts > 10.0 && burst.watts < 11.0, "{}", burst.watts);
+
+        // A load that holds for five minutes is nearly all the way in.
+        let mut rate = first;
+        for secs in (5..=300).step_by(5) {
+            rate = smooth_rate(Some(&rate), &reading("Discharging", 20.0, 40.0), at(secs));
+        }
+        assert!((rate.watts - 20.0).abs() < 0.1, "{}", rate.watts);
+
+        // Plugging in starts over rather than averaging the two directions.
+        let plugged = smooth_rate(Some(&rate), &reading("Charging", 45.0, 40.0), at(301));
+        assert_eq!(plugged.watts, 45.0);
+
+        // So does a gap such as a suspend.
+        let resumed = smooth_rate(Some(&rate), &reading("Discharging", 5.0, 30.0), at(3600));
+        assert_eq!(resumed.watts, 5.0);
+    }
+
+    /// Firmware that reports no flow still drains: the stored energy says how
+    /// fast.
+    #[test]
+    fn no_reported_rate_falls_back_to_the_energy_drop() {
+        let start = Instant::now();
+        let first = smooth_rate(None, &reading("Discharging", 0.0, 40.0), start);
+        let mut rate = first;
+        // 0.1 Wh every 36 s is 10 W.
+        for step in 1..=20u64 {
+            let energy = 40.0 - step as f32 * 0.1;
+            let summary = reading("Discharging", 0.0, energy);
+            rate = smooth_rate(
+                Some(&rate),
+                &summary,
+                start + Duration::from_secs(step * 36),
+            );
+        }
+        assert!((rate.watts - 10.0).abs() < 0.5, "{}", rate.watts);
+    }
+}
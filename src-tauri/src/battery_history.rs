@@ -0,0 +1,659 @@
+//! Battery wear over months.
+//!
+//! [`BatteryInfo`](crate::battery::BatteryInfo) is a snapshot. To see a trend,
+//! each battery's full and design capacity, cycle count and charge are sampled
+//! about once an hour into a JSON-lines file under the app config directory,
+//! one sample per line. Appending a line is all a sample costs, and a line
+//! torn by a crash is skipped on reading rather than losing the file. Once a
+//! day the file is compacted: samples past a few months are thinned to one a
+//! day, and those past [`RETENTION`] dropped, so it stops growing.
+//!
+//! The query side downsamples a range for charting and fits a straight line
+//! through health over time, to say when it will reach the replacement
+//! threshold.
+
+use crate::error::{ApiResponse, Error};
+use crate::hardware_root;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const HISTORY_FILE: &str = "battery_history.jsonl";
+
+/// How far apart samples are. Full capacity moves over weeks, so more often
+/// would only grow the file.
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often the recorder checks whether a sample is due. Due-ness is judged
+/// by the clock against the last sample on disk, so restarts and suspends
+/// neither double up samples nor stretch the gaps by much.
+const SAMPLE_CHECK: Duration = Duration::from_secs(10 * 60);
+
+/// Samples younger than this are kept as recorded. A trend needs weeks of
+/// history, not hours, so older ones are thinned to one per battery per day.
+const FULL_RESOLUTION: u64 = 90 * DAY;
+
+/// Samples older than this are dropped. Longer than a pack lasts.
+pub const RETENTION: u64 = 10 * 365 * DAY;
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// Enough of the end of the file to hold its last few lines.
+const TAIL_BYTES: u64 = 4096;
+
+/// Health below which a battery is due for replacement, unless configured.
+pub const DEFAULT_REPLACEMENT_THRESHOLD: u8 = 80;
+
+/// Points returned for a range unless asked otherwise.
+pub const DEFAULT_MAX_POINTS: usize = 200;
+
+/// A trend is only fitted over at least this much history. Over a few days
+/// the gauge's recalibrations swamp the wear.
+const MIN_TREND_SPAN: u64 = 14 * 24 * 60 * 60;
+
+const SECS_PER_YEAR: f64 = 365.25 * 24.0 * 60.0 * 60.0;
+
+/// One battery at one moment, as kept on disk.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthSample {
+    /// Unix seconds.
+    pub timestamp: u64,
+    pub battery: String,
+    /// Watt-hours.
+    pub energy_full: f32,
+    pub energy_full_design: f32,
+    pub cycle_count: u32,
+    /// Charge at the time, in percent.
+    pub capacity: u8,
+    /// Full capacity as a percentage of design, as read. Samples of batteries
+    /// that report charge were once converted at the voltage of the moment,
+    /// so their watt-hours wander; the ratio does not. Absent from samples
+    /// recorded before it was.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<f32>,
+}
+
+impl HealthSample {
+    /// Full capacity as a percentage of design, where the design is known.
+    fn health(&self) -> Option<f64> {
+        if let Some(health) = self.health {
+            return Some(health as f64);
+        }
+        (self.energy_full_design > 0.0)
+            .then(|| self.energy_full as f64 / self.energy_full_design as f64 * 100.0)
+    }
+}
+
+/// One point of a queried range: a sample, or the average of several.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthPoint {
+    pub timestamp: u64,
+    /// Percent of design capacity.
+    pub health: f32,
+    pub energy_full: f32,
+    pub energy_full_design: f32,
+    /// The highest count among the samples averaged.
+    pub cycle_count: u32,
+    pub capacity: u8,
+    /// How many samples were averaged into this point.
+    pub samples: usize,
+}
+
+/// One battery's history over a queried range.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthHistory {
+    pub battery: String,
+    pub points: Vec<HealthPoint>,
+    /// Samples in the range, before downsampling.
+    pub samples: usize,
+    pub replacement_threshold: u8,
+    /// Percentage points of health lost per year, from the fitted trend.
+    pub wear_per_year: Option<f32>,
+    /// Unix seconds at which the trend reaches the threshold. In the past if it
+    /// already has; absent with too little history or no measurable wear.
+    pub projected_replacement: Option<u64>,
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn history_path() -> Result<PathBuf, String> {
+    Ok(crate::sync::get_config_dir()?.join(HISTORY_FILE))
+}
+
+/// Every sample in the file, oldest first. A missing file is an empty history.
+fn read_samples(path: &Path) -> Vec<HealthSample> {
+    let Ok(text) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let mut samples: Vec<HealthSample> = text
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    // Appended in order, but the clock can step back.
+    samples.sort_by_key(|s| s.timestamp);
+    samples
+}
+
+/// When the last sample was written, from the end of the file alone -- the
+/// file holds years of samples and this is asked every few minutes.
+fn last_timestamp(path: &Path) -> Option<u64> {
+    let mut file = fs::File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(TAIL_BYTES)))
+        .ok()?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).ok()?;
+    // The first line may be cut by the seek and the last torn by a crash;
+    // neither parses.
+    String::from_utf8_lossy(&tail)
+        .lines()
+        .rev()
+        .find_map(|line| serde_json::from_str::<HealthSample>(line).ok())
+        .map(|s| s.timestamp)
+}
+
+fn append_samples(path: &Path, samples: &[HealthSample]) -> Result<(), String> {
+    let mut lines = String::new();
+    for sample in samples {
+        let line = serde_json::to_string(sample).map_err(|e| e.to_string())?;
+        lines.push_str(&line);
+        lines.push('\n');
+    }
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(lines.as_bytes()))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Every battery, as it stands now.
+fn sample_batteries(timestamp: u64) -> Vec<HealthSample> {
+    crate::battery::get_battery_info()
+        .data
+        .unwrap_or_default()
+        .into_iter()
+        .map(|info| HealthSample {
+            timestamp,
+            battery: info.name,
+            energy_full: info.energy_full,
+            energy_full_design: info.energy_design,
+            cycle_count: info.cycles,
+            capacity: info.capacity,
+            health: (info.energy_design > 0.0)
+                .then(|| info.energy_full / info.energy_design * 100.0),
+        })
+        .collect()
+}
+
+/// Append a sample of every battery, unless the last one is less than
+/// [`SAMPLE_INTERVAL`] old. Returns how many were written.
+fn record_if_due(path: &Path, now: u64) -> Result<usize, String> {
+    let last = last_timestamp(path);
+    if last.is_some_and(|t| t <= now && now - t < SAMPLE_INTERVAL.as_secs()) {
+        return Ok(0);
+    }
+    let samples = sample_batteries(now);
+    append_samples(path, &samples)?;
+    Ok(samples.len())
+}
+
+/// Thin samples older than [`FULL_RESOLUTION`] to the first of each day per
+/// battery and drop those older than [`RETENTION`], rewriting the file in
+/// place. Returns how many were removed.
+fn compact(path: &Path, now: u64) -> Result<usize, String> {
+    let samples = read_samples(path);
+    let mut days_kept = std::collections::BTreeSet::new();
+    let kept: Vec<&HealthSample> = samples
+        .iter()
+        .filter(|s| {
+            let age = now.saturating_sub(s.timestamp);
+            age < FULL_RESOLUTION
+                || (age < RETENTION && days_kept.insert((s.battery.as_str(), s.timestamp / DAY)))
+        })
+        .collect();
+    let removed = samples.len() - kept.len();
+    if removed == 0 {
+        return Ok(0);
+    }
+    let mut lines = String::new();
+    for sample in kept {
+        let line = serde_json::to_string(sample).map_err(|e| e.to_string())?;
+        lines.push_str(&line);
+        lines.push('\n');
+    }
+    let tmp = path.with_extension("jsonl.tmp");
+    fs::write(&tmp, lines)
+        .and_then(|()| fs::rename(&tmp, path))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(removed)
+}
+
+/// Keep sampling for as long as the app runs. Started once at setup.
+///
+/// Not under a hardware profile: its batteries are not this machine's, and
+/// their samples would stay in the history for good.
+pub async fn record_health_history() {
+    if hardware_root::is_simulated() {
+        return;
+    }
+    let path = match history_path() {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("[Battery] Not recording health history: {}", e);
+            return;
+        }
+    };
+    let mut compacted_on = None;
+    loop {
+        let now = now_secs();
+        if compacted_on != Some(now / DAY) {
+            compacted_on = Some(now / DAY);
+            if let Err(e) = compact(&path, now) {
+                eprintln!("[Battery] {}", e);
+            }
+        }
+        if let Err(e) = record_if_due(&path, now) {
+            eprintln!("[Battery] {}", e);
+        }
+        tokio::time::sleep(SAMPLE_CHECK).await;
+    }
+}
+
+/// Average `samples` into at most `max_points` points, each covering an equal
+/// slice of time. Fewer samples than that come back one point each.
+fn downsample(samples: &[&HealthSample], max_points: usize) -> Vec<HealthPoint> {
+    if samples.len() <= max_points {
+        return samples.iter().map(|s| average(&[s])).collect();
+    }
+    let first = samples[0].timestamp;
+    let span = samples[samples.len() - 1].timestamp - first;
+    let width = span / max_points as u64 + 1;
+    let slice = |s: &HealthSample| (s.timestamp - first) / width;
+    samples
+        .chunk_by(|a, b| slice(a) == slice(b))
+        .map(average)
+        .collect()
+}
+
+fn average(samples: &[&HealthSample]) -> HealthPoint {
+    let n = samples.len() as f64;
+    let mean = |f: &dyn Fn(&HealthSample) -> f64| samples.iter().map(|s| f(s)).sum::<f64>() / n;
+    HealthPoint {
+        timestamp: mean(&|s| s.timestamp as f64).round() as u64,
+        health: mean(&|s| s.health().unwrap_or_default()) as f32,
+        energy_full: mean(&|s| s.energy_full as f64) as f32,
+        energy_full_design: mean(&|s| s.energy_full_design as f64) as f32,
+        cycle_count: samples.iter().map(|s| s.cycle_count).max().unwrap_or(0),
+        capacity: mean(&|s| s.capacity as f64).round() as u8,
+        samples: samples.len(),
+    }
+}
+
+/// The samples a trend is fitted over: those since the battery was last
+/// swapped. A new battery shows up as the cycle count going back down, and
+/// its history has nothing to do with the old one's.
+fn current_pack<'a, 'b>(samples: &'b [&'a HealthSample]) -> &'b [&'a HealthSample] {
+    let start = samples
+        .windows(2)
+        .rposition(|pair| pair[1].cycle_count < pair[0].cycle_count)
+        .map_or(0, |i| i + 1);
+    &samples[start..]
+}
+
+/// Health lost per year, and when the loss reaches `threshold`, from a
+/// least-squares line through health over time.
+fn project(samples: &[&HealthSample], threshold: u8) -> (Option<f32>, Option<u64>) {
+    let points: Vec<(f64, f64)> = samples
+        .iter()
+        .filter_map(|s| Some((s.timestamp as f64, s.health()?)))
+        .collect();
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return (None, None);
+    };
+    if (last.0 - first.0) < MIN_TREND_SPAN as f64 {
+        return (None, None);
+    }
+    // Relative to the first sample, so the sums keep their precision.
+    let t0 = first.0;
+    let n = points.len() as f64;
+    let mean_t = points.iter().map(|(t, _)| t - t0).sum::<f64>() / n;
+    let mean_h = points.iter().map(|(_, h)| h).sum::<f64>() / n;
+    let covariance: f64 = points
+        .iter()
+        .map(|(t, h)| (t - t0 - mean_t) * (h - mean_h))
+        .sum();
+    let variance: f64 = points.iter().map(|(t, _)| (t - t0 - mean_t).powi(2)).sum();
+    let slope = covariance / variance;
+    let wear_per_year = (-slope * SECS_PER_YEAR) as f32;
+    if slope >= 0.0 {
+        return (Some(wear_per_year), None);
+    }
+    let crossing = t0 + mean_t + (threshold as f64 - mean_h) / slope;
+    let projected = (crossing.is_finite() && crossing > 0.0).then_some(crossing as u64);
+    (Some(wear_per_year), projected)
+}
+
+/// Each battery's history between `since` and `until`, in name order.
+fn query(
+    samples: &[HealthSample],
+    battery: Option<&str>,
+    since: Option<u64>,
+    until: Option<u64>,
+    max_points: usize,
+    threshold: u8,
+) -> Vec<HealthHistory> {
+    let mut by_battery: BTreeMap<&str, Vec<&HealthSample>> = BTreeMap::new();
+    for sample in samples {
+        let in_range = since.is_none_or(|t| sample.timestamp >= t)
+            && until.is_none_or(|t| sample.timestamp <= t);
+        // Without a design capacity there is no health to chart or fit.
+        let usable = sample.health().is_some();
+        if usable && in_range && battery.is_none_or(|b| b == sample.battery) {
+            by_battery.entry(&sample.battery).or_default().push(sample);
+        }
+    }
+    by_battery
+        .into_iter()
+        .map(|(name, samples)| {
+            let (wear_per_year, projected_replacement) = project(current_pack(&samples), threshold);
+            HealthHistory {
+                battery: name.to_string(),
+                points: downsample(&samples, max_points),
+                samples: samples.len(),
+                replacement_threshold: threshold,
+                wear_per_year,
+                projected_replacement,
+            }
+        })
+        .collect()
+}
+
+/// Recorded health of each battery, or of the one named, between `since` and
+/// `until` (unix seconds), in at most `max_points` points per battery.
+///
+/// The projection uses `replacement_threshold` if given, otherwise the
+/// `battery_replacement_threshold` setting.
+#[tauri::command]
+pub async fn get_battery_health_history(
+    app: tauri::AppHandle,
+    battery: Option<String>,
+    since: Option<u64>,
+    until: Option<u64>,
+    max_points: Option<usize>,
+    replacement_threshold: Option<u8>,
+) -> ApiResponse<Vec<HealthHistory>> {
+    let threshold = match replacement_threshold {
+        Some(threshold) => threshold,
+        None => match crate::settings::load_app_settings(app).await {
+            Ok(settings) => settings.battery_replacement_threshold,
+            Err(_) => DEFAULT_REPLACEMENT_THRESHOLD,
+        },
+    };
+    let max_points = max_points.unwrap_or(DEFAULT_MAX_POINTS);
+    if let Err(e) = validate_query(max_points, threshold) {
+        return ApiResponse::err(Error::validation(e));
+    }
+    let path = match history_path() {
+        Ok(path) => path,
+        Err(e) => return ApiResponse::err(e),
+    };
+    let samples = read_samples(&path);
+    ApiResponse::ok(query(
+        &samples,
+        battery.as_deref(),
+        since,
+        until,
+        max_points,
+        threshold,
+    ))
+}
+
+pub(crate) fn validate_replacement_threshold(threshold: u8) -> Result<(), String> {
+    if !(1..=99).contains(&threshold) {
+        return Err("Replacement threshold must be between 1% and 99%".to_string());
+    }
+    Ok(())
+}
+
+fn validate_query(max_points: usize, threshold: u8) -> Result<(), String> {
+    if max_points < 2 {
+        return Err("Ask for at least 2 points".to_string());
+    }
+    validate_replacement_threshold(threshold)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware_root::testing::with_profile;
+
+    fn sample(timestamp: u64, battery: &str, energy_full: f32, cycle_count: u32) -> HealthSample {
+        HealthSample {
+            timestamp,
+            battery: battery.to_string(),
+            energy_full,
+            energy_full_design: 100.0,
+            cycle_count,
+            capacity: 50,
+            health: None,
+        }
+    }
+
+    /// Unique temp path per call, as in the sync tests.
+    fn temp_path(tag: &str) -> PathBuf {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        std::env::temp_dir().join(format!(
+            "thinkutils_test_{}_{}_{}",
+            tag,
+            std::process::id(),
+            nanos
+        ))
+    }
+
+    #[test]
+    fn records_once_per_interval_and_survives_a_torn_line() {
+        let path = temp_path("history");
+        let now = 1_700_000_000;
+        let written = with_profile("thinkpad-p1-gen-4i", || {
+            let first = record_if_due(&path, now).unwrap();
+            let early = record_if_due(&path, now + 60).unwrap();
+            let due = record_if_due(&path, now + SAMPLE_INTERVAL.as_secs()).unwrap();
+            (first, early, due)
+        });
+        assert_eq!(written, (1, 0, 1));
+
+        // A crash mid-append leaves half a line; the rest still reads.
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"timestamp\":17")
+            .unwrap();
+        let samples = read_samples(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(samples.len(), 2);
+        let bat0 = &samples[0];
+        assert_eq!(bat0.battery, "BAT0");
+        assert_eq!(bat0.cycle_count, 100);
+        assert_eq!(bat0.capacity, 79);
+        assert!((bat0.energy_full - 75.09).abs() < 0.01);
+        assert!((bat0.energy_full_design - 90.09).abs() < 0.01);
+        assert!((bat0.health.unwrap() - 83.35).abs() < 0.01);
+    }
+
+    #[test]
+    fn the_last_sample_is_read_from_the_end_of_a_long_file() {
+        let path = temp_path("history_tail");
+        assert_eq!(last_timestamp(&path), None);
+
+        let samples: Vec<HealthSample> = (0..1000)
+            .map(|i| sample(i * 3600, "BAT0", 90.0, 1))
+            .collect();
+        append_samples(&path, &samples).unwrap();
+        assert_eq!(last_timestamp(&path), Some(999 * 3600));
+
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"timestamp\":17")
+            .unwrap();
+        let last = last_timestamp(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(last, Some(999 * 3600), "a torn line is passed over");
+    }
+
+    #[test]
+    fn compaction_thins_old_samples_and_drops_expired_ones() {
+        let path = temp_path("history_compact");
+        let now = RETENTION + 365 * DAY;
+        let hourly = |from: u64, days: u64| (0..days * 24).map(move |h| from + h * 3600);
+        let samples: Vec<HealthSample> = hourly(0, 2)
+            .chain(hourly(now - FULL_RESOLUTION - 10 * DAY, 2))
+            .chain(hourly(now - 2 * DAY, 2))
+            .flat_map(|t| [sample(t, "BAT0", 90.0, 1), sample(t, "BAT1", 50.0, 1)])
+            .collect();
+        append_samples(&path, &samples).unwrap();
+
+        let removed = compact(&path, now).unwrap();
+        let kept = read_samples(&path);
+        let again = compact(&path, now).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // The expired days go, the old ones keep a sample a day per battery,
+        // the recent ones stay hourly.
+        assert_eq!(kept.len(), 2 * 2 + 2 * 48);
+        assert_eq!(removed, samples.len() - kept.len());
+        assert_eq!(again, 0);
+        assert!(kept.iter().all(|s| now - s.timestamp < RETENTION));
+        assert_eq!(kept.iter().filter(|s| s.battery == "BAT1").count(), 50);
+    }
+
+    /// The stored ratio is what counts; the watt-hours of a charge-reporting
+    /// battery move with its voltage.
+    #[test]
+    fn the_recorded_health_is_preferred_over_the_capacities() {
+        let mut recorded = sample(DAY, "BAT0", 60.0, 1);
+        recorded.health = Some(80.0);
+        assert_eq!(recorded.health(), Some(80.0));
+        assert_eq!(sample(DAY, "BAT0", 60.0, 1).health(), Some(60.0));
+
+        let line = r#"{"timestamp":1,"battery":"BAT0","energy_full":45.0,"energy_full_design":90.0,"cycle_count":1,"capacity":50}"#;
+        let older: HealthSample = serde_json::from_str(line).unwrap();
+        assert_eq!(older.health(), Some(50.0));
+    }
+
+    #[test]
+    fn downsampling_averages_equal_slices_of_time() {
+        let samples: Vec<HealthSample> = (0..100)
+            .map(|i| sample(i * 3600, "BAT0", 100.0 - i as f32 * 0.1, i as u32))
+            .collect();
+        let refs: Vec<&HealthSample> = samples.iter().collect();
+
+        assert_eq!(downsample(&refs, 200).len(), 100, "few enough to keep all");
+
+        let points = downsample(&refs, 10);
+        assert!(points.len() <= 10);
+        assert_eq!(points.iter().map(|p| p.samples).sum::<usize>(), 100);
+        let first = &points[0];
+        assert_eq!(first.cycle_count, first.samples as u32 - 1);
+        assert!(first.health < 100.0 && first.health > 99.0);
+        assert!(points.windows(2).all(|w| w[0].timestamp < w[1].timestamp));
+    }
+
+    /// Losing 10 points a year from 90%, 80% is a year after the start.
+    #[test]
+    fn projects_when_health_reaches_the_threshold() {
+        let start = 1_700_000_000;
+        let year = SECS_PER_YEAR as u64;
+        let samples: Vec<HealthSample> = (0..=60)
+            .map(|day| {
+                let t = day * DAY;
+                let health = 90.0 - 10.0 * t as f32 / year as f32;
+                sample(start + t, "BAT0", health, day as u32)
+            })
+            .collect();
+
+        let history = query(&samples, None, None, None, 20, 80);
+        let bat0 = &history[0];
+        assert_eq!(bat0.samples, 61);
+        assert!((bat0.wear_per_year.unwrap() - 10.0).abs() < 0.01);
+        let projected = bat0.projected_replacement.unwrap();
+        assert!(projected.abs_diff(start + year) < DAY);
+
+        // Already past it: the crossing lies in the past.
+        let history = query(&samples, None, None, None, 20, 95);
+        assert!(history[0].projected_replacement.unwrap() < start);
+    }
+
+    #[test]
+    fn no_projection_without_enough_history_or_wear() {
+        let short: Vec<HealthSample> = (0..10)
+            .map(|day| sample(day * DAY, "BAT0", 90.0 - day as f32, 1))
+            .collect();
+        let history = query(&short, None, None, None, 20, 80);
+        assert_eq!(history[0].wear_per_year, None);
+        assert_eq!(history[0].projected_replacement, None);
+
+        let flat: Vec<HealthSample> = (0..30)
+            .map(|day| sample(day * DAY, "BAT0", 90.0 + (day % 2) as f32, 1))
+            .collect();
+        let history = query(&flat, None, None, None, 20, 80);
+        assert!(history[0].wear_per_year.is_some());
+        assert_eq!(history[0].projected_replacement, None);
+    }
+
+    /// A swapped battery starts a new trend: the old pack's wear says nothing
+    /// about the new one.
+    #[test]
+    fn a_new_battery_starts_a_new_trend() {
+        let mut samples: Vec<HealthSample> = (0..30)
+            .map(|day| sample(day * DAY, "BAT0", 70.0 - day as f32 * 0.5, 500 + day as u32))
+            .collect();
+        samples.extend((30..60).map(|day| sample(day * DAY, "BAT0", 100.0, 1)));
+
+        let history = query(&samples, None, None, None, 200, 80);
+        assert_eq!(history[0].points.len(), 60, "the chart keeps both");
+        assert_eq!(history[0].wear_per_year, Some(0.0));
+        assert_eq!(history[0].projected_replacement, None);
+    }
+
+    #[test]
+    fn queries_filter_by_battery_and_range() {
+        let samples = vec![
+            sample(DAY, "BAT1", 50.0, 1),
+            sample(DAY, "BAT0", 90.0, 1),
+            sample(2 * DAY, "BAT0", 89.0, 1),
+            sample(3 * DAY, "BAT0", 88.0, 1),
+        ];
+        let all = query(&samples, None, None, None, 20, 80);
+        let names: Vec<&str> = all.iter().map(|h| h.battery.as_str()).collect();
+        assert_eq!(names, ["BAT0", "BAT1"]);
+
+        let ranged = query(&samples, Some("BAT0"), Some(2 * DAY), Some(2 * DAY), 20, 80);
+        assert_eq!(ranged.len(), 1);
+        assert_eq!(ranged[0].samples, 1);
+        assert_eq!(ranged[0].points[0].health, 89.0);
+    }
+
+    #[test]
+    fn query_limits_are_checked() {
+        assert!(validate_query(DEFAULT_MAX_POINTS, DEFAULT_REPLACEMENT_THRESHOLD).is_ok());
+        assert!(validate_query(1, 80).is_err());
+        assert!(validate_query(10, 0).is_err());
+        assert!(validate_query(10, 100).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+//! Runs the fan curve and battery thresholds without the app window.
+//!
+//! Meant to be started by systemd (see packaging/systemd). The GUI finds the
+//! socket on its own and becomes a client; see `thinkutils_lib::daemon`.
+
+use std::path::PathBuf;
+use std::process::exit;
+use thinkutils_lib::daemon;
+
+const USAGE: &str = "\
+Usage: thinkutils-daemon [--socket PATH] [--config PATH]
+
+Runs the ThinkUtils fan curve and keeps battery charge thresholds applied.
+
+Options:
+  --socket PATH   Unix socket to listen on (default: $THINKUTILS_DAEMON_SOCKET
+                  or /run/thinkutils/daemon.sock)
+  --config PATH   JSON config file (default: $THINKUTILS_DAEMON_CONFIG or
+                  /var/lib/thinkutils/daemon.json)
+  -h, --help      Show this help
+  -V, --version   Show the version";
+
+#[tokio::main]
+async fn main() {
+    let mut socket = daemon::socket_path();
+    let mut config = daemon::config_path();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--socket" | "--config" => {
+                let Some(value) = args.next() else {
+                    eprintln!("{} needs a path\n\n{}", arg, USAGE);
+                    exit(2);
+                };
+                if arg == "--socket" {
+                    socket = PathBuf::from(value);
+                } else {
+                    config = PathBuf::from(value);
+                }
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            "-V" | "--version" => {
+                println!("thinkutils-daemon {}", env!("CARGO_PKG_VERSION"));
+                return;
+            }
+            _ => {
+                eprintln!("Unknown argument: {}\n\n{}", arg, USAGE);
+                exit(2);
+            }
+        }
+    }
+
+    let mut sigterm = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+    {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("[Daemon] Failed to install SIGTERM handler: {}", e);
+            exit(1);
+        }
+    };
+
+    let code = tokio::select! {
+        result = daemon::serve(socket.clone(), config) => match result {
+            Ok(()) => 0,
+            // The socket and the fan belong to the daemon already running.
+            Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => {
+                eprintln!("[Daemon] {}", e);
+                exit(1);
+            }
+            Err(e) => {
+                eprintln!("[Daemon] {}", e);
+                1
+            }
+        },
+        _ = sigterm.recv() => 0,
+        _ = tokio::signal::ctrl_c() => 0,
+    };
+
+    println!("[Daemon] Shutting down");
+    daemon::shutdown(&socket);
+    exit(code);
+}
@@ -0,0 +1,11 @@
+//! The privileged helper: the one binary the polkit rule lets run as root.
+//!
+//! Takes a single typed operation on the command line, validates it with the
+//! same code the app uses and writes only the files that operation owns. See
+//! `thinkutils_lib::helper`.
+
+fn main() {
+    std::process::exit(thinkutils_lib::helper::main(
+        std::env::args().skip(1).collect(),
+    ));
+}
@@ -0,0 +1,741 @@
+//! `thinkutils <command>`: the hardware controls without the window.
+//!
+//! Every command goes through the same functions the GUI's Tauri commands
+//! are, so validation and the privilege path (direct write, helper, pkexec)
+//! are shared rather than reimplemented. With no arguments the binary starts
+//! the GUI as before; see main.rs.
+//!
+//! The exit status says what kind of failure happened, using the sysexits.h
+//! values scripts already know:
+//!
+//! | Status | Meaning                                              |
+//! |--------|------------------------------------------------------|
+//! | 0      | Success                                              |
+//! | 1      | The hardware or a tool refused for another reason    |
+//! | 64     | The command line could not be parsed                 |
+//! | 65     | A value was rejected by validation                   |
+//! | 69     | The hardware interface is missing on this machine    |
+//! | 77     | Permission was denied                                |
+//!
+//! `--json` prints the same `{success, data, error}` envelope the GUI gets,
+//! plus a `kind` naming the failure.
+
+use crate::error::{ApiResponse, Error, ErrorCode};
+use crate::{battery, daemon, fan_control, monitor, performance};
+use serde::Serialize;
+use std::io::Write;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd};
+
+pub const USAGE: &str = "\
+Usage: thinkutils [COMMAND] [--json]
+
+Without a command, starts the ThinkUtils window.
+
+Commands:
+  status                          Fan, battery, governor, turbo and profile
+  fan [status]                    Fan level, speed and temperatures
+  fan set LEVEL [--fan N]         Set the fan level: auto, full-speed or 0-7
+  fan enable                      Load thinkpad_acpi with fan_control=1
+  battery [status]                Charge, health and cycle count
+  battery thresholds [START STOP] Show or set the charge thresholds
+  battery behaviour [MODE]        Show or set charging: auto, inhibit-charge
+                                  or force-discharge
+  governor [NAME]                 Show or set the CPU governor
+  profile [NAME]                  Show or set the power profile
+  turbo [on|off]                  Show or set turbo boost
+  monitor                         CPU, memory, disk and network usage
+
+Options:
+  --json          Print JSON instead of text
+  -v, --verbose   Show what the library does, on stderr
+  -h, --help      Show this help
+  -V, --version   Show the version
+
+Exit status: 0 success, 1 failure, 64 usage, 65 invalid value,
+69 hardware missing, 77 permission denied.";
+
+/// What went wrong, which decides the exit status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FailureKind {
+    Failed,
+    Usage,
+    Invalid,
+    Unavailable,
+    Permission,
+}
+
+impl FailureKind {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            FailureKind::Failed => 1,
+            FailureKind::Usage => 64,
+            FailureKind::Invalid => 65,
+            FailureKind::Unavailable => 69,
+            FailureKind::Permission => 77,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    pub kind: FailureKind,
+    pub message: String,
+}
+
+impl Failure {
+    fn new(kind: FailureKind, message: impl Into<String>) -> Self {
+        Failure {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+/// A failure reported by a library function, sorted by its code.
+impl From<Error> for Failure {
+    fn from(error: Error) -> Self {
+        let kind = match error.code {
+            ErrorCode::PermissionDenied => FailureKind::Permission,
+            ErrorCode::ModuleParameterMissing
+            | ErrorCode::HardwareAbsent
+            | ErrorCode::ToolMissing => FailureKind::Unavailable,
+            ErrorCode::Validation => FailureKind::Invalid,
+            ErrorCode::Simulated | ErrorCode::Failed => FailureKind::Failed,
+        };
+        Failure::new(kind, error.message)
+    }
+}
+
+/// A parsed command line.
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    Help,
+    Version,
+    Status,
+    Fan,
+    FanSet { level: String, fan: Option<u8> },
+    FanEnable,
+    Battery,
+    Thresholds,
+    SetThresholds { start: u8, stop: u8 },
+    Behaviour,
+    SetBehaviour(String),
+    Governor,
+    SetGovernor(String),
+    Profile,
+    SetProfile(String),
+    Turbo,
+    SetTurbo(bool),
+    Monitor,
+}
+
+fn usage(message: impl std::fmt::Display) -> Failure {
+    Failure::new(FailureKind::Usage, format!("{}\n\n{}", message, USAGE))
+}
+
+fn percentage(value: &str) -> Result<u8, Failure> {
+    value.parse().map_err(|_| {
+        Failure::new(
+            FailureKind::Invalid,
+            format!("{} is not a percentage", value),
+        )
+    })
+}
+
+/// Parse everything after the program name. Returns the command and whether
+/// `--json` was given. Options are accepted anywhere on the line; `--verbose`
+/// is handled by [`main`].
+fn parse(args: &[String]) -> Result<(Command, bool), Failure> {
+    let json = args.iter().any(|a| a == "--json");
+    let words: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .filter(|a| !matches!(*a, "--json" | "-v" | "--verbose"))
+        .collect();
+
+    let command = match words.as_slice() {
+        [] | ["help"] | ["-h"] | ["--help"] => Command::Help,
+        ["-V"] | ["--version"] => Command::Version,
+        ["status"] => Command::Status,
+        ["fan"] | ["fan", "status"] => Command::Fan,
+        ["fan", "set", level] => Command::FanSet {
+            level: level.to_string(),
+            fan: None,
+        },
+        ["fan", "set", level, "--fan", n] | ["fan", "set", "--fan", n, level] => {
+            let fan = n
+                .parse()
+                .map_err(|_| Failure::new(FailureKind::Invalid, format!("Invalid fan: {}", n)))?;
+            Command::FanSet {
+                level: level.to_string(),
+                fan: Some(fan),
+            }
+        }
+        ["fan", "enable"] => Command::FanEnable,
+        ["battery"] | ["battery", "status"] => Command::Battery,
+        ["battery", "thresholds"] => Command::Thresholds,
+        ["battery", "thresholds", start, stop] => Command::SetThresholds {
+            start: percentage(start)?,
+            stop: percentage(stop)?,
+        },
+        ["battery", "behaviour"] => Command::Behaviour,
+        ["battery", "behaviour", mode] => Command::SetBehaviour(mode.to_string()),
+        ["governor"] => Command::Governor,
+        ["governor", name] => Command::SetGovernor(name.to_string()),
+        ["profile"] => Command::Profile,
+        ["profile", name] => Command::SetProfile(name.to_string()),
+        ["turbo"] => Command::Turbo,
+        ["turbo", "on"] => Command::SetTurbo(true),
+        ["turbo", "off"] => Command::SetTurbo(false),
+        ["monitor"] => Command::Monitor,
+        [first, ..] if first.starts_with('-') => {
+            return Err(usage(format!("Unknown option: {}", first)))
+        }
+        _ => return Err(usage(format!("Unknown command: {}", words.join(" ")))),
+    };
+    Ok((command, json))
+}
+
+/// What a command produced: the JSON printed with `--json`, and the text
+/// printed without it.
+struct Output {
+    data: serde_json::Value,
+    text: String,
+}
+
+impl Output {
+    fn message(text: String) -> Self {
+        Output {
+            data: serde_json::Value::String(text.clone()),
+            text,
+        }
+    }
+}
+
+/// Turn an `ApiResponse` into a result, keeping what its code says about
+/// the failure.
+fn into_result<T>(response: ApiResponse<T>) -> Result<T, Failure> {
+    if let Some(error) = response.failure() {
+        return Err(error.into());
+    }
+    response
+        .data
+        .ok_or_else(|| Failure::new(FailureKind::Failed, "The operation failed"))
+}
+
+fn to_json(value: &impl Serialize) -> serde_json::Value {
+    serde_json::to_value(value).unwrap_or(serde_json::Value::Null)
+}
+
+fn format_temp(value: Option<f64>) -> String {
+    value.map_or_else(|| "--".to_string(), |v| format!("{:.1}°C", v))
+}
+
+/// Refuse early, with the GUI's own explanation, when there is no fan this
+/// app can drive. Otherwise the failure surfaces as a write error.
+fn require_fan_control() -> Result<(), Failure> {
+    let capability = into_result(fan_control::get_fan_capability())?;
+    if capability.readiness == fan_control::FanReadiness::Ready {
+        Ok(())
+    } else {
+        Err(Failure::new(FailureKind::Unavailable, capability.message))
+    }
+}
+
+fn require_thresholds() -> Result<(), Failure> {
+    match battery::threshold_paths() {
+        Some(_) => Ok(()),
+        None => Err(Failure::new(
+            FailureKind::Unavailable,
+            "This machine exposes no battery charge threshold controls.",
+        )),
+    }
+}
+
+fn fan_status() -> Result<Output, Failure> {
+    let sensors = into_result(fan_control::get_sensor_data())?;
+    let mut text = format!(
+        "Level: {}\nSpeed: {}",
+        sensors.level.as_deref().unwrap_or("unknown"),
+        sensors
+            .speed
+            .map_or_else(|| "unknown".to_string(), |rpm| format!("{} RPM", rpm))
+    );
+    for fan in &sensors.per_fan {
+        text.push_str(&format!(
+            "\nFan {}: {}",
+            fan.index,
+            fan.rpm
+                .map_or_else(|| "unreadable".to_string(), |rpm| format!("{} RPM", rpm))
+        ));
+    }
+    for reading in &sensors.temps {
+        text.push_str(&format!(
+            "\n{} {}: {}",
+            reading.chip,
+            reading.label,
+            format_temp(reading.value)
+        ));
+    }
+    Ok(Output {
+        data: to_json(&sensors),
+        text,
+    })
+}
+
+fn battery_status() -> Result<Output, Failure> {
+    let batteries = into_result(battery::get_battery_info())?;
+    let text = batteries
+        .iter()
+        .map(|b| {
+            format!(
+                "{}: {} {}% (health {}%, {} cycles)",
+                b.name, b.status, b.capacity, b.health, b.cycles
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(Output {
+        data: to_json(&batteries),
+        text,
+    })
+}
+
+fn thresholds() -> Result<Output, Failure> {
+    require_thresholds()?;
+    let thresholds = into_result(battery::get_battery_thresholds())?;
+    Ok(Output {
+        data: to_json(&thresholds),
+        text: format!("{}%-{}%", thresholds.start, thresholds.stop),
+    })
+}
+
+fn behaviour() -> Result<Output, Failure> {
+    let states = into_result(battery::get_charge_behaviour())?;
+    let text = states
+        .iter()
+        .map(|state| {
+            let available: Vec<String> = state.available.iter().map(|b| b.to_string()).collect();
+            format!(
+                "{}: {} (available: {})",
+                state.battery,
+                state
+                    .current
+                    .map_or_else(|| "unknown".to_string(), |b| b.to_string()),
+                available.join(", ")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(Output {
+        data: to_json(&states),
+        text,
+    })
+}
+
+fn governor() -> Result<Output, Failure> {
+    let cpu = into_result(performance::get_cpu_info())?;
+    if cpu.governor.is_empty() {
+        return Err(Failure::new(
+            FailureKind::Unavailable,
+            "This machine exposes no CPU governor.",
+        ));
+    }
+    let text = format!(
+        "{} (available: {})",
+        cpu.governor,
+        cpu.available_governors.join(", ")
+    );
+    Ok(Output {
+        data: to_json(&cpu),
+        text,
+    })
+}
+
+fn profile() -> Result<Output, Failure> {
+    let profile = into_result(performance::get_power_profile())?;
+    let text = format!(
+        "{} (available: {})",
+        profile.current,
+        profile.available.join(", ")
+    );
+    Ok(Output {
+        data: to_json(&profile),
+        text,
+    })
+}
+
+fn turbo() -> Result<Output, Failure> {
+    let enabled = into_result(performance::get_turbo_boost_status())?;
+    Ok(Output {
+        data: serde_json::Value::Bool(enabled),
+        text: if enabled { "on" } else { "off" }.to_string(),
+    })
+}
+
+/// Everything at a glance. A part this machine lacks is left out rather than
+/// failing the whole report.
+fn status() -> Output {
+    let sensors = fan_control::get_sensor_data().data;
+    let batteries = battery::get_battery_info().data;
+    let thresholds =
+        battery::threshold_paths().and_then(|_| battery::get_battery_thresholds().data);
+    let cpu = performance::get_cpu_info()
+        .data
+        .filter(|c| !c.governor.is_empty());
+    let turbo = performance::get_turbo_boost_status().data;
+    let profile = performance::get_power_profile().data;
+
+    let mut lines = Vec::new();
+    if let Some(s) = &sensors {
+        lines.push(format!(
+            "Fan:        level {}, {}",
+            s.level.as_deref().unwrap_or("unknown"),
+            s.speed
+                .map_or_else(|| "speed unknown".to_string(), |rpm| format!("{} RPM", rpm))
+        ));
+    }
+    for b in batteries.iter().flatten() {
+        lines.push(format!(
+            "Battery:    {} {} {}% (health {}%)",
+            b.name, b.status, b.capacity, b.health
+        ));
+    }
+    if let Some(t) = &thresholds {
+        lines.push(format!("Thresholds: {}%-{}%", t.start, t.stop));
+    }
+    if let Some(c) = &cpu {
+        lines.push(format!("Governor:   {}", c.governor));
+    }
+    if let Some(t) = turbo {
+        lines.push(format!("Turbo:      {}", if t { "on" } else { "off" }));
+    }
+    if let Some(p) = &profile {
+        lines.push(format!("Profile:    {}", p.current));
+    }
+
+    Output {
+        data: serde_json::json!({
+            "fan": sensors,
+            "batteries": batteries,
+            "thresholds": thresholds,
+            "cpu": cpu,
+            "turbo": turbo,
+            "power_profile": profile,
+        }),
+        text: lines.join("\n"),
+    }
+}
+
+async fn execute(command: Command) -> Result<Output, Failure> {
+    match command {
+        Command::Help => Ok(Output::message(USAGE.to_string())),
+        Command::Version => Ok(Output::message(format!(
+            "thinkutils {}",
+            env!("CARGO_PKG_VERSION")
+        ))),
+        Command::Status => Ok(status()),
+        Command::Fan => fan_status(),
+        Command::FanSet { level, fan } => {
+            // A time limit needs a process around to enforce it; this one
+            // exits as soon as the level is written.
+            if level.parse() == Ok(fan_control::FanLevel::Disengaged) {
+                return Err(Failure::new(
+                    FailureKind::Invalid,
+                    "Disengaged mode is only available in the app, which returns the fan to auto when its time limit runs out",
+                ));
+            }
+            fan_control::validate_fan_request(&level, fan, None)
+                .map_err(|e| Failure::new(FailureKind::Invalid, e))?;
+            require_fan_control()?;
+            into_result(fan_control::set_fan_speed(level, fan, None).await).map(Output::message)
+        }
+        Command::FanEnable => {
+            into_result(fan_control::enable_fan_control().await).map(Output::message)
+        }
+        Command::Battery => battery_status(),
+        Command::Thresholds => thresholds(),
+        Command::SetThresholds { start, stop } => {
+            battery::validate_thresholds(start, stop)
+                .map_err(|e| Failure::new(FailureKind::Invalid, e))?;
+            require_thresholds()?;
+            into_result(battery::set_battery_thresholds(start, stop, None).await)
+                .map(Output::message)
+        }
+        Command::Behaviour => behaviour(),
+        Command::SetBehaviour(mode) => {
+            // No time limit here, for the reason disengaged mode has none:
+            // this process is gone before one could run out.
+            battery::validate_behaviour_request(&mode, None)
+                .map_err(|e| Failure::new(FailureKind::Invalid, e))?;
+            into_result(battery::set_charge_behaviour(mode, None, None).await).map(Output::message)
+        }
+        Command::Governor => governor(),
+        Command::SetGovernor(name) => {
+            governor()?;
+            performance::validate_governor(&name)
+                .map_err(|e| Failure::new(FailureKind::Invalid, e))?;
+            into_result(performance::set_cpu_governor(name).await).map(Output::message)
+        }
+        Command::Profile => profile(),
+        Command::SetProfile(name) => {
+            // set_power_profile falls back to a TLP mode for any name it does
+            // not know, so an unlisted one is refused here instead.
+            let available = into_result(performance::get_power_profile())?.available;
+            if !available.contains(&name) {
+                return Err(Failure::new(
+                    FailureKind::Invalid,
+                    format!(
+                        "Power profile '{}' is not available. Available: {}",
+                        name,
+                        available.join(", ")
+                    ),
+                ));
+            }
+            into_result(performance::set_power_profile(name).await).map(Output::message)
+        }
+        Command::Turbo => turbo(),
+        Command::SetTurbo(enabled) => {
+            turbo()?;
+            into_result(performance::set_turbo_boost(enabled).await).map(Output::message)
+        }
+        Command::Monitor => {
+            let stats = into_result(monitor::get_system_monitor().await)?;
+            let text = format!(
+                "CPU:    {:.1}% (load {:.2} {:.2} {:.2})\nMemory: {:.1}% of {} MiB\n{}",
+                stats.cpu.usage_percent,
+                stats.cpu.load_avg.one_min,
+                stats.cpu.load_avg.five_min,
+                stats.cpu.load_avg.fifteen_min,
+                stats.memory.usage_percent,
+                stats.memory.total / 1024 / 1024,
+                stats
+                    .disk
+                    .iter()
+                    .map(|d| format!("Disk:   {} {:.1}%", d.mount_point, d.usage_percent))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+            Ok(Output {
+                data: to_json(&stats),
+                text: text.trim_end().to_string(),
+            })
+        }
+    }
+}
+
+/// The `--json` envelope: the GUI's `ApiResponse` plus the failure kind.
+#[derive(Serialize)]
+struct Envelope<'a> {
+    success: bool,
+    data: Option<&'a serde_json::Value>,
+    error: Option<&'a str>,
+    kind: Option<FailureKind>,
+}
+
+/// Run one command line, writing results to `out` and failures to `err`.
+/// Returns the exit status.
+pub async fn run(args: &[String], out: &mut impl Write, err: &mut impl Write) -> i32 {
+    let (result, json) = match parse(args) {
+        Ok((command, json)) => (execute(command).await, json),
+        Err(failure) => (Err(failure), args.iter().any(|a| a == "--json")),
+    };
+
+    let written = match (&result, json) {
+        (Ok(output), true) => writeln!(
+            out,
+            "{}",
+            serde_json::to_string_pretty(&Envelope {
+                success: true,
+                data: Some(&output.data),
+                error: None,
+                kind: None,
+            })
+            .unwrap_or_default()
+        ),
+        (Ok(output), false) => writeln!(out, "{}", output.text),
+        (Err(failure), true) => writeln!(
+            out,
+            "{}",
+            serde_json::to_string_pretty(&Envelope {
+                success: false,
+                data: None,
+                error: Some(&failure.message),
+                kind: Some(failure.kind),
+            })
+            .unwrap_or_default()
+        ),
+        (Err(failure), false) => writeln!(err, "thinkutils: {}", failure.message),
+    };
+    // A closed pipe (`thinkutils status | head -1`) is not the command failing.
+    let _ = written;
+
+    match result {
+        Ok(_) => 0,
+        Err(failure) => failure.kind.exit_code(),
+    }
+}
+
+/// Take over stdout for results, and send what the library prints there to
+/// `logs` instead.
+///
+/// The library narrates what it does with `println!` for the GUI's terminal.
+/// Left alone, that lands in the middle of `--json` output.
+fn redirect_library_output(logs: BorrowedFd) -> std::io::Result<std::fs::File> {
+    let results = std::io::stdout().as_fd().try_clone_to_owned()?;
+    // SAFETY: both descriptors are open for the duration of the call, and dup2
+    // only repoints fd 1; the Rust handle to the original is `results`.
+    if unsafe { libc::dup2(logs.as_raw_fd(), libc::STDOUT_FILENO) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(std::fs::File::from(results))
+}
+
+/// The entry point main.rs hands a command line to.
+pub fn main(args: Vec<String>) -> i32 {
+    let verbose = args.iter().any(|a| a == "-v" || a == "--verbose");
+    let null = std::fs::OpenOptions::new().write(true).open("/dev/null");
+    let stderr = std::io::stderr();
+    let logs = match &null {
+        Ok(null) if !verbose => null.as_fd(),
+        _ => stderr.as_fd(),
+    };
+    let mut out: Box<dyn Write> = match redirect_library_output(logs) {
+        Ok(results) => Box::new(results),
+        Err(_) => Box::new(std::io::stdout()),
+    };
+
+    // With a daemon running, threshold changes must go through it: it would
+    // put its own back within a minute of a direct write.
+    daemon::attach(&daemon::socket_path());
+
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("thinkutils: failed to start: {}", e);
+            return FailureKind::Failed.exit_code();
+        }
+    };
+    runtime.block_on(run(&args, &mut out, &mut std::io::stderr()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn commands_parse_with_json_anywhere() {
+        assert_eq!(
+            parse(&args("fan set 3")).unwrap(),
+            (
+                Command::FanSet {
+                    level: "3".into(),
+                    fan: None
+                },
+                false
+            )
+        );
+        assert_eq!(
+            parse(&args("--json battery thresholds 40 80")).unwrap(),
+            (
+                Command::SetThresholds {
+                    start: 40,
+                    stop: 80
+                },
+                true
+            )
+        );
+        assert_eq!(
+            parse(&args("fan set full-speed --fan 2 --json")).unwrap(),
+            (
+                Command::FanSet {
+                    level: "full-speed".into(),
+                    fan: Some(2)
+                },
+                true
+            )
+        );
+        assert_eq!(
+            parse(&args("turbo off")).unwrap().0,
+            Command::SetTurbo(false)
+        );
+        assert_eq!(
+            parse(&args("battery behaviour inhibit-charge")).unwrap().0,
+            Command::SetBehaviour("inhibit-charge".into())
+        );
+    }
+
+    #[test]
+    fn a_malformed_line_is_a_usage_error_and_a_bad_value_is_not() {
+        for line in [
+            "fan spin",
+            "battery thresholds 40",
+            "battery behaviour auto BAT0",
+            "turbo maybe",
+            "--frobnicate",
+        ] {
+            let e = parse(&args(line)).unwrap_err();
+            assert_eq!(e.kind, FailureKind::Usage, "{}", line);
+        }
+        for line in ["battery thresholds forty 80", "battery thresholds 40 300"] {
+            let e = parse(&args(line)).unwrap_err();
+            assert_eq!(e.kind, FailureKind::Invalid, "{}", line);
+        }
+    }
+
+    #[test]
+    fn library_codes_map_to_kinds() {
+        let kind = |e: Error| Failure::from(e).kind;
+        assert_eq!(
+            kind(Error::permission_denied("Only root may change settings")),
+            FailureKind::Permission
+        );
+        assert_eq!(
+            kind(Error::module_parameter_missing(
+                "loaded without fan_control=1"
+            )),
+            FailureKind::Unavailable
+        );
+        assert_eq!(
+            kind(Error::hardware_absent("Turbo boost control not available")),
+            FailureKind::Unavailable
+        );
+        assert_eq!(
+            kind(Error::validation("Invalid fan speed: 9")),
+            FailureKind::Invalid
+        );
+        assert_eq!(
+            kind(Error::simulated(
+                "Simulated hardware rejected the change: stop 30 is not above the current start 40"
+            )),
+            FailureKind::Failed
+        );
+        // The wording no longer decides: a message that reads like a denial
+        // but carries no code is an ordinary failure.
+        assert_eq!(kind(Error::from("Permission denied")), FailureKind::Failed);
+    }
+
+    #[test]
+    fn exit_codes_are_distinct() {
+        let kinds = [
+            FailureKind::Failed,
+            FailureKind::Usage,
+            FailureKind::Invalid,
+            FailureKind::Unavailable,
+            FailureKind::Permission,
+        ];
+        let mut codes: Vec<i32> = kinds.iter().map(|k| k.exit_code()).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), kinds.len());
+        assert!(!codes.contains(&0));
+    }
+}
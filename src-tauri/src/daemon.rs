@@ -0,0 +1,1075 @@
+//! `thinkutils-daemon`: the fan curve and battery thresholds without the GUI.
+//!
+//! The curve used to live and die with the app: it ran as a task inside the
+//! Tauri process, so quitting from the tray handed the fan back to the
+//! firmware. Thresholds the firmware drops (after a battery swap, and on some
+//! models after resume) stayed dropped until the app was opened again. The
+//! daemon runs the same curve loop as a systemd service, re-applies the
+//! thresholds, and keeps both in its own config file.
+//!
+//! When the GUI finds the daemon's socket at startup it becomes a client:
+//! configuration changes go to the daemon, and the events the daemon relays
+//! are shown as if the curve were running in-process.
+//!
+//! The protocol is one JSON object per line in each direction. A client sends
+//! a [`Request`] and reads one [`ApiResponse`] back. After `subscribe`, the
+//! connection carries [`Event`] lines until either side closes it.
+
+use crate::battery::{self, BatteryThresholds};
+use crate::error::{ApiResponse, Error};
+use crate::fan_curve::{self, CurveChange, CurveHost, FanCurveConfig, FanCurveState};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::{CStr, CString};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::broadcast;
+
+/// Where the systemd unit's `RuntimeDirectory=thinkutils` puts the socket.
+pub const DAEMON_SOCKET: &str = "/run/thinkutils/daemon.sock";
+
+/// Under the unit's `StateDirectory=thinkutils`: written by the daemon, so it
+/// belongs in /var/lib rather than the administrator's /etc.
+pub const DAEMON_CONFIG: &str = "/var/lib/thinkutils/daemon.json";
+
+/// Override [`DAEMON_SOCKET`], for the daemon and the GUI alike.
+pub const DAEMON_SOCKET_ENV: &str = "THINKUTILS_DAEMON_SOCKET";
+
+/// Override [`DAEMON_CONFIG`].
+pub const DAEMON_CONFIG_ENV: &str = "THINKUTILS_DAEMON_CONFIG";
+
+/// The config file is a JSON object. `fan_curve` is also the key the GUI's
+/// settings store uses, so a copy of the app's settings.json works as a
+/// daemon config as-is. Keys the daemon does not know are left alone.
+const CURVE_KEY: &str = "fan_curve";
+const THRESHOLDS_KEY: &str = "battery_thresholds";
+
+/// Who may change settings besides root: the groups the polkit rule already
+/// grants passwordless fan control to. Reading is open to anyone who can
+/// connect, which [`SOCKET_GROUP`] decides.
+const ADMIN_GROUPS: &[&str] = &["wheel", "sudo"];
+
+/// The group the socket belongs to. Created by the packages' sysusers.d file
+/// ([`SYSUSERS`]); members may connect, everyone else is refused by the file
+/// mode before the daemon sees them.
+pub const SOCKET_GROUP: &str = "thinkutils";
+
+/// Installed as sysusers.d/thinkutils.conf.
+pub const SYSUSERS: &str = "\
+# The group that may connect to thinkutils-daemon's socket.
+g thinkutils -
+";
+
+/// How often drifted thresholds are put back. Drift only happens on events
+/// measured in minutes (a battery swap, a resume), so this needs no tighter.
+const THRESHOLD_CHECK: Duration = Duration::from_secs(60);
+
+/// A request is a local round trip. Past this the daemon is hung, and the GUI
+/// is better off reporting that than freezing with it.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Installed as packaging/systemd/thinkutils-daemon.service. Packages ship it
+/// disabled: taking the fan away from the firmware is the user's call.
+///
+/// `ProtectSystem=strict` still leaves /proc and /sys writable, which is all
+/// the daemon writes besides its state directory. `ProtectKernelTunables` is
+/// left off because it would make the threshold files read-only.
+pub const SYSTEMD_UNIT: &str = "\
+[Unit]
+Description=ThinkUtils fan curve and battery threshold daemon
+Documentation=https://github.com/vietanhdev/ThinkUtils
+After=systemd-modules-load.service
+
+[Service]
+Type=simple
+ExecStart=/usr/bin/thinkutils-daemon
+Restart=on-failure
+RestartSec=2
+RuntimeDirectory=thinkutils
+StateDirectory=thinkutils
+ProtectSystem=strict
+ProtectHome=yes
+PrivateTmp=yes
+NoNewPrivileges=yes
+
+[Install]
+WantedBy=multi-user.target
+";
+
+pub fn socket_path() -> PathBuf {
+    std::env::var_os(DAEMON_SOCKET_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DAEMON_SOCKET))
+}
+
+pub fn config_path() -> PathBuf {
+    std::env::var_os(DAEMON_CONFIG_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DAEMON_CONFIG))
+}
+
+// -- Protocol --
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Request {
+    Status,
+    GetFanCurve,
+    /// Replace the whole curve configuration.
+    SetFanCurve {
+        config: Box<FanCurveConfig>,
+    },
+    /// Apply one edit to the curve configuration as the daemon holds it, and
+    /// reply with the result. What the GUI sends: a whole config from a
+    /// client would undo curve switches the daemon made since it was read.
+    UpdateFanCurve {
+        change: CurveChange,
+    },
+    EnableFanCurve {
+        enabled: bool,
+    },
+    /// Apply thresholds now, and keep re-applying them: to the named battery,
+    /// or to every battery that has them.
+    SetBatteryThresholds {
+        start: u8,
+        stop: u8,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        battery: Option<String>,
+    },
+    /// Stream [`Event`]s on this connection from now on.
+    Subscribe,
+}
+
+impl Request {
+    fn changes_settings(&self) -> bool {
+        matches!(
+            self,
+            Request::SetFanCurve { .. }
+                | Request::UpdateFanCurve { .. }
+                | Request::EnableFanCurve { .. }
+                | Request::SetBatteryThresholds { .. }
+        )
+    }
+}
+
+/// Something the curve loop reported: the same name and payload the GUI's
+/// window receives when the loop runs in-process.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub event: String,
+    pub payload: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Status {
+    pub version: String,
+    pub fan_curve_enabled: bool,
+    pub active_curve: String,
+    pub battery_thresholds: Thresholds,
+    pub config: PathBuf,
+}
+
+/// The thresholds the daemon keeps, by battery sysfs name. The firmware holds
+/// a separate setting per battery, and so does the daemon.
+pub type Thresholds = BTreeMap<String, BatteryThresholds>;
+
+/// What [`THRESHOLDS_KEY`] holds. Configs written before batteries were kept
+/// apart have one setting, which stands for every battery.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedThresholds {
+    Every(BatteryThresholds),
+    Each(Thresholds),
+}
+
+// -- Config file --
+
+/// The config file as a JSON object, or an empty one if there is none yet.
+fn read_config_object(path: &Path) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content)
+            .map_err(|e| format!("{} is not a JSON object: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(serde_json::Map::new()),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// Replace one key of the config file, keeping every other key as it was.
+///
+/// Written to a temporary file and renamed into place, so a crash mid-write
+/// leaves the old config rather than half of a new one.
+fn save_config_key(path: &Path, key: &str, value: serde_json::Value) -> Result<(), String> {
+    let mut object = read_config_object(path)?;
+    object.insert(key.to_string(), value);
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(&object)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json + "\n")
+        .and_then(|()| std::fs::rename(&tmp, path))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// The curve and thresholds from the config file. A file that cannot be read
+/// starts the daemon on defaults, and is only overwritten once a client
+/// changes something.
+fn load_config(path: &Path) -> (FanCurveConfig, Thresholds) {
+    let object = read_config_object(path).unwrap_or_else(|e| {
+        eprintln!("[Daemon] {}; starting with defaults", e);
+        serde_json::Map::new()
+    });
+
+    let mut curve = object
+        .get(CURVE_KEY)
+        .and_then(
+            |v| match serde_json::from_value::<FanCurveConfig>(v.clone()) {
+                Ok(c) => Some(c),
+                Err(e) => {
+                    eprintln!("[Daemon] Ignoring invalid {}: {}", CURVE_KEY, e);
+                    None
+                }
+            },
+        )
+        .unwrap_or_default();
+    curve.finish_loading();
+
+    let thresholds = match object
+        .get(THRESHOLDS_KEY)
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+    {
+        Some(SavedThresholds::Each(each)) => each,
+        Some(SavedThresholds::Every(every)) => battery::all_threshold_paths()
+            .into_iter()
+            .map(|paths| (paths.battery, every))
+            .collect(),
+        None => Thresholds::new(),
+    };
+
+    (curve, thresholds)
+}
+
+// -- Authorization --
+
+/// Grow `buf` and retry while a `get*_r` lookup says it is too small.
+/// Entries with thousands of members are real on LDAP; a megabyte is not.
+fn lookup<T>(mut call: impl FnMut(&mut [libc::c_char]) -> Result<T, libc::c_int>) -> Option<T> {
+    let mut buf = vec![0 as libc::c_char; 1024];
+    loop {
+        match call(&mut buf) {
+            Ok(found) => return Some(found),
+            Err(libc::ERANGE) if buf.len() < 1 << 20 => buf.resize(buf.len() * 2, 0),
+            Err(_) => return None,
+        }
+    }
+}
+
+/// The name and primary group of `uid`, through NSS, so LDAP and sssd
+/// accounts resolve the same as those in /etc/passwd.
+fn user_of(uid: libc::uid_t) -> Option<(CString, libc::gid_t)> {
+    lookup(|buf| {
+        // SAFETY: `pwd` and `buf` outlive the call, and `buf.len()` is its
+        // real size. The name is copied out before `buf` is reused.
+        let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut found = std::ptr::null_mut();
+        let rc =
+            unsafe { libc::getpwuid_r(uid, &mut pwd, buf.as_mut_ptr(), buf.len(), &mut found) };
+        match (rc, found.is_null()) {
+            (0, false) => Ok((
+                unsafe { CStr::from_ptr(pwd.pw_name) }.to_owned(),
+                pwd.pw_gid,
+            )),
+            (0, true) => Err(libc::ENOENT),
+            (rc, _) => Err(rc),
+        }
+    })
+}
+
+/// The id of the group called `name`, through NSS.
+fn group_id(name: &str) -> Option<libc::gid_t> {
+    let name = CString::new(name).ok()?;
+    lookup(|buf| {
+        // SAFETY: as in user_of; only the gid is read from the result.
+        let mut group: libc::group = unsafe { std::mem::zeroed() };
+        let mut found = std::ptr::null_mut();
+        let rc = unsafe {
+            libc::getgrnam_r(
+                name.as_ptr(),
+                &mut group,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut found,
+            )
+        };
+        match (rc, found.is_null()) {
+            (0, false) => Ok(group.gr_gid),
+            (0, true) => Err(libc::ENOENT),
+            (rc, _) => Err(rc),
+        }
+    })
+}
+
+/// Every group `uid` is in, primary included, as `getgrouplist` reports it.
+fn group_ids(uid: libc::uid_t) -> Option<Vec<libc::gid_t>> {
+    let (user, primary) = user_of(uid)?;
+    let mut groups: Vec<libc::gid_t> = vec![0; 64];
+    loop {
+        let mut count = groups.len() as libc::c_int;
+        // SAFETY: `groups` holds `count` entries, and getgrouplist writes at
+        // most that many.
+        let rc =
+            unsafe { libc::getgrouplist(user.as_ptr(), primary, groups.as_mut_ptr(), &mut count) };
+        if rc >= 0 {
+            groups.truncate(count as usize);
+            return Some(groups);
+        }
+        // Too small; `count` is now the size needed.
+        if groups.len() >= 1 << 16 {
+            return None;
+        }
+        groups.resize((count as usize).max(groups.len() * 2), 0);
+    }
+}
+
+fn may_change_settings(uid: u32) -> bool {
+    if uid == 0 {
+        return true;
+    }
+    let Some(groups) = group_ids(uid) else {
+        return false;
+    };
+    ADMIN_GROUPS
+        .iter()
+        .filter_map(|name| group_id(name))
+        .any(|gid| groups.contains(&gid))
+}
+
+// -- Server --
+
+/// Reports the curve loop's events to subscribers and persists curve switches
+/// to the daemon's config file.
+#[derive(Clone)]
+struct DaemonHost {
+    events: broadcast::Sender<Event>,
+    config: PathBuf,
+}
+
+impl CurveHost for DaemonHost {
+    fn emit(&self, event: &str, payload: serde_json::Value) -> Result<(), String> {
+        // No subscribers is the normal state, not an error.
+        let _ = self.events.send(Event {
+            event: event.to_string(),
+            payload,
+        });
+        Ok(())
+    }
+
+    fn save(&self, config: &FanCurveConfig) -> Result<(), String> {
+        let value = serde_json::to_value(config)
+            .map_err(|e| format!("Failed to serialize config: {}", e))?;
+        save_config_key(&self.config, CURVE_KEY, value)
+    }
+}
+
+#[derive(Clone)]
+struct Daemon {
+    host: DaemonHost,
+    curve: FanCurveState,
+    thresholds: Arc<Mutex<Thresholds>>,
+}
+
+fn ok(data: serde_json::Value) -> ApiResponse<serde_json::Value> {
+    ApiResponse::ok(data)
+}
+
+fn err(error: impl Into<Error>) -> ApiResponse<serde_json::Value> {
+    ApiResponse::err(error)
+}
+
+fn reply(result: Result<serde_json::Value, Error>) -> ApiResponse<serde_json::Value> {
+    match result {
+        Ok(data) => ok(data),
+        Err(e) => err(e),
+    }
+}
+
+async fn write_line(
+    stream: &mut (impl AsyncWriteExt + Unpin),
+    value: &impl Serialize,
+) -> std::io::Result<()> {
+    let mut line = serde_json::to_string(value).map_err(std::io::Error::other)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes()).await
+}
+
+impl Daemon {
+    async fn dispatch(&self, request: Request) -> ApiResponse<serde_json::Value> {
+        match request {
+            Request::Status => {
+                let Ok(curve) = self.curve.lock().map(|c| c.clone()) else {
+                    return err("Failed to lock state");
+                };
+                let thresholds = self
+                    .thresholds
+                    .lock()
+                    .map(|t| t.clone())
+                    .unwrap_or_default();
+                ok(serde_json::json!(Status {
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                    fan_curve_enabled: curve.enabled,
+                    active_curve: curve.active_curve,
+                    battery_thresholds: thresholds,
+                    config: self.host.config.clone(),
+                }))
+            }
+            Request::GetFanCurve => match self.curve.lock() {
+                Ok(curve) => ok(serde_json::json!(*curve)),
+                Err(e) => err(format!("Failed to lock state: {}", e)),
+            },
+            Request::SetFanCurve { mut config } => {
+                if let Err(e) = fan_curve::validate_config(&config) {
+                    return err(Error::validation(e));
+                }
+                // `points` is the editor's working copy; as in set_fan_curve,
+                // it replaces the saved curve of the same name.
+                config
+                    .curves
+                    .insert(config.active_curve.clone(), config.points.clone());
+                config.finish_loading();
+                let replaced = self.replace_curve(|curve| {
+                    *curve = *config;
+                    Ok(())
+                });
+                reply(replaced.map(|_| serde_json::Value::Null))
+            }
+            Request::UpdateFanCurve { change } => {
+                let updated =
+                    self.replace_curve(|curve| curve.apply(change).map_err(Error::validation));
+                reply(updated.and_then(|curve| {
+                    serde_json::to_value(curve)
+                        .map_err(|e| format!("Failed to serialize config: {}", e).into())
+                }))
+            }
+            Request::EnableFanCurve { enabled } => {
+                let replaced = self.replace_curve(|curve| {
+                    curve.enabled = enabled;
+                    Ok(())
+                });
+                reply(replaced.map(|_| serde_json::Value::Null))
+            }
+            Request::SetBatteryThresholds {
+                start,
+                stop,
+                battery,
+            } => {
+                // Checked here, not left to the write, so a value the daemon
+                // refuses comes back as a validation error, as it would in
+                // the app.
+                let valid = battery::validate_thresholds(start, stop)
+                    .and_then(|()| battery.as_deref().map_or(Ok(()), battery::validate_battery));
+                if let Err(e) = valid {
+                    return err(Error::validation(e));
+                }
+                let applied = battery::set_battery_thresholds(start, stop, battery.clone()).await;
+                if let Some(e) = applied.failure() {
+                    return err(e);
+                }
+                let names = match battery {
+                    Some(name) => vec![name],
+                    None => battery::all_threshold_paths()
+                        .into_iter()
+                        .map(|paths| paths.battery)
+                        .collect(),
+                };
+                let wanted = BatteryThresholds { start, stop };
+                let saved = match self.thresholds.lock() {
+                    Ok(mut t) => {
+                        t.extend(names.into_iter().map(|name| (name, wanted)));
+                        t.clone()
+                    }
+                    Err(e) => return err(format!("Failed to lock state: {}", e)),
+                };
+                match save_config_key(&self.host.config, THRESHOLDS_KEY, serde_json::json!(saved)) {
+                    Ok(()) => ok(serde_json::json!(applied.data)),
+                    Err(e) => err(e),
+                }
+            }
+            Request::Subscribe => err("subscribe is handled by the connection"),
+        }
+    }
+
+    /// Change the curve config in memory and on disk together, and return
+    /// what it became. A change that fails leaves both as they were.
+    fn replace_curve(
+        &self,
+        change: impl FnOnce(&mut FanCurveConfig) -> Result<(), Error>,
+    ) -> Result<FanCurveConfig, Error> {
+        let mut curve = self
+            .curve
+            .lock()
+            .map_err(|e| format!("Failed to lock state: {}", e))?;
+        let mut changed = curve.clone();
+        change(&mut changed)?;
+        self.host.save(&changed)?;
+        *curve = changed.clone();
+        Ok(changed)
+    }
+
+    async fn handle(&self, stream: UnixStream) -> std::io::Result<()> {
+        let uid = stream.peer_cred().ok().map(|c| c.uid());
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = tokio::io::BufReader::new(reader);
+        let mut buf = Vec::new();
+
+        loop {
+            let line = match read_request_line(&mut reader, &mut buf).await? {
+                RequestLine::Line(line) => line,
+                RequestLine::End => return Ok(()),
+                RequestLine::TooLong => {
+                    // The rest of the line is never read, so there is no next
+                    // request to find: the connection ends here.
+                    let message = format!("Request longer than {} bytes", MAX_REQUEST_BYTES);
+                    write_line(&mut writer, &err(Error::validation(message))).await?;
+                    return Ok(());
+                }
+            };
+            let request: Request = match serde_json::from_slice(&line) {
+                Ok(r) => r,
+                Err(e) => {
+                    write_line(&mut writer, &err(format!("Invalid request: {}", e))).await?;
+                    continue;
+                }
+            };
+
+            if request.changes_settings() && !uid.is_some_and(may_change_settings) {
+                let message = format!(
+                    "Only root or members of {} may change settings",
+                    ADMIN_GROUPS.join(" or ")
+                );
+                write_line(&mut writer, &err(Error::permission_denied(message))).await?;
+                continue;
+            }
+
+            if let Request::Subscribe = request {
+                let mut events = self.host.events.subscribe();
+                write_line(&mut writer, &ok(serde_json::Value::Null)).await?;
+                loop {
+                    match events.recv().await {
+                        Ok(event) => write_line(&mut writer, &event).await?,
+                        // A slow reader misses updates rather than stalling the loop.
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => return Ok(()),
+                    }
+                }
+            }
+
+            let response = self.dispatch(request).await;
+            write_line(&mut writer, &response).await?;
+        }
+    }
+}
+
+/// The longest request line the daemon reads. Requests are a few hundred
+/// bytes; without a cap, a client that never sends a newline grows the buffer
+/// until the daemon, and the fan curve with it, runs out of memory.
+const MAX_REQUEST_BYTES: usize = 64 * 1024;
+
+enum RequestLine {
+    Line(Vec<u8>),
+    TooLong,
+    End,
+}
+
+/// Read one request line, without its newline, holding no more than
+/// [`MAX_REQUEST_BYTES`] of it.
+async fn read_request_line(
+    reader: &mut (impl AsyncBufRead + Unpin),
+    buf: &mut Vec<u8>,
+) -> std::io::Result<RequestLine> {
+    buf.clear();
+    let limit = MAX_REQUEST_BYTES as u64 + 1;
+    if reader.take(limit).read_until(b'\n', buf).await? == 0 {
+        return Ok(RequestLine::End);
+    }
+    if buf.last() == Some(&b'\n') {
+        buf.pop();
+    } else if buf.len() > MAX_REQUEST_BYTES {
+        return Ok(RequestLine::TooLong);
+    }
+    Ok(RequestLine::Line(std::mem::take(buf)))
+}
+
+/// Put the thresholds back whenever the firmware has dropped them.
+async fn keep_thresholds(thresholds: Arc<Mutex<Thresholds>>) {
+    loop {
+        let wanted = thresholds.lock().map(|t| t.clone()).unwrap_or_default();
+        restore_thresholds(&wanted).await;
+        tokio::time::sleep(THRESHOLD_CHECK).await;
+    }
+}
+
+/// Re-apply each battery's thresholds where they no longer read as set. A
+/// battery the daemon holds no setting for is left alone.
+async fn restore_thresholds(wanted: &Thresholds) {
+    for paths in battery::all_threshold_paths() {
+        let Some(&wanted) = wanted.get(&paths.battery) else {
+            continue;
+        };
+        let current = battery::read_thresholds(&paths);
+        if current == wanted {
+            continue;
+        }
+        println!(
+            "[Daemon] {} thresholds read {}%-{}%; re-applying {}%-{}%",
+            paths.battery, current.start, current.stop, wanted.start, wanted.stop
+        );
+        let applied =
+            battery::set_battery_thresholds(wanted.start, wanted.stop, Some(paths.battery.clone()))
+                .await;
+        if let Some(e) = applied.error {
+            eprintln!(
+                "[Daemon] Failed to re-apply {} thresholds: {}",
+                paths.battery, e
+            );
+        }
+    }
+}
+
+/// Bind the socket, refusing to take it from a daemon that is still running.
+///
+/// Mode 0660, owned by root and [`SOCKET_GROUP`]: only members may connect,
+/// and changes are checked per request against the peer's uid (see
+/// [`ADMIN_GROUPS`]). Without the group, only root may connect.
+fn bind(socket: &Path) -> std::io::Result<UnixListener> {
+    if std::os::unix::net::UnixStream::connect(socket).is_ok() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AddrInUse,
+            format!("another daemon is listening on {}", socket.display()),
+        ));
+    }
+    // Left behind by a daemon that did not shut down cleanly.
+    let _ = std::fs::remove_file(socket);
+    if let Some(dir) = socket.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let listener = UnixListener::bind(socket)?;
+    use std::os::unix::fs::PermissionsExt;
+    let mode = match group_id(SOCKET_GROUP) {
+        Some(gid) => {
+            std::os::unix::fs::chown(socket, Some(0), Some(gid))?;
+            0o660
+        }
+        None => {
+            eprintln!(
+                "[Daemon] No {} group; only root can use the socket",
+                SOCKET_GROUP
+            );
+            0o600
+        }
+    };
+    std::fs::set_permissions(socket, std::fs::Permissions::from_mode(mode))?;
+    Ok(listener)
+}
+
+/// Run the daemon: the curve loop, the threshold keeper, and the socket.
+/// Only returns on a socket error; the caller handles shutdown signals.
+pub async fn serve(socket: PathBuf, config: PathBuf) -> std::io::Result<()> {
+    let (curve, thresholds) = load_config(&config);
+    let (events, _) = broadcast::channel(64);
+    let daemon = Daemon {
+        host: DaemonHost { events, config },
+        curve: FanCurveState::new(Mutex::new(curve)),
+        thresholds: Arc::new(Mutex::new(thresholds)),
+    };
+
+    let listener = bind(&socket)?;
+    println!("[Daemon] Listening on {}", socket.display());
+
+    tokio::spawn(fan_curve::run_curve_loop(
+        daemon.host.clone(),
+        daemon.curve.clone(),
+    ));
+    tokio::spawn(keep_thresholds(daemon.thresholds.clone()));
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let daemon = daemon.clone();
+        tokio::spawn(async move {
+            if let Err(e) = daemon.handle(stream).await {
+                eprintln!("[Daemon] Connection ended: {}", e);
+            }
+        });
+    }
+}
+
+/// Hand the fan back on the way out, as the GUI does when it exits, and
+/// remove the socket so the next start does not mistake it for a live one.
+pub fn shutdown(socket: &Path) {
+    fan_curve::restore_fan_to_auto_blocking();
+    let _ = std::fs::remove_file(socket);
+}
+
+// -- Client --
+
+/// The daemon this process is a client of, once [`attach`] has found one.
+static ATTACHED: OnceLock<PathBuf> = OnceLock::new();
+
+/// Become a client of the daemon at `socket` if one answers there.
+///
+/// A daemon whose socket this user may not open still counts. Running the
+/// curve here as well would fight it for the fan; as its client, every
+/// request fails and says why.
+pub fn attach(socket: &Path) -> bool {
+    if request(socket, &Request::Status).is_err() && !denied(socket) {
+        return false;
+    }
+    ATTACHED.get_or_init(|| socket.to_path_buf());
+    true
+}
+
+fn denied(socket: &Path) -> bool {
+    matches!(
+        std::os::unix::net::UnixStream::connect(socket),
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied
+    )
+}
+
+/// The daemon socket, if this process is a client of one.
+pub fn attached() -> Option<&'static Path> {
+    ATTACHED.get().map(PathBuf::as_path)
+}
+
+fn connect(socket: &Path) -> Result<std::os::unix::net::UnixStream, String> {
+    std::os::unix::net::UnixStream::connect(socket).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::PermissionDenied {
+            return format!(
+                "thinkutils-daemon at {} only accepts members of the {} group",
+                socket.display(),
+                SOCKET_GROUP
+            );
+        }
+        format!(
+            "thinkutils-daemon is not reachable at {}: {}",
+            socket.display(),
+            e
+        )
+    })
+}
+
+fn send(stream: &mut std::os::unix::net::UnixStream, request: &Request) -> Result<(), String> {
+    let mut line =
+        serde_json::to_string(request).map_err(|e| format!("Failed to encode request: {}", e))?;
+    line.push('\n');
+    stream
+        .write_all(line.as_bytes())
+        .map_err(|e| format!("Failed to send to thinkutils-daemon: {}", e))
+}
+
+/// Read one response. A failure keeps the daemon's code.
+fn receive(reader: &mut impl BufRead) -> Result<serde_json::Value, Error> {
+    let mut line = String::new();
+    match reader.read_line(&mut line) {
+        Ok(0) => return Err("thinkutils-daemon closed the connection".into()),
+        Ok(_) => {}
+        Err(e) => return Err(format!("Failed to read from thinkutils-daemon: {}", e).into()),
+    }
+    let response: ApiResponse<serde_json::Value> = serde_json::from_str(&line)
+        .map_err(|e| format!("Invalid reply from thinkutils-daemon: {}", e))?;
+    match response.failure() {
+        None => Ok(response.data.unwrap_or(serde_json::Value::Null)),
+        Some(e) => Err(e),
+    }
+}
+
+/// Send one request and wait for its response. Blocking, with a timeout.
+pub fn request(socket: &Path, request: &Request) -> Result<serde_json::Value, Error> {
+    let mut stream = connect(socket)?;
+    let _ = stream.set_read_timeout(Some(CLIENT_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(CLIENT_TIMEOUT));
+    send(&mut stream, request)?;
+    receive(&mut BufReader::new(stream))
+}
+
+/// [`request`] for async callers: the round trip runs on the blocking pool,
+/// so a hung daemon holds up the command that asked rather than a runtime
+/// worker.
+pub async fn call(socket: &'static Path, request: Request) -> Result<serde_json::Value, Error> {
+    tokio::task::spawn_blocking(move || self::request(socket, &request))
+        .await
+        .map_err(|e| Error::failed(format!("Daemon request did not finish: {}", e)))?
+}
+
+/// Subscribe and hand every event to `on_event` until the connection ends,
+/// which is always an error: the daemon never ends a subscription itself.
+pub fn subscribe(socket: &Path, mut on_event: impl FnMut(Event)) -> Result<(), String> {
+    let mut stream = connect(socket)?;
+    send(&mut stream, &Request::Subscribe)?;
+    let mut reader = BufReader::new(stream);
+    receive(&mut reader)?;
+
+    for line in reader.lines() {
+        let line = line.map_err(|e| format!("Failed to read from thinkutils-daemon: {}", e))?;
+        match serde_json::from_str::<Event>(&line) {
+            Ok(event) => on_event(event),
+            Err(e) => eprintln!("[Daemon] Ignoring malformed event: {}", e),
+        }
+    }
+    Err("thinkutils-daemon closed the connection".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_come_from_nss() {
+        // SAFETY: neither call can fail or touch memory.
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        let groups = group_ids(uid).expect("the running user resolves");
+        assert!(groups.contains(&gid));
+        assert_eq!(group_id("root"), Some(0));
+        assert_eq!(group_ids(0x7fff_fff0), None);
+        assert_eq!(group_id("no-such-group-thinkutils-test"), None);
+        assert!(may_change_settings(0));
+        assert!(!may_change_settings(0x7fff_fff0));
+    }
+
+    #[test]
+    fn only_setters_need_authorization() {
+        assert!(!Request::Status.changes_settings());
+        assert!(!Request::GetFanCurve.changes_settings());
+        assert!(!Request::Subscribe.changes_settings());
+        assert!(Request::EnableFanCurve { enabled: false }.changes_settings());
+        assert!(Request::SetBatteryThresholds {
+            start: 40,
+            stop: 80,
+            battery: None,
+        }
+        .changes_settings());
+    }
+
+    /// The wire format is part of the interface: scripts talk to the socket
+    /// with nothing more than `socat`.
+    #[test]
+    fn requests_are_tagged_by_command() {
+        let r: Request =
+            serde_json::from_str(r#"{"cmd":"enable_fan_curve","enabled":true}"#).unwrap();
+        assert!(matches!(r, Request::EnableFanCurve { enabled: true }));
+        assert_eq!(
+            serde_json::to_value(Request::SetBatteryThresholds {
+                start: 40,
+                stop: 80,
+                battery: None,
+            })
+            .unwrap(),
+            serde_json::json!({ "cmd": "set_battery_thresholds", "start": 40, "stop": 80 })
+        );
+        let r: Request = serde_json::from_str(
+            r#"{"cmd":"set_battery_thresholds","start":40,"stop":80,"battery":"BAT1"}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            r,
+            Request::SetBatteryThresholds { battery: Some(name), .. } if name == "BAT1"
+        ));
+    }
+
+    fn temp_config() -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "thinkutils_daemon_{}_{}/daemon.json",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ))
+    }
+
+    #[test]
+    fn saving_one_key_keeps_the_others() {
+        let path = temp_config();
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, r#"{"app_settings":{"theme":"dark"}}"#).unwrap();
+
+        save_config_key(
+            &path,
+            THRESHOLDS_KEY,
+            serde_json::json!({ "BAT1": { "start": 40, "stop": 80 } }),
+        )
+        .unwrap();
+        let (_, thresholds) = load_config(&path);
+        let object = read_config_object(&path).unwrap();
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+
+        assert_eq!(
+            thresholds,
+            Thresholds::from([(
+                "BAT1".to_string(),
+                BatteryThresholds {
+                    start: 40,
+                    stop: 80
+                }
+            )])
+        );
+        assert_eq!(object["app_settings"]["theme"], "dark");
+    }
+
+    /// The GUI's copy predates the daemon's switch to the battery curve; its
+    /// edit must not switch it back.
+    #[test]
+    fn an_edit_lands_on_the_daemons_current_config() {
+        let path = temp_config();
+        let mut curve = FanCurveConfig::default();
+        let points = curve.points.clone();
+        curve.curves.insert("Quiet".to_string(), points);
+        let stale = curve.clone();
+        curve
+            .apply(CurveChange::Activate {
+                name: "Quiet".to_string(),
+            })
+            .unwrap();
+        let (events, _) = broadcast::channel(4);
+        let daemon = Daemon {
+            host: DaemonHost {
+                events,
+                config: path.clone(),
+            },
+            curve: FanCurveState::new(Mutex::new(curve)),
+            thresholds: Arc::new(Mutex::new(Thresholds::new())),
+        };
+
+        let stall = fan_curve::StallPolicy {
+            ticks: 5,
+            ..stale.stall.clone()
+        };
+        let request = Request::UpdateFanCurve {
+            change: CurveChange::Stall(stall.clone()),
+        };
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let response = runtime.block_on(daemon.dispatch(request));
+        let (saved, _) = load_config(&path);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+
+        let reply: FanCurveConfig = serde_json::from_value(response.data.unwrap()).unwrap();
+        assert_eq!(reply.active_curve, "Quiet");
+        assert_eq!(reply.stall, stall);
+        assert_eq!(saved.active_curve, "Quiet");
+        assert_ne!(stale.active_curve, "Quiet");
+    }
+
+    #[test]
+    fn a_failed_edit_changes_nothing() {
+        let path = temp_config();
+        let (events, _) = broadcast::channel(4);
+        let daemon = Daemon {
+            host: DaemonHost {
+                events,
+                config: path.clone(),
+            },
+            curve: FanCurveState::new(Mutex::new(FanCurveConfig::default())),
+            thresholds: Arc::new(Mutex::new(Thresholds::new())),
+        };
+
+        let request = Request::UpdateFanCurve {
+            change: CurveChange::Activate {
+                name: "Missing".to_string(),
+            },
+        };
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let response = runtime.block_on(daemon.dispatch(request));
+
+        assert!(response.failure().is_some());
+        assert!(!path.exists());
+        assert_eq!(
+            daemon.curve.lock().unwrap().active_curve,
+            FanCurveConfig::default().active_curve
+        );
+    }
+
+    #[test]
+    fn a_missing_or_broken_config_starts_on_defaults() {
+        let path = temp_config();
+        let (curve, thresholds) = load_config(&path);
+        assert!(!curve.enabled);
+        assert!(thresholds.is_empty());
+
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "not json").unwrap();
+        let (curve, _) = load_config(&path);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+        assert_eq!(curve.points, FanCurveConfig::default().points);
+    }
+
+    /// A config from before the daemon kept batteries apart holds one setting;
+    /// it still applies to every battery.
+    #[test]
+    fn an_older_single_setting_covers_every_battery() {
+        crate::hardware_root::testing::with_profile("thinkpad-p1-gen-4i", || {
+            let path = temp_config();
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, r#"{"battery_thresholds":{"start":40,"stop":80}}"#).unwrap();
+            let (_, thresholds) = load_config(&path);
+            let _ = std::fs::remove_dir_all(path.parent().unwrap());
+
+            assert_eq!(
+                thresholds,
+                Thresholds::from([(
+                    "BAT0".to_string(),
+                    BatteryThresholds {
+                        start: 40,
+                        stop: 80
+                    }
+                )])
+            );
+        });
+    }
+
+    /// Each battery is checked against its own setting, not the primary's.
+    #[test]
+    fn drift_is_corrected_on_every_battery() {
+        crate::hardware_root::testing::with_scratch_profile("thinkpad-p1-gen-4i", |root| {
+            let bat1 = root.join("sys/class/power_supply/BAT1");
+            std::fs::create_dir_all(&bat1).unwrap();
+            for (attr, value) in [
+                ("type", "Battery"),
+                ("charge_control_start_threshold", "0"),
+                ("charge_control_end_threshold", "100"),
+            ] {
+                std::fs::write(bat1.join(attr), format!("{}\n", value)).unwrap();
+            }
+            let wanted = Thresholds::from([
+                (
+                    "BAT0".to_string(),
+                    BatteryThresholds {
+                        start: 75,
+                        stop: 80,
+                    },
+                ),
+                (
+                    "BAT1".to_string(),
+                    BatteryThresholds {
+                        start: 40,
+                        stop: 60,
+                    },
+                ),
+            ]);
+
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime.block_on(restore_thresholds(&wanted));
+
+            let current: Thresholds = battery::all_threshold_paths()
+                .iter()
+                .map(|paths| (paths.battery.clone(), battery::read_thresholds(paths)))
+                .collect();
+            assert_eq!(current, wanted);
+        });
+    }
+}
@@ -6,17 +6,11 @@
 //! refuses fan writes regardless of privilege, and one where the polkit rule we
 //! install is silently ignored.
 
+use crate::error::ApiResponse;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::process::Command;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ApiResponse<T> {
-    pub success: bool,
-    pub data: Option<T>,
-    pub error: Option<String>,
-}
-
 /// The first polkit release whose rules engine reads JavaScript `.rules` files.
 ///
 /// Debian and Ubuntu shipped 0.105 with that engine patched out, so the passwordless
@@ -52,8 +46,6 @@ impl PackageManager {
     /// Package names differ per distro for the same software.
     pub fn package_name(&self, tool: Tool) -> &'static str {
         match (self, tool) {
-            (PackageManager::Apt, Tool::Sensors) => "lm-sensors",
-            (_, Tool::Sensors) => "lm_sensors",
             (PackageManager::Apt, Tool::Polkit) => "policykit-1",
             (_, Tool::Polkit) => "polkit",
             // These happen to share a name across all four package managers.
@@ -73,7 +65,6 @@ impl PackageManager {
 /// everywhere, and listing them would bury the ones that matter.
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum Tool {
-    Sensors,
     Polkit,
     Clamav,
     PowerProfiles,
@@ -82,20 +73,13 @@ pub enum Tool {
 
 impl Tool {
     pub fn all() -> &'static [Tool] {
-        &[
-            Tool::Polkit,
-            Tool::Sensors,
-            Tool::PowerProfiles,
-            Tool::Tlp,
-            Tool::Clamav,
-        ]
+        &[Tool::Polkit, Tool::PowerProfiles, Tool::Tlp, Tool::Clamav]
     }
 
     /// The binary to probe for. `pkexec` rather than `polkitd`, because what
     /// matters is whether we can *invoke* elevation, not whether a daemon exists.
     pub fn binary(&self) -> &'static str {
         match self {
-            Tool::Sensors => "sensors",
             Tool::Polkit => "pkexec",
             Tool::Clamav => "clamscan",
             Tool::PowerProfiles => "powerprofilesctl",
@@ -107,7 +91,6 @@ impl Tool {
     /// most of these are optional and the app is still useful without them.
     pub fn enables(&self) -> &'static str {
         match self {
-            Tool::Sensors => "Temperature readings, and the fan curve that follows them",
             Tool::Polkit => "Fan, CPU and battery changes",
             Tool::Clamav => "Virus scanning on the Security page",
             Tool::PowerProfiles => "Power profile switching (Balanced, Performance, Power Saver)",
@@ -341,7 +324,6 @@ pub fn build_setup_steps(
     pm: &PackageManager,
     js_rules_supported: Option<bool>,
     channel: &InstallChannel,
-    sensors_present: bool,
     pkexec_present: bool,
     fan_control_enabled: bool,
     modprobe_conf_present: bool,
@@ -358,15 +340,6 @@ pub fn build_setup_steps(
         });
     }
 
-    if !sensors_present {
-        steps.push(SetupStep {
-            title: "Install lm-sensors".to_string(),
-            detail: "Needed to read temperatures. Fan control works without it, but the fan curve has nothing to follow.".to_string(),
-            command: pm.install_command(&[pm.package_name(Tool::Sensors)]),
-            actionable_in_app: false,
-        });
-    }
-
     if !fan_control_enabled {
         let detail = if modprobe_conf_present {
             "The setting is saved but the running module still has it off. Reboot, or reload the module."
@@ -390,8 +363,8 @@ pub fn build_setup_steps(
 
     if !helper_installed && *channel != InstallChannel::System {
         steps.push(SetupStep {
-            title: "Install the fan control helper".to_string(),
-            detail: "Installs a small root-owned helper that accepts only fan level commands, plus a polkit rule scoped to it.".to_string(),
+            title: "Install the hardware helper".to_string(),
+            detail: "Installs a small root-owned helper that accepts only fan, battery threshold and CPU settings, its D-Bus service, and polkit actions scoped to it.".to_string(),
             command: None,
             actionable_in_app: true,
         });
@@ -400,8 +373,8 @@ pub fn build_setup_steps(
     // Only warn once the rule would actually be relied upon.
     if js_rules_supported == Some(false) {
         steps.push(SetupStep {
-            title: "Expect a password prompt for fan changes".to_string(),
-            detail: "This system's polkit is older than 0.106 and ignores JavaScript rules, which is how ThinkUtils grants passwordless fan control. Everything works, but each fan change asks for your password. Upgrading the distribution is the only fix.".to_string(),
+            title: "Passwordless fan control uses the older polkit grant".to_string(),
+            detail: "This system's polkit is older than 0.106 and ignores JavaScript rules, which is how ThinkUtils grants passwordless fan control elsewhere. Here it comes from a local-authority file, /var/lib/polkit-1/localauthority/10-vendor.d/50-thinkutils.pkla, which Setup Permissions and the Debian package install. Without it each fan change asks for your password.".to_string(),
             command: None,
             actionable_in_app: false,
         });
@@ -445,7 +418,7 @@ pub fn get_system_report() -> ApiResponse<SystemReport> {
         std::path::Path::new("/.flatpak-info").exists(),
     );
 
-    let fan_proc = fs::read_to_string("/proc/acpi/ibm/fan").unwrap_or_default();
+    let fan_proc = crate::hardware_root::read_to_string("/proc/acpi/ibm/fan").unwrap_or_default();
     let fan_control_enabled = fan_proc
         .lines()
         .any(|l| l.trim_start().starts_with("commands:"));
@@ -459,32 +432,27 @@ pub fn get_system_report() -> ApiResponse<SystemReport> {
         &package_manager,
         polkit_supports_js_rules(polkit_parsed),
         &install_channel,
-        command_exists("sensors"),
         command_exists("pkexec"),
         fan_control_enabled,
         modprobe_conf_present,
-        crate::fan_control::helper_path().is_some(),
+        crate::helper::helper_path().is_some(),
     );
 
-    ApiResponse {
-        success: true,
-        data: Some(SystemReport {
-            distro,
-            package_manager,
-            polkit_version: polkit_parsed.map(|(a, b)| {
-                if a == 0 {
-                    format!("{}.{}", a, b)
-                } else {
-                    a.to_string()
-                }
-            }),
-            polkit_supports_js_rules: polkit_supports_js_rules(polkit_parsed),
-            install_channel,
-            tools,
-            steps,
+    ApiResponse::ok(SystemReport {
+        distro,
+        package_manager,
+        polkit_version: polkit_parsed.map(|(a, b)| {
+            if a == 0 {
+                format!("{}.{}", a, b)
+            } else {
+                a.to_string()
+            }
         }),
-        error: None,
-    }
+        polkit_supports_js_rules: polkit_supports_js_rules(polkit_parsed),
+        install_channel,
+        tools,
+        steps,
+    })
 }
 
 #[cfg(test)]
@@ -563,14 +531,7 @@ mod tests {
 
     #[test]
     fn uses_distro_correct_package_names() {
-        assert_eq!(
-            PackageManager::Apt.package_name(Tool::Sensors),
-            "lm-sensors"
-        );
-        assert_eq!(
-            PackageManager::Dnf.package_name(Tool::Sensors),
-            "lm_sensors"
-        );
+        assert_eq!(PackageManager::Dnf.package_name(Tool::Polkit), "polkit");
         assert_eq!(
             PackageManager::Apt.package_name(Tool::Polkit),
             "policykit-1"
@@ -656,22 +617,34 @@ mod tests {
     fn reports_every_tool_the_app_shells_out_to() {
         let statuses = survey_with(PackageManager::Apt, &[]);
         let binaries: Vec<&str> = statuses.iter().map(|s| s.binary.as_str()).collect();
-        for expected in [
-            "pkexec",
-            "sensors",
-            "powerprofilesctl",
-            "tlp-stat",
-            "clamscan",
-        ] {
+        for expected in ["pkexec", "powerprofilesctl", "tlp-stat", "clamscan"] {
             assert!(binaries.contains(&expected), "{} not surveyed", expected);
         }
     }
 
+    /// Temperatures come from hwmon directly now. Listing lm-sensors as missing
+    /// would send users to install a package nothing uses.
+    #[test]
+    fn lm_sensors_is_not_a_dependency() {
+        let statuses = survey_with(PackageManager::Apt, &[]);
+        assert!(!statuses.iter().any(|s| s.binary == "sensors"));
+        let steps = build_setup_steps(
+            &PackageManager::Apt,
+            Some(true),
+            &InstallChannel::System,
+            true,
+            true,
+            true,
+            true,
+        );
+        assert!(!steps.iter().any(|s| s.title.contains("sensors")));
+    }
+
     #[test]
     fn present_tools_get_no_install_command() {
-        let statuses = survey_with(PackageManager::Apt, &["pkexec", "sensors"]);
+        let statuses = survey_with(PackageManager::Apt, &["pkexec", "clamscan"]);
         for s in &statuses {
-            if s.binary == "pkexec" || s.binary == "sensors" {
+            if s.binary == "pkexec" || s.binary == "clamscan" {
                 assert!(s.present, "{} should be present", s.binary);
                 assert!(s.install_command.is_none(), "{} needs no install", s.binary);
             }
@@ -687,7 +660,10 @@ mod tests {
                 .find(|s| s.binary == bin)
                 .and_then(|s| s.install_command.clone())
         };
-        assert_eq!(cmd("sensors"), Some("sudo dnf install lm_sensors".into()));
+        assert_eq!(
+            cmd("powerprofilesctl"),
+            Some("sudo dnf install power-profiles-daemon".into())
+        );
         assert_eq!(cmd("pkexec"), Some("sudo dnf install polkit".into()));
         assert_eq!(cmd("clamscan"), Some("sudo dnf install clamav".into()));
     }
@@ -764,7 +740,6 @@ mod tests {
             true,
             true,
             true,
-            true,
         )
     }
 
@@ -780,14 +755,12 @@ mod tests {
             Some(true),
             &InstallChannel::System,
             false,
-            false,
             true,
             true,
             true,
         );
         let commands: Vec<_> = steps.iter().filter_map(|s| s.command.clone()).collect();
         assert!(commands.iter().any(|c| c == "sudo dnf install polkit"));
-        assert!(commands.iter().any(|c| c == "sudo dnf install lm_sensors"));
     }
 
     /// Once the config is written, telling the user to write it again is noise --
@@ -799,7 +772,6 @@ mod tests {
             Some(true),
             &InstallChannel::System,
             true,
-            true,
             false,
             false,
             true,
@@ -815,7 +787,6 @@ mod tests {
             Some(true),
             &InstallChannel::System,
             true,
-            true,
             false,
             true,
             true,
@@ -838,7 +809,6 @@ mod tests {
             true,
             true,
             true,
-            true,
         );
         assert_eq!(steps.len(), 1);
         assert!(steps[0].detail.contains("0.106"));
@@ -859,7 +829,6 @@ mod tests {
             true,
             true,
             true,
-            true,
             false,
         );
         assert!(!packaged.iter().any(|s| s.title.contains("helper")));
@@ -871,7 +840,6 @@ mod tests {
             true,
             true,
             true,
-            true,
             false,
         );
         assert!(appimage.iter().any(|s| s.title.contains("helper")));
@@ -884,7 +852,6 @@ mod tests {
             &PackageManager::Apt,
             Some(true),
             &InstallChannel::AppImage,
-            true,
             false,
             true,
             true,
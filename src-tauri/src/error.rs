@@ -0,0 +1,216 @@
+//! The one error type the commands report, and the one response that carries it.
+//!
+//! Every module used to define its own identical `ApiResponse` with a free-text
+//! `error`, and the frontend told "the kernel refuses because fan_control=0"
+//! from "no permission" from "this machine has no such control" by searching
+//! the message. The message is still there for people; [`ErrorCode`] is what
+//! code should look at, and its serialized names do not change between
+//! releases.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Why something failed, in terms the UI can act on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The write was not allowed: no permission on the file and no helper to
+    /// escalate through, or polkit said no. Granting permissions fixes it.
+    PermissionDenied,
+    /// thinkpad_acpi was loaded without `fan_control=1`, so the kernel refuses
+    /// fan writes whatever the permissions.
+    ModuleParameterMissing,
+    /// This machine has no such control: no fan interface, no charge
+    /// thresholds, no turbo switch.
+    HardwareAbsent,
+    /// The request itself is wrong: an unknown fan level, thresholds in the
+    /// wrong order, a governor the CPU does not offer.
+    Validation,
+    /// Running against a simulated hardware profile, which refused the change
+    /// or has nothing to do for it.
+    Simulated,
+    /// A program ThinkUtils drives is not installed. The message says how to
+    /// install it where that is known.
+    ToolMissing,
+    /// Anything else; the message says what.
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Error {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl Error {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Error {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn permission_denied(message: impl Into<String>) -> Self {
+        Error::new(ErrorCode::PermissionDenied, message)
+    }
+
+    pub fn module_parameter_missing(message: impl Into<String>) -> Self {
+        Error::new(ErrorCode::ModuleParameterMissing, message)
+    }
+
+    pub fn hardware_absent(message: impl Into<String>) -> Self {
+        Error::new(ErrorCode::HardwareAbsent, message)
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        Error::new(ErrorCode::Validation, message)
+    }
+
+    pub fn simulated(message: impl Into<String>) -> Self {
+        Error::new(ErrorCode::Simulated, message)
+    }
+
+    pub fn tool_missing(message: impl Into<String>) -> Self {
+        Error::new(ErrorCode::ToolMissing, message)
+    }
+
+    pub fn failed(message: impl Into<String>) -> Self {
+        Error::new(ErrorCode::Failed, message)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// An unclassified message. Code that knows better says so with one of the
+/// constructors.
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::failed(message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::failed(message)
+    }
+}
+
+/// For the many internal functions that still return `Result<_, String>`.
+impl From<Error> for String {
+    fn from(error: Error) -> Self {
+        error.message
+    }
+}
+
+/// What every Tauri command returns.
+///
+/// `error` stays a plain message so existing callers keep displaying it;
+/// `code` is set whenever `error` is.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiResponse<T> {
+    pub success: bool,
+    pub data: Option<T>,
+    pub error: Option<String>,
+    /// Absent from responses written before codes existed, such as an older
+    /// daemon's.
+    #[serde(default)]
+    pub code: Option<ErrorCode>,
+}
+
+impl<T> ApiResponse<T> {
+    pub fn ok(data: T) -> Self {
+        ApiResponse {
+            success: true,
+            data: Some(data),
+            error: None,
+            code: None,
+        }
+    }
+
+    pub fn err(error: impl Into<Error>) -> Self {
+        let error = error.into();
+        ApiResponse {
+            success: false,
+            data: None,
+            error: Some(error.message),
+            code: Some(error.code),
+        }
+    }
+
+    pub fn from_result<E: Into<Error>>(result: Result<T, E>) -> Self {
+        match result {
+            Ok(data) => ApiResponse::ok(data),
+            Err(e) => ApiResponse::err(e),
+        }
+    }
+
+    /// The failure as an [`Error`], for code that acts on it rather than
+    /// showing it.
+    pub fn failure(&self) -> Option<Error> {
+        self.error.as_ref().map(|message| Error {
+            code: self.code.unwrap_or(ErrorCode::Failed),
+            message: message.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The frontend matches on these strings, so renaming a variant must not
+    /// silently change them.
+    #[test]
+    fn codes_serialize_to_stable_names() {
+        for (code, name) in [
+            (ErrorCode::PermissionDenied, "permission_denied"),
+            (
+                ErrorCode::ModuleParameterMissing,
+                "module_parameter_missing",
+            ),
+            (ErrorCode::HardwareAbsent, "hardware_absent"),
+            (ErrorCode::Validation, "validation"),
+            (ErrorCode::Simulated, "simulated"),
+            (ErrorCode::ToolMissing, "tool_missing"),
+            (ErrorCode::Failed, "failed"),
+        ] {
+            assert_eq!(serde_json::to_value(code).unwrap(), name);
+        }
+    }
+
+    #[test]
+    fn a_failure_carries_its_code_and_message() {
+        let response: ApiResponse<()> = ApiResponse::err(Error::validation("Invalid fan speed: 9"));
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["success"], false);
+        assert_eq!(json["error"], "Invalid fan speed: 9");
+        assert_eq!(json["code"], "validation");
+        assert_eq!(
+            response.failure(),
+            Some(Error::validation("Invalid fan speed: 9"))
+        );
+
+        let response = ApiResponse::ok(3);
+        assert_eq!(
+            serde_json::to_value(&response).unwrap()["code"],
+            serde_json::Value::Null
+        );
+        assert_eq!(response.failure(), None);
+    }
+
+    /// A response from before codes existed still reads, as an unclassified
+    /// failure.
+    #[test]
+    fn responses_without_a_code_still_parse() {
+        let response: ApiResponse<String> =
+            serde_json::from_str(r#"{"success":false,"data":null,"error":"boom"}"#).unwrap();
+        assert_eq!(response.code, None);
+        assert_eq!(response.failure(), Some(Error::failed("boom")));
+    }
+}
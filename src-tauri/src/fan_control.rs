@@ -1,140 +1,135 @@
-use regex::Regex;
+use crate::error::{ApiResponse, Error};
+use crate::hwmon::{SensorKind, SensorReading};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use std::fs;
-use std::process::Command;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
-const PROC_FAN: &str = "/proc/acpi/ibm/fan";
+pub(crate) const PROC_FAN: &str = "/proc/acpi/ibm/fan";
 
-/// Where the privileged helper may live, in the order it is searched.
+/// Parent of thinkpad_acpi's numbered hwmon directory.
 ///
-/// A distro package ships it at one of the first two paths, both of which are
-/// root-owned and package-managed. `/usr/local` is reserved for the local
-/// administrator — Debian Policy §9.1.2 and the Fedora guidelines both forbid a
-/// package writing there — so it appears only as the legacy location used by the
-/// app's own installer, kept so existing installs keep working.
-pub const HELPER_CANDIDATES: &[&str] = &[
-    // Debian and Arch convention for an internal helper not on $PATH.
-    "/usr/lib/thinkutils/thinkutils-fan-control",
-    // Fedora convention (%{_libexecdir}).
-    "/usr/libexec/thinkutils/thinkutils-fan-control",
-    // Legacy: written at runtime by setup_permissions() on a non-packaged install.
-    "/usr/local/bin/thinkutils-fan-control",
-];
-
-/// Where `setup_permissions()` writes the helper when the app installs its own.
-///
-/// Only used when no packaged helper is present — a package-owned file must
-/// never be overwritten at runtime.
-pub const HELPER_SELF_INSTALL_PATH: &str = "/usr/local/bin/thinkutils-fan-control";
+/// The `hwmonN` suffix is assigned at boot, so this is the stable path to
+/// search under. It is also the only place a second fan shows up: procfs has
+/// no `speed2` line.
+pub const THINKPAD_HWMON_DIR: &str = "/sys/devices/platform/thinkpad_hwmon/hwmon";
 
-/// The first helper that actually exists, if any.
-pub fn helper_path() -> Option<&'static str> {
-    HELPER_CANDIDATES
-        .iter()
-        .copied()
-        .find(|p| std::path::Path::new(p).exists())
-}
-
-/// Whether the helper came from a distro package.
-///
-/// When true the app must not install, overwrite, or offer to reinstall it: those
-/// files belong to dpkg/rpm/pacman, and rewriting them puts the package database
-/// out of sync with the filesystem.
-pub fn helper_is_packaged() -> bool {
-    HELPER_CANDIDATES
-        .iter()
-        .take(2)
-        .any(|p| std::path::Path::new(p).exists())
-}
-
-/// Vendor-supplied polkit rules belong under /usr/share; /etc is the
-/// administrator's namespace. A package writing to /etc/polkit-1/rules.d shadows
-/// the admin's own rules and is never cleaned up on uninstall.
-pub const POLKIT_RULE_PATH: &str = "/etc/polkit-1/rules.d/50-thinkutils.rules";
-pub const POLKIT_RULE_PACKAGED_PATH: &str = "/usr/share/polkit-1/rules.d/50-thinkutils.rules";
-
-/// Dedicated fan control helper script - validates input before writing to fan.
-/// Installed at one of HELPER_CANDIDATES.
-pub const HELPER_SCRIPT: &str = r#"#!/bin/bash
-set -e
-FAN="/proc/acpi/ibm/fan"
-# Exact-match whitelist. "watchdog 30" is permitted because the firmware
-# watchdog can only ever return the fan to automatic control -- it is the
-# recovery path if this app dies while holding a manual level. No other
-# watchdog value is accepted, and enable/disable are deliberately absent.
-case "$1" in
-    "level auto"|"level full-speed"|"level 0"|"level 1"|"level 2"|"level 3"|"level 4"|"level 5"|"level 6"|"level 7"|"watchdog 30")
-        echo "$1" > "$FAN"
-        ;;
-    *)
-        echo "Invalid command" >&2
-        exit 1
-        ;;
-esac
-"#;
+/// thinkpad_acpi drives at most two fans. Anything higher in a `fanN` selector
+/// is rejected before it reaches the helper.
+pub const MAX_FANS: u8 = 2;
 
 /// Watchdog timeout, in seconds, that the helper is willing to arm.
 ///
-/// Must stay in sync with the literal in HELPER_SCRIPT above; a test asserts it.
+/// The helper arms this value and no other; see [`FanCommand::Watchdog`].
 pub const FAN_WATCHDOG_SECS: u32 = 30;
 
-/// Build the polkit rule granting passwordless exec to the helper, and nothing
-/// else.
-///
-/// Generated from HELPER_CANDIDATES rather than written out, because the path
-/// used to be duplicated in the rule text and in the constant — and a rule
-/// naming a path the helper is not installed at grants nothing while looking
-/// correct.
+/// A fan level thinkpad_acpi accepts, and the only ones this app sends.
 ///
-/// `subject.local && subject.active` is deliberate: without it, any SSH session
-/// belonging to a wheel/sudo user gets passwordless root-adjacent exec, as does
-/// a backgrounded session the user has switched away from. Fan control is a
-/// physical-console concern.
-pub fn polkit_rule() -> String {
-    let allowed = HELPER_CANDIDATES
-        .iter()
-        .map(|p| format!("            program == \"{}\"", p))
-        .collect::<Vec<_>>()
-        .join(" ||\n");
-
-    format!(
-        r#"/* ThinkUtils: passwordless fan control via the dedicated helper only.
- *
- * Generated from HELPER_CANDIDATES -- do not edit by hand. The helper accepts
- * fan level commands and nothing else, so this grants exactly that and no
- * general privilege.
- */
-polkit.addRule(function(action, subject) {{
-    if (action.id == "org.freedesktop.policykit.exec") {{
-        var program = action.lookup("program");
-        if (
-{}
-        ) {{
-            /* Local, active sessions only: an SSH session must not inherit
-               passwordless hardware control. */
-            if (subject.local && subject.active &&
-                (subject.isInGroup("wheel") || subject.isInGroup("sudo"))) {{
-                return polkit.Result.YES;
-            }}
-        }}
-    }}
-}});
-"#,
-        allowed
-    )
+/// This is the whitelist: the Tauri commands, the MCP tools, the CLI, the
+/// curve and the privileged helper all go through it, so adding a mode is a change
+/// here and nowhere else. It parses from and displays as the word procfs uses
+/// after `level`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FanLevel {
+    /// The firmware's own curve.
+    Auto,
+    /// The fastest regulated speed.
+    FullSpeed,
+    /// Full, unregulated speed: the EC stops regulating the fan and lets it run
+    /// as fast as it can, which can be faster than level 7 and wears the fan.
+    /// Only accepted with a time limit, after which the fan goes back to auto.
+    Disengaged,
+    /// A fixed level, 0 (stopped) to [`FanLevel::MAX_MANUAL`]. Build it with
+    /// [`FanLevel::manual`], which refuses anything out of range.
+    Manual(u8),
 }
 
-/// Valid fan speed values (whitelist)
-const VALID_SPEEDS: &[&str] = &["auto", "full-speed", "0", "1", "2", "3", "4", "5", "6", "7"];
+impl FanLevel {
+    /// The highest fixed level thinkpad_acpi accepts.
+    pub const MAX_MANUAL: u8 = 7;
 
-/// Whether a fan speed is one this app is willing to send to the hardware.
-///
-/// This is the Rust-side gate; the installed helper script re-checks the full
-/// `level <speed>` string independently, so a bypass here still hits a second
-/// whitelist before anything is written.
-fn is_valid_speed(speed: &str) -> bool {
-    VALID_SPEEDS.contains(&speed)
+    /// A fixed level, if it is one the firmware has.
+    pub fn manual(level: i32) -> Option<FanLevel> {
+        u8::try_from(level)
+            .ok()
+            .filter(|l| *l <= Self::MAX_MANUAL)
+            .map(FanLevel::Manual)
+    }
+
+    /// Every level, in the order they are listed to the user.
+    pub fn all() -> impl Iterator<Item = FanLevel> {
+        [FanLevel::Auto, FanLevel::FullSpeed, FanLevel::Disengaged]
+            .into_iter()
+            .chain((0..=Self::MAX_MANUAL).map(FanLevel::Manual))
+    }
+}
+
+impl std::fmt::Display for FanLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FanLevel::Auto => f.write_str("auto"),
+            FanLevel::FullSpeed => f.write_str("full-speed"),
+            FanLevel::Disengaged => f.write_str("disengaged"),
+            FanLevel::Manual(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+/// Exact match only: no trimming, no case folding. The string reaches a shell
+/// as `level <speed>`, so anything not spelled exactly as a level is refused.
+impl std::str::FromStr for FanLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FanLevel::all()
+            .find(|level| level.to_string() == s)
+            .ok_or_else(|| format!("Invalid fan speed: {}", s))
+    }
+}
+
+/// The longest time limit disengaged mode accepts.
+pub const MAX_DISENGAGED_SECS: u64 = 600;
+
+/// The checks a fan change passes before anything is written: the speed is
+/// whitelisted, disengaged mode comes with a time limit and nothing else does,
+/// and the fan, if one is named, is one thinkpad_acpi can drive.
+pub(crate) fn validate_fan_request(
+    speed: &str,
+    fan: Option<u8>,
+    limit_secs: Option<u64>,
+) -> Result<FanLevel, String> {
+    let level: FanLevel = speed.parse()?;
+    let disengaged = level == FanLevel::Disengaged;
+    match limit_secs {
+        None if disengaged => {
+            return Err(format!(
+                "Disengaged mode needs a time limit of 1-{} seconds",
+                MAX_DISENGAGED_SECS
+            ))
+        }
+        Some(secs) if disengaged && !(1..=MAX_DISENGAGED_SECS).contains(&secs) => {
+            return Err(format!(
+                "A time limit of {}s is out of range for disengaged mode (1-{}s)",
+                secs, MAX_DISENGAGED_SECS
+            ))
+        }
+        Some(_) if !disengaged => return Err("Only disengaged mode takes a time limit".to_string()),
+        _ => {}
+    }
+    match fan {
+        Some(n) if !(1..=MAX_FANS).contains(&n) => Err(format!("Invalid fan: {}", n)),
+        _ => Ok(level),
+    }
+}
+
+/// What thinkpad_acpi reports in /proc/acpi/ibm/fan.
+#[derive(Debug, Default, PartialEq)]
+struct ProcFan {
+    status: Option<String>,
+    level: Option<String>,
+    /// The first fan's RPM. procfs has no line for a second fan.
+    speed: Option<u32>,
 }
 
 /// Parse the contents of /proc/acpi/ibm/fan.
@@ -147,32 +142,163 @@ fn is_valid_speed(speed: &str) -> bool {
 /// ```
 /// Unknown keys are ignored — the file carries commands and capability hints
 /// (`commands:`, `watchdog:`) that are not fan readings.
-fn parse_fan_proc(content: &str) -> HashMap<String, String> {
-    let mut fans = HashMap::new();
+fn parse_fan_proc(content: &str) -> ProcFan {
+    let mut fan = ProcFan::default();
 
     for line in content.lines() {
         if let Some((key, value)) = line.split_once(':') {
-            let key = key.trim();
             let value = value.trim();
 
-            match key {
-                "status" => {
-                    fans.insert("status".to_string(), value.to_string());
-                }
-                "level" => {
-                    fans.insert("level".to_string(), value.to_string());
-                }
-                "speed" => {
-                    fans.insert("Fan1".to_string(), format!("{} RPM", value));
-                }
+            match key.trim() {
+                "status" => fan.status = Some(value.to_string()),
+                "level" => fan.level = Some(value.to_string()),
+                "speed" => fan.speed = value.parse().ok(),
                 _ => {}
             }
         }
     }
 
+    fan
+}
+
+/// The first fan's RPM as thinkpad_acpi reports it, through the hardware root.
+pub(crate) fn read_fan_rpm() -> Option<u32> {
+    crate::hardware_root::read_to_string(PROC_FAN)
+        .ok()
+        .and_then(|c| parse_fan_proc(&c).speed)
+}
+
+/// One physical fan, as seen through thinkpad_acpi's hwmon device.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fan {
+    /// 1-based, matching hwmon's `fanN_input` and the helper's `fanN` selector.
+    pub index: u8,
+    /// None when the tachometer exists but could not be read.
+    pub rpm: Option<u32>,
+    /// The level procfs reports. There is a single `level:` line, so fans that
+    /// share a channel always report the same value.
+    pub level: Option<String>,
+    /// The `pwmN` attribute driving this fan. A P1 Gen 4i reports 1 for both
+    /// fans: one channel, two tachometers.
+    pub pwm_channel: Option<u8>,
+}
+
+/// Every fan under a thinkpad hwmon parent directory, ordered by index.
+///
+/// A fan exists when its `fanN_input` does. Its channel is `pwmN` when that
+/// exists, otherwise the shared `pwm1` -- thinkpad_acpi exposes one PWM and
+/// writes the same level to both fans on every dual-fan model seen so far.
+fn fans_in(hwmon_parent: &Path, level: Option<&str>) -> Vec<Fan> {
+    let mut fans = Vec::new();
+    let Ok(entries) = fs::read_dir(hwmon_parent) else {
+        return fans;
+    };
+
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        for index in 1..=MAX_FANS {
+            let Ok(raw) = fs::read_to_string(dir.join(format!("fan{}_input", index))) else {
+                continue;
+            };
+            let pwm_channel = [index, 1]
+                .into_iter()
+                .find(|n| dir.join(format!("pwm{}", n)).exists());
+            fans.push(Fan {
+                index,
+                rpm: raw.trim().parse().ok(),
+                level: level.map(String::from),
+                pwm_channel,
+            });
+        }
+    }
+
+    fans.sort_by_key(|f| f.index);
     fans
 }
 
+/// The fans on this machine, read through the hardware root.
+pub fn discover_fans() -> Vec<Fan> {
+    let level = crate::hardware_root::read_to_string(PROC_FAN)
+        .ok()
+        .and_then(|c| parse_fan_proc(&c).level);
+    fans_in(
+        &crate::hardware_root::resolve(THINKPAD_HWMON_DIR),
+        level.as_deref(),
+    )
+}
+
+/// Whether the firmware accepts a `fanN` selector on level commands.
+///
+/// Like [`fan_control_is_enabled`], this is read off the `commands:` lines:
+/// firmware that can address fans separately says so there. Without it a
+/// `fan2 level 3` is at best ignored, so the app must not offer per-fan control.
+fn per_fan_control_supported(proc_fan_content: &str) -> bool {
+    proc_fan_content
+        .lines()
+        .filter(|l| l.trim_start().starts_with("commands:"))
+        .any(|l| l.contains("fan1") && l.contains("fan2"))
+}
+
+/// The procfs command for a speed, addressed to one fan when `fan` is given.
+///
+/// `fan` must be within `1..=MAX_FANS`; the helper re-checks the whole string
+/// regardless.
+pub(crate) fn fan_command(level: FanLevel, fan: Option<u8>) -> String {
+    match fan {
+        Some(n) => format!("fan{} level {}", n, level),
+        None => format!("level {}", level),
+    }
+}
+
+/// A procfs command the privileged helper is willing to write: a level, for
+/// every fan or one, or the watchdog at [`FAN_WATCHDOG_SECS`].
+///
+/// Parsing accepts exactly the strings [`fan_command`] and the watchdog arm
+/// produce. It runs as root inside the helper, so anything spelled any other
+/// way — a second watchdog value, a `fan3` selector, trailing text — is refused
+/// rather than passed to the kernel to judge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FanCommand {
+    Level { level: FanLevel, fan: Option<u8> },
+    Watchdog,
+}
+
+impl std::fmt::Display for FanCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FanCommand::Level { level, fan } => f.write_str(&fan_command(*level, *fan)),
+            FanCommand::Watchdog => write!(f, "watchdog {}", FAN_WATCHDOG_SECS),
+        }
+    }
+}
+
+impl std::str::FromStr for FanCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid fan command: {:?}", s);
+        if s == FanCommand::Watchdog.to_string() {
+            return Ok(FanCommand::Watchdog);
+        }
+        let (fan, rest) = match s.split_once(' ') {
+            Some((selector, rest)) if selector.starts_with("fan") => {
+                let n = selector["fan".len()..]
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|n| (1..=MAX_FANS).contains(n) && selector == format!("fan{}", n))
+                    .ok_or_else(invalid)?;
+                (Some(n), rest)
+            }
+            _ => (None, s),
+        };
+        let level = rest
+            .strip_prefix("level ")
+            .and_then(|l| l.parse().ok())
+            .ok_or_else(invalid)?;
+        Ok(FanCommand::Level { level, fan })
+    }
+}
+
 /// Path to the modprobe config that enables fan control at boot.
 pub const MODPROBE_CONF_PATH: &str = "/etc/modprobe.d/thinkpad_acpi.conf";
 
@@ -195,6 +321,64 @@ fn fan_control_is_enabled(proc_fan_content: &str) -> bool {
         .any(|l| l.trim_start().starts_with("commands:"))
 }
 
+/// How thinkpad_acpi answers a write to /proc/acpi/ibm/fan, for a simulated
+/// hardware root. None for any other path.
+///
+/// Follows `fan_write()`: EPERM without `fan_control=1`, EINVAL for a command it
+/// does not know, and a level change shows up on the `level:` line. A `fanN`
+/// selector is only understood when the firmware advertises it.
+pub(crate) fn simulate_write(path: &str, command: &str) -> Option<std::io::Result<()>> {
+    if path != PROC_FAN {
+        return None;
+    }
+    let target = crate::hardware_root::resolve(PROC_FAN);
+    let content = match fs::read_to_string(&target) {
+        Ok(c) => c,
+        Err(e) => return Some(Err(e)),
+    };
+    if !fan_control_is_enabled(&content) {
+        return Some(Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            "thinkpad_acpi was loaded without fan_control=1",
+        )));
+    }
+
+    let level_command = match command.strip_prefix("fan") {
+        Some(rest) if per_fan_control_supported(&content) => rest
+            .split_once(' ')
+            .filter(|(n, _)| n.parse::<u8>().is_ok_and(|n| (1..=MAX_FANS).contains(&n)))
+            .map(|(_, cmd)| cmd),
+        _ => Some(command),
+    };
+    let updated = match level_command.and_then(|c| c.split_once(' ')) {
+        Some(("level", level)) if level.parse::<FanLevel>().is_ok() => {
+            replace_proc_line(&content, "level", level)
+        }
+        Some(("watchdog", secs)) if secs.parse::<u32>().is_ok_and(|s| s <= 120) => content.clone(),
+        None if command == "enable" => replace_proc_line(&content, "status", "enabled"),
+        None if command == "disable" => replace_proc_line(&content, "status", "disabled"),
+        _ => {
+            return Some(Err(crate::hardware_root::invalid_value(format!(
+                "thinkpad_acpi does not accept '{}'",
+                command
+            ))))
+        }
+    };
+    Some(fs::write(target, updated))
+}
+
+/// Replace the value of one `key:\t\tvalue` line, keeping the layout.
+fn replace_proc_line(content: &str, key: &str, value: &str) -> String {
+    content
+        .lines()
+        .map(|line| match line.split_once(':') {
+            Some((k, _)) if k.trim() == key => format!("{}:\t\t{}", k, value),
+            _ => line.to_string(),
+        })
+        .map(|line| line + "\n")
+        .collect()
+}
+
 /// What is standing between the user and working fan control.
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub enum FanReadiness {
@@ -213,6 +397,11 @@ pub struct FanCapability {
     /// True once the modprobe config exists, meaning the fix is applied but a
     /// reboot or module reload is still needed for it to take effect.
     pub modprobe_conf_present: bool,
+    /// How many tachometers hwmon exposes. Two on P-series and X1 Extreme.
+    pub fan_count: usize,
+    /// Whether each fan can be given its own level. False on firmware that
+    /// drives every fan from one channel, which is the common case.
+    pub per_fan_control: bool,
     pub message: String,
 }
 
@@ -222,7 +411,12 @@ pub fn get_fan_capability() -> ApiResponse<FanCapability> {
         .map(|c| c.contains("fan_control=1"))
         .unwrap_or(false);
 
-    let (readiness, message) = match crate::hardware_root::read_to_string(PROC_FAN) {
+    let proc_fan = crate::hardware_root::read_to_string(PROC_FAN);
+    let per_fan_control = proc_fan
+        .as_ref()
+        .is_ok_and(|c| per_fan_control_supported(c));
+
+    let (readiness, message) = match proc_fan {
         Err(_) => (
             FanReadiness::NoThinkpadFan,
             "No ThinkPad fan interface found. Load the thinkpad_acpi module, or this model may not be supported.".to_string(),
@@ -240,259 +434,253 @@ pub fn get_fan_capability() -> ApiResponse<FanCapability> {
         ),
     };
 
-    ApiResponse {
-        success: true,
-        data: Some(FanCapability {
-            readiness,
-            modprobe_conf_present,
-            message,
-        }),
-        error: None,
+    ApiResponse::ok(FanCapability {
+        readiness,
+        modprobe_conf_present,
+        fan_count: discover_fans().len(),
+        per_fan_control,
+        message,
+    })
+}
+
+/// What [`MODPROBE_CONF_PATH`] holds once fan control is enabled.
+pub const MODPROBE_OPTIONS: &str = "options thinkpad_acpi fan_control=1\n";
+
+/// modprobe by absolute path: pkexec and the service both run with a fixed
+/// environment, and `/sbin` is `/usr/sbin` or `/usr/bin` on every merged-usr
+/// distribution the packages target.
+const MODPROBE: &str = "/sbin/modprobe";
+
+/// Write [`MODPROBE_CONF_PATH`] and reload thinkpad_acpi so the option takes
+/// effect now. Needs root: the helper's `fan-module` operation.
+///
+/// The reload can fail if the module is busy (an open /proc handle, a laptop
+/// dock driver holding it). That is not an error worth failing on -- the
+/// config file is written either way, so a reboot will apply it.
+pub fn write_module_options() -> Result<String, String> {
+    // A captured profile has no module behind it, and /etc is not part of one.
+    if crate::hardware_root::is_simulated() {
+        return Err("A simulated hardware profile has no kernel module to configure".to_string());
+    }
+    fs::write(MODPROBE_CONF_PATH, MODPROBE_OPTIONS)
+        .map_err(|e| format!("Could not write {}: {}", MODPROBE_CONF_PATH, e))?;
+    let modprobe = |args: &[&str]| {
+        std::process::Command::new(MODPROBE)
+            .args(args)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    };
+    if modprobe(&["-r", "thinkpad_acpi"]) && modprobe(&["thinkpad_acpi"]) {
+        Ok("thinkpad_acpi reloaded with fan_control=1".to_string())
+    } else {
+        Ok(format!(
+            "Wrote {}; thinkpad_acpi is in use and picks it up at the next boot",
+            MODPROBE_CONF_PATH
+        ))
     }
 }
 
 /// Write the modprobe config and try to reload the module.
 #[tauri::command]
 pub async fn enable_fan_control() -> ApiResponse<String> {
-    // The reload can fail if the module is busy (an open /proc handle, a laptop
-    // dock driver holding it). That is not an error worth failing on -- the
-    // config file is written either way, so a reboot will apply it.
-    let script = format!(
-        "#!/bin/bash\nset -e\nprintf 'options thinkpad_acpi fan_control=1\\n' > {}\nmodprobe -r thinkpad_acpi 2>/dev/null && modprobe thinkpad_acpi 2>/dev/null || true\nexit 0\n",
-        MODPROBE_CONF_PATH
-    );
-
-    match crate::privileged::run_script(&script).await {
-        Ok(output) if output.status.success() => {
-            // Re-probe rather than assume the reload worked.
-            let now_ready = crate::hardware_root::read_to_string(PROC_FAN)
-                .map(|c| fan_control_is_enabled(&c))
-                .unwrap_or(false);
-
-            ApiResponse {
-                success: true,
-                data: Some(if now_ready {
-                    "Fan control enabled.".to_string()
-                } else {
-                    "Fan control configured. Reboot to activate it — the module could not be reloaded while in use.".to_string()
-                }),
-                error: None,
-            }
+    if crate::hardware_root::is_simulated() {
+        return ApiResponse::err(Error::simulated(
+            "Running against a simulated hardware profile; there is no kernel module to configure.",
+        ));
+    }
+
+    // Running as root this succeeds in-process; anyone else asks the helper.
+    if let Err(direct) = write_module_options() {
+        println!("[Fan] Need elevated permissions: {}", direct);
+        if let Err(e) = crate::helper::run(&crate::helper::Operation::FanModule).await {
+            return ApiResponse::err(e);
         }
-        Ok(output) => ApiResponse {
-            success: false,
-            data: None,
-            error: Some(format!(
-                "Could not enable fan control: {}",
-                String::from_utf8_lossy(&output.stderr)
-            )),
-        },
-        Err(e) => ApiResponse {
-            success: false,
-            data: None,
-            error: Some(format!("Could not enable fan control: {}", e)),
-        },
     }
-}
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SensorData {
-    pub temps: HashMap<String, String>,
-    pub fans: HashMap<String, String>,
+    // Re-probe rather than assume the reload worked.
+    let now_ready = crate::hardware_root::read_to_string(PROC_FAN)
+        .map(|c| fan_control_is_enabled(&c))
+        .unwrap_or(false);
+
+    ApiResponse::ok(if now_ready {
+        "Fan control enabled.".to_string()
+    } else {
+        "Fan control configured. Reboot to activate it — the module could not be reloaded while in use.".to_string()
+    })
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct ApiResponse<T> {
-    pub success: bool,
-    pub data: Option<T>,
-    pub error: Option<String>,
+pub struct SensorData {
+    /// Every hwmon temperature input, on every chip.
+    pub temps: Vec<SensorReading>,
+    /// Every hwmon tachometer. On a ThinkPad these are `thinkpad` fan1 and fan2.
+    pub fans: Vec<SensorReading>,
+    /// thinkpad_acpi's `status:` line, `enabled` or `disabled`.
+    pub status: Option<String>,
+    /// thinkpad_acpi's `level:` line: `auto`, `0`-`7`, `disengaged`...
+    pub level: Option<String>,
+    /// thinkpad_acpi's own RPM for the first fan. Present even when its hwmon
+    /// device is not.
+    pub speed: Option<u32>,
+    /// Each fan on its own, with its level and PWM channel.
+    pub per_fan: Vec<Fan>,
 }
 
 #[tauri::command]
 pub fn get_sensor_data() -> ApiResponse<SensorData> {
-    let mut temps = HashMap::new();
-    let mut fans = HashMap::new();
-
-    // Get fan info from /proc/acpi/ibm/fan
-    match crate::hardware_root::read_to_string(PROC_FAN) {
-        Ok(content) => {
-            fans.extend(parse_fan_proc(&content));
-        }
+    let proc_fan = match crate::hardware_root::read_to_string(PROC_FAN) {
+        Ok(content) => parse_fan_proc(&content),
         Err(e) => {
-            return ApiResponse {
-                success: false,
-                data: None,
-                error: Some(format!("Failed to read {}: {}", PROC_FAN, e)),
-            };
+            return ApiResponse::err(Error::hardware_absent(format!(
+                "Failed to read {}: {}",
+                PROC_FAN, e
+            )));
         }
-    }
+    };
 
-    // Get temperature data from sensors command
-    match Command::new("sensors").arg("thinkpad-isa-0000").output() {
-        Ok(output) => {
-            if output.status.success() {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                // Both patterns are compiled once. rpm_re used to be rebuilt on
-                // every line of `sensors` output, which is the hot path here.
-                let temp_re = Regex::new(r"^(.+?):\s+\+?([0-9.]+°C)").unwrap();
-                let rpm_re = Regex::new(r"(\d+\s*RPM)").unwrap();
-
-                for line in stdout.lines() {
-                    if let Some(caps) = temp_re.captures(line) {
-                        let label = caps.get(1).unwrap().as_str().trim();
-                        let value = caps.get(2).unwrap().as_str();
-                        let label_lower = label.to_lowercase();
-                        if label_lower.contains("cpu") || label_lower.contains("gpu") {
-                            temps.insert(label.to_string(), value.to_string());
-                        }
-                    }
+    let (temps, fans) = crate::hwmon::inventory()
+        .into_iter()
+        .partition(|r| r.kind == SensorKind::Temperature);
+
+    ApiResponse::ok(SensorData {
+        temps,
+        fans,
+        status: proc_fan.status,
+        level: proc_fan.level,
+        speed: proc_fan.speed,
+        per_fan: discover_fans(),
+    })
+}
 
-                    if line.starts_with("fan") && line.contains("RPM") {
-                        if let Some((label, rest)) = line.split_once(':') {
-                            let label = label.trim();
-                            if let Some(rpm_match) = rpm_re.find(rest) {
-                                fans.insert(label.to_string(), rpm_match.as_str().to_string());
-                            }
-                        }
-                    }
-                }
-            } else {
-                temps.insert(
-                    "Info".to_string(),
-                    "Install lm-sensors for temperature data".to_string(),
-                );
-            }
-        }
-        Err(_) => {
-            temps.insert(
-                "Info".to_string(),
-                "Install lm-sensors: sudo apt install lm-sensors".to_string(),
-            );
-        }
-    }
+/// Set the fan level, for every fan or for one.
+///
+/// `fan` is the 1-based index from [`Fan::index`]. Omitting it addresses every
+/// fan at once, which is the only thing most firmware can do. `limit_secs` is
+/// required for [`FanLevel::Disengaged`] and refused for anything else: the fan returns
+/// to auto once it runs out, or sooner if this process dies.
+#[tauri::command]
+pub async fn set_fan_speed(
+    speed: String,
+    fan: Option<u8>,
+    limit_secs: Option<u64>,
+) -> ApiResponse<String> {
+    let level = match validate_fan_request(&speed, fan, limit_secs) {
+        Ok(level) => level,
+        Err(e) => return ApiResponse::err(Error::validation(e)),
+    };
 
-    ApiResponse {
-        success: true,
-        data: Some(SensorData { temps, fans }),
-        error: None,
+    let change = note_fan_change();
+    let mut response = apply_fan_speed(level, fan).await;
+    if let (true, Some(secs)) = (response.success, limit_secs) {
+        hand_back_after(Duration::from_secs(secs), fan, change);
+        response.data = Some(format!("Fan disengaged for {}s, then back to auto", secs));
     }
+    response
 }
 
-#[tauri::command]
-pub async fn set_fan_speed(speed: String) -> ApiResponse<String> {
-    // Validate speed against whitelist
-    if !is_valid_speed(&speed) {
-        return ApiResponse {
-            success: false,
-            data: None,
-            error: Some(format!("Invalid fan speed: {}", speed)),
-        };
-    }
+/// Counts fan changes made by the user, the curve or a calibration, so a
+/// disengaged timer can tell something else has since been chosen.
+static FAN_CHANGES: AtomicU64 = AtomicU64::new(0);
 
-    // Reads may come from a captured hardware profile; writes never may. A test
-    // must not be able to believe it changed a real fan.
-    if crate::hardware_root::is_simulated() {
-        return ApiResponse {
-            success: false,
-            data: None,
-            error: Some(
-                "Running against a simulated hardware profile. Fan changes are disabled."
-                    .to_string(),
+/// Record a fan change before it is written. Whatever it sets overrides a
+/// disengaged timer still running.
+pub(crate) fn note_fan_change() -> u64 {
+    FAN_CHANGES.fetch_add(1, Ordering::SeqCst) + 1
+}
+
+/// Return the fan to auto once a disengaged time limit runs out.
+///
+/// Until then the firmware watchdog is kept armed, so if this process dies the
+/// fan still goes back to auto within [`FAN_WATCHDOG_SECS`]. The timer stands
+/// down once anything else has been written, by the user or the curve, which
+/// [`note_fan_change`] counts.
+///
+/// Only the counter decides that. The procfs `level:` line describes the fans
+/// together, so with one fan disengaged it can read as something else from
+/// the start, and the timer would leave without ever arming the watchdog.
+pub(crate) fn hand_back_after(limit: Duration, fan: Option<u8>, change: u64) {
+    tokio::spawn(async move {
+        let rearm = Duration::from_secs((FAN_WATCHDOG_SECS / 2) as u64);
+        let started = Instant::now();
+        loop {
+            if FAN_CHANGES.load(Ordering::SeqCst) != change {
+                return;
+            }
+            let remaining = limit.saturating_sub(started.elapsed());
+            if remaining.is_zero() {
+                break;
+            }
+            let _ = crate::fan_curve::write_fan_command(&format!("watchdog {}", FAN_WATCHDOG_SECS))
+                .await;
+            tokio::time::sleep(remaining.min(rearm)).await;
+        }
+
+        match crate::fan_curve::write_fan_command(&fan_command(FanLevel::Auto, fan)).await {
+            Ok(()) => println!("[Fan] Disengaged time limit reached; fan returned to auto"),
+            Err(e) => eprintln!(
+                "[Fan] FAILED to end disengaged mode: {} (the watchdog still will)",
+                e
             ),
-        };
-    }
+        }
+    });
+}
 
+/// Write a validated fan speed, elevating if needed.
+async fn apply_fan_speed(speed: FanLevel, fan: Option<u8>) -> ApiResponse<String> {
     println!("[Fan] Setting speed to: {}", speed);
-    let command_str = format!("level {}", speed);
+    let command_str = fan_command(speed, fan);
 
     // Check the module parameter before trying anything. If fan_control=1 is
     // missing the kernel returns -EPERM no matter who we are, and telling the
     // user to grant permissions sends them somewhere that cannot help.
-    if let Ok(content) = crate::hardware_root::read_to_string(PROC_FAN) {
-        if !fan_control_is_enabled(&content) {
-            return ApiResponse {
-                success: false,
-                data: None,
-                error: Some(
-                    "The thinkpad_acpi module was loaded without fan_control=1, so the kernel will refuse fan changes. Enable it from the Fan Control page — this is a module setting, not a permissions problem.".to_string(),
-                ),
-            };
+    let proc_fan = crate::hardware_root::read_to_string(PROC_FAN).ok();
+    if let Some(content) = &proc_fan {
+        if !fan_control_is_enabled(content) {
+            return ApiResponse::err(Error::module_parameter_missing(
+                "The thinkpad_acpi module was loaded without fan_control=1, so the kernel will refuse fan changes. Enable it from the Fan Control page — this is a module setting, not a permissions problem.",
+            ));
         }
     }
 
+    // A selector the firmware did not advertise is not an error the kernel
+    // reports clearly, so refuse it here. An unreadable procfs counts as
+    // unadvertised: per-fan control is opt-in, never assumed.
+    if fan.is_some() && !proc_fan.as_deref().is_some_and(per_fan_control_supported) {
+        return ApiResponse::err(Error::hardware_absent(
+            "This firmware drives all fans together, so they cannot be set separately. Set the level without choosing a fan.",
+        ));
+    }
+
+    // A captured profile has no device behind it to escalate for. The write
+    // either lands in a scratch copy or is refused; it never reaches pkexec.
+    if crate::hardware_root::is_simulated() {
+        return match crate::hardware_root::write(PROC_FAN, &command_str) {
+            Ok(()) => ApiResponse::ok(format!("Fan speed set to: {}", speed)),
+            Err(e) => ApiResponse::err(Error::simulated(format!(
+                "Simulated hardware rejected the change: {}",
+                e
+            ))),
+        };
+    }
+
     // 1. Try direct write (no elevation needed)
     if fs::write(PROC_FAN, &command_str).is_ok() {
         println!("[Fan] ✓ Speed set successfully");
-        return ApiResponse {
-            success: true,
-            data: Some(format!("Fan speed set to: {}", speed)),
-            error: None,
-        };
+        return ApiResponse::ok(format!("Fan speed set to: {}", speed));
     }
 
     println!("[Fan] Need elevated permissions");
 
-    // 2. Use dedicated helper if installed (passwordless via polkit rule)
-    if let Some(helper) = helper_path() {
-        match tokio::process::Command::new("pkexec")
-            .arg(helper)
-            .arg(&command_str)
-            .output()
-            .await
-        {
-            Ok(output) if output.status.success() => {
-                println!("[Fan] ✓ Speed set via helper");
-                return ApiResponse {
-                    success: true,
-                    data: Some(format!("Fan speed set to: {}", speed)),
-                    error: None,
-                };
-            }
-            Ok(_) => {
-                return ApiResponse {
-                    success: false,
-                    data: None,
-                    error: Some("Permission denied. Click 'Grant Permissions' to enable passwordless fan control.".to_string()),
-                };
-            }
-            Err(e) => {
-                return ApiResponse {
-                    success: false,
-                    data: None,
-                    error: Some(format!("Failed to execute helper: {}", e)),
-                };
-            }
-        }
-    }
-
-    // 3. Fallback: secure temp script + pkexec (will prompt for password)
-    let script_content = format!(
-        "#!/bin/bash\nset -e\necho '{}' > {}\nexit 0\n",
-        command_str, PROC_FAN
-    );
-
-    match crate::privileged::run_script(&script_content).await {
-        Ok(output) => {
-            if output.status.success() {
-                println!("[Fan] ✓ Speed set via pkexec");
-                ApiResponse {
-                    success: true,
-                    data: Some(format!("Fan speed set to: {}", speed)),
-                    error: None,
-                }
-            } else {
-                ApiResponse {
-                    success: false,
-                    data: None,
-                    error: Some("Permission denied. Click 'Grant Permissions' to avoid repeated password prompts.".to_string()),
-                }
-            }
+    // 2. The privileged helper (passwordless via the polkit rule)
+    let command = FanCommand::Level { level: speed, fan };
+    match crate::helper::run(&crate::helper::Operation::Fan(command)).await {
+        Ok(_) => {
+            println!("[Fan] ✓ Speed set via helper");
+            ApiResponse::ok(format!("Fan speed set to: {}", speed))
         }
-        Err(e) => ApiResponse {
-            success: false,
-            data: None,
-            error: Some(format!("Failed to execute pkexec: {}", e)),
-        },
+        Err(e) => ApiResponse::err(e),
     }
 }
 
@@ -504,17 +692,238 @@ pub fn check_permissions() -> ApiResponse<bool> {
     // Check if the dedicated helper is installed (installed alongside the polkit rule).
     // We only check the helper because /etc/polkit-1/rules.d/ is root-only,
     // so Path::exists() on the polkit rule always fails for normal users.
-    let helper_installed = helper_path().is_some();
+    let helper_installed = crate::helper::helper_path().is_some();
 
     let has_permission = direct_write || helper_installed;
 
-    ApiResponse {
-        success: true,
-        data: Some(has_permission),
-        error: None,
+    ApiResponse::ok(has_permission)
+}
+
+/// The model this machine reports, used to key per-machine measurements.
+///
+/// `product_version` carries the marketing name on ThinkPads ("ThinkPad P1 Gen
+/// 4i") and is shared by every machine type of the model, which is what fan
+/// behaviour follows; `product_name` is the machine type, used when the version
+/// is blank.
+pub fn machine_id() -> String {
+    [
+        "/sys/class/dmi/id/product_version",
+        "/sys/class/dmi/id/product_name",
+    ]
+    .into_iter()
+    .filter_map(|path| crate::hardware_root::read_to_string(path).ok())
+    .map(|s| s.trim().to_string())
+    .find(|s| !s.is_empty())
+    .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// The speed one fan level was measured to run at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalibrationPoint {
+    pub level: i32,
+    pub rpm: u32,
+    /// False when the fan was still changing speed when the calibration moved
+    /// on; `rpm` is then the average of the last few samples.
+    pub settled: bool,
+}
+
+/// The level→RPM table of one machine, as measured by [`calibrate_fan`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FanCalibration {
+    /// The [`machine_id`] it was measured on.
+    pub machine: String,
+    /// One entry per level, lowest first.
+    pub points: Vec<CalibrationPoint>,
+    /// Seconds since the Unix epoch.
+    pub measured_at: u64,
+}
+
+impl FanCalibration {
+    /// The lowest level measured to reach `rpm`. A target above anything the
+    /// fan reached gets the fastest level measured.
+    pub fn level_for_rpm(&self, rpm: u32) -> i32 {
+        let mut points = self.points.clone();
+        points.sort_by_key(|p| p.level);
+        points
+            .iter()
+            .find(|p| p.rpm >= rpm)
+            .or(points.last())
+            .map_or(crate::fan_curve::MAX_FAN_LEVEL, |p| p.level)
+            .clamp(
+                crate::fan_curve::MIN_FAN_LEVEL,
+                crate::fan_curve::MAX_FAN_LEVEL,
+            )
     }
 }
 
+/// How the calibration waits for the fan at each level.
+#[derive(Debug, Clone, Copy)]
+pub struct CalibrationTiming {
+    /// Time between RPM samples.
+    pub sample_interval: Duration,
+    /// How long to wait for the fan to settle before recording the level as
+    /// unsettled. Must stay under [`FAN_WATCHDOG_SECS`], which is re-armed once
+    /// per level.
+    pub max_wait: Duration,
+}
+
+impl Default for CalibrationTiming {
+    fn default() -> Self {
+        Self {
+            sample_interval: Duration::from_secs(1),
+            max_wait: Duration::from_secs(20),
+        }
+    }
+}
+
+/// Consecutive samples that must agree before a level counts as settled.
+const SETTLE_SAMPLES: usize = 3;
+
+/// How far apart those samples may be: 3% of the speed, but never less than
+/// 100 RPM, which is about the jitter of a ThinkPad tachometer at low speed.
+const SETTLE_TOLERANCE_PERCENT: u32 = 3;
+const SETTLE_TOLERANCE_MIN_RPM: u32 = 100;
+
+/// Whether the last [`SETTLE_SAMPLES`] readings agree closely enough to call
+/// the fan settled.
+fn settled(samples: &[u32]) -> bool {
+    if samples.len() < SETTLE_SAMPLES {
+        return false;
+    }
+    let last = &samples[samples.len() - SETTLE_SAMPLES..];
+    let (min, max) = (*last.iter().min().unwrap(), *last.iter().max().unwrap());
+    max - min <= SETTLE_TOLERANCE_MIN_RPM.max(max * SETTLE_TOLERANCE_PERCENT / 100)
+}
+
+/// The speed to record: the mean of the last few samples.
+fn settled_rpm(samples: &[u32]) -> u32 {
+    let last = &samples[samples.len().saturating_sub(SETTLE_SAMPLES)..];
+    (last.iter().map(|&r| r as u64).sum::<u64>() / last.len().max(1) as u64) as u32
+}
+
+static CALIBRATING: AtomicBool = AtomicBool::new(false);
+static CALIBRATION_CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Whether [`calibrate_fan`] is steering the fan right now.
+pub fn calibration_running() -> bool {
+    CALIBRATING.load(Ordering::SeqCst)
+}
+
+/// Ask a running calibration to stop. It hands the fan back to the firmware
+/// at its next sample.
+pub fn cancel_calibration() {
+    CALIBRATION_CANCELLED.store(true, Ordering::SeqCst);
+}
+
+/// Clears [`CALIBRATING`] however the calibration ends.
+struct CalibrationRun;
+
+impl Drop for CalibrationRun {
+    fn drop(&mut self) {
+        CALIBRATING.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Step the fan through every level and record the speed it settles at.
+///
+/// Each level is held with the firmware watchdog armed, so a crash part-way
+/// leaves the fan at a fixed level for at most [`FAN_WATCHDOG_SECS`]. The fan
+/// goes back to auto when the calibration finishes, fails or is cancelled.
+/// `on_point` is called as each level is measured.
+///
+/// The low levels are held long enough for a busy machine to heat up, so
+/// `temperature` is read with every sample. The calibration gives up at
+/// `critical_temp`, the thermal override's limit, or when the temperature
+/// cannot be read.
+pub async fn calibrate_fan(
+    timing: CalibrationTiming,
+    critical_temp: i32,
+    mut temperature: impl FnMut() -> Result<i32, String>,
+    mut on_point: impl FnMut(&CalibrationPoint),
+) -> Result<FanCalibration, String> {
+    if CALIBRATING.swap(true, Ordering::SeqCst) {
+        return Err("A fan calibration is already running".to_string());
+    }
+    let _run = CalibrationRun;
+    CALIBRATION_CANCELLED.store(false, Ordering::SeqCst);
+    note_fan_change();
+
+    let measured = measure_levels(timing, critical_temp, &mut temperature, &mut on_point).await;
+    if let Err(e) = crate::fan_curve::write_fan_command(&fan_command(FanLevel::Auto, None)).await {
+        eprintln!(
+            "[Fan] FAILED to restore fan to auto after calibration: {}",
+            e
+        );
+    }
+
+    Ok(FanCalibration {
+        machine: machine_id(),
+        points: measured?,
+        measured_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+    })
+}
+
+async fn measure_levels(
+    timing: CalibrationTiming,
+    critical_temp: i32,
+    temperature: &mut impl FnMut() -> Result<i32, String>,
+    on_point: &mut impl FnMut(&CalibrationPoint),
+) -> Result<Vec<CalibrationPoint>, String> {
+    use crate::fan_curve::write_fan_command;
+
+    let mut points = Vec::new();
+    for n in 0..=FanLevel::MAX_MANUAL {
+        let level = i32::from(n);
+        // Unlike the curve, which can run without it, the calibration holds
+        // the fan at level 0 for a while; it does not start without a watchdog.
+        // Armed first, as in `hand_back_after`: a crash between the two writes
+        // must not leave a level held with nothing to end it. The level write
+        // that follows rearms it.
+        write_fan_command(&format!("watchdog {}", FAN_WATCHDOG_SECS))
+            .await
+            .map_err(|e| format!("Could not arm the fan watchdog: {}", e))?;
+        write_fan_command(&fan_command(FanLevel::Manual(n), None)).await?;
+
+        let started = Instant::now();
+        let mut samples = Vec::new();
+        loop {
+            tokio::time::sleep(timing.sample_interval).await;
+            if CALIBRATION_CANCELLED.load(Ordering::SeqCst) {
+                return Err("Fan calibration cancelled".to_string());
+            }
+            let temp = temperature()
+                .map_err(|e| format!("Fan calibration stopped; no temperature: {}", e))?;
+            if temp >= critical_temp {
+                return Err(format!(
+                    "Fan calibration stopped at {}°C, the thermal override limit of {}°C",
+                    temp, critical_temp
+                ));
+            }
+            samples.push(read_fan_rpm().ok_or("Could not read the fan speed")?);
+            if settled(&samples) || started.elapsed() >= timing.max_wait {
+                break;
+            }
+        }
+
+        let point = CalibrationPoint {
+            level,
+            rpm: settled_rpm(&samples),
+            settled: settled(&samples),
+        };
+        println!(
+            "[Fan] Level {} runs at {} RPM{}",
+            level,
+            point.rpm,
+            if point.settled { "" } else { " (not settled)" }
+        );
+        on_point(&point);
+        points.push(point);
+    }
+    Ok(points)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -524,8 +933,25 @@ mod tests {
     #[test]
     fn accepts_every_documented_speed() {
         for s in ["auto", "full-speed", "0", "1", "2", "3", "4", "5", "6", "7"] {
-            assert!(is_valid_speed(s), "'{}' should be accepted", s);
+            assert!(s.parse::<FanLevel>().is_ok(), "'{}' should be accepted", s);
+        }
+    }
+
+    #[test]
+    fn every_level_parses_back_from_its_display() {
+        for level in FanLevel::all() {
+            assert_eq!(level.to_string().parse::<FanLevel>(), Ok(level));
         }
+        assert_eq!(FanLevel::all().count(), 11);
+    }
+
+    #[test]
+    fn manual_levels_stop_at_the_firmware_range() {
+        assert_eq!(FanLevel::manual(0), Some(FanLevel::Manual(0)));
+        assert_eq!(FanLevel::manual(7), Some(FanLevel::Manual(7)));
+        assert_eq!(FanLevel::manual(8), None);
+        assert_eq!(FanLevel::manual(-1), None);
+        assert_eq!(FanLevel::manual(256), None);
     }
 
     #[test]
@@ -544,7 +970,7 @@ mod tests {
             "0x3",
             "level 3",
         ] {
-            assert!(!is_valid_speed(s), "'{}' should be rejected", s);
+            assert!(s.parse::<FanLevel>().is_err(), "'{}' should be rejected", s);
         }
     }
 
@@ -559,7 +985,7 @@ mod tests {
             "3\nrm -rf /",
             "3 > /etc/passwd",
         ] {
-            assert!(!is_valid_speed(s), "{:?} should be rejected", s);
+            assert!(s.parse::<FanLevel>().is_err(), "{:?} should be rejected", s);
         }
     }
 
@@ -577,38 +1003,44 @@ commands:\twatchdog <timeout> (0 disables, timeout is 0-120)
 
     #[test]
     fn parses_status_speed_and_level() {
-        let fans = parse_fan_proc(SAMPLE_PROC_FAN);
-        assert_eq!(fans.get("status").map(String::as_str), Some("enabled"));
-        assert_eq!(fans.get("level").map(String::as_str), Some("auto"));
-        assert_eq!(fans.get("Fan1").map(String::as_str), Some("3084 RPM"));
+        let fan = parse_fan_proc(SAMPLE_PROC_FAN);
+        assert_eq!(fan.status.as_deref(), Some("enabled"));
+        assert_eq!(fan.level.as_deref(), Some("auto"));
+        assert_eq!(fan.speed, Some(3084));
     }
 
     /// `commands:` lines describe capabilities, not readings. Treating them as fan
     /// data would surface "level <level> (<level> is 0-7...)" in the UI as a level.
     #[test]
     fn ignores_command_and_capability_lines() {
-        let fans = parse_fan_proc(SAMPLE_PROC_FAN);
-        assert_eq!(fans.len(), 3, "unexpected keys: {:?}", fans);
-        assert!(!fans.contains_key("commands"));
-        assert!(!fans.contains_key("watchdog"));
+        assert_eq!(
+            parse_fan_proc(SAMPLE_PROC_FAN),
+            ProcFan {
+                status: Some("enabled".to_string()),
+                level: Some("auto".to_string()),
+                speed: Some(3084),
+            }
+        );
     }
 
     #[test]
     fn tolerates_empty_and_malformed_input() {
-        assert!(parse_fan_proc("").is_empty());
-        assert!(parse_fan_proc("no colon here\nanother line").is_empty());
-        // A key with no value should not panic and should yield an empty string.
+        assert_eq!(parse_fan_proc(""), ProcFan::default());
         assert_eq!(
-            parse_fan_proc("level:").get("level").map(String::as_str),
-            Some("")
+            parse_fan_proc("no colon here\nanother line"),
+            ProcFan::default()
         );
+        // A key with no value should not panic and should yield an empty string.
+        assert_eq!(parse_fan_proc("level:").level.as_deref(), Some(""));
+        // Nor should a speed that is not a number.
+        assert_eq!(parse_fan_proc("speed:\tn/a").speed, None);
     }
 
     #[test]
     fn parses_numeric_level_not_just_auto() {
-        let fans = parse_fan_proc("status:\tenabled\nlevel:\t7\nspeed:\t4500\n");
-        assert_eq!(fans.get("level").map(String::as_str), Some("7"));
-        assert_eq!(fans.get("Fan1").map(String::as_str), Some("4500 RPM"));
+        let fan = parse_fan_proc("status:\tenabled\nlevel:\t7\nspeed:\t4500\n");
+        assert_eq!(fan.level.as_deref(), Some("7"));
+        assert_eq!(fan.speed, Some(4500));
     }
 
     // -- fan_control=1 module parameter detection --
@@ -636,47 +1068,88 @@ commands:\twatchdog <timeout> (0 disables, timeout is 0-120)
         assert!(!fan_control_is_enabled("level:\tcommands: not really"));
     }
 
-    // -- The installed privileged helper --
+    // -- Dual fans --
+
+    fn p1_gen4i_hwmon() -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/hardware/thinkpad-p1-gen-4i")
+            .join(THINKPAD_HWMON_DIR.trim_start_matches('/'))
+    }
+
+    /// The P1 Gen 4i has two tachometers and one PWM. Both fans must be found,
+    /// and both must report the shared channel rather than a pwm2 that does not
+    /// exist.
+    #[test]
+    fn finds_both_fans_on_a_shared_channel() {
+        let fans = fans_in(&p1_gen4i_hwmon(), Some("auto"));
+        assert_eq!(
+            fans,
+            vec![
+                Fan {
+                    index: 1,
+                    rpm: Some(2420),
+                    level: Some("auto".to_string()),
+                    pwm_channel: Some(1),
+                },
+                Fan {
+                    index: 2,
+                    rpm: Some(2228),
+                    level: Some("auto".to_string()),
+                    pwm_channel: Some(1),
+                },
+            ]
+        );
+    }
 
-    /// Write HELPER_SCRIPT to a temp file and run it, so we test the bash that
-    /// actually gets installed at HELPER_PATH and invoked under pkexec.
-    fn run_helper(arg: &str) -> std::process::Output {
-        use std::io::Write;
-        use std::os::unix::fs::OpenOptionsExt;
+    #[test]
+    fn no_hwmon_directory_means_no_fans() {
+        assert!(fans_in(Path::new("/nonexistent/thinkpad_hwmon"), None).is_empty());
+    }
 
-        let nanos = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_nanos();
-        let path = std::env::temp_dir().join(format!(
-            "thinkutils_helper_test_{}_{}.sh",
-            std::process::id(),
-            nanos
-        ));
+    /// The stock driver's commands: lines mention "level" but no selector.
+    /// Offering per-fan control there would send commands the kernel rejects.
+    #[test]
+    fn per_fan_control_needs_the_selector_advertised() {
+        assert!(!per_fan_control_supported(SAMPLE_PROC_FAN));
+        assert!(!per_fan_control_supported(""));
+        let dual = format!(
+            "{}commands:\tfan1, fan2 (select the fan for the next level command)\n",
+            SAMPLE_PROC_FAN
+        );
+        assert!(per_fan_control_supported(&dual));
+        // A reading that happens to mention fan2 is not a capability.
+        assert!(!per_fan_control_supported("level:\tfan1 fan2\n"));
+    }
 
-        let mut f = fs::OpenOptions::new()
-            .create_new(true)
-            .write(true)
-            .mode(0o700)
-            .open(&path)
-            .expect("create helper");
-        f.write_all(HELPER_SCRIPT.as_bytes()).expect("write helper");
-        drop(f);
+    #[test]
+    fn disengaged_mode_needs_a_time_limit_and_nothing_else_takes_one() {
+        assert!(validate_fan_request("disengaged", None, None).is_err());
+        assert!(validate_fan_request("disengaged", None, Some(0)).is_err());
+        assert!(validate_fan_request("disengaged", None, Some(MAX_DISENGAGED_SECS + 1)).is_err());
+        assert_eq!(
+            validate_fan_request("disengaged", None, Some(60)),
+            Ok(FanLevel::Disengaged)
+        );
+        assert!(validate_fan_request("full-speed", None, Some(60)).is_err());
+        assert_eq!(
+            validate_fan_request("full-speed", None, None),
+            Ok(FanLevel::FullSpeed)
+        );
+    }
 
-        let out = Command::new("bash")
-            .arg(&path)
-            .arg(arg)
-            .output()
-            .expect("run helper");
-        let _ = fs::remove_file(&path);
-        out
+    #[test]
+    fn fan_command_adds_the_selector_only_when_asked() {
+        assert_eq!(fan_command(FanLevel::Manual(3), None), "level 3");
+        assert_eq!(fan_command(FanLevel::Auto, Some(2)), "fan2 level auto");
     }
 
+    // -- The commands the privileged helper accepts --
+
     /// This is the security boundary: the helper runs as root under a polkit rule
-    /// that grants it passwordless. If its case statement ever accepts something
-    /// outside the whitelist, that is arbitrary root.
+    /// that grants it passwordless. If this parser ever accepts something
+    /// outside the whitelist, the kernel is the only thing left to refuse it.
     #[test]
-    fn helper_rejects_everything_outside_the_whitelist() {
+    fn fan_commands_outside_the_whitelist_are_refused() {
         let payloads = [
             "level 8",
             "level -1",
@@ -690,103 +1163,268 @@ commands:\twatchdog <timeout> (0 disables, timeout is 0-120)
             "; rm -rf /",
             "../../etc/passwd",
             "level AUTO",
+            "level  3",
+            "level 3 ",
+            "fan3 level 3",
+            "fan0 level 3",
+            "fan01 level 3",
+            "fan1 level 8",
+            "fan1 watchdog 30",
+            "fan1 enable",
+            "fan2 level auto; rm -rf /",
+            "fan1",
             "",
         ];
         for p in payloads {
-            let out = run_helper(p);
-            let stderr = String::from_utf8_lossy(&out.stderr);
             assert!(
-                stderr.contains("Invalid command"),
-                "helper did not reject {:?} (stderr: {:?})",
-                p,
-                stderr
+                p.parse::<FanCommand>().is_err(),
+                "{:?} must not reach the helper",
+                p
             );
-            assert!(!out.status.success(), "helper exited 0 for {:?}", p);
         }
     }
 
     /// The watchdog is the recovery path when this app dies holding a manual
     /// level, so exactly one value is permitted -- and only that value.
     #[test]
-    fn helper_accepts_only_the_one_watchdog_value() {
-        let out = run_helper(&format!("watchdog {}", FAN_WATCHDOG_SECS));
-        assert!(
-            !String::from_utf8_lossy(&out.stderr).contains("Invalid command"),
-            "helper must accept the watchdog arm command"
+    fn only_the_one_watchdog_value_is_accepted() {
+        assert_eq!(
+            format!("watchdog {}", FAN_WATCHDOG_SECS).parse(),
+            Ok(FanCommand::Watchdog)
         );
         for bad in ["watchdog 0", "watchdog 1", "watchdog 120", "watchdog"] {
-            let out = run_helper(bad);
-            assert!(
-                String::from_utf8_lossy(&out.stderr).contains("Invalid command"),
-                "helper must reject {:?}",
-                bad
-            );
+            assert!(bad.parse::<FanCommand>().is_err(), "{:?} accepted", bad);
         }
     }
 
-    /// HELPER_SCRIPT hardcodes the timeout in a bash case arm, so a change to
-    /// the constant alone would silently stop the watchdog from ever arming.
+    /// Complements the above: the whitelist must not be so tight that a command
+    /// the app sends is refused. Every level reaches every fan, in exactly the
+    /// form fan_command() builds.
     #[test]
-    fn watchdog_constant_matches_helper_script() {
-        assert!(
-            HELPER_SCRIPT.contains(&format!("watchdog {}", FAN_WATCHDOG_SECS)),
-            "FAN_WATCHDOG_SECS ({}) has no matching arm in HELPER_SCRIPT",
-            FAN_WATCHDOG_SECS
+    fn every_command_the_app_sends_parses_back() {
+        for fan in std::iter::once(None).chain((1..=MAX_FANS).map(Some)) {
+            for level in FanLevel::all() {
+                let command = fan_command(level, fan);
+                assert_eq!(
+                    command.parse(),
+                    Ok(FanCommand::Level { level, fan }),
+                    "{:?} was refused",
+                    command
+                );
+            }
+        }
+        assert_eq!(
+            FanCommand::Watchdog.to_string(),
+            format!("watchdog {}", FAN_WATCHDOG_SECS)
         );
     }
 
-    /// Complements the above: the whitelist must not be so tight that legitimate
-    /// commands are refused. We assert only that they are not *rejected* — the
-    /// write itself needs a ThinkPad and root, so it is expected to fail in CI.
+    fn simulated_fan(f: impl FnOnce(&Path)) {
+        crate::hardware_root::testing::with_scratch_profile("thinkpad-p1-gen-4i", f);
+    }
+
+    fn proc_fan_in(root: &Path) -> String {
+        fs::read_to_string(root.join("proc/acpi/ibm/fan")).unwrap()
+    }
+
     #[test]
-    fn helper_accepts_the_documented_commands() {
-        for level in ["auto", "full-speed", "0", "1", "2", "3", "4", "5", "6", "7"] {
-            let arg = format!("level {}", level);
-            let out = run_helper(&arg);
-            let stderr = String::from_utf8_lossy(&out.stderr);
-            assert!(
-                !stderr.contains("Invalid command"),
-                "helper wrongly rejected {:?} (stderr: {:?})",
-                arg,
-                stderr
+    fn simulated_level_write_changes_only_the_level_line() {
+        simulated_fan(|root| {
+            let before = proc_fan_in(root);
+            crate::hardware_root::write(PROC_FAN, "level disengaged").unwrap();
+            let after = proc_fan_in(root);
+
+            assert_eq!(parse_fan_proc(&after).level.as_deref(), Some("disengaged"));
+            assert_eq!(before.lines().count(), after.lines().count());
+            for (b, a) in before.lines().zip(after.lines()) {
+                if !b.starts_with("level:") {
+                    assert_eq!(b, a);
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn simulated_fan_rejects_what_thinkpad_acpi_rejects() {
+        simulated_fan(|root| {
+            for bad in ["level 8", "level", "watchdog 121", "fan2 level 3", "reboot"] {
+                let err = crate::hardware_root::write(PROC_FAN, bad).unwrap_err();
+                assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput, "{}", bad);
+            }
+            crate::hardware_root::write(PROC_FAN, "watchdog 30").unwrap();
+            crate::hardware_root::write(PROC_FAN, "disable").unwrap();
+            assert_eq!(
+                parse_fan_proc(&proc_fan_in(root)).status.as_deref(),
+                Some("disabled")
             );
+        });
+    }
+
+    /// Without `fan_control=1` the kernel refuses every command with EPERM,
+    /// and the model has to agree or the module-parameter path goes untested.
+    #[test]
+    fn simulated_fan_without_fan_control_is_eperm() {
+        simulated_fan(|root| {
+            let stripped: String = proc_fan_in(root)
+                .lines()
+                .filter(|l| !l.starts_with("commands:"))
+                .map(|l| format!("{}\n", l))
+                .collect();
+            fs::write(root.join("proc/acpi/ibm/fan"), stripped).unwrap();
+
+            let err = crate::hardware_root::write(PROC_FAN, "level 3").unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+        });
+    }
+
+    // -- Calibration --
+
+    #[test]
+    fn a_fan_is_settled_once_three_samples_agree() {
+        assert!(!settled(&[2400, 2410]));
+        assert!(settled(&[1200, 2380, 2410, 2400]));
+        // Still spinning up.
+        assert!(!settled(&[1800, 2100, 2400]));
+        // Low speeds get the absolute floor, not 3% of almost nothing.
+        assert!(settled(&[0, 90, 40]));
+        // High speeds get 3%: 150 RPM apart at 5000 is noise.
+        assert!(settled(&[4900, 5000, 5050]));
+    }
+
+    fn table(rpms: &[u32]) -> FanCalibration {
+        FanCalibration {
+            machine: "ThinkPad T14 Gen 3".to_string(),
+            points: rpms
+                .iter()
+                .enumerate()
+                .map(|(level, &rpm)| CalibrationPoint {
+                    level: level as i32,
+                    rpm,
+                    settled: true,
+                })
+                .collect(),
+            measured_at: 0,
         }
     }
 
-    /// The polkit rule grants passwordless root. It must name the helper binaries
-    /// and nothing else -- granting a shell would make the tight helper pointless.
     #[test]
-    fn polkit_rule_grants_only_the_helper_binaries() {
-        let rule = polkit_rule();
-        for candidate in HELPER_CANDIDATES {
-            assert!(
-                rule.contains(candidate),
-                "polkit rule must cover {}",
-                candidate
+    fn an_rpm_target_gets_the_lowest_level_that_reaches_it() {
+        let t14 = table(&[0, 1900, 2300, 2700, 3100, 3500, 3900, 4500]);
+        assert_eq!(t14.level_for_rpm(0), 0);
+        assert_eq!(t14.level_for_rpm(2300), 2);
+        assert_eq!(t14.level_for_rpm(2301), 3);
+        assert_eq!(t14.level_for_rpm(9000), 7);
+
+        // Two levels that measure the same: the quieter one is enough.
+        let flat = table(&[0, 2400, 2400, 2400, 3000, 3000, 3600, 3600]);
+        assert_eq!(flat.level_for_rpm(2400), 1);
+    }
+
+    fn block_on<T>(future: impl std::future::Future<Output = T>) -> T {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    const FAST: CalibrationTiming = CalibrationTiming {
+        sample_interval: Duration::from_millis(1),
+        max_wait: Duration::from_millis(50),
+    };
+
+    #[test]
+    fn calibration_measures_every_level_and_hands_the_fan_back() {
+        simulated_fan(|root| {
+            let mut reported = Vec::new();
+            let calibration = block_on(calibrate_fan(
+                FAST,
+                95,
+                || Ok(50),
+                |p| reported.push(p.level),
+            ))
+            .unwrap();
+
+            let levels: Vec<i32> = calibration.points.iter().map(|p| p.level).collect();
+            assert_eq!(levels, (0..=7).collect::<Vec<_>>());
+            assert_eq!(reported, levels);
+            // The simulated tachometer does not change with the level.
+            assert!(calibration
+                .points
+                .iter()
+                .all(|p| p.rpm == 2420 && p.settled));
+            assert_eq!(calibration.machine, "ThinkPad P1 Gen 4i");
+            assert_eq!(
+                parse_fan_proc(&proc_fan_in(root)).level.as_deref(),
+                Some("auto")
             );
-        }
-        for forbidden in ["/bin/bash", "/bin/sh", "/usr/bin/bash", "/usr/bin/env"] {
-            assert!(
-                !rule.contains(forbidden),
-                "polkit rule must not grant {}",
-                forbidden
+            assert!(!calibration_running());
+        });
+    }
+
+    #[test]
+    fn a_cancelled_calibration_still_hands_the_fan_back() {
+        simulated_fan(|root| {
+            let e =
+                block_on(calibrate_fan(FAST, 95, || Ok(50), |_| cancel_calibration())).unwrap_err();
+            assert!(e.contains("cancelled"), "{}", e);
+            assert_eq!(
+                parse_fan_proc(&proc_fan_in(root)).level.as_deref(),
+                Some("auto")
             );
-        }
+            assert!(!calibration_running());
+        });
     }
 
-    /// Without local+active, any SSH session belonging to a wheel/sudo user
-    /// inherits passwordless hardware control, as does a background session the
-    /// user has switched away from.
+    /// The `level:` line covers every fan, so with one disengaged it can read
+    /// as anything. Only a newer change stands the timer down.
     #[test]
-    fn polkit_rule_requires_a_local_active_session() {
-        let rule = polkit_rule();
-        assert!(
-            rule.contains("subject.local"),
-            "rule must require a local session"
-        );
-        assert!(
-            rule.contains("subject.active"),
-            "rule must require an active session"
-        );
+    fn the_disengaged_timer_goes_by_changes_not_the_level_line() {
+        simulated_fan(|root| {
+            let level = || parse_fan_proc(&proc_fan_in(root)).level;
+            let run = |overtake: bool| {
+                crate::hardware_root::write(PROC_FAN, "level 7").unwrap();
+                let change = note_fan_change();
+                block_on(async {
+                    hand_back_after(Duration::from_millis(20), None, change);
+                    if overtake {
+                        note_fan_change();
+                    }
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                });
+            };
+
+            run(false);
+            assert_eq!(level().as_deref(), Some("auto"));
+            run(true);
+            assert_eq!(level().as_deref(), Some("7"));
+        });
+    }
+
+    /// Level 0 held on a busy machine: the temperature climbs, and the
+    /// calibration gives the fan back rather than wait out the level.
+    #[test]
+    fn a_calibration_that_reaches_the_limit_hands_the_fan_back() {
+        simulated_fan(|root| {
+            let mut temp = 80;
+            let mut reported = Vec::new();
+            let heating = || {
+                temp += 5;
+                Ok(temp)
+            };
+            let e =
+                block_on(calibrate_fan(FAST, 95, heating, |p| reported.push(p.level))).unwrap_err();
+            assert!(e.contains("95°C"), "{}", e);
+            assert!(reported.len() < 8);
+            assert_eq!(
+                parse_fan_proc(&proc_fan_in(root)).level.as_deref(),
+                Some("auto")
+            );
+
+            let e =
+                block_on(calibrate_fan(FAST, 95, || Err("gone".to_string()), |_| {})).unwrap_err();
+            assert!(e.contains("no temperature"), "{}", e);
+            assert!(!calibration_running());
+        });
     }
 }
@@ -1,35 +1,654 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter, Manager};
 use tauri_plugin_store::StoreExt;
 use tokio::time::sleep;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::fan_control::{fan_command, machine_id, CalibrationTiming, FanCalibration, FanLevel};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CurvePoint {
+    /// Temperature at which this point applies while the CPU is heating up.
     pub temp: i32,
     pub level: i32,
+    /// Temperature at which this point applies while cooling down. Must not be
+    /// above `temp`; the gap between the two is the hysteresis band. `None`
+    /// means no band: the point applies at `temp` in both directions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fall_temp: Option<i32>,
+    /// A target fan speed instead of a level. The point then runs at the
+    /// lowest level this machine's [`FanCalibration`] reaches it at, and
+    /// `level` only records what that was when the curve was edited.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rpm: Option<u32>,
+}
+
+impl CurvePoint {
+    fn falling_temp(&self) -> i32 {
+        self.fall_temp.unwrap_or(self.temp)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FanCurveConfig {
     pub enabled: bool,
     pub points: Vec<CurvePoint>,
+    /// How long a level is held before the curve may lower it again.
+    #[serde(default = "default_min_dwell_secs")]
+    pub min_dwell_secs: u64,
+    /// Sensors the curve follows, by [`TemperatureSensor::id`]. With several,
+    /// the hottest one wins. Empty means the built-in CPU heuristic in
+    /// [`get_cpu_temperature`].
+    #[serde(default)]
+    pub sensors: Vec<String>,
+    /// Time constant of the moving average applied to the temperature, in
+    /// seconds. 0 follows every sample as-is.
+    #[serde(default)]
+    pub smoothing_secs: u64,
+    /// Every saved curve by name. `points` above is the working copy of the
+    /// one named by `active_curve`, and the two are kept in step.
+    #[serde(default)]
+    pub curves: BTreeMap<String, Vec<CurvePoint>>,
+    #[serde(default = "default_curve_name")]
+    pub active_curve: String,
+    #[serde(default)]
+    pub power_rules: PowerRules,
+    /// When a fan reading 0 RPM counts as stalled, and what happens then.
+    #[serde(default)]
+    pub stall: StallPolicy,
+    /// The ceiling above which the fan runs flat out whatever the curve says.
+    #[serde(default)]
+    pub emergency: ThermalOverride,
+    /// Measured level→RPM tables by [`machine_id`], for points that target an
+    /// RPM. Keyed rather than single so a config copied to another model does
+    /// not steer its fan through the wrong table.
+    #[serde(default)]
+    pub calibrations: BTreeMap<String, FanCalibration>,
 }
 
-impl Default for FanCurveConfig {
+/// What the curve does once the fan is confirmed stalled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StallAction {
+    /// Drive the fan as hard as the firmware allows. Frees a fan held by dust
+    /// or a stiff bearing, and costs only noise if the tachometer is at fault.
+    #[default]
+    FullSpeed,
+    /// Give the fan back to the firmware, whose own thermal protection still
+    /// works when this app's view of the fan does not.
+    Auto,
+}
+
+/// When a fan that should be turning counts as stalled, and what to do then.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StallPolicy {
+    /// Consecutive ticks at 0 RPM, at a level of 1 or more, before acting.
+    #[serde(default = "default_stall_ticks")]
+    pub ticks: u32,
+    #[serde(default)]
+    pub action: StallAction,
+}
+
+/// How the thermal override drives the fan.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverrideAction {
+    /// The fastest regulated speed.
+    #[default]
+    FullSpeed,
+    /// No regulation at all. Faster than full speed on most models, at the
+    /// cost of running the fan past its rated RPM.
+    Disengaged,
+}
+
+impl OverrideAction {
+    fn level(self) -> FanLevel {
+        match self {
+            OverrideAction::FullSpeed => FanLevel::FullSpeed,
+            OverrideAction::Disengaged => FanLevel::Disengaged,
+        }
+    }
+}
+
+/// A hard ceiling on the curve. Above `critical_temp` the fan is forced to
+/// `action` until the temperature falls below `release_temp`, whatever the
+/// curve points say — a curve holding level 0 to 85°C is the user's choice,
+/// but it must not be able to cook the machine.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ThermalOverride {
+    /// °C at which the override engages. None takes the lowest `temp*_crit`
+    /// published by the sensors the curve follows.
+    #[serde(default)]
+    pub critical_temp: Option<i32>,
+    /// °C the temperature must fall below before the curve gets the fan back.
+    /// None is [`DEFAULT_RELEASE_GAP`] under the critical temperature.
+    #[serde(default)]
+    pub release_temp: Option<i32>,
+    #[serde(default)]
+    pub action: OverrideAction,
+}
+
+impl Default for StallPolicy {
     fn default() -> Self {
         Self {
+            ticks: DEFAULT_STALL_TICKS,
+            action: StallAction::default(),
+        }
+    }
+}
+
+/// Which curve to switch to when the machine is plugged in or unplugged.
+///
+/// Rules fire on the transition only, so a curve picked by hand stays active
+/// until the next plug event rather than being overridden every tick.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PowerRules {
+    pub on_ac: Option<String>,
+    pub on_battery: Option<String>,
+}
+
+/// The name given to the single curve of a config saved before named curves.
+const DEFAULT_CURVE_NAME: &str = "Default";
+
+fn default_curve_name() -> String {
+    DEFAULT_CURVE_NAME.to_string()
+}
+
+impl FanCurveConfig {
+    /// Make sure the working curve is also in `curves`.
+    ///
+    /// A config saved before named curves has only `points`; this files them
+    /// under the active name so nothing the user set up is lost.
+    fn ensure_active_saved(&mut self) {
+        self.curves
+            .entry(self.active_curve.clone())
+            .or_insert_with(|| self.points.clone());
+    }
+
+    /// Fix up any stored curve that would fail [`validate_curve`]. Configs
+    /// written before validation existed could hold anything the editor sent,
+    /// and an out-of-range level would otherwise only surface as a failed
+    /// write in the background task. Returns the names of curves changed.
+    fn normalize_stored(&mut self) -> Vec<String> {
+        let mut changed = Vec::new();
+        for (name, points) in self.curves.iter_mut() {
+            if !points.is_empty() && !validate_curve(points).is_empty() {
+                *points = normalize_curve(points);
+                changed.push(name.clone());
+            }
+        }
+        if let Some(active) = self.curves.get(&self.active_curve) {
+            self.points = active.clone();
+        }
+        changed
+    }
+
+    /// Bring a config read from disk up to the current shape. Shared by the
+    /// GUI's store and the daemon's config file.
+    pub fn finish_loading(&mut self) {
+        self.ensure_active_saved();
+        for name in self.normalize_stored() {
+            eprintln!(
+                "[Fan Curve] Stored curve {} was invalid; normalized it",
+                name
+            );
+        }
+    }
+
+    /// Make a saved curve the one the fan follows.
+    fn activate(&mut self, name: &str) -> Result<(), String> {
+        let points = self
+            .curves
+            .get(name)
+            .ok_or_else(|| format!("No fan curve named {}", name))?;
+        self.points = points.clone();
+        self.active_curve = name.to_string();
+        Ok(())
+    }
+
+    /// The curve a power rule wants, if the power source just changed to
+    /// `on_ac` and that curve is not already active.
+    ///
+    /// `previous` is None on the first reading after startup, which counts as
+    /// a change: the app starting on battery should pick the battery curve.
+    fn power_rule_switch(&self, previous: Option<bool>, on_ac: bool) -> Option<&str> {
+        if previous == Some(on_ac) {
+            return None;
+        }
+        let rule = if on_ac {
+            &self.power_rules.on_ac
+        } else {
+            &self.power_rules.on_battery
+        };
+        rule.as_deref()
+            .filter(|name| *name != self.active_curve && self.curves.contains_key(*name))
+    }
+
+    /// The working curve with every RPM target turned into the level that
+    /// reaches it on `machine`. Fails when a point targets an RPM and that
+    /// machine has not been calibrated.
+    fn resolve_rpm_targets(mut self, machine: &str) -> Result<Self, String> {
+        if self.points.iter().all(|p| p.rpm.is_none()) {
+            return Ok(self);
+        }
+        let calibration = self.calibrations.get(machine).ok_or_else(|| {
+            format!(
+                "The fan curve targets RPM, but this {} has not been calibrated. Calibrate it from the Fan Control page.",
+                machine
+            )
+        })?;
+        for p in &mut self.points {
+            if let Some(rpm) = p.rpm {
+                p.level = calibration.level_for_rpm(rpm);
+            }
+        }
+        // A table measured after the curve was edited can put an RPM point
+        // below the level point before it. Raise it rather than let the fan
+        // slow down as it heats up.
+        let mut order: Vec<usize> = (0..self.points.len()).collect();
+        order.sort_by_key(|&i| self.points[i].temp);
+        let mut floor = MIN_FAN_LEVEL;
+        for i in order {
+            let p = &mut self.points[i];
+            p.level = p.level.max(floor);
+            floor = p.level;
+        }
+        Ok(self)
+    }
+}
+
+/// Long enough to ride out a short burst of load, short enough that the fan
+/// still winds down within a few seconds of the work finishing.
+const DEFAULT_MIN_DWELL_SECS: u64 = 10;
+
+/// Dwell is a comfort setting, not a safety one, but a very long dwell holds
+/// the fan loud long after the load has gone. Five minutes is well past any
+/// plausible preference.
+const MAX_MIN_DWELL_SECS: u64 = 300;
+
+/// Width of the band on the default curve. Package sensors routinely wander
+/// 2–3°C between samples at a steady load, so anything narrower still flips.
+const DEFAULT_HYSTERESIS: i32 = 4;
+
+/// Smoothing delays the response to a real temperature rise as well as to
+/// noise. Past a minute the fan lags a sustained load badly enough to matter.
+const MAX_SMOOTHING_SECS: u64 = 60;
+
+/// Three ticks is six seconds. A fan going from 0 to level 1 is spinning well
+/// within one tick, so this is far past spin-up and still acts quickly.
+const DEFAULT_STALL_TICKS: u32 = 3;
+
+/// A minute of a stopped fan under a level that asked for airflow is already
+/// long; the setting exists to tolerate a slow tachometer, not to disable this.
+const MAX_STALL_TICKS: u32 = 30;
+
+/// One tick would be the tick after the level was written, before a fan
+/// coming up from 0 has had a reading at speed.
+const MIN_STALL_TICKS: u32 = 2;
+
+/// Used when neither the settings nor any followed sensor give a critical
+/// temperature. Under the 100°C most Intel parts throttle at, and well over
+/// anything a working fan curve should let a laptop reach.
+const FALLBACK_CRITICAL_TEMP: i32 = 95;
+
+/// A released override that re-engages a few seconds later just makes the fan
+/// surge. Ten degrees is enough for the curve to have caught up by then.
+const DEFAULT_RELEASE_GAP: i32 = 10;
+
+/// Configured critical temperatures must lie in this range. Below it the
+/// override fires under ordinary load; above it sensors have stopped making
+/// sense (see [`hottest_of`]).
+const MIN_CRITICAL_TEMP: i32 = 60;
+const MAX_CRITICAL_TEMP: i32 = 115;
+
+fn default_min_dwell_secs() -> u64 {
+    DEFAULT_MIN_DWELL_SECS
+}
+
+fn default_stall_ticks() -> u32 {
+    DEFAULT_STALL_TICKS
+}
+
+impl Default for FanCurveConfig {
+    fn default() -> Self {
+        let mut config = Self {
             enabled: false,
-            points: vec![
-                CurvePoint { temp: 40, level: 0 },
-                CurvePoint { temp: 50, level: 1 },
-                CurvePoint { temp: 60, level: 3 },
-                CurvePoint { temp: 70, level: 5 },
-                CurvePoint { temp: 80, level: 7 },
-            ],
+            points: [(40, 0), (50, 1), (60, 3), (70, 5), (80, 7)]
+                .into_iter()
+                .map(|(temp, level)| CurvePoint {
+                    temp,
+                    level,
+                    fall_temp: Some(temp - DEFAULT_HYSTERESIS),
+                    rpm: None,
+                })
+                .collect(),
+            min_dwell_secs: DEFAULT_MIN_DWELL_SECS,
+            sensors: Vec::new(),
+            smoothing_secs: 0,
+            curves: BTreeMap::new(),
+            active_curve: default_curve_name(),
+            power_rules: PowerRules::default(),
+            stall: StallPolicy::default(),
+            emergency: ThermalOverride::default(),
+            calibrations: BTreeMap::new(),
+        };
+        config.ensure_active_saved();
+        config
+    }
+}
+
+/// Check a thermal override before it is stored.
+///
+/// A release temperature at or above the critical one would let the override
+/// engage and release on the same reading, which is no hysteresis at all.
+fn validate_thermal_override(emergency: &ThermalOverride) -> Result<(), String> {
+    if let Some(critical) = emergency.critical_temp {
+        if !(MIN_CRITICAL_TEMP..=MAX_CRITICAL_TEMP).contains(&critical) {
+            return Err(format!(
+                "Critical temperature must be between {}°C and {}°C, not {}°C",
+                MIN_CRITICAL_TEMP, MAX_CRITICAL_TEMP, critical
+            ));
+        }
+    }
+    if let Some(release) = emergency.release_temp {
+        if let Some(critical) = emergency.critical_temp.filter(|c| release >= *c) {
+            return Err(format!(
+                "Release temperature {}°C must be below the critical temperature {}°C",
+                release, critical
+            ));
+        }
+        if release < MIN_CRITICAL_TEMP - DEFAULT_RELEASE_GAP {
+            return Err(format!(
+                "Release temperature {}°C is too low (minimum {}°C)",
+                release,
+                MIN_CRITICAL_TEMP - DEFAULT_RELEASE_GAP
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The levels `/proc/acpi/ibm/fan` accepts as numbers.
+pub const MIN_FAN_LEVEL: i32 = 0;
+pub const MAX_FAN_LEVEL: i32 = FanLevel::MAX_MANUAL as i32;
+
+/// Curve temperatures must lie in the range sensor readings are trusted in
+/// (see [`hottest_of`]); a point outside it can never be reached.
+const MIN_CURVE_TEMP: i32 = 0;
+const MAX_CURVE_TEMP: i32 = 120;
+
+/// The part of a point a [`CurveError`] is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CurveField {
+    Temp,
+    Level,
+    FallTemp,
+    Rpm,
+}
+
+/// One thing wrong with a curve, pointing at the point that caused it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CurveError {
+    /// Position in the points as sent, not after sorting. None when the
+    /// problem is with the curve as a whole.
+    pub index: Option<usize>,
+    pub field: Option<CurveField>,
+    pub message: String,
+}
+
+impl CurveError {
+    fn at(index: usize, field: CurveField, message: String) -> Self {
+        Self {
+            index: Some(index),
+            field: Some(field),
+            message,
+        }
+    }
+}
+
+/// Everything wrong with a set of curve points, in the order the points were
+/// sent. Empty means the curve can be stored as-is.
+///
+/// The curve is read in temperature order, so ordering problems are reported
+/// against whichever point comes second in that order:
+/// - two points at one temperature make the level there ambiguous;
+/// - a level lower than the point before it would slow the fan as the machine
+///   heats up, and so would an RPM target lower than the one before it. Levels
+///   are only compared between points without a target, and targets between
+///   points with one;
+/// - a fall temperature above its rise temperature inverts the hysteresis
+///   band, so the curve would drop the level where it also wants to raise it
+///   and flip every tick;
+/// - fall temperatures out of order make the falling curve a different shape
+///   from the rising one rather than a shifted copy of it.
+pub fn validate_curve(points: &[CurvePoint]) -> Vec<CurveError> {
+    if points.is_empty() {
+        return vec![CurveError {
+            index: None,
+            field: None,
+            message: "A fan curve needs at least one point".to_string(),
+        }];
+    }
+
+    let mut errors = Vec::new();
+    for (i, p) in points.iter().enumerate() {
+        if !(MIN_CURVE_TEMP..=MAX_CURVE_TEMP).contains(&p.temp) {
+            errors.push(CurveError::at(
+                i,
+                CurveField::Temp,
+                format!(
+                    "Point {} is at {}°C; temperatures must be {}–{}°C",
+                    i + 1,
+                    p.temp,
+                    MIN_CURVE_TEMP,
+                    MAX_CURVE_TEMP
+                ),
+            ));
+        }
+        if !(MIN_FAN_LEVEL..=MAX_FAN_LEVEL).contains(&p.level) {
+            errors.push(CurveError::at(
+                i,
+                CurveField::Level,
+                format!(
+                    "Point {} has level {}; levels must be {}–{}",
+                    i + 1,
+                    p.level,
+                    MIN_FAN_LEVEL,
+                    MAX_FAN_LEVEL
+                ),
+            ));
+        }
+        if p.falling_temp() > p.temp {
+            errors.push(CurveError::at(
+                i,
+                CurveField::FallTemp,
+                format!(
+                    "Point at {}°C has a fall temperature of {}°C; it must not be above the rise temperature",
+                    p.temp,
+                    p.falling_temp()
+                ),
+            ));
+        }
+    }
+
+    // Stable, so of two points at one temperature the later one is reported.
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    order.sort_by_key(|&i| points[i].temp);
+    for pair in order.windows(2) {
+        let (prev, (i, p)) = (&points[pair[0]], (pair[1], &points[pair[1]]));
+        if p.temp == prev.temp {
+            errors.push(CurveError::at(
+                i,
+                CurveField::Temp,
+                format!("Two points are at {}°C", p.temp),
+            ));
+            continue;
+        }
+        if p.rpm.is_none() && prev.rpm.is_none() && p.level < prev.level {
+            errors.push(CurveError::at(
+                i,
+                CurveField::Level,
+                format!(
+                    "Level {} at {}°C is below level {} at {}°C; the fan must not slow down as it heats up",
+                    p.level, p.temp, prev.level, prev.temp
+                ),
+            ));
+        }
+        // Across a level point and an RPM point, the RPM point's recorded
+        // level stands in for its speed.
+        if p.level < prev.level {
+            match (p.rpm, prev.rpm) {
+                (Some(rpm), None) => errors.push(CurveError::at(
+                    i,
+                    CurveField::Rpm,
+                    format!(
+                        "{} RPM at {}°C runs at level {}, below level {} at {}°C; the fan must not slow down as it heats up",
+                        rpm, p.temp, p.level, prev.level, prev.temp
+                    ),
+                )),
+                (None, Some(prev_rpm)) => errors.push(CurveError::at(
+                    i,
+                    CurveField::Level,
+                    format!(
+                        "Level {} at {}°C is below level {}, which {} RPM at {}°C runs at; the fan must not slow down as it heats up",
+                        p.level, p.temp, prev.level, prev_rpm, prev.temp
+                    ),
+                )),
+                _ => {}
+            }
+        }
+        if let (Some(rpm), Some(prev_rpm)) = (p.rpm, prev.rpm) {
+            if rpm < prev_rpm {
+                errors.push(CurveError::at(
+                    i,
+                    CurveField::Rpm,
+                    format!(
+                        "{} RPM at {}°C is below {} RPM at {}°C; the fan must not slow down as it heats up",
+                        rpm, p.temp, prev_rpm, prev.temp
+                    ),
+                ));
+            }
+        }
+        if p.falling_temp() < prev.falling_temp() {
+            errors.push(CurveError::at(
+                i,
+                CurveField::FallTemp,
+                format!(
+                    "Fall temperature {}°C of the point at {}°C is below that of the point at {}°C",
+                    p.falling_temp(),
+                    p.temp,
+                    prev.temp
+                ),
+            ));
+        }
+    }
+
+    errors.sort_by_key(|e| e.index);
+    errors
+}
+
+/// Turn any curve into the nearest one [`validate_curve`] accepts, short of
+/// inventing points for an empty one.
+///
+/// Temperatures and levels are clamped into range, points are sorted, and of
+/// several at one temperature the highest level is kept. Levels and RPM
+/// targets are then raised where needed so they never fall, and fall
+/// temperatures are pulled into their valid band. Every adjustment errs
+/// towards more airflow.
+pub fn normalize_curve(points: &[CurvePoint]) -> Vec<CurvePoint> {
+    let mut sorted: Vec<CurvePoint> = points
+        .iter()
+        .map(|p| CurvePoint {
+            temp: p.temp.clamp(MIN_CURVE_TEMP, MAX_CURVE_TEMP),
+            level: p.level.clamp(MIN_FAN_LEVEL, MAX_FAN_LEVEL),
+            fall_temp: p.fall_temp,
+            rpm: p.rpm,
+        })
+        .collect();
+    sorted.sort_by_key(|p| (p.temp, std::cmp::Reverse(p.level)));
+    sorted.dedup_by_key(|p| p.temp);
+
+    let mut level = MIN_FAN_LEVEL;
+    let mut rpm = 0;
+    let mut fall = i32::MIN;
+    for p in &mut sorted {
+        level = level.max(p.level);
+        p.level = level;
+        if let Some(r) = p.rpm {
+            rpm = rpm.max(r);
+            p.rpm = Some(rpm);
+        }
+        if let Some(f) = p.fall_temp {
+            p.fall_temp = Some(f.max(fall).min(p.temp));
         }
+        fall = p.falling_temp();
+    }
+    sorted
+}
+
+/// The curve check as a single message, for callers that report a string.
+pub fn check_curve(points: &[CurvePoint]) -> Result<(), String> {
+    let errors = validate_curve(points);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors
+            .into_iter()
+            .map(|e| e.message)
+            .collect::<Vec<_>>()
+            .join("; "))
+    }
+}
+
+/// Check a whole config, as sent to the daemon, against the same limits the
+/// individual setters apply.
+pub fn validate_config(config: &FanCurveConfig) -> Result<(), String> {
+    validate_curve_settings(&config.points, config.min_dwell_secs)?;
+    for (name, points) in &config.curves {
+        check_curve(points).map_err(|e| format!("Curve {}: {}", name, e))?;
+    }
+    if config.smoothing_secs > MAX_SMOOTHING_SECS {
+        return Err(format!(
+            "Smoothing of {}s is too long (maximum {}s)",
+            config.smoothing_secs, MAX_SMOOTHING_SECS
+        ));
+    }
+    check_stall_ticks(config.stall.ticks)?;
+    validate_thermal_override(&config.emergency)
+}
+
+/// Check a curve and its dwell setting before they are stored.
+fn validate_curve_settings(points: &[CurvePoint], min_dwell_secs: u64) -> Result<(), String> {
+    if min_dwell_secs > MAX_MIN_DWELL_SECS {
+        return Err(format!(
+            "Minimum dwell of {}s is too long (maximum {}s)",
+            min_dwell_secs, MAX_MIN_DWELL_SECS
+        ));
     }
+    check_curve(points)
+}
+
+/// A curve as it would be stored, and what is still wrong with it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CurveCheck {
+    /// The points as sent, or normalized if that was asked for.
+    pub points: Vec<CurvePoint>,
+    pub errors: Vec<CurveError>,
+}
+
+/// Check curve points without storing them, optionally normalizing first.
+pub fn check_curve_points(points: Vec<CurvePoint>, normalize: bool) -> CurveCheck {
+    let points = if normalize {
+        normalize_curve(&points)
+    } else {
+        points
+    };
+    let errors = validate_curve(&points);
+    CurveCheck { points, errors }
 }
 
 pub type FanCurveState = Arc<Mutex<FanCurveConfig>>;
@@ -37,7 +656,130 @@ pub type FanCurveState = Arc<Mutex<FanCurveConfig>>;
 const STORE_FILE: &str = "settings.json";
 const CURVE_KEY: &str = "fan_curve";
 
-/// Save fan curve config to persistent storage
+/// One edit to the curve config, as the commands below make it.
+///
+/// Edits travel as changes rather than whole configs so that, with the daemon
+/// attached, they land on the daemon's current config. The GUI's copy can be
+/// behind it: the daemon switches curves on power rules while no window is
+/// watching.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum CurveChange {
+    /// Replace the working curve, and the saved curve of the same name.
+    Points {
+        points: Vec<CurvePoint>,
+        min_dwell_secs: Option<u64>,
+    },
+    Save {
+        name: String,
+        points: Vec<CurvePoint>,
+    },
+    Delete {
+        name: String,
+    },
+    Activate {
+        name: String,
+    },
+    PowerRules(PowerRules),
+    Stall(StallPolicy),
+    ThermalOverride(ThermalOverride),
+    Sensors {
+        sensors: Vec<String>,
+        smoothing_secs: u64,
+    },
+    Calibration(FanCalibration),
+    Enabled {
+        enabled: bool,
+    },
+}
+
+impl FanCurveConfig {
+    /// Apply one change, or leave the config untouched and say why not.
+    pub fn apply(&mut self, change: CurveChange) -> Result<(), String> {
+        match change {
+            CurveChange::Points {
+                points,
+                min_dwell_secs,
+            } => {
+                let min_dwell_secs = min_dwell_secs.unwrap_or(self.min_dwell_secs);
+                validate_curve_settings(&points, min_dwell_secs)?;
+                self.points = points.clone();
+                self.min_dwell_secs = min_dwell_secs;
+                self.curves.insert(self.active_curve.clone(), points);
+            }
+            CurveChange::Save { name, points } => {
+                let name = name.trim().to_string();
+                if name.is_empty() {
+                    return Err("A fan curve needs a name".to_string());
+                }
+                validate_curve_settings(&points, self.min_dwell_secs)?;
+                if name == self.active_curve {
+                    self.points = points.clone();
+                }
+                self.curves.insert(name, points);
+            }
+            CurveChange::Delete { name } => {
+                if name == self.active_curve {
+                    return Err(format!(
+                        "{} is the active fan curve; switch to another first",
+                        name
+                    ));
+                }
+                let rules = &self.power_rules;
+                if rules.on_ac.as_deref() == Some(name.as_str())
+                    || rules.on_battery.as_deref() == Some(name.as_str())
+                {
+                    return Err(format!(
+                        "{} is used by an AC/battery rule; change the rule first",
+                        name
+                    ));
+                }
+                if self.curves.remove(&name).is_none() {
+                    return Err(format!("No fan curve named {}", name));
+                }
+            }
+            CurveChange::Activate { name } => self.activate(&name)?,
+            CurveChange::PowerRules(rules) => {
+                for name in [&rules.on_ac, &rules.on_battery].into_iter().flatten() {
+                    if !self.curves.contains_key(name) {
+                        return Err(format!("No fan curve named {}", name));
+                    }
+                }
+                self.power_rules = rules;
+            }
+            CurveChange::Stall(policy) => {
+                check_stall_ticks(policy.ticks)?;
+                self.stall = policy;
+            }
+            CurveChange::ThermalOverride(emergency) => {
+                validate_thermal_override(&emergency)?;
+                self.emergency = emergency;
+            }
+            CurveChange::Sensors {
+                sensors,
+                smoothing_secs,
+            } => {
+                if smoothing_secs > MAX_SMOOTHING_SECS {
+                    return Err(format!(
+                        "Smoothing of {}s is too long (maximum {}s)",
+                        smoothing_secs, MAX_SMOOTHING_SECS
+                    ));
+                }
+                self.sensors = sensors;
+                self.smoothing_secs = smoothing_secs;
+            }
+            CurveChange::Calibration(calibration) => {
+                self.calibrations
+                    .insert(calibration.machine.clone(), calibration);
+            }
+            CurveChange::Enabled { enabled } => self.enabled = enabled,
+        }
+        Ok(())
+    }
+}
+
+/// Save fan curve config to persistent storage. Callers hold no lock on the
+/// state while this writes.
 fn save_config_to_store(app: &AppHandle, config: &FanCurveConfig) -> Result<(), String> {
     let store = app
         .store(STORE_FILE)
@@ -55,13 +797,51 @@ fn save_config_to_store(app: &AppHandle, config: &FanCurveConfig) -> Result<(),
     Ok(())
 }
 
+/// Make one change to the curve config and persist it.
+///
+/// With `thinkutils-daemon` attached, the daemon is what steers the fan, so
+/// the change is applied there and its resulting config replaces this
+/// process's copy; the store only mirrors it for the next start. Without the
+/// daemon the change is applied here. Either way the state is locked only
+/// for the change itself, never across the socket or the store write.
+async fn update_config(
+    app: &AppHandle,
+    state: &FanCurveState,
+    change: CurveChange,
+) -> Result<FanCurveConfig, String> {
+    let config = match crate::daemon::attached() {
+        Some(socket) => {
+            let value =
+                crate::daemon::call(socket, crate::daemon::Request::UpdateFanCurve { change })
+                    .await?;
+            let config: FanCurveConfig = serde_json::from_value(value)
+                .map_err(|e| format!("Invalid config from thinkutils-daemon: {}", e))?;
+            *state
+                .lock()
+                .map_err(|e| format!("Failed to lock state: {}", e))? = config.clone();
+            config
+        }
+        None => {
+            let mut config = state
+                .lock()
+                .map_err(|e| format!("Failed to lock state: {}", e))?;
+            config.apply(change)?;
+            config.clone()
+        }
+    };
+
+    save_config_to_store(app, &config)?;
+    Ok(config)
+}
+
 /// Load fan curve config from persistent storage
 pub fn load_config_from_store(app: &AppHandle) -> FanCurveConfig {
     match app.store(STORE_FILE) {
         Ok(store) => {
             if let Some(config_value) = store.get(CURVE_KEY) {
                 match serde_json::from_value::<FanCurveConfig>(config_value.clone()) {
-                    Ok(config) => {
+                    Ok(mut config) => {
+                        config.finish_loading();
                         println!("[Fan Curve] Configuration loaded from store");
                         return config;
                     }
@@ -80,89 +860,455 @@ pub fn load_config_from_store(app: &AppHandle) -> FanCurveConfig {
     FanCurveConfig::default()
 }
 
+/// Check curve points the way [`set_fan_curve`] would, and say what is wrong
+/// with each one rather than stopping at the first problem.
+#[tauri::command]
+pub async fn validate_fan_curve(points: Vec<CurvePoint>, normalize: Option<bool>) -> CurveCheck {
+    check_curve_points(points, normalize.unwrap_or(false))
+}
+
+/// Store the active curve. With `normalize`, the points are first fixed up by
+/// [`normalize_curve`] instead of being refused.
 #[tauri::command]
 pub async fn set_fan_curve(
     app: AppHandle,
     state: tauri::State<'_, FanCurveState>,
     points: Vec<CurvePoint>,
+    min_dwell_secs: Option<u64>,
+    normalize: Option<bool>,
 ) -> Result<(), String> {
-    let mut config = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    config.points = points.clone();
-
-    // Save to persistent storage
-    save_config_to_store(&app, &config)?;
+    let points = if normalize.unwrap_or(false) {
+        normalize_curve(&points)
+    } else {
+        points
+    };
+    let change = CurveChange::Points {
+        points,
+        min_dwell_secs,
+    };
+    update_config(&app, &state, change).await?;
 
     Ok(())
 }
 
+/// Save a curve under a name, creating or replacing it.
+///
+/// Saving over the active curve takes effect immediately, the same as
+/// [`set_fan_curve`].
 #[tauri::command]
-pub async fn get_fan_curve(
+pub async fn save_fan_curve(
+    app: AppHandle,
     state: tauri::State<'_, FanCurveState>,
-) -> Result<FanCurveConfig, String> {
-    let config = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    Ok(config.clone())
+    name: String,
+    points: Vec<CurvePoint>,
+    normalize: Option<bool>,
+) -> Result<(), String> {
+    let points = if normalize.unwrap_or(false) {
+        normalize_curve(&points)
+    } else {
+        points
+    };
+    update_config(&app, &state, CurveChange::Save { name, points }).await?;
+
+    Ok(())
 }
 
+/// Delete a saved curve.
+///
+/// The active curve and any curve a power rule points at are refused: either
+/// would leave the background task, or the next plug event, with nothing to
+/// switch to.
 #[tauri::command]
-pub async fn enable_fan_curve(
+pub async fn delete_fan_curve(
     app: AppHandle,
     state: tauri::State<'_, FanCurveState>,
-    enabled: bool,
+    name: String,
 ) -> Result<(), String> {
-    let mut config = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    config.enabled = enabled;
+    update_config(&app, &state, CurveChange::Delete { name }).await?;
+
+    Ok(())
+}
 
-    // Save to persistent storage
-    save_config_to_store(&app, &config)?;
+/// Switch to a saved curve by hand.
+#[tauri::command]
+pub async fn activate_fan_curve(
+    app: AppHandle,
+    state: tauri::State<'_, FanCurveState>,
+    name: String,
+) -> Result<(), String> {
+    let change = CurveChange::Activate { name: name.clone() };
+    update_config(&app, &state, change).await?;
 
+    emit_curve_switched(&app, &name, "manual");
     Ok(())
 }
 
-/// Calculate fan level based on temperature and curve points
-fn calculate_fan_level(temp: i32, points: &[CurvePoint]) -> i32 {
-    if points.is_empty() {
-        return 0;
-    }
+/// Set which curves the AC and battery rules switch to. `None` turns a rule
+/// off.
+#[tauri::command]
+pub async fn set_fan_curve_power_rules(
+    app: AppHandle,
+    state: tauri::State<'_, FanCurveState>,
+    on_ac: Option<String>,
+    on_battery: Option<String>,
+) -> Result<(), String> {
+    let change = CurveChange::PowerRules(PowerRules { on_ac, on_battery });
+    update_config(&app, &state, change).await?;
 
-    // Sort points by temperature
-    let mut sorted_points = points.to_vec();
-    sorted_points.sort_by_key(|p| p.temp);
+    Ok(())
+}
 
-    // If temp is below first point, use first level
-    if temp <= sorted_points[0].temp {
-        return sorted_points[0].level;
+fn check_stall_ticks(ticks: u32) -> Result<(), String> {
+    if !(MIN_STALL_TICKS..=MAX_STALL_TICKS).contains(&ticks) {
+        return Err(format!(
+            "Stall detection needs between {} and {} ticks, not {}",
+            MIN_STALL_TICKS, MAX_STALL_TICKS, ticks
+        ));
     }
+    Ok(())
+}
 
-    // If temp is above last point, use last level
-    if temp >= sorted_points[sorted_points.len() - 1].temp {
-        return sorted_points[sorted_points.len() - 1].level;
-    }
+/// Set how long the fan may read 0 RPM before the curve steps in, and how.
+#[tauri::command]
+pub async fn set_fan_stall_policy(
+    app: AppHandle,
+    state: tauri::State<'_, FanCurveState>,
+    ticks: u32,
+    action: StallAction,
+) -> Result<(), String> {
+    let change = CurveChange::Stall(StallPolicy { ticks, action });
+    update_config(&app, &state, change).await?;
 
-    // Find the two points to interpolate between
-    for i in 0..sorted_points.len() - 1 {
-        if temp >= sorted_points[i].temp && temp <= sorted_points[i + 1].temp {
-            // Linear interpolation
-            let t = (temp - sorted_points[i].temp) as f64
-                / (sorted_points[i + 1].temp - sorted_points[i].temp) as f64;
-            let level = sorted_points[i].level as f64
-                + t * (sorted_points[i + 1].level - sorted_points[i].level) as f64;
-            return level.round() as i32;
-        }
-    }
+    Ok(())
+}
 
-    sorted_points[sorted_points.len() - 1].level
+/// Set the thermal override. Leaving a temperature out restores its default.
+#[tauri::command]
+pub async fn set_fan_thermal_override(
+    app: AppHandle,
+    state: tauri::State<'_, FanCurveState>,
+    critical_temp: Option<i32>,
+    release_temp: Option<i32>,
+    action: OverrideAction,
+) -> Result<(), String> {
+    let change = CurveChange::ThermalOverride(ThermalOverride {
+        critical_temp,
+        release_temp,
+        action,
+    });
+    update_config(&app, &state, change).await?;
+
+    Ok(())
 }
 
-/// Get CPU temperature from sensors
-fn get_cpu_temperature() -> Result<i32, String> {
-    use std::fs;
-    use std::path::Path;
+/// Tell the UI the thermal override engaged or released. `action` is None on
+/// release, and on engage when the fan could not be forced and went to auto.
+fn emit_thermal_override(
+    host: &impl CurveHost,
+    active: bool,
+    temperature: i32,
+    critical: i32,
+    release: i32,
+    action: Option<OverrideAction>,
+) {
+    let _ = host.emit(
+        "fan-thermal-override",
+        serde_json::json!({
+            "active": active,
+            "temperature": temperature,
+            "critical": critical,
+            "release": release,
+            "action": action,
+        }),
+    );
+}
+
+/// Tell the UI which curve is now active and why.
+fn emit_curve_switched(host: &impl CurveHost, name: &str, reason: &str) {
+    println!("[Fan Curve] Switched to curve {:?} ({})", name, reason);
+    if let Err(e) = host.emit(
+        "fan-curve-switched",
+        serde_json::json!({
+            "curve": name,
+            "reason": reason,
+        }),
+    ) {
+        eprintln!("[Fan Curve] Failed to emit event: {}", e);
+    }
+}
+
+/// Whether any `AC*` power supply under `dir` reports itself online.
+///
+/// None when the machine has no AC supply at all (a desktop, or a fixture
+/// without one), in which case the power rules never fire.
+fn on_ac_power_in(dir: &Path) -> Option<bool> {
+    let mut found = false;
+    for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+        if !entry.file_name().to_string_lossy().starts_with("AC") {
+            continue;
+        }
+        match read_trimmed(&entry.path().join("online")).as_deref() {
+            Some("1") => return Some(true),
+            Some(_) => found = true,
+            None => {}
+        }
+    }
+    found.then_some(false)
+}
+
+fn on_ac_power() -> Option<bool> {
+    on_ac_power_in(&crate::hardware_root::resolve("/sys/class/power_supply"))
+}
+
+/// Choose which sensors drive the curve and how much to smooth them.
+///
+/// Unknown ids are refused rather than stored: a curve following a sensor that
+/// does not exist reads nothing, and the background task would hand the fan
+/// back to the firmware on every tick.
+#[tauri::command]
+pub async fn set_fan_curve_sensors(
+    app: AppHandle,
+    state: tauri::State<'_, FanCurveState>,
+    sensors: Vec<String>,
+    smoothing_secs: u64,
+) -> Result<(), String> {
+    let available = list_sensors();
+    if let Some(unknown) = sensors
+        .iter()
+        .find(|id| !available.iter().any(|s| &s.id == *id))
+    {
+        return Err(format!("Unknown temperature sensor: {}", unknown));
+    }
+
+    let change = CurveChange::Sensors {
+        sensors,
+        smoothing_secs,
+    };
+    update_config(&app, &state, change).await?;
+
+    Ok(())
+}
+
+/// Every temperature sensor the curve can follow, with its current reading.
+#[tauri::command]
+pub async fn list_temperature_sensors() -> Result<Vec<TemperatureSensor>, String> {
+    Ok(list_sensors())
+}
+
+#[tauri::command]
+pub async fn get_fan_curve(
+    state: tauri::State<'_, FanCurveState>,
+) -> Result<FanCurveConfig, String> {
+    let config = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    Ok(config.clone())
+}
+
+#[tauri::command]
+pub async fn enable_fan_curve(
+    app: AppHandle,
+    state: tauri::State<'_, FanCurveState>,
+    enabled: bool,
+) -> Result<(), String> {
+    if enabled && crate::fan_control::calibration_running() {
+        return Err("Wait for the fan calibration to finish before enabling the curve".to_string());
+    }
+    update_config(&app, &state, CurveChange::Enabled { enabled }).await?;
+
+    Ok(())
+}
+
+/// Whether the curve is running, asking the daemon when there is one: the
+/// copy here does not see it enabled from the CLI or another session.
+async fn curve_running(config: &FanCurveConfig) -> Result<bool, String> {
+    let Some(socket) = crate::daemon::attached() else {
+        return Ok(config.enabled);
+    };
+    let status = crate::daemon::call(socket, crate::daemon::Request::Status).await?;
+    let status: crate::daemon::Status = serde_json::from_value(status)
+        .map_err(|e| format!("Invalid status from thinkutils-daemon: {}", e))?;
+    Ok(status.fan_curve_enabled)
+}
+
+/// Measure this machine's level→RPM table and store it with the curve.
+///
+/// Takes the fan for about a minute, so it refuses while the curve is running,
+/// in this process or in the daemon. Each level is reported as a
+/// `fan-calibration-progress` event as it is measured. The thermal override's
+/// limit still applies: reaching it ends the calibration with the fan on auto.
+#[tauri::command]
+pub async fn start_fan_calibration(
+    app: AppHandle,
+    state: tauri::State<'_, FanCurveState>,
+) -> Result<FanCalibration, String> {
+    let config = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .clone();
+    if curve_running(&config).await? {
+        return Err(
+            "Turn the fan curve off before calibrating; both would steer the fan".to_string(),
+        );
+    }
+
+    let (critical, _) = thermal_limits(&config, &list_sensors());
+    let calibration = crate::fan_control::calibrate_fan(
+        CalibrationTiming::default(),
+        critical,
+        || read_curve_temperature(&config),
+        |point| {
+            let _ = app.emit_to("main", "fan-calibration-progress", point);
+        },
+    )
+    .await?;
+
+    let change = CurveChange::Calibration(calibration.clone());
+    update_config(&app, &state, change).await?;
+    Ok(calibration)
+}
+
+#[tauri::command]
+pub async fn cancel_fan_calibration() {
+    crate::fan_control::cancel_calibration();
+}
+
+/// This machine's stored calibration, if it has one.
+#[tauri::command]
+pub async fn get_fan_calibration(
+    state: tauri::State<'_, FanCurveState>,
+) -> Result<Option<FanCalibration>, String> {
+    let config = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    Ok(config.calibrations.get(&machine_id()).cloned())
+}
+
+/// Calculate fan level based on temperature and curve points
+fn calculate_fan_level(temp: i32, points: &[CurvePoint]) -> i32 {
+    if points.is_empty() {
+        return 0;
+    }
+
+    // Sort points by temperature
+    let mut sorted_points = points.to_vec();
+    sorted_points.sort_by_key(|p| p.temp);
+
+    // If temp is below first point, use first level
+    if temp <= sorted_points[0].temp {
+        return sorted_points[0].level;
+    }
+
+    // If temp is above last point, use last level
+    if temp >= sorted_points[sorted_points.len() - 1].temp {
+        return sorted_points[sorted_points.len() - 1].level;
+    }
+
+    // Find the two points to interpolate between
+    for i in 0..sorted_points.len() - 1 {
+        if temp >= sorted_points[i].temp && temp <= sorted_points[i + 1].temp {
+            // Linear interpolation
+            let t = (temp - sorted_points[i].temp) as f64
+                / (sorted_points[i + 1].temp - sorted_points[i].temp) as f64;
+            let level = sorted_points[i].level as f64
+                + t * (sorted_points[i + 1].level - sorted_points[i].level) as f64;
+            return level.round() as i32;
+        }
+    }
+
+    sorted_points[sorted_points.len() - 1].level
+}
+
+/// The level the curve should run at, given the one it is running at now.
+///
+/// Two curves are in play: the configured one, used while heating, and a copy
+/// shifted down to each point's fall temperature, used while cooling. The level
+/// rises as soon as the rising curve asks for more, but only falls once the
+/// falling curve asks for less — so a CPU sitting on a boundary stays on one
+/// side of it instead of flipping the fan every tick.
+///
+/// `held_for` is how long `current` has been applied. Lowering the level waits
+/// out the dwell time; raising it never does, because a fan slow to speed up is
+/// a thermal problem and one slow to wind down is only a noise problem.
+fn next_fan_level(
+    temp: i32,
+    config: &FanCurveConfig,
+    current: Option<i32>,
+    held_for: Duration,
+) -> i32 {
+    let rising = calculate_fan_level(temp, &config.points);
+    let Some(current) = current else {
+        return rising;
+    };
+
+    if rising > current {
+        return rising;
+    }
+
+    let falling_points: Vec<CurvePoint> = config
+        .points
+        .iter()
+        .map(|p| CurvePoint {
+            temp: p.falling_temp(),
+            ..p.clone()
+        })
+        .collect();
+    let falling = calculate_fan_level(temp, &falling_points);
+
+    if falling < current && held_for >= Duration::from_secs(config.min_dwell_secs) {
+        falling
+    } else {
+        current
+    }
+}
+
+/// How often the curve reads the temperature and decides on a level.
+const CURVE_TICK: Duration = Duration::from_secs(2);
+
+/// What one steering tick does to the fan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TickPlan {
+    level: i32,
+    /// The level differs from the one last applied. Writing it also re-arms
+    /// the watchdog.
+    write_level: bool,
+    arm_watchdog: bool,
+}
+
+/// Decide one tick: the level from [`next_fan_level`], and which fan commands
+/// it takes. Only changes are written, but holding a level still counts as
+/// steering the fan, so the watchdog is re-armed whenever [`watchdog_due`]
+/// says so even if nothing else is sent.
+///
+/// `held_for` and `since_armed` are None when no level is applied or no
+/// watchdog armed. Shared by the loop and [`simulate_curve`], so a dry run
+/// makes the decisions the fan would see.
+fn plan_tick(
+    temp: i32,
+    config: &FanCurveConfig,
+    last_level: Option<i32>,
+    held_for: Option<Duration>,
+    since_armed: Option<Duration>,
+) -> TickPlan {
+    let level = next_fan_level(temp, config, last_level, held_for.unwrap_or(Duration::MAX));
+    if last_level != Some(level) {
+        return TickPlan {
+            level,
+            write_level: true,
+            arm_watchdog: true,
+        };
+    }
+    TickPlan {
+        level,
+        write_level: false,
+        arm_watchdog: since_armed.is_none_or(watchdog_due),
+    }
+}
+
+/// Get CPU temperature from sensors
+fn get_cpu_temperature() -> Result<i32, String> {
+    use crate::hardware_root;
+    use std::fs;
 
     // Try to read from common thermal zones
     let thermal_zones = [
@@ -172,22 +1318,20 @@ fn get_cpu_temperature() -> Result<i32, String> {
     ];
 
     for zone in &thermal_zones {
-        if Path::new(zone).exists() {
-            if let Ok(content) = fs::read_to_string(zone) {
-                if let Ok(temp_millidegrees) = content.trim().parse::<i32>() {
-                    let temp = temp_millidegrees / 1000;
-                    // Sanity check: temperature should be between 0 and 120°C
-                    if temp > 0 && temp < 120 {
-                        return Ok(temp);
-                    }
+        if let Ok(content) = hardware_root::read_to_string(zone) {
+            if let Ok(temp_millidegrees) = content.trim().parse::<i32>() {
+                let temp = temp_millidegrees / 1000;
+                // Sanity check: temperature should be between 0 and 120°C
+                if temp > 0 && temp < 120 {
+                    return Ok(temp);
                 }
             }
         }
     }
 
-    // Try hwmon sensors
-    let hwmon_path = "/sys/class/hwmon";
-    if let Ok(entries) = fs::read_dir(hwmon_path) {
+    // Try hwmon sensors. Entries come back already resolved, so the reads
+    // below stay inside the hardware root.
+    if let Ok(entries) = hardware_root::read_dir("/sys/class/hwmon") {
         for entry in entries.flatten() {
             let path = entry.path();
 
@@ -198,11 +1342,7 @@ fn get_cpu_temperature() -> Result<i32, String> {
 
                 if temp_label_path.exists() && temp_input_path.exists() {
                     if let Ok(label) = fs::read_to_string(&temp_label_path) {
-                        let label_lower = label.to_lowercase();
-                        if label_lower.contains("cpu")
-                            || label_lower.contains("package")
-                            || label_lower.contains("core")
-                        {
+                        if is_cpu_label(&label) {
                             if let Ok(content) = fs::read_to_string(&temp_input_path) {
                                 if let Ok(temp_millidegrees) = content.trim().parse::<i32>() {
                                     let temp = temp_millidegrees / 1000;
@@ -221,13 +1361,303 @@ fn get_cpu_temperature() -> Result<i32, String> {
     Err("Could not read CPU temperature".to_string())
 }
 
+/// Whether a hwmon label names a CPU temperature, as the heuristic in
+/// [`get_cpu_temperature`] understands it.
+fn is_cpu_label(label: &str) -> bool {
+    let label = label.to_lowercase();
+    label.contains("cpu") || label.contains("package") || label.contains("core")
+}
+
+/// A temperature input the curve can follow.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemperatureSensor {
+    /// Stable across reboots, unlike the hwmonN and thermal_zoneN numbering:
+    /// `hwmon:<chip>:<label>` or `thermal:<zone type>`. Two inputs can share an
+    /// id (several `acpitz` zones, say); following it takes the hottest.
+    pub id: String,
+    /// Human-readable name for the sensor picker.
+    pub name: String,
+    /// Current reading in °C, if it could be read.
+    pub temp: Option<i32>,
+    /// The driver's critical limit in °C. Thermal zones publish none.
+    pub crit: Option<i32>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+/// Read a sysfs millidegree file as whole degrees.
+fn read_millidegrees(path: &Path) -> Option<i32> {
+    std::fs::read_to_string(path)
+        .ok()?
+        .trim()
+        .parse::<i32>()
+        .ok()
+        .map(|m| m / 1000)
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_string())
+}
+
+/// Every hwmon temperature input and thermal zone under the given directories.
+fn sensors_in(hwmon_dir: &Path, thermal_dir: &Path) -> Vec<TemperatureSensor> {
+    let mut sensors = Vec::new();
+
+    for reading in crate::hwmon::inventory_in(hwmon_dir) {
+        if reading.kind != crate::hwmon::SensorKind::Temperature {
+            continue;
+        }
+        sensors.push(TemperatureSensor {
+            id: format!("hwmon:{}:{}", reading.chip, reading.label),
+            name: format!("{} {}", reading.chip, reading.label),
+            temp: reading.value.map(|t| t as i32),
+            crit: reading.crit.map(|t| t as i32),
+            path: reading.input_path().to_path_buf(),
+        });
+    }
+
+    for zone in std::fs::read_dir(thermal_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+    {
+        let zone_dir = zone.path();
+        let zone_name = zone.file_name().to_string_lossy().to_string();
+        if !zone_name.starts_with("thermal_zone") {
+            continue;
+        }
+        let Some(kind) = read_trimmed(&zone_dir.join("type")) else {
+            continue;
+        };
+        sensors.push(TemperatureSensor {
+            id: format!("thermal:{}", kind),
+            name: format!("{} ({})", kind, zone_name),
+            temp: read_millidegrees(&zone_dir.join("temp")),
+            crit: None,
+            path: zone_dir.join("temp"),
+        });
+    }
+
+    sensors.sort_by(|a, b| a.id.cmp(&b.id).then_with(|| a.path.cmp(&b.path)));
+    sensors
+}
+
+/// The sensors on this machine, read through the hardware root.
+fn list_sensors() -> Vec<TemperatureSensor> {
+    sensors_in(
+        &crate::hardware_root::resolve("/sys/class/hwmon"),
+        &crate::hardware_root::resolve("/sys/class/thermal"),
+    )
+}
+
+/// The hottest current reading among the selected sensors.
+///
+/// Readings outside 0–120°C are discarded, as in [`get_cpu_temperature`]: a
+/// sensor that reports -273 or 255 when idle would otherwise either mask the
+/// others or pin the fan at full speed.
+fn hottest_of(sensors: &[TemperatureSensor], selected: &[String]) -> Result<i32, String> {
+    sensors
+        .iter()
+        .filter(|s| selected.contains(&s.id))
+        .filter_map(|s| read_millidegrees(&s.path))
+        .filter(|t| *t > 0 && *t < 120)
+        .max()
+        .ok_or_else(|| {
+            format!(
+                "None of the selected sensors are readable: {}",
+                selected.join(", ")
+            )
+        })
+}
+
+/// The temperature the curve should act on this tick, before smoothing.
+fn read_curve_temperature(config: &FanCurveConfig) -> Result<i32, String> {
+    if config.sensors.is_empty() {
+        get_cpu_temperature()
+    } else {
+        hottest_of(&list_sensors(), &config.sensors)
+    }
+}
+
+/// The lowest critical limit among the sensors the curve follows.
+///
+/// With no selection the curve follows the CPU heuristic, so the CPU sensors
+/// stand in for it. `hwmon:<chip>:<label>` ids are matched exactly; thermal
+/// zones carry no limit and never contribute.
+fn followed_critical_temp(sensors: &[TemperatureSensor], selected: &[String]) -> Option<i32> {
+    sensors
+        .iter()
+        .filter(|s| {
+            if selected.is_empty() {
+                s.id.strip_prefix("hwmon:")
+                    .and_then(|rest| rest.split_once(':'))
+                    .is_some_and(|(_, label)| is_cpu_label(label))
+            } else {
+                selected.contains(&s.id)
+            }
+        })
+        .filter_map(|s| s.crit)
+        .filter(|c| *c > 0)
+        .min()
+}
+
+/// The (critical, release) pair the override works to, with defaults filled.
+fn thermal_limits(config: &FanCurveConfig, sensors: &[TemperatureSensor]) -> (i32, i32) {
+    let critical = config
+        .emergency
+        .critical_temp
+        .or_else(|| followed_critical_temp(sensors, &config.sensors))
+        .unwrap_or(FALLBACK_CRITICAL_TEMP);
+    let release = config
+        .emergency
+        .release_temp
+        .filter(|r| *r < critical)
+        .unwrap_or(critical - DEFAULT_RELEASE_GAP);
+    (critical, release)
+}
+
+/// What a tick did to the thermal override.
+#[derive(Debug, PartialEq, Eq)]
+enum OverrideChange {
+    Engaged,
+    Released,
+}
+
+/// Tracks whether the thermal override holds the fan.
+#[derive(Debug, Default)]
+struct ThermalGuard {
+    engaged: bool,
+}
+
+impl ThermalGuard {
+    /// Feed one raw (unsmoothed) reading. Engages at `critical` and releases
+    /// only below `release`, so a reading sitting on the limit does not
+    /// toggle the fan every tick.
+    fn update(&mut self, temp: i32, critical: i32, release: i32) -> Option<OverrideChange> {
+        if !self.engaged && temp >= critical {
+            self.engaged = true;
+            Some(OverrideChange::Engaged)
+        } else if self.engaged && temp < release {
+            self.engaged = false;
+            Some(OverrideChange::Released)
+        } else {
+            None
+        }
+    }
+}
+
+/// Fold one sample into an exponential moving average.
+///
+/// `window_secs` is the time constant: after that long at a new temperature
+/// the average has covered about 63% of the step. The weight is derived from
+/// the real tick interval so the window means the same thing whatever the
+/// loop cadence.
+fn smooth_temperature(previous: Option<f64>, sample: i32, tick: Duration, window_secs: u64) -> f64 {
+    let sample = sample as f64;
+    match previous {
+        Some(prev) if window_secs > 0 => {
+            let alpha = 1.0 - (-tick.as_secs_f64() / window_secs as f64).exp();
+            prev + alpha * (sample - prev)
+        }
+        _ => sample,
+    }
+}
+
+/// Counts, for each fan, consecutive ticks where it reads 0 RPM at a level
+/// that should turn it.
+///
+/// Level 0 is off by design and is never a stall. An unreadable tachometer is
+/// not evidence either way and resets that fan's count: the temperature path
+/// already handles a machine the app cannot see. Nor is the tick a level was
+/// written on: the RPM read straight after it is from before the change.
+#[derive(Debug, Default)]
+struct StallDetector {
+    /// Indexed like the `rpms` passed to [`observe`](Self::observe).
+    zero_ticks: Vec<u32>,
+    level: Option<i32>,
+}
+
+impl StallDetector {
+    /// Feed one tick. Returns the position in `rpms` of a fan whose stall is
+    /// confirmed on this tick, and only this one, so the caller acts once
+    /// rather than every tick afterwards.
+    fn observe(
+        &mut self,
+        level: Option<i32>,
+        rpms: &[Option<u32>],
+        threshold: u32,
+    ) -> Option<usize> {
+        let changed = level != self.level;
+        self.level = level;
+        self.zero_ticks.resize(rpms.len(), 0);
+        let mut confirmed = None;
+        for (i, (ticks, rpm)) in self.zero_ticks.iter_mut().zip(rpms).enumerate() {
+            if !changed && level.is_some_and(|l| l >= 1) && *rpm == Some(0) {
+                *ticks += 1;
+            } else {
+                *ticks = 0;
+            }
+            if *ticks == threshold && confirmed.is_none() {
+                confirmed = Some(i);
+            }
+        }
+        confirmed
+    }
+
+    fn reset(&mut self) {
+        self.zero_ticks.clear();
+        self.level = None;
+    }
+}
+
+/// Every fan's RPM, fan 1 first. Falls back to the single procfs speed where
+/// the hwmon tachometers cannot be found.
+fn read_fan_rpms() -> Vec<Option<u32>> {
+    let fans = crate::fan_control::discover_fans();
+    if fans.is_empty() {
+        return vec![read_fan_rpm()];
+    }
+    fans.iter().map(|fan| fan.rpm).collect()
+}
+
+/// Send a fixed fan level and arm the watchdog behind it. Falls back to auto
+/// if the level cannot be set, and says so by returning false.
+async fn force_fan(level: FanLevel) -> bool {
+    crate::fan_control::note_fan_change();
+    match write_fan_command(&fan_command(level, None)).await {
+        Ok(()) => {
+            arm_fan_watchdog().await;
+            true
+        }
+        Err(e) => {
+            eprintln!("[Fan Curve] Could not set the fan to {}: {}", level, e);
+            restore_fan_to_auto().await;
+            false
+        }
+    }
+}
+
+/// Apply the stall policy. Falls back to auto if full speed cannot be set: a
+/// fan nobody is steering is safer than one held at a level that stalled it.
+async fn escalate_stall(action: StallAction) -> StallAction {
+    if action == StallAction::FullSpeed && force_fan(FanLevel::FullSpeed).await {
+        return StallAction::FullSpeed;
+    }
+    if action == StallAction::Auto {
+        restore_fan_to_auto().await;
+    }
+    StallAction::Auto
+}
+
 /// How long the firmware watchdog waits before forcing the fan back to auto.
 ///
 /// Shared with the helper script's whitelist, which only accepts this exact
 /// value. thinkpad_acpi's watchdog is one-shot and only rearms when it receives
 /// a fan command, so it is re-armed on a timer — see [`watchdog_due`] for why
 /// re-arming on level change alone was not enough.
-use crate::fan_control::FAN_WATCHDOG_SECS;
+use crate::fan_control::{read_fan_rpm, FAN_WATCHDOG_SECS};
 
 /// Hand the fan back to firmware control.
 ///
@@ -236,7 +1666,8 @@ use crate::fan_control::FAN_WATCHDOG_SECS;
 /// treats every read failure as a reason to restore rather than to hold the last
 /// value — holding is what leaves a fan stopped under load.
 async fn restore_fan_to_auto() {
-    match write_fan_command("level auto").await {
+    crate::fan_control::note_fan_change();
+    match write_fan_command(&fan_command(FanLevel::Auto, None)).await {
         Ok(_) => println!("[Fan Curve] Fan returned to automatic control"),
         Err(e) => eprintln!("[Fan Curve] FAILED to restore fan to auto: {}", e),
     }
@@ -274,51 +1705,56 @@ pub fn restore_fan_to_auto_blocking() {
     use std::fs;
     use std::io::Write;
 
+    let command = fan_command(FanLevel::Auto, None);
+    if crate::hardware_root::is_simulated() {
+        let _ = crate::hardware_root::write("/proc/acpi/ibm/fan", &command);
+        return;
+    }
+
     if let Ok(mut file) = fs::OpenOptions::new()
         .write(true)
         .open("/proc/acpi/ibm/fan")
     {
-        if file.write_all(b"level auto").is_ok() {
+        if file.write_all(command.as_bytes()).is_ok() {
             println!("[Fan Curve] Fan returned to automatic control on exit");
             return;
         }
     }
 
-    if let Some(helper) = crate::fan_control::helper_path() {
-        match std::process::Command::new("pkexec")
-            .arg(helper)
-            .arg("level auto")
-            .output()
-        {
-            Ok(o) if o.status.success() => {
-                println!("[Fan Curve] Fan returned to automatic control on exit");
-            }
-            _ => eprintln!("[Fan Curve] Could not restore fan to auto on exit"),
-        }
+    let auto = crate::fan_control::FanCommand::Level {
+        level: FanLevel::Auto,
+        fan: None,
+    };
+    match crate::helper::run_blocking(&crate::helper::Operation::Fan(auto)) {
+        Ok(_) => println!("[Fan Curve] Fan returned to automatic control on exit"),
+        Err(e) => eprintln!("[Fan Curve] Could not restore fan to auto on exit: {}", e),
     }
 }
 
-/// Set fan speed with pkexec fallback using the dedicated helper script.
-/// Only attempts pkexec if the helper and polkit rule are both installed
-/// (guaranteeing passwordless operation for the background task).
+/// Set a fixed fan level, falling back to the privileged helper.
+/// Only escalates through the installed helper, which the polkit rule lets
+/// run passwordless, so the background task never raises a prompt.
 async fn set_fan_speed_internal(level: i32) -> Result<(), String> {
-    let command = if (0..=7).contains(&level) {
-        format!("level {}", level)
-    } else {
-        return Err("Invalid fan level".to_string());
-    };
-
-    write_fan_command(&command).await
+    let level = FanLevel::manual(level).ok_or("Invalid fan level")?;
+    crate::fan_control::note_fan_change();
+    write_fan_command(&fan_command(level, None)).await
 }
 
 /// Write a single command to /proc/acpi/ibm/fan, elevating via the helper when
 /// a direct write is not permitted.
-async fn write_fan_command(command: &str) -> Result<(), String> {
+pub(crate) async fn write_fan_command(command: &str) -> Result<(), String> {
     use std::fs;
     use std::io::Write;
 
     let fan_level_path = "/proc/acpi/ibm/fan";
 
+    // A simulated profile takes the write or refuses it; there is nothing to
+    // escalate to.
+    if crate::hardware_root::is_simulated() {
+        return crate::hardware_root::write(fan_level_path, command)
+            .map_err(|e| format!("Simulated hardware rejected the change: {}", e));
+    }
+
     // Try direct write first
     if let Ok(mut file) = fs::OpenOptions::new().write(true).open(fan_level_path) {
         if file.write_all(command.as_bytes()).is_ok() {
@@ -326,38 +1762,97 @@ async fn write_fan_command(command: &str) -> Result<(), String> {
         }
     }
 
-    // Use dedicated helper if installed (polkit rule grants passwordless access).
-    // We only check the helper because /etc/polkit-1/rules.d/ is root-only.
-    let Some(helper) = crate::fan_control::helper_path() else {
-        return Err("No write permission. Grant permissions to enable fan curve mode.".to_string());
+    // Otherwise through the privileged helper (the polkit rule grants it
+    // passwordless), which only takes commands it can parse back.
+    let command = command.parse()?;
+    crate::helper::run(&crate::helper::Operation::Fan(command))
+        .await
+        .map(|_| ())
+        .map_err(String::from)
+}
+
+/// Switch curves if a power rule asks for it, returning the new curve's name.
+fn apply_power_rule(
+    host: &impl CurveHost,
+    state: &FanCurveState,
+    previous: Option<bool>,
+    on_ac: bool,
+) -> Option<String> {
+    let mut config = match state.lock() {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            eprintln!("[Fan Curve] Failed to lock state: {}", e);
+            return None;
+        }
     };
+    let name = config.power_rule_switch(previous, on_ac)?.to_string();
 
-    let output = tokio::process::Command::new("pkexec")
-        .arg(helper)
-        .arg(command)
-        .output()
-        .await
-        .map_err(|e| format!("Failed to execute helper: {}", e))?;
+    if let Err(e) = config.activate(&name).and_then(|_| host.save(&config)) {
+        eprintln!("[Fan Curve] Failed to switch curve: {}", e);
+        return None;
+    }
+    Some(name)
+}
 
-    if output.status.success() {
-        Ok(())
-    } else {
-        Err("Failed to set fan speed via helper".to_string())
+/// What the curve loop needs from whoever runs it: somewhere to report what
+/// it does, and somewhere to persist a curve switch made by a power rule.
+///
+/// The GUI runs the loop with its [`AppHandle`]; `thinkutils-daemon` runs the
+/// same loop with neither a window nor a Tauri store.
+pub trait CurveHost: Send + Sync + 'static {
+    fn emit(&self, event: &str, payload: serde_json::Value) -> Result<(), String>;
+    fn save(&self, config: &FanCurveConfig) -> Result<(), String>;
+}
+
+impl CurveHost for AppHandle {
+    fn emit(&self, event: &str, payload: serde_json::Value) -> Result<(), String> {
+        self.emit_to("main", event, payload)
+            .map_err(|e| e.to_string())
+    }
+
+    fn save(&self, config: &FanCurveConfig) -> Result<(), String> {
+        save_config_to_store(self, config)
     }
 }
 
 /// Background task that monitors temperature and adjusts fan speed
 pub async fn fan_curve_background_task(app: AppHandle) {
-    let state = app.state::<FanCurveState>();
+    let state = app.state::<FanCurveState>().inner().clone();
+    run_curve_loop(app, state).await
+}
+
+/// The curve itself: read the temperature, set the level, keep the fan safe.
+/// Runs until the process exits.
+pub async fn run_curve_loop(host: impl CurveHost, state: FanCurveState) {
     let mut last_level: Option<i32> = None;
+    let mut level_since: Option<Instant> = None;
+    let mut smoothed: Option<f64> = None;
+    let mut last_on_ac: Option<bool> = None;
     let mut last_armed: Option<Instant> = None;
+    let mut stall = StallDetector::default();
+    // Set once a stall has been acted on. The curve then stops steering until
+    // it is turned off and on again, so the next tick cannot quietly put the
+    // fan back at the level it stalled at.
+    let mut stalled: Option<StallAction> = None;
+    let mut guard = ThermalGuard::default();
     let mut error_count = 0;
     let mut permission_error_reported = false;
+    let mut calibration_error_reported = false;
     const MAX_ERRORS: i32 = 5;
+    // Read once: the model does not change under a running process.
+    let machine = machine_id();
 
     loop {
-        // Sleep for 2 seconds between checks
-        sleep(Duration::from_secs(2)).await;
+        sleep(CURVE_TICK).await;
+
+        // Apply the AC/battery rules before reading the config, so this tick
+        // already runs on the curve for the new power source.
+        if let Some(on_ac) = on_ac_power() {
+            if let Some(name) = apply_power_rule(&host, &state, last_on_ac, on_ac) {
+                emit_curve_switched(&host, &name, if on_ac { "ac" } else { "battery" });
+            }
+            last_on_ac = Some(on_ac);
+        }
 
         // Check if curve mode is enabled
         let config = match state.lock() {
@@ -372,19 +1867,66 @@ pub async fn fan_curve_background_task(app: AppHandle) {
             // Hand the fan back rather than leaving it wherever the curve last
             // put it. Turning the curve off used to strand the fan at its last
             // manual level indefinitely.
-            if last_level.is_some() {
+            if last_level.is_some() || stalled == Some(StallAction::FullSpeed) || guard.engaged {
                 restore_fan_to_auto().await;
             }
             last_level = None;
             last_armed = None;
+            smoothed = None;
+            stall.reset();
+            stalled = None;
+            guard = ThermalGuard::default();
             permission_error_reported = false;
+            calibration_error_reported = false;
+            continue;
+        }
+
+        if let Some(action) = stalled {
+            // Full speed only lasts while the watchdog is fed.
+            if action == StallAction::FullSpeed
+                && last_armed.is_none_or(|t| watchdog_due(t.elapsed()))
+            {
+                arm_fan_watchdog().await;
+                last_armed = Some(Instant::now());
+            }
             continue;
         }
 
-        // Get current CPU temperature
-        let temp = match get_cpu_temperature() {
-            Ok(t) => t,
+        // A curve in RPM cannot run without this machine's table, and guessing
+        // a level from another model's is what the table exists to avoid. The
+        // firmware keeps the fan instead, as when the sensors are unreadable.
+        let config = match config.resolve_rpm_targets(&machine) {
+            Ok(config) => {
+                calibration_error_reported = false;
+                config
+            }
+            Err(e) => {
+                if last_level.is_some() || guard.engaged {
+                    restore_fan_to_auto().await;
+                    last_level = None;
+                    last_armed = None;
+                    guard = ThermalGuard::default();
+                }
+                if !calibration_error_reported {
+                    calibration_error_reported = true;
+                    eprintln!("[Fan Curve] {}", e);
+                    let _ = host.emit("fan-curve-error", serde_json::json!({ "error": e }));
+                }
+                continue;
+            }
+        };
+
+        // Get current CPU temperature. The curve acts on the smoothed value;
+        // the override acts on the raw one, which smoothing would only delay.
+        let (temp, raw_temp) = match read_curve_temperature(&config) {
+            Ok(t) => {
+                let avg = smooth_temperature(smoothed, t, CURVE_TICK, config.smoothing_secs);
+                smoothed = Some(avg);
+                (avg.round() as i32, t)
+            }
             Err(e) => {
+                // Resume from the next real sample, not from a stale average.
+                smoothed = None;
                 error_count += 1;
                 if error_count <= MAX_ERRORS {
                     eprintln!("[Fan Curve] Failed to read temperature: {}", e);
@@ -394,13 +1936,13 @@ pub async fn fan_curve_background_task(app: AppHandle) {
                 // curve is holding a low level and load rises, nothing will raise
                 // it. Give the fan back to the firmware, which can still see the
                 // sensors we cannot.
-                if last_level.is_some() {
+                if last_level.is_some() || guard.engaged {
                     eprintln!("[Fan Curve] Temperature unreadable — returning fan to auto");
                     restore_fan_to_auto().await;
                     last_level = None;
                     last_armed = None;
-                    let _ = app.emit_to(
-                        "main",
+                    guard = ThermalGuard::default();
+                    let _ = host.emit(
                         "fan-curve-error",
                         serde_json::json!({
                             "error": "Temperature sensors unreadable. Fan returned to automatic control.",
@@ -413,11 +1955,68 @@ pub async fn fan_curve_background_task(app: AppHandle) {
 
         error_count = 0; // Reset error count on success
 
-        // Calculate target fan level
-        let target_level = calculate_fan_level(temp, &config.points);
+        let (critical, release) = thermal_limits(&config, &list_sensors());
+        match guard.update(raw_temp, critical, release) {
+            Some(OverrideChange::Engaged) => {
+                let forced = force_fan(config.emergency.action.level()).await;
+                eprintln!(
+                    "[Fan Curve] {}°C reached the {}°C ceiling — overriding the curve until below {}°C{}",
+                    raw_temp,
+                    critical,
+                    release,
+                    if forced { "" } else { " (could not force the fan; returned to auto)" }
+                );
+                last_level = None;
+                last_armed = forced.then(Instant::now);
+                stall.reset();
+                emit_thermal_override(
+                    &host,
+                    true,
+                    raw_temp,
+                    critical,
+                    release,
+                    forced.then_some(config.emergency.action),
+                );
+            }
+            Some(OverrideChange::Released) => {
+                println!(
+                    "[Fan Curve] {}°C is below {}°C — returning the fan to the curve",
+                    raw_temp, release
+                );
+                // last_level is None, so the curve writes its level this tick.
+                emit_thermal_override(&host, false, raw_temp, critical, release, None);
+            }
+            None => {}
+        }
 
-        // Only write to fan hardware when level actually changes
-        if last_level != Some(target_level) {
+        if guard.engaged {
+            // A level forced this way lapses with the watchdog like any other.
+            if last_armed.is_some_and(|t| watchdog_due(t.elapsed())) {
+                arm_fan_watchdog().await;
+                last_armed = Some(Instant::now());
+            }
+            let _ = host.emit(
+                "fan-curve-update",
+                serde_json::json!({
+                    "temperature": temp,
+                    "fan_level": null,
+                    "controlling": last_armed.is_some(),
+                    "override": config.emergency.action,
+                }),
+            );
+            continue;
+        }
+
+        let plan = plan_tick(
+            temp,
+            &config,
+            last_level,
+            level_since.map(|t| t.elapsed()),
+            last_armed.map(|t| t.elapsed()),
+        );
+        let target_level = plan.level;
+
+        if plan.write_level {
             match set_fan_speed_internal(target_level).await {
                 Ok(_) => {
                     println!(
@@ -425,6 +2024,7 @@ pub async fn fan_curve_background_task(app: AppHandle) {
                         temp, target_level
                     );
                     last_level = Some(target_level);
+                    level_since = Some(Instant::now());
                     permission_error_reported = false;
                     arm_fan_watchdog().await;
                     last_armed = Some(Instant::now());
@@ -435,8 +2035,7 @@ pub async fn fan_curve_background_task(app: AppHandle) {
                     // Notify frontend once about permission issues
                     if !permission_error_reported {
                         permission_error_reported = true;
-                        let _ = app.emit_to(
-                            "main",
+                        let _ = host.emit(
                             "fan-curve-error",
                             serde_json::json!({
                                 "error": e,
@@ -445,13 +2044,36 @@ pub async fn fan_curve_background_task(app: AppHandle) {
                     }
                 }
             }
-        } else if last_level.is_some() && last_armed.is_none_or(|t| watchdog_due(t.elapsed())) {
-            // Holding a level still counts as steering the fan, so the watchdog
-            // has to be kept alive even though nothing is being changed.
+        } else if plan.arm_watchdog {
             arm_fan_watchdog().await;
             last_armed = Some(Instant::now());
         }
 
+        // A level the curve set is only a request. Check the fan is turning.
+        if let Some(fan) = stall.observe(last_level, &read_fan_rpms(), config.stall.ticks) {
+            let level = last_level.unwrap_or_default();
+            eprintln!(
+                "[Fan Curve] Fan {} reads 0 RPM at level {} for {} ticks — treating it as stalled",
+                fan + 1,
+                level,
+                config.stall.ticks
+            );
+            let applied = escalate_stall(config.stall.action).await;
+            stalled = Some(applied);
+            last_level = None;
+            last_armed = Some(Instant::now());
+            let _ = host.emit(
+                "fan-stall",
+                serde_json::json!({
+                    "fan": fan + 1,
+                    "level": level,
+                    "ticks": config.stall.ticks,
+                    "action": applied,
+                }),
+            );
+            continue;
+        }
+
         // Always emit temperature and current level to frontend for live UI updates.
         //
         // `controlling` says whether that level was actually applied. When every
@@ -461,8 +2083,7 @@ pub async fn fan_curve_background_task(app: AppHandle) {
         // to miss or dismiss, after which the display looked correct forever.
         let controlling = last_level.is_some();
         let display_level = last_level.unwrap_or(target_level);
-        if let Err(e) = app.emit_to(
-            "main",
+        if let Err(e) = host.emit(
             "fan-curve-update",
             serde_json::json!({
                 "temperature": temp,
@@ -475,6 +2096,215 @@ pub async fn fan_curve_background_task(app: AppHandle) {
     }
 }
 
+// -- Dry run --
+
+/// One temperature reading of a recorded trace.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TraceSample {
+    /// Seconds, on any clock; only the gaps between samples matter.
+    pub timestamp: f64,
+    /// °C, as the curve's sensors would have read it.
+    pub temp: f64,
+}
+
+/// Read a temperature trace from JSON or CSV.
+///
+/// JSON is an array of `{"timestamp": .., "temp": ..}` objects. CSV has one
+/// `timestamp,temp` row per sample; a header row, blank lines and `#` comments
+/// are skipped. Timestamps must increase.
+pub fn parse_trace(text: &str) -> Result<Vec<TraceSample>, String> {
+    let samples = if text.trim_start().starts_with('[') {
+        serde_json::from_str::<Vec<TraceSample>>(text)
+            .map_err(|e| format!("Invalid JSON trace: {}", e))?
+    } else {
+        parse_csv_trace(text)?
+    };
+
+    if samples.is_empty() {
+        return Err("The trace has no samples".to_string());
+    }
+    for (i, s) in samples.iter().enumerate() {
+        if !s.timestamp.is_finite() || !s.temp.is_finite() {
+            return Err(format!("Sample {} is not a number", i + 1));
+        }
+        if i > 0 && s.timestamp <= samples[i - 1].timestamp {
+            return Err(format!(
+                "Sample {} at {}s does not come after the one at {}s",
+                i + 1,
+                s.timestamp,
+                samples[i - 1].timestamp
+            ));
+        }
+    }
+    Ok(samples)
+}
+
+fn parse_csv_trace(text: &str) -> Result<Vec<TraceSample>, String> {
+    let mut samples = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let parsed = match fields.as_slice() {
+            [t, temp] => t.parse::<f64>().ok().zip(temp.parse::<f64>().ok()),
+            _ => None,
+        };
+        match parsed {
+            Some((timestamp, temp)) => samples.push(TraceSample { timestamp, temp }),
+            // The header, wherever the file starts.
+            None if samples.is_empty() && fields.len() == 2 => continue,
+            None => {
+                return Err(format!(
+                    "Line {}: expected 'timestamp,temp', got '{}'",
+                    n + 1,
+                    line
+                ))
+            }
+        }
+    }
+    Ok(samples)
+}
+
+/// What the curve did at one sample of a dry run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulatedTick {
+    pub timestamp: f64,
+    /// The temperature the curve acted on, after smoothing.
+    pub temp: i32,
+    /// The level applied. None while the thermal override holds the fan.
+    pub level: Option<i32>,
+    #[serde(rename = "override")]
+    pub override_active: bool,
+    /// A level command was sent this tick.
+    pub level_written: bool,
+    /// The watchdog was re-armed this tick.
+    pub watchdog_armed: bool,
+}
+
+/// The outcome of running a trace through a curve.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Simulation {
+    pub ticks: Vec<SimulatedTick>,
+    /// Level commands, including those sent by the thermal override.
+    pub level_writes: usize,
+    pub watchdog_writes: usize,
+    /// Every command written to the fan.
+    pub fan_writes: usize,
+}
+
+/// Run a trace through the curve as the background task would, without
+/// touching the fan.
+///
+/// Each sample is one tick, timed by its timestamp: smoothing, dwell and
+/// watchdog re-arms all follow the trace's clock. Writes are assumed to
+/// succeed, and the fan is assumed to turn, so stall handling never fires. The
+/// thermal override uses the configured limits, or the fallback critical
+/// temperature, since a trace carries no sensor limits. RPM targets must be
+/// resolved first.
+pub fn simulate_curve(config: &FanCurveConfig, trace: &[TraceSample]) -> Simulation {
+    let secs = |d: f64| Duration::from_secs_f64(d.max(0.0));
+    let (critical, release) = thermal_limits(config, &[]);
+
+    let mut ticks = Vec::with_capacity(trace.len());
+    let (mut level_writes, mut watchdog_writes) = (0, 0);
+    let mut last_level: Option<i32> = None;
+    let mut level_since: Option<f64> = None;
+    let mut last_armed: Option<f64> = None;
+    let mut smoothed: Option<f64> = None;
+    let mut guard = ThermalGuard::default();
+    let mut previous: Option<f64> = None;
+
+    for sample in trace {
+        let now = sample.timestamp;
+        let tick = previous.map_or(CURVE_TICK, |p| secs(now - p));
+        previous = Some(now);
+
+        let raw = sample.temp.round() as i32;
+        let avg = smooth_temperature(smoothed, raw, tick, config.smoothing_secs);
+        smoothed = Some(avg);
+        let temp = avg.round() as i32;
+
+        let mut step = SimulatedTick {
+            timestamp: now,
+            temp,
+            level: None,
+            override_active: false,
+            level_written: false,
+            watchdog_armed: false,
+        };
+
+        if guard.update(raw, critical, release) == Some(OverrideChange::Engaged) {
+            step.level_written = true;
+            step.watchdog_armed = true;
+            last_level = None;
+            last_armed = Some(now);
+        }
+        if guard.engaged {
+            if last_armed.is_some_and(|t| watchdog_due(secs(now - t))) {
+                step.watchdog_armed = true;
+                last_armed = Some(now);
+            }
+            step.override_active = true;
+        } else {
+            let plan = plan_tick(
+                temp,
+                config,
+                last_level,
+                level_since.map(|t| secs(now - t)),
+                last_armed.map(|t| secs(now - t)),
+            );
+            if plan.write_level {
+                last_level = Some(plan.level);
+                level_since = Some(now);
+                step.level_written = true;
+            }
+            if plan.arm_watchdog {
+                last_armed = Some(now);
+                step.watchdog_armed = true;
+            }
+            step.level = last_level;
+        }
+
+        level_writes += step.level_written as usize;
+        watchdog_writes += step.watchdog_armed as usize;
+        ticks.push(step);
+    }
+
+    Simulation {
+        ticks,
+        level_writes,
+        watchdog_writes,
+        fan_writes: level_writes + watchdog_writes,
+    }
+}
+
+/// Dry-run a curve against a recorded temperature trace.
+///
+/// `trace` is JSON or CSV as read by [`parse_trace`]. `config` defaults to the
+/// curve configuration in use; one passed in is checked as the daemon would
+/// check it. Nothing is written to the fan.
+#[tauri::command]
+pub async fn simulate_fan_curve(
+    state: tauri::State<'_, FanCurveState>,
+    trace: String,
+    config: Option<FanCurveConfig>,
+) -> Result<Simulation, String> {
+    let config = match config {
+        Some(config) => {
+            validate_config(&config)?;
+            config
+        }
+        None => state
+            .lock()
+            .map_err(|e| format!("Failed to lock state: {}", e))?
+            .clone(),
+    };
+    let config = config.resolve_rpm_targets(&machine_id())?;
+    Ok(simulate_curve(&config, &parse_trace(&trace)?))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -553,11 +2383,36 @@ mod tests {
     #[test]
     fn test_calculate_fan_level() {
         let points = vec![
-            CurvePoint { temp: 40, level: 0 },
-            CurvePoint { temp: 50, level: 1 },
-            CurvePoint { temp: 60, level: 3 },
-            CurvePoint { temp: 70, level: 5 },
-            CurvePoint { temp: 80, level: 7 },
+            CurvePoint {
+                temp: 40,
+                level: 0,
+                fall_temp: None,
+                rpm: None,
+            },
+            CurvePoint {
+                temp: 50,
+                level: 1,
+                fall_temp: None,
+                rpm: None,
+            },
+            CurvePoint {
+                temp: 60,
+                level: 3,
+                fall_temp: None,
+                rpm: None,
+            },
+            CurvePoint {
+                temp: 70,
+                level: 5,
+                fall_temp: None,
+                rpm: None,
+            },
+            CurvePoint {
+                temp: 80,
+                level: 7,
+                fall_temp: None,
+                rpm: None,
+            },
         ];
 
         // Below first point
@@ -575,4 +2430,1048 @@ mod tests {
         // Above last point
         assert_eq!(calculate_fan_level(85, &points), 7);
     }
+
+    fn curve_with_band(min_dwell_secs: u64) -> FanCurveConfig {
+        FanCurveConfig {
+            enabled: true,
+            points: vec![
+                CurvePoint {
+                    temp: 50,
+                    level: 2,
+                    fall_temp: Some(47),
+                    rpm: None,
+                },
+                CurvePoint {
+                    temp: 60,
+                    level: 4,
+                    fall_temp: Some(57),
+                    rpm: None,
+                },
+            ],
+            min_dwell_secs,
+            ..FanCurveConfig::default()
+        }
+    }
+
+    /// Feed a temperature trace through next_fan_level at the loop's 2s
+    /// cadence and return how many times the level changed.
+    fn level_changes(config: &FanCurveConfig, temps: &[i32]) -> (usize, Vec<i32>) {
+        const TICK: Duration = Duration::from_secs(2);
+        let mut current = None;
+        let mut held_for = Duration::ZERO;
+        let mut changes = 0;
+        let mut levels = Vec::new();
+
+        for &t in temps {
+            let next = next_fan_level(t, config, current, held_for);
+            if current != Some(next) {
+                if current.is_some() {
+                    changes += 1;
+                }
+                current = Some(next);
+                held_for = Duration::ZERO;
+            }
+            held_for += TICK;
+            levels.push(next);
+        }
+        (changes, levels)
+    }
+
+    /// The reported problem: a CPU wandering ±1°C across the temperature where
+    /// the interpolated level rounds up (57.5°C on this curve). Without a band
+    /// this flips the fan every tick.
+    #[test]
+    fn boundary_wobble_inside_the_band_does_not_flip_the_fan() {
+        let wobble: Vec<i32> = [57, 58, 57, 56, 58, 57, 58, 56, 57, 58].repeat(6);
+        let (changes, levels) = level_changes(&curve_with_band(0), &wobble);
+        assert!(
+            changes <= 1,
+            "level changed {} times on a ±1°C wobble: {:?}",
+            changes,
+            levels
+        );
+    }
+
+    #[test]
+    fn without_a_band_the_same_wobble_oscillates() {
+        let mut config = curve_with_band(0);
+        for p in &mut config.points {
+            p.fall_temp = None;
+        }
+        let wobble: Vec<i32> = [57, 58].repeat(10);
+        let (changes, _) = level_changes(&config, &wobble);
+        assert!(changes >= 10, "expected flipping without hysteresis");
+    }
+
+    /// Cooling below the fall temperature must still bring the level down —
+    /// the band delays the drop, it must not prevent it.
+    #[test]
+    fn cooling_past_the_fall_temperature_lowers_the_level() {
+        let config = curve_with_band(0);
+        assert_eq!(next_fan_level(60, &config, None, Duration::ZERO), 4);
+        // Inside the band: hold.
+        assert_eq!(next_fan_level(58, &config, Some(4), Duration::MAX), 4);
+        // Below it: drop along the falling curve.
+        assert_eq!(next_fan_level(47, &config, Some(4), Duration::MAX), 2);
+    }
+
+    /// Dwell throttles how often the level can come down, but a rise is never
+    /// held back: a fan slow to spin up under load is the dangerous case.
+    #[test]
+    fn dwell_delays_drops_but_never_rises() {
+        let config = curve_with_band(10);
+        assert_eq!(
+            next_fan_level(40, &config, Some(4), Duration::from_secs(4)),
+            4,
+            "dropped before the dwell elapsed"
+        );
+        assert_eq!(
+            next_fan_level(40, &config, Some(4), Duration::from_secs(10)),
+            2
+        );
+        assert_eq!(
+            next_fan_level(65, &config, Some(2), Duration::ZERO),
+            4,
+            "a rise must not wait for the dwell"
+        );
+    }
+
+    /// A spike-and-drop pattern wider than the band still changes level, but
+    /// with a dwell it cannot do so more often than once per dwell period.
+    #[test]
+    fn dwell_rate_limits_a_wide_oscillation() {
+        // Alternate every 2s tick between well above and well below the band.
+        let swing: Vec<i32> = [65, 45].repeat(30);
+        let (fast, _) = level_changes(&curve_with_band(0), &swing);
+        let (slow, _) = level_changes(&curve_with_band(10), &swing);
+        assert!(
+            slow * 2 < fast,
+            "dwell did not reduce level changes ({} vs {})",
+            slow,
+            fast
+        );
+        // 60 ticks = 120s; a 10s dwell allows at most one drop per 10s, and
+        // each drop is followed by at most one rise.
+        assert!(slow <= 2 * (120 / 10), "too many changes: {}", slow);
+    }
+
+    #[test]
+    fn validation_rejects_an_inverted_band() {
+        let mut points = curve_with_band(0).points;
+        points[1].fall_temp = Some(62);
+        assert!(validate_curve_settings(&points, 0).is_err());
+    }
+
+    #[test]
+    fn validation_rejects_fall_temperatures_out_of_order() {
+        let points = vec![
+            CurvePoint {
+                temp: 50,
+                level: 2,
+                fall_temp: Some(45),
+                rpm: None,
+            },
+            CurvePoint {
+                temp: 52,
+                level: 4,
+                fall_temp: Some(40),
+                rpm: None,
+            },
+        ];
+        assert!(validate_curve_settings(&points, 0).is_err());
+    }
+
+    #[test]
+    fn validation_bounds_the_dwell_and_accepts_the_default() {
+        let config = FanCurveConfig::default();
+        assert!(validate_curve_settings(&config.points, config.min_dwell_secs).is_ok());
+        assert!(validate_curve_settings(&config.points, MAX_MIN_DWELL_SECS + 1).is_err());
+    }
+
+    fn pts(points: &[(i32, i32)]) -> Vec<CurvePoint> {
+        points
+            .iter()
+            .map(|&(temp, level)| CurvePoint {
+                temp,
+                level,
+                fall_temp: None,
+                rpm: None,
+            })
+            .collect()
+    }
+
+    /// What a caller can act on: which point, which field.
+    fn located(errors: &[CurveError]) -> Vec<(Option<usize>, Option<CurveField>)> {
+        errors.iter().map(|e| (e.index, e.field)).collect()
+    }
+
+    #[test]
+    fn validation_reports_every_bad_point_by_position() {
+        // Sent out of order: index 2 is the coolest point.
+        let points = pts(&[(60, 3), (50, 9), (40, 0), (60, 4), (130, 7), (70, 2)]);
+        assert_eq!(
+            located(&validate_curve(&points)),
+            [
+                // Below the level 9 at 50°C, even though 9 is itself invalid.
+                (Some(0), Some(CurveField::Level)),
+                (Some(1), Some(CurveField::Level)),
+                (Some(3), Some(CurveField::Temp)),
+                (Some(4), Some(CurveField::Temp)),
+                (Some(5), Some(CurveField::Level)),
+            ]
+        );
+    }
+
+    #[test]
+    fn an_empty_curve_is_an_error_for_the_whole_curve() {
+        assert_eq!(located(&validate_curve(&[])), [(None, None)]);
+        assert!(check_curve(&normalize_curve(&[])).is_err());
+    }
+
+    #[test]
+    fn an_unsorted_valid_curve_is_accepted() {
+        assert!(validate_curve(&pts(&[(80, 7), (40, 0), (60, 3)])).is_empty());
+    }
+
+    #[test]
+    fn normalizing_sorts_dedupes_clamps_and_never_slows_the_fan() {
+        let points = pts(&[
+            (60, 3),
+            (50, 9),
+            (40, 0),
+            (60, 1),
+            (130, 7),
+            (70, 2),
+            (-5, -1),
+        ]);
+        let normalized = normalize_curve(&points);
+        assert_eq!(
+            normalized,
+            pts(&[(0, 0), (40, 0), (50, 7), (60, 7), (70, 7), (120, 7)])
+        );
+        assert!(validate_curve(&normalized).is_empty());
+    }
+
+    #[test]
+    fn normalizing_pulls_fall_temperatures_into_their_band() {
+        let mut points = pts(&[(50, 2), (52, 4), (60, 5)]);
+        points[0].fall_temp = Some(45);
+        points[1].fall_temp = Some(40);
+        points[2].fall_temp = Some(65);
+        let falls: Vec<Option<i32>> = normalize_curve(&points)
+            .iter()
+            .map(|p| p.fall_temp)
+            .collect();
+        assert_eq!(falls, [Some(45), Some(45), Some(60)]);
+    }
+
+    #[test]
+    fn a_valid_curve_survives_normalizing_unchanged() {
+        let config = FanCurveConfig::default();
+        assert_eq!(normalize_curve(&config.points), config.points);
+    }
+
+    #[test]
+    fn invalid_stored_curves_are_normalized_on_load() {
+        let stored = serde_json::json!({
+            "enabled": true,
+            "points": [{ "temp": 40, "level": 3 }, { "temp": 70, "level": 12 }],
+            "curves": { "Quiet": [{ "temp": 50, "level": 0 }, { "temp": 80, "level": 4 }] },
+        });
+        let mut config: FanCurveConfig = serde_json::from_value(stored).unwrap();
+        config.ensure_active_saved();
+        assert_eq!(config.normalize_stored(), [DEFAULT_CURVE_NAME]);
+        assert_eq!(config.points, pts(&[(40, 3), (70, 7)]));
+        assert_eq!(config.curves["Quiet"], pts(&[(50, 0), (80, 4)]));
+    }
+
+    #[test]
+    fn structured_errors_serialize_for_the_ui() {
+        let check = check_curve_points(pts(&[(40, 0), (40, 8)]), false);
+        let json = serde_json::to_value(&check.errors).unwrap();
+        assert_eq!(json[0]["index"], 1);
+        assert_eq!(json[0]["field"], "level");
+        assert_eq!(json[1]["field"], "temp");
+    }
+
+    /// Curves saved before hysteresis existed have neither field. They must
+    /// still load, with no band and the default dwell.
+    #[test]
+    fn configs_saved_before_hysteresis_still_load() {
+        let old = serde_json::json!({
+            "enabled": true,
+            "points": [{ "temp": 40, "level": 0 }, { "temp": 80, "level": 7 }],
+        });
+        let config: FanCurveConfig = serde_json::from_value(old).unwrap();
+        assert_eq!(config.points[0].fall_temp, None);
+        assert_eq!(config.min_dwell_secs, DEFAULT_MIN_DWELL_SECS);
+    }
+
+    // -- Sensor selection and smoothing --
+
+    /// A small sysfs tree with the shape that caused the complaint: an ACPI
+    /// zone reading cooler than the package sensor.
+    fn sensor_tree() -> PathBuf {
+        let root = std::env::temp_dir().join(format!(
+            "thinkutils_sensors_{}_{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        let files = [
+            ("hwmon/hwmon0/name", "coretemp\n"),
+            ("hwmon/hwmon0/temp1_label", "Package id 0\n"),
+            ("hwmon/hwmon0/temp1_input", "71000\n"),
+            ("hwmon/hwmon0/temp2_label", "Core 0\n"),
+            ("hwmon/hwmon0/temp2_input", "64000\n"),
+            ("hwmon/hwmon1/name", "nvme\n"),
+            ("hwmon/hwmon1/temp1_input", "38850\n"),
+            ("thermal/thermal_zone0/type", "acpitz\n"),
+            ("thermal/thermal_zone0/temp", "52000\n"),
+            ("thermal/thermal_zone1/type", "acpitz\n"),
+            ("thermal/thermal_zone1/temp", "-273000\n"),
+            ("thermal/cooling_device0/type", "Processor\n"),
+        ];
+        for (rel, content) in files {
+            let path = root.join(rel);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn lists_hwmon_inputs_and_thermal_zones_by_stable_id() {
+        let root = sensor_tree();
+        let sensors = sensors_in(&root.join("hwmon"), &root.join("thermal"));
+        let _ = std::fs::remove_dir_all(&root);
+
+        let ids: Vec<&str> = sensors.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            [
+                "hwmon:coretemp:Core 0",
+                "hwmon:coretemp:Package id 0",
+                // Unlabelled inputs fall back to their attribute name.
+                "hwmon:nvme:temp1",
+                // Both acpitz zones share an id; cooling devices are not sensors.
+                "thermal:acpitz",
+                "thermal:acpitz",
+            ]
+        );
+        assert_eq!(sensors[1].temp, Some(71));
+    }
+
+    /// With several sensors chosen, the hottest drives the fan — and a zone
+    /// reporting a nonsense value must not win, or mask the real ones.
+    #[test]
+    fn follows_the_hottest_selected_sensor() {
+        let root = sensor_tree();
+        let sensors = sensors_in(&root.join("hwmon"), &root.join("thermal"));
+
+        let acpi_only = hottest_of(&sensors, &["thermal:acpitz".to_string()]);
+        let both = hottest_of(
+            &sensors,
+            &[
+                "thermal:acpitz".to_string(),
+                "hwmon:coretemp:Package id 0".to_string(),
+            ],
+        );
+        let missing = hottest_of(&sensors, &["hwmon:k10temp:Tctl".to_string()]);
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert_eq!(acpi_only, Ok(52));
+        assert_eq!(both, Ok(71));
+        assert!(missing.is_err(), "an absent sensor must read as an error");
+    }
+
+    #[test]
+    fn no_smoothing_follows_each_sample() {
+        let tick = Duration::from_secs(2);
+        assert_eq!(smooth_temperature(Some(40.0), 80, tick, 0), 80.0);
+        assert_eq!(smooth_temperature(None, 55, tick, 20), 55.0);
+    }
+
+    /// A one-tick spike should barely move a 20s average, but a sustained
+    /// step must still be followed: after one time constant the average has
+    /// covered roughly 63% of it, and it converges well within five.
+    #[test]
+    fn smoothing_damps_spikes_but_tracks_sustained_load() {
+        let tick = Duration::from_secs(2);
+
+        let after_spike = smooth_temperature(Some(50.0), 90, tick, 20);
+        assert!(
+            after_spike < 55.0,
+            "spike moved the average to {}",
+            after_spike
+        );
+
+        let mut avg = Some(50.0);
+        for _ in 0..10 {
+            avg = Some(smooth_temperature(avg, 90, tick, 20));
+        }
+        let one_window = avg.unwrap();
+        assert!(
+            (one_window - (50.0 + 40.0 * 0.632)).abs() < 0.5,
+            "after one time constant: {}",
+            one_window
+        );
+
+        for _ in 0..40 {
+            avg = Some(smooth_temperature(avg, 90, tick, 20));
+        }
+        assert!(avg.unwrap() > 89.5, "never converged: {:?}", avg);
+    }
+
+    #[test]
+    fn configs_saved_before_sensor_selection_use_the_heuristic() {
+        let old = serde_json::json!({ "enabled": false, "points": [] });
+        let config: FanCurveConfig = serde_json::from_value(old).unwrap();
+        assert!(config.sensors.is_empty());
+        assert_eq!(config.smoothing_secs, 0);
+    }
+
+    // -- Named curves and power rules --
+
+    fn two_curves() -> FanCurveConfig {
+        let mut config = FanCurveConfig::default();
+        config.curves.insert(
+            "Quiet".to_string(),
+            vec![CurvePoint {
+                temp: 70,
+                level: 2,
+                fall_temp: None,
+                rpm: None,
+            }],
+        );
+        config.power_rules = PowerRules {
+            on_ac: Some(DEFAULT_CURVE_NAME.to_string()),
+            on_battery: Some("Quiet".to_string()),
+        };
+        config
+    }
+
+    /// A store written before named curves has one anonymous curve. It must
+    /// come back as a saved, active curve, not vanish on the first switch.
+    #[test]
+    fn a_single_curve_config_becomes_the_default_named_curve() {
+        let old = serde_json::json!({
+            "enabled": true,
+            "points": [{ "temp": 55, "level": 4 }],
+        });
+        let mut config: FanCurveConfig = serde_json::from_value(old).unwrap();
+        config.ensure_active_saved();
+
+        assert_eq!(config.active_curve, DEFAULT_CURVE_NAME);
+        assert_eq!(config.curves[DEFAULT_CURVE_NAME][0].temp, 55);
+        assert_eq!(config.power_rules, PowerRules::default());
+    }
+
+    #[test]
+    fn activating_a_curve_replaces_the_working_points() {
+        let mut config = two_curves();
+        config.activate("Quiet").unwrap();
+        assert_eq!(config.active_curve, "Quiet");
+        assert_eq!(config.points.len(), 1);
+        assert_eq!(config.points[0].level, 2);
+        assert!(config.activate("Missing").is_err());
+        assert_eq!(
+            config.active_curve, "Quiet",
+            "a failed switch changes nothing"
+        );
+    }
+
+    /// Rules fire on plug events only. Re-applying them every tick would undo
+    /// a curve the user picked by hand two seconds later.
+    #[test]
+    fn power_rules_fire_on_transitions_only() {
+        let mut config = two_curves();
+
+        // Starting up on battery counts as a transition.
+        assert_eq!(config.power_rule_switch(None, false), Some("Quiet"));
+        config.activate("Quiet").unwrap();
+
+        // Still on battery: nothing to do.
+        assert_eq!(config.power_rule_switch(Some(false), false), None);
+
+        // A manual switch back to Default survives while the power state holds.
+        config.activate(DEFAULT_CURVE_NAME).unwrap();
+        assert_eq!(config.power_rule_switch(Some(false), false), None);
+
+        // Plugging in when the AC curve is already active is not a switch.
+        assert_eq!(config.power_rule_switch(Some(false), true), None);
+
+        // Unplugging is.
+        assert_eq!(config.power_rule_switch(Some(true), false), Some("Quiet"));
+    }
+
+    #[test]
+    fn a_rule_naming_a_missing_curve_is_ignored() {
+        let mut config = two_curves();
+        config.curves.remove("Quiet");
+        assert_eq!(config.power_rule_switch(Some(true), false), None);
+    }
+
+    fn power_supply_tree(supplies: &[(&str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!(
+            "thinkutils_psu_{}_{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        for (name, online) in supplies {
+            let dir = root.join(name);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("online"), format!("{}\n", online)).unwrap();
+        }
+        std::fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    #[test]
+    fn reads_ac_state_from_any_ac_supply() {
+        for (supplies, expected) in [
+            (vec![("AC", "1")], Some(true)),
+            (vec![("AC", "0")], Some(false)),
+            // Only the AC* prefix the request names is matched: a USB-C source
+            // reporting online must not count as mains.
+            (
+                vec![("AC0", "0"), ("ucsi-source-psy-USBC000:001", "1")],
+                Some(false),
+            ),
+            (vec![("AC0", "0"), ("AC1", "1")], Some(true)),
+            (vec![("BAT0", "1")], None),
+            (vec![], None),
+        ] {
+            let root = power_supply_tree(&supplies);
+            let got = on_ac_power_in(&root);
+            let _ = std::fs::remove_dir_all(&root);
+            assert_eq!(got, expected, "supplies {:?}", supplies);
+        }
+    }
+
+    /// Feed a scripted run of (level, rpm) ticks for one fan; returns the
+    /// ticks that fired.
+    fn stall_ticks(script: &[(Option<i32>, Option<u32>)], threshold: u32) -> Vec<usize> {
+        let mut detector = StallDetector::default();
+        script
+            .iter()
+            .enumerate()
+            .filter(|(_, &(level, rpm))| detector.observe(level, &[rpm], threshold).is_some())
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    #[test]
+    fn a_fan_spinning_up_is_not_a_stall() {
+        let script = [
+            (Some(3), Some(0)),
+            (Some(3), Some(0)),
+            (Some(3), Some(1800)),
+            (Some(3), Some(2400)),
+        ];
+        assert!(stall_ticks(&script, 3).is_empty());
+    }
+
+    /// The first tick at a level is the one it was written on; counting starts
+    /// on the next.
+    #[test]
+    fn a_sustained_zero_fires_once_at_the_threshold() {
+        let script = [(Some(4), Some(0)); 8];
+        assert_eq!(stall_ticks(&script, 3), [3]);
+    }
+
+    /// Coming up from 0, the reading taken with the write is still 0 RPM.
+    #[test]
+    fn the_tick_a_level_changes_is_not_counted() {
+        let script = [
+            (Some(0), Some(0)),
+            (Some(2), Some(0)),
+            (Some(2), Some(1500)),
+            (Some(3), Some(0)),
+            (Some(3), Some(0)),
+            (Some(3), Some(0)),
+        ];
+        assert_eq!(stall_ticks(&script, 2), [5]);
+        assert!(check_stall_ticks(1).is_err());
+        assert!(check_stall_ticks(MIN_STALL_TICKS).is_ok());
+    }
+
+    /// On a dual-fan machine either fan stopping is a stall.
+    #[test]
+    fn a_second_fan_stalling_is_caught() {
+        let mut detector = StallDetector::default();
+        let fired: Vec<Option<usize>> = (0..5)
+            .map(|_| detector.observe(Some(3), &[Some(2400), Some(0)], 3))
+            .collect();
+        assert_eq!(fired, [None, None, None, Some(1), None]);
+    }
+
+    #[test]
+    fn level_zero_at_zero_rpm_is_the_fan_doing_as_told() {
+        let script = [(Some(0), Some(0)); 6];
+        assert!(stall_ticks(&script, 3).is_empty());
+        assert!(stall_ticks(&[(None, Some(0)); 6], 3).is_empty());
+    }
+
+    #[test]
+    fn a_reading_that_moves_or_fails_restarts_the_count() {
+        let script = [
+            (Some(2), Some(0)),
+            (Some(2), Some(0)),
+            (Some(2), Some(1500)),
+            (Some(2), Some(0)),
+            (Some(2), Some(0)),
+            (Some(2), None),
+            (Some(2), Some(0)),
+            (Some(2), Some(0)),
+            (Some(2), Some(0)),
+        ];
+        assert_eq!(stall_ticks(&script, 3), [8]);
+    }
+
+    #[test]
+    fn configs_saved_before_stall_detection_get_the_default_policy() {
+        let config: FanCurveConfig =
+            serde_json::from_str(r#"{"enabled":true,"points":[]}"#).unwrap();
+        assert_eq!(config.stall, StallPolicy::default());
+        assert_eq!(config.stall.action, StallAction::FullSpeed);
+
+        let policy: StallPolicy = serde_json::from_str(r#"{"action":"auto"}"#).unwrap();
+        assert_eq!(policy.ticks, DEFAULT_STALL_TICKS);
+        assert_eq!(policy.action, StallAction::Auto);
+    }
+
+    /// Feed a run of raw temperatures; returns what changed on each tick.
+    fn override_changes(temps: &[i32]) -> Vec<Option<OverrideChange>> {
+        let mut guard = ThermalGuard::default();
+        temps.iter().map(|t| guard.update(*t, 95, 85)).collect()
+    }
+
+    #[test]
+    fn the_override_engages_at_critical_and_holds_until_release() {
+        use OverrideChange::*;
+        assert_eq!(
+            override_changes(&[80, 94, 95, 97, 90, 86, 85, 84, 90, 96]),
+            [
+                None,
+                None,
+                Some(Engaged),
+                None,
+                None,
+                None,
+                None,
+                Some(Released),
+                None,
+                Some(Engaged)
+            ]
+        );
+    }
+
+    /// The request's example: a curve that idles the fan up to 85°C must not
+    /// be able to keep it idle at 100°C.
+    #[test]
+    fn a_lax_curve_does_not_stop_the_override() {
+        let config = FanCurveConfig {
+            points: vec![
+                CurvePoint {
+                    temp: 85,
+                    level: 0,
+                    fall_temp: None,
+                    rpm: None,
+                },
+                CurvePoint {
+                    temp: 120,
+                    level: 1,
+                    fall_temp: None,
+                    rpm: None,
+                },
+            ],
+            ..FanCurveConfig::default()
+        };
+        assert_eq!(calculate_fan_level(100, &config.points), 0);
+
+        let (critical, release) = thermal_limits(&config, &[]);
+        let mut guard = ThermalGuard::default();
+        assert_eq!(
+            guard.update(100, critical, release),
+            Some(OverrideChange::Engaged)
+        );
+    }
+
+    #[test]
+    fn configured_limits_win_and_a_bad_release_is_ignored() {
+        let mut config = FanCurveConfig::default();
+        assert_eq!(
+            thermal_limits(&config, &[]),
+            (
+                FALLBACK_CRITICAL_TEMP,
+                FALLBACK_CRITICAL_TEMP - DEFAULT_RELEASE_GAP
+            )
+        );
+
+        config.emergency.critical_temp = Some(90);
+        config.emergency.release_temp = Some(75);
+        assert_eq!(thermal_limits(&config, &[]), (90, 75));
+
+        // A release saved against a higher derived limit no longer fits.
+        config.emergency.release_temp = Some(92);
+        assert_eq!(thermal_limits(&config, &[]), (90, 80));
+    }
+
+    #[test]
+    fn rejects_override_settings_that_cannot_work() {
+        let emergency = |critical_temp, release_temp| ThermalOverride {
+            critical_temp,
+            release_temp,
+            action: OverrideAction::FullSpeed,
+        };
+        assert!(validate_thermal_override(&emergency(None, None)).is_ok());
+        assert!(validate_thermal_override(&emergency(Some(90), Some(80))).is_ok());
+        assert!(validate_thermal_override(&emergency(Some(90), Some(90))).is_err());
+        assert!(validate_thermal_override(&emergency(Some(40), None)).is_err());
+        assert!(validate_thermal_override(&emergency(Some(130), None)).is_err());
+        assert!(validate_thermal_override(&emergency(None, Some(20))).is_err());
+    }
+
+    #[test]
+    fn configs_saved_before_the_override_get_the_default() {
+        let config: FanCurveConfig =
+            serde_json::from_str(r#"{"enabled":true,"points":[]}"#).unwrap();
+        assert_eq!(config.emergency, ThermalOverride::default());
+        assert_eq!(config.emergency.action.level(), FanLevel::FullSpeed);
+
+        let emergency: ThermalOverride =
+            serde_json::from_str(r#"{"critical_temp":88,"action":"disengaged"}"#).unwrap();
+        assert_eq!(emergency.critical_temp, Some(88));
+        assert_eq!(emergency.action.level(), FanLevel::Disengaged);
+    }
+
+    // -- RPM targets --
+
+    /// A curve asking for the same speeds at the same temperatures.
+    fn rpm_curve(targets: &[(i32, u32)]) -> Vec<CurvePoint> {
+        targets
+            .iter()
+            .map(|&(temp, rpm)| CurvePoint {
+                temp,
+                level: 0,
+                fall_temp: None,
+                rpm: Some(rpm),
+            })
+            .collect()
+    }
+
+    fn calibrated(machine: &str, rpms: [u32; 8]) -> FanCalibration {
+        FanCalibration {
+            machine: machine.to_string(),
+            points: (0..8)
+                .map(|level| crate::fan_control::CalibrationPoint {
+                    level,
+                    rpm: rpms[level as usize],
+                    settled: true,
+                })
+                .collect(),
+            measured_at: 0,
+        }
+    }
+
+    /// The request behind RPM targets: one curve, two models, the same speeds.
+    #[test]
+    fn one_rpm_curve_picks_each_machines_own_levels() {
+        let mut config = FanCurveConfig {
+            points: rpm_curve(&[(50, 2000), (70, 3500)]),
+            ..FanCurveConfig::default()
+        };
+        for c in [
+            calibrated(
+                "ThinkPad T14 Gen 3",
+                [0, 1900, 2300, 2700, 3100, 3500, 3900, 4500],
+            ),
+            calibrated(
+                "ThinkPad P1 Gen 4i",
+                [0, 2100, 2600, 3000, 3600, 4100, 4600, 5200],
+            ),
+        ] {
+            config.calibrations.insert(c.machine.clone(), c);
+        }
+
+        let levels = |machine: &str| -> Vec<i32> {
+            config
+                .clone()
+                .resolve_rpm_targets(machine)
+                .unwrap()
+                .points
+                .iter()
+                .map(|p| p.level)
+                .collect()
+        };
+        assert_eq!(levels("ThinkPad T14 Gen 3"), [2, 5]);
+        assert_eq!(levels("ThinkPad P1 Gen 4i"), [1, 4]);
+    }
+
+    #[test]
+    fn an_rpm_curve_does_not_run_on_an_uncalibrated_machine() {
+        let config = FanCurveConfig {
+            points: rpm_curve(&[(50, 2000)]),
+            ..FanCurveConfig::default()
+        };
+        let e = config
+            .resolve_rpm_targets("ThinkPad X1 Carbon")
+            .unwrap_err();
+        assert!(e.contains("not been calibrated"), "{}", e);
+
+        // A level curve needs no table.
+        let levels = FanCurveConfig::default();
+        assert_eq!(
+            levels
+                .clone()
+                .resolve_rpm_targets("ThinkPad X1 Carbon")
+                .unwrap()
+                .points,
+            levels.points
+        );
+    }
+
+    #[test]
+    fn rpm_targets_must_not_fall_as_the_machine_heats_up() {
+        let points = rpm_curve(&[(40, 1500), (60, 3000), (80, 2500)]);
+        assert_eq!(
+            located(&validate_curve(&points)),
+            [(Some(2), Some(CurveField::Rpm))]
+        );
+
+        let rpms: Vec<Option<u32>> = normalize_curve(&points).iter().map(|p| p.rpm).collect();
+        assert_eq!(rpms, [Some(1500), Some(3000), Some(3000)]);
+    }
+
+    /// In a mixed curve, an RPM point counts as the level it was recorded
+    /// at, and on a machine whose table puts it lower it is raised.
+    #[test]
+    fn a_mixed_curve_must_not_fall_between_levels_and_rpm() {
+        let point = |temp, level, rpm| CurvePoint {
+            temp,
+            level,
+            fall_temp: None,
+            rpm,
+        };
+        let points = vec![
+            point(40, 4, None),
+            point(60, 2, Some(2300)),
+            point(70, 5, Some(3900)),
+            point(80, 3, None),
+        ];
+        assert_eq!(
+            located(&validate_curve(&points)),
+            [
+                (Some(1), Some(CurveField::Rpm)),
+                (Some(3), Some(CurveField::Level))
+            ]
+        );
+
+        let mut config = FanCurveConfig {
+            points: vec![point(40, 4, None), point(60, 4, Some(2300))],
+            ..FanCurveConfig::default()
+        };
+        let table = calibrated(
+            "ThinkPad T14 Gen 3",
+            [0, 1900, 2300, 2700, 3100, 3500, 3900, 4500],
+        );
+        config.calibrations.insert(table.machine.clone(), table);
+        let levels: Vec<i32> = config
+            .resolve_rpm_targets("ThinkPad T14 Gen 3")
+            .unwrap()
+            .points
+            .iter()
+            .map(|p| p.level)
+            .collect();
+        assert_eq!(levels, [4, 4]);
+    }
+
+    #[test]
+    fn configs_saved_before_calibration_have_no_tables_or_targets() {
+        let config: FanCurveConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "points": [{ "temp": 40, "level": 0 }],
+        }))
+        .unwrap();
+        assert!(config.calibrations.is_empty());
+        assert_eq!(config.points[0].rpm, None);
+        // Nor do level points grow an `rpm: null` on the way back out.
+        assert!(serde_json::to_value(&config.points).unwrap()[0]
+            .get("rpm")
+            .is_none());
+    }
+
+    // -- Dry run --
+
+    /// A trace sampled every 2s, as the loop ticks.
+    fn trace(temps: &[f64]) -> Vec<TraceSample> {
+        temps
+            .iter()
+            .enumerate()
+            .map(|(i, &temp)| TraceSample {
+                timestamp: 2.0 * i as f64,
+                temp,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn traces_read_from_csv_or_json() {
+        let csv = "# captured on a P1\ntimestamp,temp\n0,45.5\n2, 47\n\n4,52\n";
+        let json =
+            r#"[{"timestamp":0,"temp":45.5},{"timestamp":2,"temp":47},{"timestamp":4,"temp":52}]"#;
+        assert_eq!(parse_trace(csv).unwrap(), parse_trace(json).unwrap());
+        assert_eq!(parse_trace(csv).unwrap()[2].temp, 52.0);
+    }
+
+    #[test]
+    fn a_malformed_trace_says_where() {
+        let e = parse_trace("timestamp,temp\n0,45\n2,hot\n").unwrap_err();
+        assert!(e.starts_with("Line 3:"), "{}", e);
+        let e = parse_trace("0,45\n4,50\n2,55\n").unwrap_err();
+        assert!(e.contains("Sample 3"), "{}", e);
+        assert!(parse_trace("timestamp,temp\n").is_err());
+        assert!(parse_trace("[{\"timestamp\":0}]").is_err());
+    }
+
+    /// A steady temperature is one level write, then only watchdog re-arms:
+    /// the regression [`watchdog_due`] exists for, seen from a trace.
+    #[test]
+    fn a_steady_trace_writes_once_and_keeps_the_watchdog_fed() {
+        let sim = simulate_curve(&FanCurveConfig::default(), &trace(&[65.0; 31]));
+
+        assert_eq!(sim.level_writes, 1);
+        assert!(sim.ticks.iter().all(|t| t.level == Some(4)));
+        // Armed with the level at 0s, then every 15s over the following 60s.
+        let armed: Vec<f64> = sim
+            .ticks
+            .iter()
+            .filter(|t| t.watchdog_armed)
+            .map(|t| t.timestamp)
+            .collect();
+        assert_eq!(armed, [0.0, 16.0, 32.0, 48.0]);
+        assert_eq!(sim.fan_writes, 5);
+    }
+
+    #[test]
+    fn the_dry_run_follows_the_bands_and_dwell_the_loop_uses() {
+        let config = curve_with_band(10);
+        let temps = [
+            58.0, 61.0, 59.0, 61.0, 59.0, 61.0, 40.0, 40.0, 40.0, 40.0, 40.0, 40.0, 40.0,
+        ];
+        let sim = simulate_curve(&config, &trace(&temps));
+
+        let levels: Vec<Option<i32>> = sim.ticks.iter().map(|t| t.level).collect();
+        let (changes, expected) = level_changes(&config, &temps.map(|t| t as i32));
+        assert_eq!(levels, expected.into_iter().map(Some).collect::<Vec<_>>());
+        // level_changes does not count the first write.
+        assert_eq!(sim.level_writes, changes + 1);
+    }
+
+    #[test]
+    fn the_override_takes_over_in_a_dry_run() {
+        let sim = simulate_curve(
+            &FanCurveConfig::default(),
+            &trace(&[70.0, 96.0, 97.0, 90.0, 80.0]),
+        );
+
+        let timeline: Vec<(Option<i32>, bool)> = sim
+            .ticks
+            .iter()
+            .map(|t| (t.level, t.override_active))
+            .collect();
+        // The fallback limit is 95°C, released below 85°C.
+        assert_eq!(
+            timeline,
+            [
+                (Some(5), false),
+                (None, true),
+                (None, true),
+                (None, true),
+                (Some(7), false)
+            ]
+        );
+        // Level 5, full speed, then the curve again.
+        assert_eq!(sim.level_writes, 3);
+    }
+
+    // -- Against a captured P1 Gen 4i --
+
+    use crate::hardware_root::testing::with_profile;
+
+    /// The complaint behind sensor selection, reproduced on real data: the
+    /// heuristic lands on the ACPI zone, which reads several degrees under the
+    /// package sensor.
+    #[test]
+    fn heuristic_reads_the_acpi_zone_on_the_p1() {
+        assert_eq!(
+            with_profile("thinkpad-p1-gen-4i", get_cpu_temperature),
+            Ok(52)
+        );
+    }
+
+    #[test]
+    fn sensors_and_power_state_come_from_the_profile() {
+        let (sensors, on_ac) =
+            with_profile("thinkpad-p1-gen-4i", || (list_sensors(), on_ac_power()));
+
+        let package = sensors
+            .iter()
+            .find(|s| s.id == "hwmon:coretemp:Package id 0")
+            .expect("coretemp package sensor");
+        assert_eq!(package.temp, Some(59));
+        assert!(sensors.iter().any(|s| s.id == "thermal:x86_pkg_temp"));
+        assert!(sensors.iter().any(|s| s.id == "hwmon:thinkpad:CPU"));
+        assert_eq!(on_ac, Some(true));
+    }
+
+    #[test]
+    fn a_selected_sensor_drives_the_curve_on_the_p1() {
+        let config = FanCurveConfig {
+            sensors: vec![
+                "thermal:acpitz".to_string(),
+                "hwmon:coretemp:Package id 0".to_string(),
+            ],
+            ..FanCurveConfig::default()
+        };
+        assert_eq!(
+            with_profile("thinkpad-p1-gen-4i", || read_curve_temperature(&config)),
+            Ok(59)
+        );
+    }
+
+    /// With no sensors chosen the limit comes from the CPU package, not from
+    /// the lower NVMe limit elsewhere on the machine; choosing the NVMe drive
+    /// makes its limit the one that counts.
+    #[test]
+    fn the_critical_temperature_defaults_from_the_followed_sensors() {
+        let sensors = with_profile("thinkpad-p1-gen-4i", list_sensors);
+
+        let heuristic = FanCurveConfig::default();
+        assert_eq!(thermal_limits(&heuristic, &sensors), (100, 90));
+
+        let nvme = FanCurveConfig {
+            sensors: vec![
+                "hwmon:coretemp:Package id 0".to_string(),
+                "hwmon:nvme:Composite".to_string(),
+            ],
+            ..FanCurveConfig::default()
+        };
+        assert_eq!(thermal_limits(&nvme, &sensors), (84, 74));
+
+        let zone_only = FanCurveConfig {
+            sensors: vec!["thermal:acpitz".to_string()],
+            ..FanCurveConfig::default()
+        };
+        assert_eq!(
+            thermal_limits(&zone_only, &sensors).0,
+            FALLBACK_CRITICAL_TEMP
+        );
+    }
 }
@@ -4,16 +4,33 @@
 //! captured hardware profile instead, which is how a container with no ThinkPad
 //! can still exercise the dual-fan read path.
 //!
-//! Only reads are redirected. Writes always target the real paths and are gated
-//! by the usual permission checks, so a fixture can never be mistaken for a
-//! writable device.
+//! Every read of `/proc` or `/sys` goes through here -- [`read_to_string`],
+//! [`exists`] and [`read_dir`] -- so a profile exercises the same code the app
+//! runs on a ThinkPad.
+//!
+//! Writes go through [`write`]. On real hardware that is a plain write. Under a
+//! profile it is refused, unless the profile is a scratch copy made by
+//! [`scratch_copy`] and [`HARDWARE_WRITABLE_ENV`] is set -- then the write lands
+//! in the copy, filtered through a small model of what the kernel would accept.
+//! That is what lets a test drive the full command surface without root or a
+//! ThinkPad, while a fixture can still never be mistaken for a writable device.
 
+use std::io;
 use std::path::{Path, PathBuf};
 
 /// Environment variable naming a captured hardware profile to read instead of
 /// the live machine. Set by tests and by the hardware-matrix CI job.
 pub const HARDWARE_ROOT_ENV: &str = "THINKUTILS_HARDWARE_ROOT";
 
+/// Set to `1`, alongside [`HARDWARE_ROOT_ENV`], to let writes land in the
+/// profile. Has no effect without a root: it can never redirect real writes.
+pub const HARDWARE_WRITABLE_ENV: &str = "THINKUTILS_HARDWARE_WRITABLE";
+
+/// Dropped into a directory by [`scratch_copy`]. Writes are refused under any
+/// root without it, so a test that forgot to copy cannot modify the committed
+/// fixtures.
+pub(crate) const SCRATCH_MARKER: &str = ".thinkutils-scratch";
+
 /// Resolve an absolute hardware path against the configured root.
 ///
 /// Returns the path unchanged when no root is set, which is the shipped
@@ -31,8 +48,9 @@ pub fn resolve(path: &str) -> PathBuf {
 
 /// True when reads are being served from a fixture rather than real hardware.
 ///
-/// Callers use this to refuse writes: a test must never believe it changed a fan
-/// speed because a fixture file happened to be writable.
+/// Callers use this to keep writes away from privilege escalation: with no real
+/// device behind the profile, a write either goes through [`write`] or not at
+/// all, and never through pkexec.
 pub fn is_simulated() -> bool {
     std::env::var(HARDWARE_ROOT_ENV).is_ok_and(|v| !v.is_empty())
 }
@@ -47,16 +65,109 @@ pub fn exists(path: &str) -> bool {
     resolve(path).exists()
 }
 
+/// List a hardware directory through the configured root.
+///
+/// Entries carry resolved paths, so reads through them stay inside the fixture.
+/// Never hand such a path to a write: anything that will be written must be
+/// rebuilt from the unresolved directory and the entry's file name.
+pub fn read_dir(path: &str) -> std::io::Result<std::fs::ReadDir> {
+    std::fs::read_dir(resolve(path))
+}
+
+/// Whether writes are being applied to a scratch profile.
+pub fn writes_are_simulated() -> bool {
+    is_simulated() && std::env::var(HARDWARE_WRITABLE_ENV).is_ok_and(|v| v == "1")
+}
+
+/// Copy a captured profile to `dest` and mark it as safe to write.
+///
+/// Symlinks are copied as symlinks. The `/sys/class` entries are relative, so
+/// they keep pointing inside the copy.
+pub fn scratch_copy(profile: &Path, dest: &Path) -> io::Result<()> {
+    copy_tree(profile, dest)?;
+    std::fs::write(dest.join(SCRATCH_MARKER), "")
+}
+
+fn copy_tree(from: &Path, to: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        let kind = entry.file_type()?;
+        if kind.is_symlink() {
+            std::os::unix::fs::symlink(std::fs::read_link(entry.path())?, &target)?;
+        } else if kind.is_dir() {
+            copy_tree(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write a hardware file.
+///
+/// On real hardware this is `std::fs::write(path)`, and permission errors come
+/// back as they would from the kernel. Under a profile, see the module docs.
+pub fn write(path: &str, contents: &str) -> io::Result<()> {
+    if !is_simulated() {
+        return std::fs::write(path, contents);
+    }
+    if !writes_are_simulated() {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "simulated hardware profile is read-only",
+        ));
+    }
+    if !resolve("/").join(SCRATCH_MARKER).exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "hardware root is not a scratch copy; refusing to modify a fixture",
+        ));
+    }
+    simulated_write(path, contents.trim())
+}
+
+/// Apply a write to the scratch profile the way the kernel would.
+///
+/// sysfs and procfs never create files, so a path the profile lacks is
+/// NotFound. Each module that owns an interface with rules -- the fan's
+/// command language, the threshold ordering, the governor list -- gets first
+/// refusal; anything else is stored as written.
+fn simulated_write(path: &str, value: &str) -> io::Result<()> {
+    let target = resolve(path);
+    if !target.is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} does not exist in the profile", path),
+        ));
+    }
+
+    if let Some(result) = crate::fan_control::simulate_write(path, value)
+        .or_else(|| crate::battery::simulate_write(path, value))
+        .or_else(|| crate::performance::simulate_write(path, value))
+    {
+        return result;
+    }
+    std::fs::write(target, format!("{}\n", value))
+}
+
+/// The error the kernel returns for a value it does not accept.
+pub(crate) fn invalid_value(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+/// Test support shared by every module that reads hardware.
 #[cfg(test)]
-mod tests {
-    use super::*;
+pub(crate) mod testing {
+    use super::HARDWARE_ROOT_ENV;
 
-    /// The env var is process-global, so these run under one lock and restore it
-    /// afterwards. Without that, a parallel test seeing another's root would fail
-    /// in a way that looks like a logic bug.
+    /// The env var is process-global, so tests that set it run under one lock and
+    /// restore it afterwards. Without that, a parallel test seeing another's root
+    /// would fail in a way that looks like a logic bug.
     static ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
 
-    fn with_root<T>(root: Option<&str>, f: impl FnOnce() -> T) -> T {
+    pub(crate) fn with_root<T>(root: Option<&str>, f: impl FnOnce() -> T) -> T {
         let _guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
         let previous = std::env::var(HARDWARE_ROOT_ENV).ok();
 
@@ -74,6 +185,53 @@ mod tests {
         out
     }
 
+    /// A captured profile under tests/fixtures/hardware.
+    pub(crate) fn profile(name: &str) -> String {
+        format!(
+            "{}/tests/fixtures/hardware/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        )
+    }
+
+    /// Read a path with the root set to a captured profile.
+    pub(crate) fn with_profile<T>(name: &str, f: impl FnOnce() -> T) -> T {
+        with_root(Some(&profile(name)), f)
+    }
+
+    /// Run `f` against a writable scratch copy of a profile, then delete it.
+    /// `f` gets the copy's root so it can inspect what was written.
+    pub(crate) fn with_scratch_profile<T>(name: &str, f: impl FnOnce(&std::path::Path) -> T) -> T {
+        let scratch = std::env::temp_dir().join(format!(
+            "thinkutils_scratch_{}_{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        super::scratch_copy(std::path::Path::new(&profile(name)), &scratch).expect("copy profile");
+
+        let previous = std::env::var(super::HARDWARE_WRITABLE_ENV).ok();
+        let out = with_root(Some(scratch.to_str().unwrap()), || {
+            std::env::set_var(super::HARDWARE_WRITABLE_ENV, "1");
+            let out = f(&scratch);
+            match &previous {
+                Some(p) => std::env::set_var(super::HARDWARE_WRITABLE_ENV, p),
+                None => std::env::remove_var(super::HARDWARE_WRITABLE_ENV),
+            }
+            out
+        });
+        let _ = std::fs::remove_dir_all(&scratch);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::{profile, with_root, with_scratch_profile};
+    use super::*;
+
     #[test]
     fn unset_root_leaves_paths_untouched() {
         with_root(None, || {
@@ -113,4 +271,88 @@ mod tests {
             assert!(!is_simulated());
         });
     }
+
+    /// The fixture carries the /sys/class symlinks a live machine has, so
+    /// discovery by class finds the same devices it would on the hardware.
+    #[test]
+    fn read_dir_lists_class_devices_in_the_profile() {
+        with_root(Some(&profile("thinkpad-p1-gen-4i")), || {
+            let mut names: Vec<String> = read_dir("/sys/class/power_supply")
+                .expect("profile has power_supply")
+                .flatten()
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .collect();
+            names.sort();
+            assert_eq!(
+                names,
+                [
+                    "AC",
+                    "BAT0",
+                    "ucsi-source-psy-USBC000:001",
+                    "ucsi-source-psy-USBC000:002"
+                ]
+            );
+            assert_eq!(
+                read_to_string("/sys/class/hwmon/hwmon6/name")
+                    .unwrap()
+                    .trim(),
+                "thinkpad"
+            );
+        });
+    }
+
+    /// A read-only profile must refuse every write, whatever the path.
+    #[test]
+    fn a_plain_profile_refuses_writes() {
+        with_root(Some(&profile("thinkpad-p1-gen-4i")), || {
+            let err = write("/proc/acpi/ibm/fan", "level 3").unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        });
+    }
+
+    /// Setting the writable flag on the committed fixture itself is the
+    /// mistake the marker exists for.
+    #[test]
+    fn the_writable_flag_alone_does_not_unlock_a_fixture() {
+        let before = std::fs::read_to_string(
+            Path::new(&profile("thinkpad-p1-gen-4i")).join("proc/acpi/ibm/fan"),
+        )
+        .unwrap();
+        let err = with_root(Some(&profile("thinkpad-p1-gen-4i")), || {
+            std::env::set_var(HARDWARE_WRITABLE_ENV, "1");
+            let err = write("/proc/acpi/ibm/fan", "level 3");
+            std::env::remove_var(HARDWARE_WRITABLE_ENV);
+            err
+        })
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        let after = std::fs::read_to_string(
+            Path::new(&profile("thinkpad-p1-gen-4i")).join("proc/acpi/ibm/fan"),
+        )
+        .unwrap();
+        assert_eq!(before, after);
+    }
+
+    #[test]
+    fn a_scratch_copy_keeps_class_symlinks_inside_the_copy() {
+        with_scratch_profile("thinkpad-p1-gen-4i", |scratch| {
+            let link = scratch.join("sys/class/hwmon/hwmon6");
+            assert!(std::fs::symlink_metadata(&link)
+                .unwrap()
+                .file_type()
+                .is_symlink());
+            assert!(std::fs::canonicalize(&link)
+                .unwrap()
+                .starts_with(std::fs::canonicalize(scratch).unwrap()));
+        });
+    }
+
+    /// sysfs does not create attributes on write, and neither does the model.
+    #[test]
+    fn writes_to_missing_files_are_not_found() {
+        with_scratch_profile("thinkpad-p1-gen-4i", |_| {
+            let err = write("/sys/class/power_supply/BAT1/capacity", "50").unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        });
+    }
 }
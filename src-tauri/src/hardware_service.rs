@@ -0,0 +1,700 @@
+//! `org.thinkutils.Hardware1`: the privileged helper's operations as a system
+//! bus service.
+//!
+//! Going through pkexec costs a process launch for every fan change the curve
+//! makes, and pkexec only consults the JavaScript rule for whether to prompt.
+//! The service is the same helper binary, started once by bus activation
+//! (`thinkutils-helper --dbus`), with one method per [`Operation`]. Each method
+//! asks polkit whether the caller may perform that operation's [`Action`], then
+//! validates and applies it exactly as the helper does on the command line.
+//!
+//! [`call`] is the client side. It answers `None` when no service is there to
+//! ask, and [`crate::helper::run`] falls back to pkexec.
+//!
+//! For development the service also runs on the session bus
+//! (`THINKUTILS_HARDWARE_BUS=session`), where there is no polkit to ask. It
+//! refuses to start there unless the hardware root is a writable simulated
+//! profile, so that stand-in can never touch real hardware.
+
+use crate::error::Error;
+use crate::helper::{Action, Operation};
+use std::collections::HashMap;
+use zbus::message::Header;
+use zbus::zvariant::Value;
+
+/// The well-known name, object path and interface are all the same string,
+/// as is conventional for a single-object service.
+pub const BUS_NAME: &str = "org.thinkutils.Hardware1";
+pub const OBJECT_PATH: &str = "/org/thinkutils/Hardware1";
+
+/// Set to `session` to serve and call the service on the session bus.
+pub const HARDWARE_BUS_ENV: &str = "THINKUTILS_HARDWARE_BUS";
+
+/// The systemd unit bus activation starts. dbus-broker, the system bus on
+/// Fedora and Arch, only activates through systemd, so the activation file
+/// names this rather than relying on `Exec=` alone.
+pub const SYSTEMD_SERVICE: &str = "thinkutils-hardware.service";
+
+fn session_bus() -> bool {
+    std::env::var(HARDWARE_BUS_ENV).as_deref() == Ok("session")
+}
+
+/// Who may do what.
+pub enum Authority {
+    /// Ask polkit, over this system bus connection, whether the sender may
+    /// perform each action. Interaction is allowed, so an action left at
+    /// `auth_admin_keep` prompts the caller's session agent.
+    Polkit(zbus::Connection),
+    /// Allow exactly these actions to any caller: the session-bus stand-in,
+    /// and tests.
+    Granted(Vec<Action>),
+}
+
+impl Authority {
+    async fn check(&self, sender: Option<&str>, action: Action) -> zbus::fdo::Result<()> {
+        let allowed = match self {
+            Authority::Granted(actions) => actions.contains(&action),
+            Authority::Polkit(connection) => {
+                let sender = sender.ok_or_else(|| {
+                    zbus::fdo::Error::AccessDenied("The caller has no bus name".to_string())
+                })?;
+                polkit_allows(connection, sender, action)
+                    .await
+                    .map_err(|e| zbus::fdo::Error::Failed(format!("polkit: {}", e)))?
+            }
+        };
+        if allowed {
+            Ok(())
+        } else {
+            Err(zbus::fdo::Error::AccessDenied(format!(
+                "Not authorized for {}",
+                action.id()
+            )))
+        }
+    }
+}
+
+/// `CheckAuthorization` for a `system-bus-name` subject. Flag 1 is
+/// `AllowUserInteraction`.
+async fn polkit_allows(
+    connection: &zbus::Connection,
+    sender: &str,
+    action: Action,
+) -> zbus::Result<bool> {
+    let subject: HashMap<&str, Value> = HashMap::from([("name", Value::from(sender))]);
+    let details: HashMap<&str, &str> = HashMap::new();
+    let reply = connection
+        .call_method(
+            Some("org.freedesktop.PolicyKit1"),
+            "/org/freedesktop/PolicyKit1/Authority",
+            Some("org.freedesktop.PolicyKit1.Authority"),
+            "CheckAuthorization",
+            &(("system-bus-name", subject), action.id(), details, 1u32, ""),
+        )
+        .await?;
+    let (authorized, _challenge, _details): (bool, bool, HashMap<String, String>) =
+        reply.body().deserialize()?;
+    Ok(authorized)
+}
+
+/// The object served at [`OBJECT_PATH`].
+pub struct Hardware {
+    authority: Authority,
+}
+
+impl Hardware {
+    pub fn new(authority: Authority) -> Self {
+        Hardware { authority }
+    }
+
+    /// Authorise, validate, apply: the helper's `main`, with the caller checked
+    /// first so nothing about the request is acted on for someone not allowed
+    /// to make it.
+    async fn perform(
+        &self,
+        header: &Header<'_>,
+        operation: Operation,
+    ) -> zbus::fdo::Result<String> {
+        let sender = header.sender().map(|s| s.as_str());
+        self.authority.check(sender, operation.action()).await?;
+        operation
+            .validate()
+            .map_err(zbus::fdo::Error::InvalidArgs)?;
+        operation.apply().map_err(zbus::fdo::Error::Failed)
+    }
+}
+
+#[zbus::interface(name = "org.thinkutils.Hardware1")]
+impl Hardware {
+    /// A `/proc/acpi/ibm/fan` command, e.g. `level 3` or `watchdog 30`.
+    async fn set_fan(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+        command: String,
+    ) -> zbus::fdo::Result<String> {
+        let command = command.parse().map_err(zbus::fdo::Error::InvalidArgs)?;
+        self.perform(&header, Operation::Fan(command)).await
+    }
+
+    /// Load thinkpad_acpi with `fan_control=1`, now and at every boot.
+    async fn enable_fan_module(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+    ) -> zbus::fdo::Result<String> {
+        self.perform(&header, Operation::FanModule).await
+    }
+
+    async fn set_battery_thresholds(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+        start: u8,
+        stop: u8,
+    ) -> zbus::fdo::Result<String> {
+        self.perform(
+            &header,
+            Operation::BatteryThresholds {
+                start,
+                stop,
+                battery: None,
+            },
+        )
+        .await
+    }
+
+    /// One battery's thresholds, by its sysfs name, e.g. `BAT1`.
+    async fn set_battery_thresholds_of(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+        battery: String,
+        start: u8,
+        stop: u8,
+    ) -> zbus::fdo::Result<String> {
+        self.perform(
+            &header,
+            Operation::BatteryThresholds {
+                start,
+                stop,
+                battery: Some(battery),
+            },
+        )
+        .await
+    }
+
+    /// A charge behaviour for every battery with the control: `auto`,
+    /// `inhibit-charge` or `force-discharge`.
+    async fn set_charge_behaviour(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+        behaviour: String,
+    ) -> zbus::fdo::Result<String> {
+        let behaviour = behaviour.parse().map_err(zbus::fdo::Error::InvalidArgs)?;
+        self.perform(
+            &header,
+            Operation::ChargeBehaviour {
+                behaviour,
+                battery: None,
+            },
+        )
+        .await
+    }
+
+    /// One battery's charge behaviour, by its sysfs name.
+    async fn set_charge_behaviour_of(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+        battery: String,
+        behaviour: String,
+    ) -> zbus::fdo::Result<String> {
+        let behaviour = behaviour.parse().map_err(zbus::fdo::Error::InvalidArgs)?;
+        self.perform(
+            &header,
+            Operation::ChargeBehaviour {
+                behaviour,
+                battery: Some(battery),
+            },
+        )
+        .await
+    }
+
+    async fn set_governor(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+        name: String,
+    ) -> zbus::fdo::Result<String> {
+        self.perform(&header, Operation::Governor(name)).await
+    }
+
+    async fn set_turbo(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+        enabled: bool,
+    ) -> zbus::fdo::Result<String> {
+        self.perform(&header, Operation::Turbo(enabled)).await
+    }
+
+    async fn set_platform_profile(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+        name: String,
+    ) -> zbus::fdo::Result<String> {
+        self.perform(&header, Operation::PlatformProfile(name))
+            .await
+    }
+}
+
+#[zbus::proxy(
+    interface = "org.thinkutils.Hardware1",
+    default_service = "org.thinkutils.Hardware1",
+    default_path = "/org/thinkutils/Hardware1"
+)]
+trait Hardware1 {
+    fn set_fan(&self, command: &str) -> zbus::Result<String>;
+    fn enable_fan_module(&self) -> zbus::Result<String>;
+    fn set_battery_thresholds(&self, start: u8, stop: u8) -> zbus::Result<String>;
+    fn set_battery_thresholds_of(&self, battery: &str, start: u8, stop: u8)
+        -> zbus::Result<String>;
+    fn set_charge_behaviour(&self, behaviour: &str) -> zbus::Result<String>;
+    fn set_charge_behaviour_of(&self, battery: &str, behaviour: &str) -> zbus::Result<String>;
+    fn set_governor(&self, name: &str) -> zbus::Result<String>;
+    fn set_turbo(&self, enabled: bool) -> zbus::Result<String>;
+    fn set_platform_profile(&self, name: &str) -> zbus::Result<String>;
+}
+
+/// Who may own the name and who may call it, installed under
+/// /usr/share/dbus-1/system.d. Calling is open: the methods check polkit
+/// themselves, per action.
+pub const BUS_POLICY: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE busconfig PUBLIC "-//freedesktop//DTD D-BUS Bus Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+<!-- ThinkUtils: generated from hardware_service -- do not edit by hand. -->
+<busconfig>
+  <policy user="root">
+    <allow own="org.thinkutils.Hardware1"/>
+  </policy>
+  <policy context="default">
+    <allow send_destination="org.thinkutils.Hardware1"
+           send_interface="org.thinkutils.Hardware1"/>
+    <allow send_destination="org.thinkutils.Hardware1"
+           send_interface="org.freedesktop.DBus.Introspectable"/>
+    <allow send_destination="org.thinkutils.Hardware1"
+           send_interface="org.freedesktop.DBus.Peer"/>
+  </policy>
+</busconfig>
+"#;
+
+/// Where the bus reads [`BUS_POLICY`] and [`activation_file`] from. Packages and
+/// `setup_permissions()` alike use /usr/share: dbus-broker does not read
+/// activation files anywhere under /etc.
+pub const BUS_POLICY_PATH: &str = "/usr/share/dbus-1/system.d/org.thinkutils.Hardware1.conf";
+pub const ACTIVATION_FILE_PATH: &str =
+    "/usr/share/dbus-1/system-services/org.thinkutils.Hardware1.service";
+
+/// Where `setup_permissions()` writes [`systemd_unit`] for a self-installed
+/// helper; packages put theirs under /usr/lib/systemd/system.
+pub const SELF_INSTALL_UNIT_PATH: &str = "/etc/systemd/system/thinkutils-hardware.service";
+
+/// The bus activation file for a helper installed at `helper`.
+pub fn activation_file(helper: &str) -> String {
+    format!(
+        "[D-BUS Service]\nName={}\nExec={} --dbus\nUser=root\nSystemdService={}\n",
+        BUS_NAME, helper, SYSTEMD_SERVICE
+    )
+}
+
+/// The unit bus activation starts. It has no `[Install]` section: nothing
+/// enables it, and it runs only once something calls the service.
+///
+/// The one file it writes outside /sys and /proc is the modprobe option, so
+/// /etc/modprobe.d is the only path left writable; `-` lets the unit start on a
+/// system without that directory.
+pub fn systemd_unit(helper: &str) -> String {
+    format!(
+        "\
+[Unit]
+Description=ThinkUtils privileged hardware control
+Documentation=https://github.com/vietanhdev/ThinkUtils
+
+[Service]
+Type=dbus
+BusName={}
+ExecStart={} --dbus
+ProtectSystem=strict
+ReadWritePaths=-/etc/modprobe.d
+ProtectHome=yes
+PrivateTmp=yes
+NoNewPrivileges=yes
+",
+        BUS_NAME, helper
+    )
+}
+
+/// Serve until the process is stopped.
+pub async fn serve() -> Result<(), String> {
+    let (connection, authority) = if session_bus() {
+        if !crate::hardware_root::is_simulated() {
+            return Err(format!(
+                "{}=session needs a writable simulated hardware profile; the session bus has no polkit to ask",
+                HARDWARE_BUS_ENV
+            ));
+        }
+        let connection = zbus::Connection::session()
+            .await
+            .map_err(|e| format!("Could not connect to the session bus: {}", e))?;
+        let helper_actions = Action::ALL
+            .into_iter()
+            .filter(|a| a.verb().is_some())
+            .collect();
+        (connection, Authority::Granted(helper_actions))
+    } else {
+        let connection = zbus::Connection::system()
+            .await
+            .map_err(|e| format!("Could not connect to the system bus: {}", e))?;
+        (connection.clone(), Authority::Polkit(connection))
+    };
+
+    connection
+        .object_server()
+        .at(OBJECT_PATH, Hardware::new(authority))
+        .await
+        .map_err(|e| format!("Could not serve {}: {}", OBJECT_PATH, e))?;
+    connection
+        .request_name(BUS_NAME)
+        .await
+        .map_err(|e| format!("Could not own {}: {}", BUS_NAME, e))?;
+
+    std::future::pending::<()>().await;
+    Ok(())
+}
+
+/// `thinkutils-helper --dbus`. Returns the process exit status.
+pub fn main() -> i32 {
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Could not start the runtime: {}", e);
+            return crate::helper::EXIT_FAILED;
+        }
+    };
+    match runtime.block_on(serve()) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            crate::helper::EXIT_FAILED
+        }
+    }
+}
+
+/// The connection the app reuses for every call, so the curve's changes cost a
+/// message each rather than a connection each.
+static CONNECTION: tokio::sync::OnceCell<zbus::Connection> = tokio::sync::OnceCell::const_new();
+
+async fn connection() -> zbus::Result<&'static zbus::Connection> {
+    CONNECTION
+        .get_or_try_init(|| async {
+            if session_bus() {
+                zbus::Connection::session().await
+            } else {
+                zbus::Connection::system().await
+            }
+        })
+        .await
+}
+
+/// Perform `operation` through the service, or `None` if there is no service to
+/// ask: no bus, or nothing installed under [`BUS_NAME`].
+pub async fn call(operation: &Operation) -> Option<Result<String, Error>> {
+    let proxy = Hardware1Proxy::new(connection().await.ok()?).await.ok()?;
+    let reply = match operation {
+        Operation::Fan(command) => proxy.set_fan(&command.to_string()).await,
+        Operation::FanModule => proxy.enable_fan_module().await,
+        Operation::BatteryThresholds {
+            start,
+            stop,
+            battery: None,
+        } => proxy.set_battery_thresholds(*start, *stop).await,
+        Operation::BatteryThresholds {
+            start,
+            stop,
+            battery: Some(battery),
+        } => {
+            proxy
+                .set_battery_thresholds_of(battery, *start, *stop)
+                .await
+        }
+        Operation::ChargeBehaviour {
+            behaviour,
+            battery: None,
+        } => proxy.set_charge_behaviour(&behaviour.to_string()).await,
+        Operation::ChargeBehaviour {
+            behaviour,
+            battery: Some(battery),
+        } => {
+            proxy
+                .set_charge_behaviour_of(battery, &behaviour.to_string())
+                .await
+        }
+        Operation::Governor(name) => proxy.set_governor(name).await,
+        Operation::Turbo(enabled) => proxy.set_turbo(*enabled).await,
+        Operation::PlatformProfile(name) => proxy.set_platform_profile(name).await,
+    };
+    outcome(reply)
+}
+
+/// [`call`], for the callers that cannot await.
+pub fn call_blocking(operation: &Operation) -> Option<Result<String, Error>> {
+    let connection = if session_bus() {
+        zbus::blocking::Connection::session()
+    } else {
+        zbus::blocking::Connection::system()
+    }
+    .ok()?;
+    let proxy = Hardware1ProxyBlocking::new(&connection).ok()?;
+    let reply = match operation {
+        Operation::Fan(command) => proxy.set_fan(&command.to_string()),
+        Operation::FanModule => proxy.enable_fan_module(),
+        Operation::BatteryThresholds {
+            start,
+            stop,
+            battery: None,
+        } => proxy.set_battery_thresholds(*start, *stop),
+        Operation::BatteryThresholds {
+            start,
+            stop,
+            battery: Some(battery),
+        } => proxy.set_battery_thresholds_of(battery, *start, *stop),
+        Operation::ChargeBehaviour {
+            behaviour,
+            battery: None,
+        } => proxy.set_charge_behaviour(&behaviour.to_string()),
+        Operation::ChargeBehaviour {
+            behaviour,
+            battery: Some(battery),
+        } => proxy.set_charge_behaviour_of(battery, &behaviour.to_string()),
+        Operation::Governor(name) => proxy.set_governor(name),
+        Operation::Turbo(enabled) => proxy.set_turbo(*enabled),
+        Operation::PlatformProfile(name) => proxy.set_platform_profile(name),
+    };
+    outcome(reply)
+}
+
+/// The bus answers for a name nobody owns and nothing can activate with one of
+/// these; a failed activation comes back as `Spawn.*`. Either way pkexec is
+/// still worth trying.
+fn service_missing(error_name: &str) -> bool {
+    error_name == "org.freedesktop.DBus.Error.ServiceUnknown"
+        || error_name == "org.freedesktop.DBus.Error.NameHasNoOwner"
+        || error_name.starts_with("org.freedesktop.DBus.Error.Spawn.")
+}
+
+fn outcome(reply: zbus::Result<String>) -> Option<Result<String, Error>> {
+    match reply {
+        Ok(message) => Some(Ok(message)),
+        Err(zbus::Error::MethodError(name, message, _)) => {
+            if service_missing(name.as_str()) {
+                None
+            } else if name.as_str() == "org.freedesktop.DBus.Error.AccessDenied" {
+                Some(Err(Error::permission_denied(crate::helper::REFUSED)))
+            } else {
+                let message = message.unwrap_or_else(|| name.to_string());
+                if name.as_str() == "org.freedesktop.DBus.Error.InvalidArgs" {
+                    Some(Err(Error::validation(message)))
+                } else {
+                    Some(Err(Error::failed(message)))
+                }
+            }
+        }
+        Err(e) => Some(Err(Error::failed(format!("Hardware service error: {}", e)))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware_root::testing::with_scratch_profile;
+
+    /// A client and the service on either end of a socket pair: the bus without
+    /// a bus daemon. There is no sender on a peer-to-peer connection, which
+    /// [`Authority::Granted`] does not look at.
+    async fn serve_pair(authority: Authority) -> (zbus::Connection, zbus::Connection) {
+        let (a, b) = std::os::unix::net::UnixStream::pair().unwrap();
+        let guid = zbus::Guid::generate();
+        let server = zbus::connection::Builder::unix_stream(a)
+            .server(guid)
+            .unwrap()
+            .p2p()
+            .serve_at(OBJECT_PATH, Hardware::new(authority))
+            .unwrap()
+            .build();
+        let client = zbus::connection::Builder::unix_stream(b).p2p().build();
+        // Both ends have to make progress for the handshake to finish.
+        let (server, client) = tokio::join!(server, client);
+        (server.unwrap(), client.unwrap())
+    }
+
+    async fn proxy(client: &zbus::Connection) -> Hardware1Proxy<'static> {
+        Hardware1Proxy::builder(client)
+            .destination(BUS_NAME)
+            .unwrap()
+            .build()
+            .await
+            .unwrap()
+    }
+
+    fn error_name(reply: zbus::Result<String>) -> String {
+        match reply {
+            Err(zbus::Error::MethodError(name, _, _)) => name.to_string(),
+            other => panic!("expected a D-Bus error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn each_method_applies_its_operation() {
+        with_scratch_profile("thinkpad-p1-gen-4i", |root| {
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime.block_on(async {
+                let helper_actions = Action::ALL
+                    .into_iter()
+                    .filter(|a| a.verb().is_some())
+                    .collect();
+                let (_server, client) = serve_pair(Authority::Granted(helper_actions)).await;
+                let hardware = proxy(&client).await;
+
+                hardware.set_fan("level 6").await.unwrap();
+                hardware.set_battery_thresholds(55, 65).await.unwrap();
+                hardware
+                    .set_battery_thresholds_of("BAT0", 60, 70)
+                    .await
+                    .unwrap();
+                hardware
+                    .set_charge_behaviour("force-discharge")
+                    .await
+                    .unwrap();
+                hardware.set_governor("performance").await.unwrap();
+                hardware.set_turbo(false).await.unwrap();
+                hardware.set_platform_profile("low-power").await.unwrap();
+            });
+
+            let read = |rel: &str| std::fs::read_to_string(root.join(rel)).unwrap();
+            assert!(read("proc/acpi/ibm/fan").contains("level:\t\t6"));
+            let thresholds = crate::battery::get_battery_thresholds().data.unwrap();
+            assert_eq!((thresholds.start, thresholds.stop), (60, 70));
+            assert_eq!(
+                read("sys/class/power_supply/BAT0/charge_behaviour").trim(),
+                "auto inhibit-charge [force-discharge]"
+            );
+            assert_eq!(
+                read("sys/devices/system/cpu/cpu0/cpufreq/scaling_governor").trim(),
+                "performance"
+            );
+            assert_eq!(
+                read("sys/devices/system/cpu/intel_pstate/no_turbo").trim(),
+                "1"
+            );
+            assert_eq!(
+                read("sys/firmware/acpi/platform_profile").trim(),
+                "low-power"
+            );
+        });
+    }
+
+    /// The check is per method: granting fan control grants nothing else, and
+    /// a refused call writes nothing.
+    #[test]
+    fn each_method_is_checked_against_its_own_action() {
+        with_scratch_profile("thinkpad-p1-gen-4i", |root| {
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime.block_on(async {
+                let (_server, client) =
+                    serve_pair(Authority::Granted(vec![Action::FanControl])).await;
+                let hardware = proxy(&client).await;
+
+                assert!(hardware.set_fan("level 2").await.is_ok());
+                for reply in [
+                    hardware.set_governor("performance").await,
+                    hardware.set_turbo(false).await,
+                    hardware.set_battery_thresholds(50, 60).await,
+                    hardware.set_battery_thresholds_of("BAT0", 50, 60).await,
+                    hardware
+                        .set_charge_behaviour_of("BAT0", "inhibit-charge")
+                        .await,
+                ] {
+                    assert_eq!(error_name(reply), "org.freedesktop.DBus.Error.AccessDenied");
+                }
+            });
+
+            let read = |rel: &str| std::fs::read_to_string(root.join(rel)).unwrap();
+            assert_eq!(
+                read("sys/devices/system/cpu/cpu0/cpufreq/scaling_governor").trim(),
+                "powersave"
+            );
+            assert_eq!(
+                read("sys/devices/system/cpu/intel_pstate/no_turbo").trim(),
+                "0"
+            );
+        });
+    }
+
+    /// The service validates with the helper's code: what the command line
+    /// refuses, the bus refuses too.
+    #[test]
+    fn invalid_arguments_are_refused_before_anything_is_written() {
+        with_scratch_profile("thinkpad-p1-gen-4i", |root| {
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime.block_on(async {
+                let (_server, client) = serve_pair(Authority::Granted(Action::ALL.to_vec())).await;
+                let hardware = proxy(&client).await;
+
+                for reply in [
+                    hardware.set_fan("level 4; reboot").await,
+                    hardware.set_fan("level 8").await,
+                    hardware.set_battery_thresholds(80, 40).await,
+                    hardware.set_battery_thresholds_of("BAT9", 40, 80).await,
+                    hardware.set_charge_behaviour("discharge").await,
+                    hardware.set_charge_behaviour_of("BAT9", "auto").await,
+                    hardware.set_governor("schedutil").await,
+                    hardware.set_platform_profile("quiet").await,
+                ] {
+                    assert_eq!(error_name(reply), "org.freedesktop.DBus.Error.InvalidArgs");
+                }
+            });
+
+            let read = |rel: &str| std::fs::read_to_string(root.join(rel)).unwrap();
+            assert!(read("proc/acpi/ibm/fan").contains("level:\t\tauto"));
+        });
+    }
+
+    #[test]
+    fn a_missing_service_falls_back_and_a_refusal_does_not() {
+        let error = |name: &str| {
+            let name = zbus::names::OwnedErrorName::try_from(name.to_string()).unwrap();
+            let message = zbus::message::Message::method_call("/", "Ping")
+                .unwrap()
+                .build(&())
+                .unwrap();
+            zbus::Error::MethodError(name, Some("detail".to_string()), message)
+        };
+        assert_eq!(
+            outcome(Err(error("org.freedesktop.DBus.Error.ServiceUnknown"))),
+            None
+        );
+        assert_eq!(
+            outcome(Err(error("org.freedesktop.DBus.Error.Spawn.ExecFailed"))),
+            None
+        );
+        assert!(matches!(
+            outcome(Err(error("org.freedesktop.DBus.Error.AccessDenied"))),
+            Some(Err(e)) if e.code == crate::error::ErrorCode::PermissionDenied
+        ));
+        assert_eq!(
+            outcome(Err(error("org.freedesktop.DBus.Error.InvalidArgs"))),
+            Some(Err(Error::validation("detail")))
+        );
+        assert_eq!(
+            outcome(Err(error("org.freedesktop.DBus.Error.Failed"))),
+            Some(Err(Error::failed("detail")))
+        );
+    }
+}
@@ -0,0 +1,929 @@
+//! The privileged helper: the one program polkit lets this app run as root.
+//!
+//! It used to be a bash `case` script for fan commands, with every other write —
+//! battery thresholds, governor, turbo — going through a script generated per
+//! call and handed to `pkexec bash`. That script file belonged to the invoking
+//! user between being written and being executed, so they could swap its
+//! contents (see `privileged`).
+//!
+//! Now every hardware write root makes is one typed [`Operation`], passed on the
+//! helper's command line. The helper (`src/bin/thinkutils-helper.rs`) parses it
+//! with the same library code the app validates with, and writes only to the
+//! fixed paths that operation owns. There is no shell and no file in between.
+//!
+//! The app side is [`run`]: through the helper's D-Bus service when it is
+//! installed (see `hardware_service`), otherwise
+//! `pkexec <helper> <operation> <args...>`.
+
+use crate::battery::ChargeBehaviour;
+use crate::error::{Error, ErrorCode};
+use crate::fan_control::{FanCommand, PROC_FAN};
+use std::process::Output;
+
+/// Where the privileged helper may live, in the order it is searched.
+///
+/// A distro package ships it at one of the first two paths, both of which are
+/// root-owned and package-managed. `/usr/local` is reserved for the local
+/// administrator — Debian Policy §9.1.2 and the Fedora guidelines both forbid a
+/// package writing there — so it appears only as the location used by the app's
+/// own installer on a non-packaged install.
+pub const HELPER_CANDIDATES: &[&str] = &[
+    // Debian and Arch convention for an internal helper not on $PATH.
+    "/usr/lib/thinkutils/thinkutils-helper",
+    // Fedora convention (%{_libexecdir}).
+    "/usr/libexec/thinkutils/thinkutils-helper",
+    // Written at runtime by setup_permissions() on a non-packaged install.
+    "/usr/local/bin/thinkutils-helper",
+];
+
+/// Where `setup_permissions()` writes the helper when the app installs its own.
+///
+/// Only used when no packaged helper is present — a package-owned file must
+/// never be overwritten at runtime.
+pub const HELPER_SELF_INSTALL_PATH: &str = "/usr/local/bin/thinkutils-helper";
+
+/// The bash helper earlier versions installed. `setup_permissions()` removes it,
+/// since the rule that granted it is replaced at the same time.
+pub const LEGACY_SCRIPT_HELPER_PATH: &str = "/usr/local/bin/thinkutils-fan-control";
+
+/// The helper's file name, as built by cargo and installed by every package.
+pub const HELPER_BINARY_NAME: &str = "thinkutils-helper";
+
+/// The first helper that actually exists, if any.
+pub fn helper_path() -> Option<&'static str> {
+    HELPER_CANDIDATES
+        .iter()
+        .copied()
+        .find(|p| std::path::Path::new(p).exists())
+}
+
+/// Whether the helper came from a distro package.
+///
+/// When true the app must not install, overwrite, or offer to reinstall it: those
+/// files belong to dpkg/rpm/pacman, and rewriting them puts the package database
+/// out of sync with the filesystem.
+pub fn helper_is_packaged() -> bool {
+    HELPER_CANDIDATES
+        .iter()
+        .take(2)
+        .any(|p| std::path::Path::new(p).exists())
+}
+
+/// The helper built alongside this binary, for `setup_permissions()` to copy
+/// into place on a non-packaged install.
+pub fn bundled_helper() -> Option<std::path::PathBuf> {
+    std::env::current_exe()
+        .ok()
+        .map(|exe| exe.with_file_name(HELPER_BINARY_NAME))
+        .filter(|p| p.is_file())
+}
+
+/// Vendor-supplied polkit rules belong under /usr/share; /etc is the
+/// administrator's namespace. A package writing to /etc/polkit-1/rules.d shadows
+/// the admin's own rules and is never cleaned up on uninstall.
+pub const POLKIT_RULE_PATH: &str = "/etc/polkit-1/rules.d/50-thinkutils.rules";
+pub const POLKIT_RULE_PACKAGED_PATH: &str = "/usr/share/polkit-1/rules.d/50-thinkutils.rules";
+
+/// The same grant for polkit 0.105, which reads local-authority `.pkla` files
+/// instead of rules. 10-vendor.d is the packages' directory there; an
+/// administrator's files under /etc/polkit-1/localauthority are read after it.
+pub const POLKIT_PKLA_PATH: &str =
+    "/var/lib/polkit-1/localauthority/10-vendor.d/50-thinkutils.pkla";
+
+/// Where the action definitions go. polkit reads actions from /usr/share only,
+/// so a self-installed helper's policy lands here too.
+///
+/// Not `com.thinkutils.policy`: that name belongs to the legacy file that
+/// redefined the shared exec action, and scripts/uninstall-legacy-polkit.sh
+/// deletes it on sight.
+pub const POLKIT_POLICY_PATH: &str = "/usr/share/polkit-1/actions/com.thinkutils.helper.policy";
+
+/// The helper locations the packages install to, by the directory the files
+/// naming it are generated into (under packaging/polkit and packaging/dbus):
+/// Debian and Arch install the helper to `/usr/lib`, Fedora to `/usr/libexec`.
+pub const PACKAGED_POLICIES: [(&str, &str); 2] = [
+    ("lib", HELPER_CANDIDATES[0]),
+    ("libexec", HELPER_CANDIDATES[1]),
+];
+
+/// Where pkexec finds `freshclam` on every distribution the packages target.
+pub const FRESHCLAM_PATH: &str = "/usr/bin/freshclam";
+
+/// One thing an administrator can allow or deny on its own.
+///
+/// Running the helper through the generic `org.freedesktop.policykit.exec`
+/// action made it all or nothing: whoever could set the fan could also change
+/// the governor. pkexec picks a more specific action when one is annotated with
+/// the program's path and, for the helper, its first argument — which is why
+/// the operations are grouped under a verb per action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    FanControl,
+    /// Loading thinkpad_acpi with `fan_control=1`, now and at every boot.
+    FanModule,
+    BatteryThresholds,
+    /// Inhibiting charge or forcing a discharge.
+    ChargeBehaviour,
+    CpuTuning,
+    /// `pkexec freshclam` from the Security view. Installing ClamAV itself goes
+    /// through the package manager and keeps the generic exec action.
+    SecurityUpdates,
+}
+
+impl Action {
+    pub const ALL: [Action; 6] = [
+        Action::FanControl,
+        Action::FanModule,
+        Action::BatteryThresholds,
+        Action::ChargeBehaviour,
+        Action::CpuTuning,
+        Action::SecurityUpdates,
+    ];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            Action::FanControl => "com.thinkutils.fan-control",
+            Action::FanModule => "com.thinkutils.fan-module",
+            Action::BatteryThresholds => "com.thinkutils.battery-thresholds",
+            Action::ChargeBehaviour => "com.thinkutils.charge-behaviour",
+            Action::CpuTuning => "com.thinkutils.cpu-tuning",
+            Action::SecurityUpdates => "com.thinkutils.security-updates",
+        }
+    }
+
+    /// The helper's first argument for operations under this action, or `None`
+    /// for an action that is not the helper's.
+    pub fn verb(self) -> Option<&'static str> {
+        match self {
+            Action::FanControl => Some("fan"),
+            Action::FanModule => Some("fan-module"),
+            Action::BatteryThresholds => Some("battery-thresholds"),
+            Action::ChargeBehaviour => Some("charge-behaviour"),
+            Action::CpuTuning => Some("cpu"),
+            Action::SecurityUpdates => None,
+        }
+    }
+
+    /// Whether the shipped rule grants this without a password. Fan control
+    /// has to be: the curve runs every two seconds in the background. Thresholds
+    /// are set once and left. A timed charge behaviour is ended by the app
+    /// in the background, which must not stop to ask. Enabling the module, and
+    /// governor and ClamAV changes, still ask.
+    pub fn passwordless(self) -> bool {
+        matches!(
+            self,
+            Action::FanControl | Action::BatteryThresholds | Action::ChargeBehaviour
+        )
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Action::FanControl => "Control the ThinkPad fan",
+            Action::FanModule => "Enable fan control in the thinkpad_acpi module",
+            Action::BatteryThresholds => "Set battery charge thresholds",
+            Action::ChargeBehaviour => "Inhibit battery charging or force a discharge",
+            Action::CpuTuning => "Change the CPU governor, turbo boost or platform profile",
+            Action::SecurityUpdates => "Update the ClamAV virus definitions",
+        }
+    }
+
+    fn message(self) -> &'static str {
+        match self {
+            Action::FanControl => "Authentication is required to change the fan speed",
+            Action::FanModule => "Authentication is required to enable fan control",
+            Action::BatteryThresholds => {
+                "Authentication is required to change the battery charge thresholds"
+            }
+            Action::ChargeBehaviour => {
+                "Authentication is required to change how the battery charges"
+            }
+            Action::CpuTuning => "Authentication is required to change CPU performance settings",
+            Action::SecurityUpdates => "Authentication is required to update the virus definitions",
+        }
+    }
+}
+
+/// Build the polkit action definitions for a helper installed at `helper`.
+///
+/// One file per install location, because `exec.path` takes a single path: the
+/// packages generate theirs for the path they install to, and
+/// `setup_permissions()` for [`HELPER_SELF_INSTALL_PATH`].
+///
+/// Every action defaults to `auth_admin_keep`; [`polkit_rule`] is what makes
+/// some of them passwordless, and an administrator's rule can override that per
+/// action.
+pub fn polkit_policy(helper: &str) -> String {
+    let actions: String = Action::ALL
+        .iter()
+        .map(|action| {
+            let (path, argv1) = match action.verb() {
+                Some(verb) => (
+                    helper,
+                    format!(
+                        "\n    <annotate key=\"org.freedesktop.policykit.exec.argv1\">{}</annotate>",
+                        verb
+                    ),
+                ),
+                None => (FRESHCLAM_PATH, String::new()),
+            };
+            format!(
+                r#"
+  <action id="{id}">
+    <description>{description}</description>
+    <message>{message}</message>
+    <defaults>
+      <allow_any>no</allow_any>
+      <allow_inactive>no</allow_inactive>
+      <allow_active>auth_admin_keep</allow_active>
+    </defaults>
+    <annotate key="org.freedesktop.policykit.exec.path">{path}</annotate>{argv1}
+  </action>
+"#,
+                id = action.id(),
+                description = action.description(),
+                message = action.message(),
+                path = path,
+                argv1 = argv1,
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE policyconfig PUBLIC
+ "-//freedesktop//DTD PolicyKit Policy Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/PolicyKit/1/policyconfig.dtd">
+<!-- ThinkUtils: one action per kind of privileged change.
+     Generated from helper::Action -- do not edit by hand. -->
+<policyconfig>
+  <vendor>ThinkUtils</vendor>
+{}</policyconfig>
+"#,
+        actions
+    )
+}
+
+/// Build the polkit rule that makes the [`Action::passwordless`] actions
+/// passwordless, and nothing else.
+///
+/// Generated rather than written out, because an action ID duplicated by hand
+/// in the rule text grants nothing once it drifts, while looking correct.
+///
+/// `subject.local && subject.active` is deliberate: without it, any SSH session
+/// belonging to a wheel/sudo user gets passwordless root-adjacent exec, as does
+/// a backgrounded session the user has switched away from. Hardware control is
+/// a physical-console concern.
+pub fn polkit_rule() -> String {
+    let allowed = Action::ALL
+        .iter()
+        .filter(|a| a.passwordless())
+        .map(|a| format!("        action.id == \"{}\"", a.id()))
+        .collect::<Vec<_>>()
+        .join(" ||\n");
+
+    format!(
+        r#"/* ThinkUtils: passwordless access to the actions listed below, only.
+ *
+ * Generated from helper::Action -- do not edit by hand. Each kind of change
+ * the helper makes is its own polkit action (com.thinkutils.helper.policy);
+ * every action not listed here keeps the policy's auth_admin_keep. To grant
+ * or withhold a different set, return a result for those action IDs from a
+ * lower-numbered rule in /etc/polkit-1/rules.d.
+ */
+polkit.addRule(function(action, subject) {{
+    if (
+{}
+    ) {{
+        /* Local, active sessions only: an SSH session must not inherit
+           passwordless hardware control. */
+        if (subject.local && subject.active &&
+            (subject.isInGroup("wheel") || subject.isInGroup("sudo"))) {{
+            return polkit.Result.YES;
+        }}
+    }}
+}});
+"#,
+        allowed
+    )
+}
+
+/// Build the local-authority grant that does [`polkit_rule`]'s job on polkit
+/// 0.105, still Ubuntu 22.04's, which ignores JavaScript rules: the
+/// [`Action::passwordless`] actions, to active sessions of wheel/sudo members.
+///
+/// Only on top of the policy's `auth_admin_keep`, never in place of it -- a
+/// `yes` default would hand every local user root writes to the fan and the
+/// battery wherever this file is not read. polkit 0.106 and later ignore it.
+pub fn polkit_pkla() -> String {
+    let actions = Action::ALL
+        .iter()
+        .filter(|a| a.passwordless())
+        .map(|a| a.id())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    format!(
+        r#"# ThinkUtils: passwordless access to the actions listed below, for
+# wheel/sudo members, on polkit 0.105. Later versions read
+# 50-thinkutils.rules instead and ignore this file.
+#
+# Generated from helper::Action -- do not edit by hand. To grant or withhold
+# a different set, add a file under /etc/polkit-1/localauthority/50-local.d,
+# which is read after this one.
+[ThinkUtils passwordless hardware control]
+Identity=unix-group:sudo;unix-group:wheel
+Action={}
+ResultAny=no
+ResultInactive=no
+ResultActive=yes
+"#,
+        actions
+    )
+}
+
+/// One hardware write the helper performs as root.
+///
+/// On the command line each is a verb and its arguments:
+///
+/// ```text
+/// fan "level 3"                       fan "fan2 level auto"    fan "watchdog 30"
+/// fan-module
+/// battery-thresholds 40 80            battery-thresholds 40 80 BAT1
+/// charge-behaviour inhibit-charge     charge-behaviour auto BAT1
+/// cpu governor powersave              cpu turbo on
+/// cpu platform-profile low-power
+/// ```
+///
+/// The first argument is the [`Action::verb`] polkit authorises it under.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    /// A `/proc/acpi/ibm/fan` command.
+    Fan(FanCommand),
+    /// Write [`crate::fan_control::MODPROBE_CONF_PATH`] and reload thinkpad_acpi.
+    FanModule,
+    /// Both charge thresholds, written in the order the firmware accepts, on
+    /// the named battery or on every battery that has them.
+    BatteryThresholds {
+        start: u8,
+        stop: u8,
+        battery: Option<String>,
+    },
+    /// A charge behaviour, on the named battery or on every battery that has
+    /// the control.
+    ChargeBehaviour {
+        behaviour: ChargeBehaviour,
+        battery: Option<String>,
+    },
+    /// A scaling governor for every online CPU.
+    Governor(String),
+    /// Turbo boost on or off, through whichever switch the CPU driver has.
+    Turbo(bool),
+    /// An ACPI platform profile, one of `platform_profile_choices`.
+    PlatformProfile(String),
+}
+
+impl Operation {
+    /// Parse the helper's arguments. Syntax only: [`Operation::validate`] checks
+    /// the values against the machine.
+    ///
+    /// Numbers must be written the way [`Operation::args`] writes them, so
+    /// `+40` or `040` is refused rather than normalised.
+    pub fn parse(args: &[String]) -> Result<Operation, String> {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        match args.as_slice() {
+            ["fan", command] => command.parse().map(Operation::Fan),
+            ["fan-module"] => Ok(Operation::FanModule),
+            ["battery-thresholds", start, stop] => Ok(Operation::BatteryThresholds {
+                start: percentage(start)?,
+                stop: percentage(stop)?,
+                battery: None,
+            }),
+            ["battery-thresholds", start, stop, battery] => Ok(Operation::BatteryThresholds {
+                start: percentage(start)?,
+                stop: percentage(stop)?,
+                battery: Some(battery.to_string()),
+            }),
+            ["charge-behaviour", behaviour] => Ok(Operation::ChargeBehaviour {
+                behaviour: behaviour.parse()?,
+                battery: None,
+            }),
+            ["charge-behaviour", behaviour, battery] => Ok(Operation::ChargeBehaviour {
+                behaviour: behaviour.parse()?,
+                battery: Some(battery.to_string()),
+            }),
+            ["cpu", "governor", name] => Ok(Operation::Governor(name.to_string())),
+            ["cpu", "turbo", "on"] => Ok(Operation::Turbo(true)),
+            ["cpu", "turbo", "off"] => Ok(Operation::Turbo(false)),
+            ["cpu", "platform-profile", name] => Ok(Operation::PlatformProfile(name.to_string())),
+            _ => Err(format!("Invalid command: {:?}", args.join(" "))),
+        }
+    }
+
+    /// The polkit action this operation is authorised under.
+    pub fn action(&self) -> Action {
+        match self {
+            Operation::Fan(_) => Action::FanControl,
+            Operation::FanModule => Action::FanModule,
+            Operation::BatteryThresholds { .. } => Action::BatteryThresholds,
+            Operation::ChargeBehaviour { .. } => Action::ChargeBehaviour,
+            Operation::Governor(_) | Operation::Turbo(_) | Operation::PlatformProfile(_) => {
+                Action::CpuTuning
+            }
+        }
+    }
+
+    /// The arguments that [`Operation::parse`] reads back as this operation.
+    pub fn args(&self) -> Vec<String> {
+        let rest: Vec<String> = match self {
+            Operation::Fan(command) => vec![command.to_string()],
+            Operation::FanModule => Vec::new(),
+            Operation::BatteryThresholds {
+                start,
+                stop,
+                battery,
+            } => [start.to_string(), stop.to_string()]
+                .into_iter()
+                .chain(battery.clone())
+                .collect(),
+            Operation::ChargeBehaviour { behaviour, battery } => {
+                std::iter::once(behaviour.to_string())
+                    .chain(battery.clone())
+                    .collect()
+            }
+            Operation::Governor(name) => vec!["governor".into(), name.clone()],
+            Operation::Turbo(enabled) => {
+                vec!["turbo".into(), if *enabled { "on" } else { "off" }.into()]
+            }
+            Operation::PlatformProfile(name) => vec!["platform-profile".into(), name.clone()],
+        };
+        let verb = self
+            .action()
+            .verb()
+            .expect("every operation is the helper's");
+        std::iter::once(verb.to_string()).chain(rest).collect()
+    }
+
+    /// The checks the app makes before asking, made again on the root side.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            // Parsing a FanCommand is its whitelist.
+            Operation::Fan(_) => Ok(()),
+            // Takes no arguments, and writes one fixed line.
+            Operation::FanModule => Ok(()),
+            Operation::BatteryThresholds {
+                start,
+                stop,
+                battery,
+            } => crate::battery::validate_thresholds(*start, *stop).and_then(|()| {
+                battery
+                    .as_deref()
+                    .map_or(Ok(()), crate::battery::validate_battery)
+            }),
+            Operation::ChargeBehaviour { behaviour, battery } => {
+                crate::battery::validate_charge_behaviour(*behaviour, battery.as_deref())
+            }
+            Operation::Governor(name) => crate::performance::validate_governor(name),
+            Operation::Turbo(_) => Ok(()),
+            Operation::PlatformProfile(name) => crate::performance::validate_platform_profile(name),
+        }
+    }
+
+    /// Perform the write. Every path is fixed by the operation; nothing in the
+    /// arguments names a file.
+    pub fn apply(&self) -> Result<String, String> {
+        match self {
+            Operation::Fan(command) => crate::hardware_root::write(PROC_FAN, &command.to_string())
+                .map(|()| format!("Fan: {}", command))
+                .map_err(|e| format!("Could not write {}: {}", PROC_FAN, e)),
+            Operation::FanModule => crate::fan_control::write_module_options(),
+            Operation::BatteryThresholds {
+                start,
+                stop,
+                battery,
+            } => crate::battery::write_thresholds(*start, *stop, battery.as_deref())
+                .map(|()| format!("Thresholds set: {}%-{}%", start, stop)),
+            Operation::ChargeBehaviour { behaviour, battery } => {
+                crate::battery::write_charge_behaviour(*behaviour, battery.as_deref())
+                    .map(|()| format!("Charge behaviour set to {}", behaviour))
+            }
+            Operation::Governor(name) => crate::performance::apply_governor(name)
+                .map(|n| format!("Governor {} applied to {} CPU(s)", name, n)),
+            Operation::Turbo(enabled) => crate::performance::write_turbo(*enabled).map(|()| {
+                format!(
+                    "Turbo boost {}",
+                    if *enabled { "enabled" } else { "disabled" }
+                )
+            }),
+            Operation::PlatformProfile(name) => crate::performance::write_platform_profile(name)
+                .map(|()| format!("Platform profile set to: {}", name)),
+        }
+    }
+}
+
+/// A threshold argument, in canonical form only.
+fn percentage(s: &str) -> Result<u8, String> {
+    s.parse::<u8>()
+        .ok()
+        .filter(|n| n.to_string() == s)
+        .ok_or_else(|| format!("Invalid percentage: {:?}", s))
+}
+
+/// Exit status for arguments that are not an operation, or fail validation.
+pub const EXIT_INVALID: i32 = 2;
+/// Exit status for an operation the hardware refused.
+pub const EXIT_FAILED: i32 = 1;
+
+/// The helper binary's entry point. Returns the process exit status.
+///
+/// pkexec runs this with a scrubbed environment, so `THINKUTILS_HARDWARE_ROOT`
+/// never reaches it there; run directly under a simulated profile it writes to
+/// the profile, which is how the tests drive it. `--dbus` serves the same
+/// operations on the bus instead; see `hardware_service`.
+pub fn main(args: Vec<String>) -> i32 {
+    if args == ["--dbus"] {
+        return crate::hardware_service::main();
+    }
+    let operation = match Operation::parse(&args).and_then(|op| op.validate().map(|()| op)) {
+        Ok(op) => op,
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_INVALID;
+        }
+    };
+    match operation.apply() {
+        Ok(message) => {
+            println!("{}", message);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            EXIT_FAILED
+        }
+    }
+}
+
+/// What the user is told when no helper is installed to escalate through.
+const NOT_INSTALLED: &str =
+    "No permission, and the privileged helper is not installed. Click 'Grant Permissions' to set it up.";
+
+/// What the user is told when polkit refuses, by pkexec or on the bus.
+pub(crate) const REFUSED: &str =
+    "Permission denied. Click 'Grant Permissions' to enable passwordless hardware control.";
+
+/// Run one operation as root through the installed helper.
+///
+/// Succeeds with the helper's report, or fails with a message fit to show the
+/// user as-is.
+pub async fn run(operation: &Operation) -> Result<String, Error> {
+    if let Some(result) = crate::hardware_service::call(operation).await {
+        return result;
+    }
+    let helper = helper_path().ok_or_else(|| Error::permission_denied(NOT_INSTALLED))?;
+    let output = tokio::process::Command::new("pkexec")
+        .arg(helper)
+        .args(operation.args())
+        .output()
+        .await
+        .map_err(|e| format!("Failed to execute helper: {}", e))?;
+    outcome(output)
+}
+
+/// [`run`], for the few callers that cannot await: the exit handler and the
+/// MCP tools.
+pub fn run_blocking(operation: &Operation) -> Result<String, Error> {
+    if let Some(result) = crate::hardware_service::call_blocking(operation) {
+        return result;
+    }
+    let helper = helper_path().ok_or_else(|| Error::permission_denied(NOT_INSTALLED))?;
+    let output = std::process::Command::new("pkexec")
+        .arg(helper)
+        .args(operation.args())
+        .output()
+        .map_err(|e| format!("Failed to execute helper: {}", e))?;
+    outcome(output)
+}
+
+/// pkexec exits 126 when the authentication dialog is dismissed and 127 when
+/// authorisation is refused; anything else came from the helper itself.
+fn outcome(output: Output) -> Result<String, Error> {
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    let code = match output.status.code() {
+        Some(0) => return Ok(stdout),
+        Some(126) | Some(127) => return Err(Error::permission_denied(REFUSED)),
+        Some(EXIT_INVALID) => ErrorCode::Validation,
+        _ => ErrorCode::Failed,
+    };
+    if stderr.is_empty() {
+        Err(Error::new(
+            code,
+            "The privileged helper failed without saying why",
+        ))
+    } else {
+        Err(Error::new(code, stderr))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fan_control::FanLevel;
+    use crate::hardware_root::testing::{with_profile, with_scratch_profile};
+
+    fn args(line: &[&str]) -> Vec<String> {
+        line.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn every_operation_parses_back_from_its_args() {
+        for op in [
+            Operation::Fan(FanCommand::Level {
+                level: FanLevel::Manual(3),
+                fan: None,
+            }),
+            Operation::Fan(FanCommand::Level {
+                level: FanLevel::Auto,
+                fan: Some(2),
+            }),
+            Operation::Fan(FanCommand::Watchdog),
+            Operation::FanModule,
+            Operation::BatteryThresholds {
+                start: 40,
+                stop: 80,
+                battery: None,
+            },
+            Operation::BatteryThresholds {
+                start: 40,
+                stop: 80,
+                battery: Some("BAT1".into()),
+            },
+            Operation::ChargeBehaviour {
+                behaviour: ChargeBehaviour::InhibitCharge,
+                battery: None,
+            },
+            Operation::ChargeBehaviour {
+                behaviour: ChargeBehaviour::Auto,
+                battery: Some("BAT1".into()),
+            },
+            Operation::Governor("powersave".into()),
+            Operation::Turbo(true),
+            Operation::Turbo(false),
+            Operation::PlatformProfile("low-power".into()),
+        ] {
+            assert_eq!(Operation::parse(&op.args()), Ok(op.clone()));
+            // pkexec matches the action on argv[1], so that has to be the verb.
+            assert_eq!(Some(op.args()[0].as_str()), op.action().verb());
+        }
+    }
+
+    /// The helper is the security boundary. Anything that is not exactly an
+    /// operation is refused before any code that touches a file runs.
+    #[test]
+    fn malformed_arguments_are_refused() {
+        for line in [
+            &[][..],
+            &["fan"],
+            &["fan", "level 3", "extra"],
+            &["fan", "level auto; rm -rf /"],
+            &["fan", "watchdog 0"],
+            &["fan", "enable"],
+            &["fan-module", "fan_control=0"],
+            &["fan-module", "/etc/modprobe.d/evil.conf"],
+            &["battery-thresholds", "40"],
+            &["battery-thresholds", "+40", "80"],
+            &["battery-thresholds", "040", "80"],
+            &["battery-thresholds", "40", "800"],
+            &["battery-thresholds", "40", "80 "],
+            &["battery-thresholds", "40", "80", "BAT0", "BAT1"],
+            &["charge-behaviour"],
+            &["charge-behaviour", "[auto]"],
+            &["charge-behaviour", "inhibit"],
+            &["charge-behaviour", "auto", "BAT0", "BAT1"],
+            &["cpu", "turbo", "yes"],
+            &["cpu", "turbo", "1"],
+            &["cpu", "governor"],
+            &["governor", "powersave"],
+            &["turbo", "on"],
+            &["bash", "-c", "id"],
+            &["write", "/etc/shadow", "x"],
+        ] {
+            assert!(
+                Operation::parse(&args(line)).is_err(),
+                "{:?} must be refused",
+                line
+            );
+        }
+    }
+
+    /// Syntax is not enough: the values go through the library's checks again,
+    /// against what this machine offers.
+    #[test]
+    fn validation_uses_the_machines_lists() {
+        with_profile("thinkpad-p1-gen-4i", || {
+            let check = |line: &[&str]| Operation::parse(&args(line)).and_then(|op| op.validate());
+            assert!(check(&["cpu", "governor", "performance"]).is_ok());
+            assert!(check(&["cpu", "governor", "schedutil"]).is_err());
+            assert!(check(&["cpu", "governor", "$(id)"]).is_err());
+            assert!(check(&["battery-thresholds", "80", "40"]).is_err());
+            assert!(check(&["battery-thresholds", "40", "80", "BAT0"]).is_ok());
+            // A name is looked up, never joined onto a path.
+            assert!(check(&["battery-thresholds", "40", "80", "BAT1"]).is_err());
+            assert!(check(&["battery-thresholds", "40", "80", "../../../etc"]).is_err());
+            assert!(check(&["battery-thresholds", "40", "80", "AC"]).is_err());
+            assert!(check(&["charge-behaviour", "force-discharge", "BAT0"]).is_ok());
+            assert!(check(&["charge-behaviour", "auto", "../BAT0"]).is_err());
+            assert!(check(&["cpu", "platform-profile", "low-power"]).is_ok());
+            assert!(check(&["cpu", "platform-profile", "quiet"]).is_err());
+        });
+    }
+
+    #[test]
+    fn the_helper_exits_with_distinct_statuses() {
+        with_scratch_profile("thinkpad-p1-gen-4i", |_| {
+            assert_eq!(main(args(&["cpu", "turbo", "maybe"])), EXIT_INVALID);
+            assert_eq!(main(args(&["fan", "level 3"])), 0);
+            // The P1 does not advertise a fan selector, so the kernel refuses it.
+            assert_eq!(main(args(&["fan", "fan2 level 3"])), EXIT_FAILED);
+        });
+    }
+
+    #[test]
+    fn each_operation_writes_only_its_own_files() {
+        with_scratch_profile("thinkpad-p1-gen-4i", |root| {
+            let read = |rel: &str| std::fs::read_to_string(root.join(rel)).unwrap();
+
+            Operation::BatteryThresholds {
+                start: 50,
+                stop: 60,
+                battery: None,
+            }
+            .apply()
+            .unwrap();
+            let thresholds = crate::battery::get_battery_thresholds().data.unwrap();
+            assert_eq!((thresholds.start, thresholds.stop), (50, 60));
+
+            Operation::ChargeBehaviour {
+                behaviour: ChargeBehaviour::InhibitCharge,
+                battery: Some("BAT0".into()),
+            }
+            .apply()
+            .unwrap();
+            assert_eq!(
+                read("sys/class/power_supply/BAT0/charge_behaviour").trim(),
+                "auto [inhibit-charge] force-discharge"
+            );
+
+            Operation::Governor("performance".into()).apply().unwrap();
+            assert_eq!(
+                read("sys/devices/system/cpu/cpu0/cpufreq/scaling_governor").trim(),
+                "performance"
+            );
+
+            Operation::Turbo(false).apply().unwrap();
+            assert_eq!(
+                read("sys/devices/system/cpu/intel_pstate/no_turbo").trim(),
+                "1"
+            );
+
+            Operation::PlatformProfile("performance".into())
+                .apply()
+                .unwrap();
+            assert_eq!(
+                read("sys/firmware/acpi/platform_profile").trim(),
+                "performance"
+            );
+        });
+    }
+
+    /// A profile has no module to reload and no /etc, so the module operation
+    /// refuses rather than writing anywhere.
+    #[test]
+    fn the_module_operation_never_writes_under_a_profile() {
+        with_scratch_profile("thinkpad-p1-gen-4i", |root| {
+            assert!(Operation::FanModule.apply().is_err());
+            assert!(!root.join("etc/modprobe.d").exists());
+        });
+    }
+
+    /// The polkit rule grants passwordless root. It must name the passwordless
+    /// actions and nothing else -- granting the generic exec action, or a
+    /// program by path, would hand over every operation again.
+    #[test]
+    fn polkit_rule_grants_only_the_passwordless_actions() {
+        let rule = polkit_rule();
+        for action in Action::ALL {
+            assert_eq!(
+                rule.contains(action.id()),
+                action.passwordless(),
+                "{} in the rule",
+                action.id()
+            );
+        }
+        assert!(Action::FanControl.passwordless());
+        assert!(Action::ChargeBehaviour.passwordless());
+        assert!(!Action::FanModule.passwordless());
+        assert!(!Action::CpuTuning.passwordless());
+        assert!(!Action::SecurityUpdates.passwordless());
+        for forbidden in [
+            "org.freedesktop.policykit.exec",
+            "program",
+            "/bin/bash",
+            LEGACY_SCRIPT_HELPER_PATH,
+        ] {
+            assert!(
+                !rule.contains(forbidden),
+                "polkit rule must not grant {}",
+                forbidden
+            );
+        }
+    }
+
+    /// Each helper action is tied to the helper's path and its verb, so pkexec
+    /// picks it over the generic exec action; none is granted by default.
+    #[test]
+    fn the_policy_maps_each_verb_to_its_own_action() {
+        let helper = HELPER_CANDIDATES[1];
+        let policy = polkit_policy(helper);
+        for action in Action::ALL {
+            let start = policy
+                .find(&format!("<action id=\"{}\">", action.id()))
+                .unwrap_or_else(|| panic!("{} is not defined", action.id()));
+            let block = &policy[start..start + policy[start..].find("</action>").unwrap()];
+            let path = action.verb().map_or(FRESHCLAM_PATH, |_| helper);
+            assert!(block.contains(&format!(
+                "<annotate key=\"org.freedesktop.policykit.exec.path\">{}</annotate>",
+                path
+            )));
+            match action.verb() {
+                Some(verb) => assert!(block.contains(&format!(
+                    "<annotate key=\"org.freedesktop.policykit.exec.argv1\">{}</annotate>",
+                    verb
+                ))),
+                None => assert!(!block.contains("exec.argv1")),
+            }
+            assert!(block.contains("<allow_active>auth_admin_keep</allow_active>"));
+        }
+        assert!(!policy.contains(">yes<"));
+        assert!(!policy.contains("org.freedesktop.policykit.exec\""));
+    }
+
+    /// The defaults are what every local user gets wherever neither the rule
+    /// nor the .pkla is read, so they never grant outright.
+    #[test]
+    fn the_policy_never_grants_without_authentication() {
+        for helper in HELPER_CANDIDATES
+            .iter()
+            .copied()
+            .chain([HELPER_SELF_INSTALL_PATH])
+        {
+            let policy = polkit_policy(helper);
+            assert_eq!(
+                policy
+                    .matches("<allow_active>auth_admin_keep</allow_active>")
+                    .count(),
+                Action::ALL.len()
+            );
+            assert!(!policy.contains("<allow_active>yes</allow_active>"));
+            assert!(!policy.contains(">yes<"));
+        }
+    }
+
+    /// On polkit 0.105 the .pkla stands in for the rule: the same actions, to
+    /// the same groups, and only in an active session.
+    #[test]
+    fn the_pkla_grants_the_passwordless_actions_to_administrators() {
+        let pkla = polkit_pkla();
+        let value = |key: &str| {
+            pkla.lines()
+                .find_map(|line| line.strip_prefix(&format!("{}=", key)))
+                .unwrap_or_else(|| panic!("no {}", key))
+        };
+        let granted: Vec<&str> = value("Action").split(';').collect();
+        let passwordless: Vec<&str> = Action::ALL
+            .iter()
+            .filter(|a| a.passwordless())
+            .map(|a| a.id())
+            .collect();
+        assert_eq!(granted, passwordless);
+        assert_eq!(value("Identity"), "unix-group:sudo;unix-group:wheel");
+        assert_eq!(value("ResultActive"), "yes");
+        assert_eq!(value("ResultInactive"), "no");
+        assert_eq!(value("ResultAny"), "no");
+    }
+
+    /// Without local+active, any SSH session belonging to a wheel/sudo user
+    /// inherits passwordless hardware control, as does a background session the
+    /// user has switched away from.
+    #[test]
+    fn polkit_rule_requires_a_local_active_session() {
+        let rule = polkit_rule();
+        assert!(
+            rule.contains("subject.local"),
+            "rule must require a local session"
+        );
+        assert!(
+            rule.contains("subject.active"),
+            "rule must require an active session"
+        );
+    }
+}
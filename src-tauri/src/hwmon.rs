@@ -0,0 +1,251 @@
+//! Every hwmon sensor on the machine, read straight from sysfs.
+//!
+//! lm-sensors reads these same files; `sensors` only formats them for people.
+//! Parsing that output meant one chip per call, numbers in whatever format the
+//! locale chose, and no readings at all on a machine without the package. The
+//! kernel's hwmon ABI is stable and documented, so it is read directly:
+//! `name` names the chip, and each `tempN_*` or `fanN_*` group is one sensor.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Where every registered hwmon device is linked, whatever its driver.
+pub const HWMON_CLASS_DIR: &str = "/sys/class/hwmon";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SensorKind {
+    Temperature,
+    Fan,
+}
+
+impl SensorKind {
+    /// The attribute prefix hwmon uses for this kind.
+    fn prefix(self) -> &'static str {
+        match self {
+            SensorKind::Temperature => "temp",
+            SensorKind::Fan => "fan",
+        }
+    }
+
+    /// The unit values are reported in, after conversion.
+    pub fn unit(self) -> Unit {
+        match self {
+            SensorKind::Temperature => Unit::Celsius,
+            SensorKind::Fan => Unit::Rpm,
+        }
+    }
+
+    /// hwmon stores temperatures in millidegrees and fan speeds in RPM.
+    fn scale(self) -> f64 {
+        match self {
+            SensorKind::Temperature => 1000.0,
+            SensorKind::Fan => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    Celsius,
+    Rpm,
+}
+
+/// One hwmon input and the limits the driver publishes for it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SensorReading {
+    /// The chip's `name`: `thinkpad`, `coretemp`, `nvme`, `acpitz`...
+    pub chip: String,
+    /// The input's `_label`, or its attribute stem (`temp3`) when the driver
+    /// gives none.
+    pub label: String,
+    pub kind: SensorKind,
+    pub unit: Unit,
+    /// None when the input exists but could not be read, as happens with a
+    /// powered-down GPU.
+    pub value: Option<f64>,
+    /// The driver's warning limit (`_max`), in the same unit.
+    pub max: Option<f64>,
+    /// The driver's critical limit (`_crit`). Temperatures only.
+    pub crit: Option<f64>,
+    /// 1-based, from the attribute name. Orders `temp10` after `temp9`.
+    #[serde(skip)]
+    index: u32,
+    #[serde(skip)]
+    input: PathBuf,
+}
+
+impl SensorReading {
+    /// Read the input again, for callers that keep an inventory across polls.
+    pub fn read(&self) -> Option<f64> {
+        read_scaled(&self.input, self.kind)
+    }
+
+    /// The resolved `_input` file this reading came from.
+    pub fn input_path(&self) -> &Path {
+        &self.input
+    }
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_string())
+}
+
+fn read_scaled(path: &Path, kind: SensorKind) -> Option<f64> {
+    read_trimmed(path)?
+        .parse::<i64>()
+        .ok()
+        .map(|raw| raw as f64 / kind.scale())
+}
+
+/// The inputs of one kind on one chip directory, e.g. every `tempN_input`.
+fn chip_inputs(chip_dir: &Path, chip: &str, kind: SensorKind) -> Vec<SensorReading> {
+    let mut readings = Vec::new();
+    for file in std::fs::read_dir(chip_dir).into_iter().flatten().flatten() {
+        let file_name = file.file_name().to_string_lossy().to_string();
+        let Some(index) = file_name
+            .strip_prefix(kind.prefix())
+            .and_then(|rest| rest.strip_suffix("_input"))
+            .and_then(|n| n.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        let stem = format!("{}{}", kind.prefix(), index);
+        let limit =
+            |suffix: &str| read_scaled(&chip_dir.join(format!("{}_{}", stem, suffix)), kind);
+
+        readings.push(SensorReading {
+            chip: chip.to_string(),
+            label: read_trimmed(&chip_dir.join(format!("{}_label", stem))).unwrap_or(stem.clone()),
+            kind,
+            unit: kind.unit(),
+            value: read_scaled(&file.path(), kind),
+            max: limit("max"),
+            crit: match kind {
+                SensorKind::Temperature => limit("crit"),
+                SensorKind::Fan => None,
+            },
+            index,
+            input: file.path(),
+        });
+    }
+    readings
+}
+
+/// Every temperature and fan input under a hwmon class directory.
+///
+/// Ordered by chip, then kind, then index, so the list is stable between polls
+/// even though the `hwmonN` numbering is not stable between boots. A directory
+/// without a `name` is not a hwmon device and is skipped.
+pub fn inventory_in(hwmon_dir: &Path) -> Vec<SensorReading> {
+    let mut readings = Vec::new();
+    for entry in std::fs::read_dir(hwmon_dir).into_iter().flatten().flatten() {
+        let chip_dir = entry.path();
+        let Some(chip) = read_trimmed(&chip_dir.join("name")) else {
+            continue;
+        };
+        for kind in [SensorKind::Temperature, SensorKind::Fan] {
+            readings.extend(chip_inputs(&chip_dir, &chip, kind));
+        }
+    }
+
+    readings.sort_by(|a, b| {
+        (&a.chip, a.kind, &a.input.parent(), a.index).cmp(&(
+            &b.chip,
+            b.kind,
+            &b.input.parent(),
+            b.index,
+        ))
+    });
+    readings
+}
+
+/// The sensors on this machine, read through the hardware root.
+pub fn inventory() -> Vec<SensorReading> {
+    inventory_in(&crate::hardware_root::resolve(HWMON_CLASS_DIR))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware_root::testing::with_profile;
+
+    const P1: &str = "thinkpad-p1-gen-4i";
+
+    fn find<'a>(readings: &'a [SensorReading], chip: &str, label: &str) -> &'a SensorReading {
+        readings
+            .iter()
+            .find(|r| r.chip == chip && r.label == label)
+            .unwrap_or_else(|| panic!("no {} {} in {:?}", chip, label, readings))
+    }
+
+    #[test]
+    fn reads_every_chip_in_the_profile() {
+        let readings = with_profile(P1, inventory);
+        let mut chips: Vec<&str> = readings.iter().map(|r| r.chip.as_str()).collect();
+        chips.dedup();
+        for chip in ["acpitz", "coretemp", "iwlwifi_1", "nvme", "thinkpad"] {
+            assert!(chips.contains(&chip), "{} missing from {:?}", chip, chips);
+        }
+    }
+
+    #[test]
+    fn converts_units_and_reads_limits() {
+        let readings = with_profile(P1, inventory);
+
+        let package = find(&readings, "coretemp", "Package id 0");
+        assert_eq!(package.kind, SensorKind::Temperature);
+        assert_eq!(package.unit, Unit::Celsius);
+        assert_eq!(package.value, Some(59.0));
+        assert!(package.crit.is_some_and(|c| c > 59.0));
+
+        let nvme = find(&readings, "nvme", "Composite");
+        assert_eq!(nvme.value, Some(45.85));
+        assert_eq!((nvme.max, nvme.crit), (Some(81.85), Some(84.85)));
+
+        let fan2 = find(&readings, "thinkpad", "fan2");
+        assert_eq!((fan2.kind, fan2.unit), (SensorKind::Fan, Unit::Rpm));
+        assert_eq!(fan2.value, Some(2228.0));
+        assert_eq!(fan2.crit, None);
+    }
+
+    /// thinkpad_acpi labels only its first two temperatures; the rest must
+    /// still appear, under the name `sensors` would give them.
+    #[test]
+    fn unlabelled_inputs_use_the_attribute_name() {
+        let readings = with_profile(P1, inventory);
+        assert_eq!(find(&readings, "thinkpad", "CPU").value, Some(52.0));
+        assert_eq!(find(&readings, "thinkpad", "temp3").value, Some(51.0));
+    }
+
+    /// The P1 was captured with its dGPU asleep: `temp2_label` says GPU but
+    /// there is no `temp2_input`, so there is no reading to list.
+    #[test]
+    fn order_is_by_chip_kind_and_numeric_index() {
+        let readings = with_profile(P1, inventory);
+        let thinkpad: Vec<&str> = readings
+            .iter()
+            .filter(|r| r.chip == "thinkpad")
+            .map(|r| r.label.as_str())
+            .collect();
+        assert_eq!(
+            thinkpad,
+            ["CPU", "temp3", "temp4", "temp5", "temp6", "temp7", "fan1", "fan2"]
+        );
+
+        let coretemp: Vec<u32> = readings
+            .iter()
+            .filter(|r| r.chip == "coretemp")
+            .map(|r| r.index)
+            .collect();
+        assert!(coretemp.windows(2).all(|w| w[0] < w[1]), "{:?}", coretemp);
+    }
+
+    #[test]
+    fn a_missing_class_directory_is_an_empty_inventory() {
+        assert!(inventory_in(Path::new("/nonexistent/hwmon")).is_empty());
+    }
+}
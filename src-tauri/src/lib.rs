@@ -1,12 +1,19 @@
 mod auth;
-mod battery;
+pub mod battery;
+pub mod battery_history;
+pub mod cli;
+pub mod daemon;
 pub mod environment;
+pub mod error;
 pub mod fan_control;
-mod fan_curve;
+pub mod fan_curve;
 pub mod hardware_root;
+pub mod hardware_service;
+pub mod helper;
+pub mod hwmon;
 mod mcp;
 mod monitor;
-mod performance;
+pub mod performance;
 mod permissions;
 mod privileged;
 mod security;
@@ -17,7 +24,7 @@ mod system_info;
 use tauri::{
     menu::{Menu, MenuItem},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    AppHandle, Manager,
+    AppHandle, Emitter, Manager,
 };
 
 #[tauri::command]
@@ -150,18 +157,21 @@ pub fn run() {
             // container every one is absent, and that is a legitimate supported
             // state -- not a failure. Printing it is what lets a test tell
             // "started fine, no ThinkPad here" from "broken", which are otherwise
-            // indistinguishable from the outside.
+            // indistinguishable from the outside. Read through the hardware root, so
+            // the same check reports a captured profile's shape when one is set.
             let present = |p: &str| {
-                if std::path::Path::new(p).exists() {
+                if hardware_root::exists(p) {
                     "present"
                 } else {
                     "absent"
                 }
             };
             let ibm_fan = present("/proc/acpi/ibm/fan");
-            let bat0 = present("/sys/class/power_supply/BAT0");
+            let batteries = battery::battery_dirs().len();
             let cpufreq = present("/sys/devices/system/cpu/cpu0/cpufreq");
-            println!("[thinkutils] hw probe: ibm_fan={ibm_fan} bat0={bat0} cpufreq={cpufreq}");
+            println!(
+                "[thinkutils] hw probe: ibm_fan={ibm_fan} batteries={batteries} cpufreq={cpufreq}"
+            );
 
             // Only the thinkpad_acpi fan interface distinguishes a supported
             // machine. A battery and cpufreq exist on every Linux laptop, and a
@@ -178,7 +188,24 @@ pub fn run() {
             );
 
             // Load fan curve config from persistent storage
-            let saved_config = fan_curve::load_config_from_store(app.handle());
+            let mut saved_config = fan_curve::load_config_from_store(app.handle());
+
+            // With thinkutils-daemon running, the daemon steers the fan and its
+            // config is the one in force; this process only edits it.
+            let daemon_socket = daemon::socket_path();
+            let daemon_attached = daemon::attach(&daemon_socket);
+            if daemon_attached {
+                println!(
+                    "[thinkutils] fan curve: thinkutils-daemon at {}",
+                    daemon_socket.display()
+                );
+                match daemon::request(&daemon_socket, &daemon::Request::GetFanCurve).and_then(|v| {
+                    serde_json::from_value(v).map_err(|e| error::Error::failed(e.to_string()))
+                }) {
+                    Ok(config) => saved_config = config,
+                    Err(e) => eprintln!("[thinkutils] Failed to read the daemon's curve: {}", e),
+                }
+            }
             let fan_curve_state =
                 fan_curve::FanCurveState::new(std::sync::Mutex::new(saved_config));
             app.manage(fan_curve_state);
@@ -188,11 +215,39 @@ pub fn run() {
                 mcp::McpState::new(tokio::sync::Mutex::new(mcp::McpServerState::default()));
             app.manage(mcp_state);
 
-            // Start fan curve background task
+            // Start fan curve background task, or relay the daemon's events to
+            // the window in its place.
             let app_handle = app.handle().clone();
-            tauri::async_runtime::spawn(async move {
-                fan_curve::fan_curve_background_task(app_handle).await;
-            });
+            if daemon_attached {
+                std::thread::spawn(move || {
+                    let result = daemon::subscribe(&daemon_socket, |event| {
+                        let _ = app_handle.emit_to("main", &event.event, event.payload);
+                    });
+                    if let Err(e) = result {
+                        eprintln!("[thinkutils] Lost the daemon's event stream: {}", e);
+                        let _ = app_handle.emit_to(
+                            "main",
+                            "fan-curve-error",
+                            serde_json::json!({
+                                "error": format!("Lost connection to thinkutils-daemon: {}", e),
+                            }),
+                        );
+                    }
+                });
+            } else {
+                tauri::async_runtime::spawn(async move {
+                    fan_curve::fan_curve_background_task(app_handle).await;
+                });
+            }
+            // A calibration a crash or a reboot interrupted is still holding the
+            // battery's thresholds: carry it on, or put them back.
+            battery::resume_battery_calibration(app.handle().clone());
+            // Likewise a timed inhibit or discharge that never got its revert.
+            battery::resume_timed_charge_behaviour();
+
+            // Sample battery wear in the background, for the health history.
+            tauri::async_runtime::spawn(battery_history::record_health_history());
+
             // Create tray menu
             let show = MenuItem::with_id(app, "show", "Show Window", true, None::<&str>)?;
             let hide = MenuItem::with_id(app, "hide", "Hide Window", true, None::<&str>)?;
@@ -287,6 +342,19 @@ pub fn run() {
             fan_curve::set_fan_curve,
             fan_curve::get_fan_curve,
             fan_curve::enable_fan_curve,
+            fan_curve::start_fan_calibration,
+            fan_curve::cancel_fan_calibration,
+            fan_curve::get_fan_calibration,
+            fan_curve::simulate_fan_curve,
+            fan_curve::set_fan_curve_sensors,
+            fan_curve::list_temperature_sensors,
+            fan_curve::save_fan_curve,
+            fan_curve::delete_fan_curve,
+            fan_curve::activate_fan_curve,
+            fan_curve::set_fan_curve_power_rules,
+            fan_curve::set_fan_stall_policy,
+            fan_curve::set_fan_thermal_override,
+            fan_curve::validate_fan_curve,
             // Sync
             sync::get_settings,
             sync::save_settings,
@@ -301,7 +369,14 @@ pub fn run() {
             battery::get_battery_info,
             battery::get_battery_thresholds,
             battery::set_battery_thresholds,
+            battery::get_battery_summary,
+            battery::get_charge_behaviour,
+            battery::set_charge_behaviour,
+            battery::start_battery_calibration,
+            battery::cancel_battery_calibration,
+            battery::get_battery_calibration,
             battery::get_power_consumption,
+            battery_history::get_battery_health_history,
             // Performance
             performance::get_cpu_info,
             performance::set_cpu_governor,
@@ -328,8 +403,18 @@ pub fn run() {
             // Never exit leaving the fan under manual control. The firmware
             // watchdog is the backstop for a hard kill, but on a clean exit we
             // hand the fan back explicitly and immediately.
+            //
+            // Unless a daemon owns the fan: it keeps the curve running after the
+            // window is gone, which is the reason to run one.
+            //
+            // A timed charge behaviour or a battery calibration has no such
+            // backstop, and is ended here whoever owns the fan.
             if let tauri::RunEvent::ExitRequested { .. } | tauri::RunEvent::Exit = event {
-                fan_curve::restore_fan_to_auto_blocking();
+                if daemon::attached().is_none() {
+                    fan_curve::restore_fan_to_auto_blocking();
+                }
+                battery::end_timed_charge_behaviour_blocking();
+                battery::end_battery_calibration_blocking();
             }
         });
 }
@@ -2,5 +2,12 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // `thinkutils <command>` is the CLI; a bare `thinkutils` is the app. The
+    // check comes first so a command never reaches the single-instance plugin,
+    // which would hand it to an open window instead of running it.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(thinkutils_lib::cli::main(args));
+    }
     thinkutils_lib::run()
 }
@@ -1,3 +1,6 @@
+use crate::error::{ApiResponse, Error};
+use crate::fan_control::FanLevel;
+use crate::hardware_root;
 use rmcp::handler::server::wrapper::Parameters;
 use rmcp::model::ServerInfo;
 use rmcp::transport::streamable_http_server::session::local::LocalSessionManager;
@@ -6,8 +9,8 @@ use rmcp::{schemars, tool, tool_handler, tool_router, ServerHandler};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::net::SocketAddr;
-use std::process::Command;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
 use tokio_util::sync::CancellationToken;
 
@@ -18,17 +21,33 @@ use tokio_util::sync::CancellationToken;
 /// failed with no visible error. A test asserts the two stay different.
 pub const DEFAULT_MCP_PORT: u16 = 8779;
 
-const VALID_FAN_SPEEDS: &[&str] = &["auto", "full-speed", "0", "1", "2", "3", "4", "5", "6", "7"];
-
 fn validate_fan_speed(speed: &str) -> Option<String> {
-    if VALID_FAN_SPEEDS.contains(&speed) {
-        None
-    } else {
-        Some(format!(
-            "Invalid speed '{}'. Use: auto, full-speed, or 0-7",
-            speed
-        ))
-    }
+    speed.parse::<FanLevel>().err().map(|_| {
+        let levels: Vec<String> = FanLevel::all().map(|l| l.to_string()).collect();
+        format!("Invalid speed '{}'. Use: {}", speed, levels.join(", "))
+    })
+}
+
+/// Write a validated fan speed, through the helper when a direct write is not
+/// permitted. The error is the message the tool returns.
+fn write_fan_level(speed: FanLevel) -> Result<(), String> {
+    let command = crate::fan_control::fan_command(speed, None);
+    match hardware_root::write("/proc/acpi/ibm/fan", &command) {
+        Ok(()) => return Ok(()),
+        // A simulated profile has no helper to fall back to.
+        Err(e) if hardware_root::is_simulated() => return Err(format!("Failed: {}", e)),
+        Err(_) => {}
+    }
+    if crate::helper::helper_path().is_none() {
+        return Err("No permission. Grant permissions in ThinkUtils first.".into());
+    }
+    let command = crate::fan_control::FanCommand::Level {
+        level: speed,
+        fan: None,
+    };
+    crate::helper::run_blocking(&crate::helper::Operation::Fan(command))
+        .map(|_| ())
+        .map_err(|e| format!("Failed: {}", e))
 }
 
 fn validate_battery_thresholds(start: u32, stop: u32) -> Option<String> {
@@ -95,19 +114,16 @@ pub type McpState = Arc<Mutex<McpServerState>>;
 
 // -- API response --
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ApiResponse<T> {
-    pub success: bool,
-    pub data: Option<T>,
-    pub error: Option<String>,
-}
-
 // -- MCP tool request types --
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct SetFanSpeedRequest {
-    #[schemars(description = "Fan speed: auto, full-speed, or 0-7")]
+    #[schemars(description = "Fan speed: auto, full-speed, disengaged, or 0-7")]
     pub speed: String,
+    #[schemars(
+        description = "Seconds before the fan returns to auto (1-600). Required for disengaged, refused otherwise"
+    )]
+    pub limit_secs: Option<u64>,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
@@ -118,6 +134,28 @@ pub struct SetBatteryThresholdsRequest {
     pub stop: u32,
 }
 
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct FanCurvePoint {
+    #[schemars(description = "Temperature in °C at which this point applies while heating")]
+    pub temp: i32,
+    #[schemars(description = "Fan level 0-7")]
+    pub level: i32,
+    #[schemars(description = "Temperature in °C at which this point applies while cooling")]
+    pub fall_temp: Option<i32>,
+    #[schemars(
+        description = "Target fan speed in RPM, translated to a level through this machine's calibration"
+    )]
+    pub rpm: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ValidateFanCurveRequest {
+    #[schemars(description = "Curve points, in any order")]
+    pub points: Vec<FanCurvePoint>,
+    #[schemars(description = "Sort, dedupe, clamp and make levels non-decreasing before checking")]
+    pub normalize: Option<bool>,
+}
+
 // -- MCP Server handler --
 
 #[derive(Debug, Clone)]
@@ -127,28 +165,49 @@ struct ThinkUtilsHandler;
 impl ThinkUtilsHandler {
     #[tool(description = "Get ThinkPad fan status: speed (RPM), level, and status")]
     fn get_fan_status(&self) -> String {
-        fs::read_to_string("/proc/acpi/ibm/fan").unwrap_or_else(|e| format!("Error: {}", e))
+        hardware_root::read_to_string("/proc/acpi/ibm/fan")
+            .unwrap_or_else(|e| format!("Error: {}", e))
     }
 
     #[tool(
-        description = "Set ThinkPad fan speed. Values: 'auto', 'full-speed', or '0' through '7'"
+        description = "Set ThinkPad fan speed. Values: 'auto', 'full-speed', 'disengaged' (unregulated, needs limit_secs), or '0' through '7'"
     )]
     fn set_fan_speed(&self, Parameters(req): Parameters<SetFanSpeedRequest>) -> String {
         if let Some(err) = validate_fan_speed(&req.speed) {
             return err;
         }
-        let command = format!("level {}", req.speed);
-        if fs::write("/proc/acpi/ibm/fan", &command).is_ok() {
-            return format!("Fan speed set to: {}", req.speed);
-        }
-        if let Some(helper) = crate::fan_control::helper_path() {
-            match Command::new("pkexec").arg(helper).arg(&command).output() {
-                Ok(o) if o.status.success() => return format!("Fan speed set to: {}", req.speed),
-                Ok(o) => return format!("Failed: {}", String::from_utf8_lossy(&o.stderr)),
-                Err(e) => return format!("Error: {}", e),
+        let level = match crate::fan_control::validate_fan_request(&req.speed, None, req.limit_secs)
+        {
+            Ok(level) => level,
+            Err(e) => return format!("Error: {}", e),
+        };
+        let change = crate::fan_control::note_fan_change();
+        match (write_fan_level(level), req.limit_secs) {
+            (Ok(()), Some(secs)) => {
+                crate::fan_control::hand_back_after(Duration::from_secs(secs), None, change);
+                format!("Fan disengaged for {}s, then back to auto", secs)
             }
+            (Ok(()), None) => format!("Fan speed set to: {}", level),
+            (Err(e), _) => e,
         }
-        "No permission. Grant permissions in ThinkUtils first.".into()
+    }
+
+    #[tool(
+        description = "Check a fan curve without applying it. Returns the points (normalized if asked) and a list of errors, each naming the point index and field"
+    )]
+    fn validate_fan_curve(&self, Parameters(req): Parameters<ValidateFanCurveRequest>) -> String {
+        let points = req
+            .points
+            .into_iter()
+            .map(|p| crate::fan_curve::CurvePoint {
+                temp: p.temp,
+                level: p.level,
+                fall_temp: p.fall_temp,
+                rpm: p.rpm,
+            })
+            .collect();
+        let check = crate::fan_curve::check_curve_points(points, req.normalize.unwrap_or(false));
+        serde_json::to_string_pretty(&check).unwrap_or_else(|e| format!("Error: {}", e))
     }
 
     #[tool(description = "Get CPU temperature readings from all thermal zones")]
@@ -157,7 +216,10 @@ impl ThinkUtilsHandler {
         for i in 0..10 {
             let path = format!("/sys/class/thermal/thermal_zone{}/temp", i);
             let type_path = format!("/sys/class/thermal/thermal_zone{}/type", i);
-            if let (Ok(t), Ok(n)) = (fs::read_to_string(&path), fs::read_to_string(&type_path)) {
+            if let (Ok(t), Ok(n)) = (
+                hardware_root::read_to_string(&path),
+                hardware_root::read_to_string(&type_path),
+            ) {
                 if let Ok(m) = t.trim().parse::<i32>() {
                     temps.push(format!("{}: {:.1}°C", n.trim(), m as f64 / 1000.0));
                 }
@@ -172,33 +234,25 @@ impl ThinkUtilsHandler {
 
     #[tool(description = "Get battery information: status, capacity, health, charge thresholds")]
     fn get_battery_info(&self) -> String {
-        let bat = "/sys/class/power_supply/BAT0";
-        if !std::path::Path::new(bat).exists() {
+        // The battery page's own reader: every battery, found by type, with the
+        // threshold files resolved the same way the setter resolves them.
+        let Some(batteries) = crate::battery::get_battery_info().data else {
             return "No battery found".into();
-        }
-        let r = |f: &str| {
-            fs::read_to_string(format!("{}/{}", bat, f))
-                .map(|s| s.trim().to_string())
-                .unwrap_or("N/A".into())
-        };
-        // Resolve the threshold files the same way the setter does. Naming them
-        // directly meant this reader picked one spelling while the machine might
-        // only expose the other, reporting N/A for thresholds that work fine.
-        let read_path = |p: &str| {
-            fs::read_to_string(p)
-                .map(|s| s.trim().to_string())
-                .unwrap_or("N/A".into())
         };
-        let (start, stop) = match crate::battery::threshold_paths() {
-            Some((s, e)) => (read_path(&s), read_path(&e)),
-            None => ("N/A".into(), "N/A".into()),
-        };
-
-        format!(
-            "Status: {}\nCapacity: {}%\nCycle Count: {}\nTechnology: {}\nStart Threshold: {}%\nStop Threshold: {}%",
-            r("status"), r("capacity"), r("cycle_count"), r("technology"),
-            start, stop,
-        )
+        batteries
+            .iter()
+            .map(|b| {
+                let (start, stop) = match b.thresholds {
+                    Some(t) => (format!("{}%", t.start), format!("{}%", t.stop)),
+                    None => ("N/A".into(), "N/A".into()),
+                };
+                format!(
+                    "{}\nStatus: {}\nCapacity: {}%\nCycle Count: {}\nTechnology: {}\nStart Threshold: {}\nStop Threshold: {}",
+                    b.name, b.status, b.capacity, b.cycles, b.technology, start, stop,
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
     }
 
     #[tool(description = "Set battery charge thresholds (start and stop percentages)")]
@@ -217,11 +271,11 @@ impl ThinkUtilsHandler {
         };
 
         let mut r = Vec::new();
-        match fs::write(&stop_path, req.stop.to_string()) {
+        match hardware_root::write(&stop_path, &req.stop.to_string()) {
             Ok(_) => r.push(format!("Stop set to {}%", req.stop)),
             Err(e) => r.push(format!("Stop failed: {}", e)),
         }
-        match fs::write(&start_path, req.start.to_string()) {
+        match hardware_root::write(&start_path, &req.start.to_string()) {
             Ok(_) => r.push(format!("Start set to {}%", req.start)),
             Err(e) => r.push(format!("Start failed: {}", e)),
         }
@@ -231,20 +285,26 @@ impl ThinkUtilsHandler {
     #[tool(description = "Get CPU information: governor, frequency, turbo boost status")]
     fn get_cpu_info(&self) -> String {
         let mut info = Vec::new();
-        if let Ok(v) = fs::read_to_string("/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor") {
+        if let Ok(v) =
+            hardware_root::read_to_string("/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor")
+        {
             info.push(format!("Governor: {}", v.trim()));
         }
-        if let Ok(v) = fs::read_to_string("/sys/devices/system/cpu/cpu0/cpufreq/scaling_cur_freq") {
+        if let Ok(v) =
+            hardware_root::read_to_string("/sys/devices/system/cpu/cpu0/cpufreq/scaling_cur_freq")
+        {
             if let Ok(k) = v.trim().parse::<u64>() {
                 info.push(format!("Frequency: {} MHz", k / 1000));
             }
         }
-        if let Ok(v) =
-            fs::read_to_string("/sys/devices/system/cpu/cpu0/cpufreq/scaling_available_governors")
-        {
+        if let Ok(v) = hardware_root::read_to_string(
+            "/sys/devices/system/cpu/cpu0/cpufreq/scaling_available_governors",
+        ) {
             info.push(format!("Available: {}", v.trim()));
         }
-        if let Ok(v) = fs::read_to_string("/sys/devices/system/cpu/intel_pstate/no_turbo") {
+        if let Ok(v) =
+            hardware_root::read_to_string("/sys/devices/system/cpu/intel_pstate/no_turbo")
+        {
             info.push(format!(
                 "Turbo Boost: {}",
                 if v.trim() == "0" {
@@ -263,7 +323,7 @@ impl ThinkUtilsHandler {
 
     #[tool(description = "Get system memory usage")]
     fn get_memory_info(&self) -> String {
-        fs::read_to_string("/proc/meminfo")
+        hardware_root::read_to_string("/proc/meminfo")
             .map(|c| c.lines().take(5).collect::<Vec<_>>().join("\n"))
             .unwrap_or_else(|e| format!("Error: {}", e))
     }
@@ -285,7 +345,7 @@ impl ThinkUtilsHandler {
                 }
             }
         }
-        if let Ok(v) = fs::read_to_string("/proc/cpuinfo") {
+        if let Ok(v) = hardware_root::read_to_string("/proc/cpuinfo") {
             for l in v.lines() {
                 if let Some((_, rest)) = l.split_once("model name") {
                     if let Some((_, name)) = rest.split_once(':') {
@@ -353,7 +413,8 @@ impl ServerHandler for ThinkUtilsHandler {
         let mut info = ServerInfo::default();
         info.instructions = Some(
             "ThinkUtils MCP Server - monitor and control ThinkPad hardware: fan speed and \
-             status, CPU temperature and governor, battery info and charge thresholds."
+             status, fan curve validation, CPU temperature and governor, battery info and \
+             charge thresholds."
                 .into(),
         );
         info
@@ -377,16 +438,12 @@ pub async fn get_mcp_status(
     state: tauri::State<'_, McpState>,
 ) -> Result<ApiResponse<McpStatus>, String> {
     let s = state.lock().await;
-    Ok(ApiResponse {
-        success: true,
-        data: Some(McpStatus {
-            running: s.cancel_token.is_some(),
-            host: s.host.clone(),
-            port: s.port,
-            path: MCP_PATH.to_string(),
-        }),
-        error: None,
-    })
+    Ok(ApiResponse::ok(McpStatus {
+        running: s.cancel_token.is_some(),
+        host: s.host.clone(),
+        port: s.port,
+        path: MCP_PATH.to_string(),
+    }))
 }
 
 #[tauri::command]
@@ -398,19 +455,11 @@ pub async fn start_mcp_server(
     let mut s = state.lock().await;
 
     if s.cancel_token.is_some() {
-        return Ok(ApiResponse {
-            success: false,
-            data: None,
-            error: Some("MCP server is already running".into()),
-        });
+        return Ok(ApiResponse::err("MCP server is already running"));
     }
 
     if let Some(err) = validate_mcp_host(&host) {
-        return Ok(ApiResponse {
-            success: false,
-            data: None,
-            error: Some(err),
-        });
+        return Ok(ApiResponse::err(Error::validation(err)));
     }
 
     let bind_host = resolve_bind_host(&host);
@@ -447,11 +496,10 @@ pub async fn start_mcp_server(
         Ok(l) => l,
         Err(e) => {
             eprintln!("[MCP] Failed to bind {}: {}", addr, e);
-            return Ok(ApiResponse {
-                success: false,
-                data: None,
-                error: Some(format!("Could not listen on {}: {}", addr, e)),
-            });
+            return Ok(ApiResponse::err(format!(
+                "Could not listen on {}: {}",
+                addr, e
+            )));
         }
     };
 
@@ -484,14 +532,10 @@ pub async fn start_mcp_server(
     s.port = port;
 
     println!("[MCP] Server started on {}:{}", host, port);
-    Ok(ApiResponse {
-        success: true,
-        data: Some(format!(
-            "MCP server running on http://{}:{}/mcp",
-            host, port
-        )),
-        error: None,
-    })
+    Ok(ApiResponse::ok(format!(
+        "MCP server running on http://{}:{}/mcp",
+        host, port
+    )))
 }
 
 #[tauri::command]
@@ -503,17 +547,9 @@ pub async fn stop_mcp_server(
     if let Some(ct) = s.cancel_token.take() {
         ct.cancel();
         println!("[MCP] Server stopped");
-        Ok(ApiResponse {
-            success: true,
-            data: Some("MCP server stopped".into()),
-            error: None,
-        })
+        Ok(ApiResponse::ok("MCP server stopped".into()))
     } else {
-        Ok(ApiResponse {
-            success: false,
-            data: None,
-            error: Some("MCP server is not running".into()),
-        })
+        Ok(ApiResponse::err("MCP server is not running"))
     }
 }
 
@@ -637,9 +673,9 @@ mod tests {
 
     #[test]
     fn valid_fan_speeds_accepted() {
-        for speed in VALID_FAN_SPEEDS {
+        for speed in FanLevel::all().map(|l| l.to_string()) {
             assert!(
-                validate_fan_speed(speed).is_none(),
+                validate_fan_speed(&speed).is_none(),
                 "expected '{}' to be valid",
                 speed
             );
@@ -751,11 +787,7 @@ mod tests {
 
     #[test]
     fn api_response_success_serialization() {
-        let resp = ApiResponse {
-            success: true,
-            data: Some("ok".to_string()),
-            error: None,
-        };
+        let resp = ApiResponse::ok("ok".to_string());
         let json = serde_json::to_value(&resp).unwrap();
         assert_eq!(json["success"], true);
         assert_eq!(json["data"], "ok");
@@ -764,14 +796,11 @@ mod tests {
 
     #[test]
     fn api_response_error_serialization() {
-        let resp: ApiResponse<String> = ApiResponse {
-            success: false,
-            data: None,
-            error: Some("something broke".into()),
-        };
+        let resp: ApiResponse<String> = ApiResponse::err("something broke");
         let json = serde_json::to_value(&resp).unwrap();
         assert_eq!(json["success"], false);
         assert!(json["data"].is_null());
         assert_eq!(json["error"], "something broke");
+        assert_eq!(json["code"], "failed");
     }
 }
@@ -1,6 +1,7 @@
+use crate::error::ApiResponse;
+use crate::hardware_root;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fs;
 use std::process::Command;
 use std::thread;
 use std::time::Duration;
@@ -74,13 +75,6 @@ pub struct ProcessInfo {
     pub status: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ApiResponse<T> {
-    pub success: bool,
-    pub data: Option<T>,
-    pub error: Option<String>,
-}
-
 /// Collecting stats blocks: it sleeps 200ms between the two `/proc/stat`
 /// samples it has to diff, and shells out to `df` and `ps aux`.
 ///
@@ -94,21 +88,9 @@ pub struct ApiResponse<T> {
 #[tauri::command]
 pub async fn get_system_monitor() -> ApiResponse<SystemMonitor> {
     match tokio::task::spawn_blocking(collect_system_stats).await {
-        Ok(Ok(monitor)) => ApiResponse {
-            success: true,
-            data: Some(monitor),
-            error: None,
-        },
-        Ok(Err(e)) => ApiResponse {
-            success: false,
-            data: None,
-            error: Some(e),
-        },
-        Err(e) => ApiResponse {
-            success: false,
-            data: None,
-            error: Some(format!("Monitor collection failed to run: {}", e)),
-        },
+        Ok(Ok(monitor)) => ApiResponse::ok(monitor),
+        Ok(Err(e)) => ApiResponse::err(e),
+        Err(e) => ApiResponse::err(format!("Monitor collection failed to run: {}", e)),
     }
 }
 
@@ -124,7 +106,7 @@ fn collect_system_stats() -> Result<SystemMonitor, String> {
 
 fn get_cpu_stats() -> Result<CpuStats, String> {
     // Take first snapshot
-    let stat1 = fs::read_to_string("/proc/stat")
+    let stat1 = hardware_root::read_to_string("/proc/stat")
         .map_err(|e| format!("Failed to read /proc/stat: {}", e))?;
 
     let snapshot1 = parse_cpu_snapshot(&stat1)?;
@@ -133,7 +115,7 @@ fn get_cpu_stats() -> Result<CpuStats, String> {
     thread::sleep(Duration::from_millis(200));
 
     // Take second snapshot
-    let stat2 = fs::read_to_string("/proc/stat")
+    let stat2 = hardware_root::read_to_string("/proc/stat")
         .map_err(|e| format!("Failed to read /proc/stat: {}", e))?;
 
     let snapshot2 = parse_cpu_snapshot(&stat2)?;
@@ -266,14 +248,14 @@ fn get_core_frequency(core_id: usize) -> u64 {
         "/sys/devices/system/cpu/cpu{}/cpufreq/scaling_cur_freq",
         core_id
     );
-    fs::read_to_string(path)
+    hardware_root::read_to_string(&path)
         .ok()
         .and_then(|s| s.trim().parse::<u64>().ok())
         .unwrap_or(0)
 }
 
 fn get_load_average() -> Result<LoadAverage, String> {
-    let loadavg = fs::read_to_string("/proc/loadavg")
+    let loadavg = hardware_root::read_to_string("/proc/loadavg")
         .map_err(|e| format!("Failed to read /proc/loadavg: {}", e))?;
 
     let parts: Vec<&str> = loadavg.split_whitespace().collect();
@@ -289,7 +271,7 @@ fn get_load_average() -> Result<LoadAverage, String> {
 }
 
 fn get_memory_stats() -> Result<MemoryStats, String> {
-    let meminfo = fs::read_to_string("/proc/meminfo")
+    let meminfo = hardware_root::read_to_string("/proc/meminfo")
         .map_err(|e| format!("Failed to read /proc/meminfo: {}", e))?;
 
     let mut mem_map: HashMap<String, u64> = HashMap::new();
@@ -369,7 +351,7 @@ fn get_disk_stats() -> Result<Vec<DiskStats>, String> {
 }
 
 fn get_network_stats() -> Result<Vec<NetworkStats>, String> {
-    let net_dev = fs::read_to_string("/proc/net/dev")
+    let net_dev = hardware_root::read_to_string("/proc/net/dev")
         .map_err(|e| format!("Failed to read /proc/net/dev: {}", e))?;
 
     let mut interfaces = Vec::new();
@@ -446,7 +428,7 @@ fn get_top_processes() -> Result<Vec<ProcessInfo>, String> {
 }
 
 fn get_total_memory_mb() -> f64 {
-    fs::read_to_string("/proc/meminfo")
+    hardware_root::read_to_string("/proc/meminfo")
         .ok()
         .and_then(|content| {
             content
@@ -518,4 +500,54 @@ ctxt 67890
             assert!(*id < 8, "parsed a bogus core id {}", id);
         }
     }
+
+    /// The whole collection path, through the hardware root, on a synthetic
+    /// tree: /proc/stat with the cpu5 hole, a load average, and cpufreq for
+    /// the cores on either side of the gap.
+    #[test]
+    fn collects_cpu_stats_through_the_hardware_root() {
+        let root = std::env::temp_dir().join(format!(
+            "thinkutils_monitor_{}_{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        let files = [
+            ("proc/stat", STAT_WITH_CPU5_OFFLINE),
+            ("proc/loadavg", "0.52 0.61 0.70 1/845 12345\n"),
+            (
+                "sys/devices/system/cpu/cpu4/cpufreq/scaling_cur_freq",
+                "2400000\n",
+            ),
+            (
+                "sys/devices/system/cpu/cpu6/cpufreq/scaling_cur_freq",
+                "3100000\n",
+            ),
+        ];
+        for (rel, content) in files {
+            let path = root.join(rel);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+
+        let stats =
+            crate::hardware_root::testing::with_root(Some(root.to_str().unwrap()), get_cpu_stats);
+        let _ = std::fs::remove_dir_all(&root);
+
+        let stats = stats.expect("collects from the synthetic tree");
+        let freq = |id: usize| {
+            stats
+                .cores
+                .iter()
+                .find(|c| c.core_id == id)
+                .map(|c| c.frequency)
+        };
+        assert_eq!(stats.cores.len(), 7);
+        assert_eq!(freq(4), Some(2400000));
+        assert_eq!(freq(6), Some(3100000));
+        assert_eq!(freq(5), None, "cpu5 is offline");
+        assert_eq!(stats.load_avg.one_min, 0.52);
+    }
 }
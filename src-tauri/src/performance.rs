@@ -1,5 +1,5 @@
+use crate::error::{ApiResponse, Error};
 use serde::{Deserialize, Serialize};
-use std::fs;
 use std::process::Command;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -17,19 +17,12 @@ pub struct PowerProfile {
     pub available: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ApiResponse<T> {
-    pub success: bool,
-    pub data: Option<T>,
-    pub error: Option<String>,
-}
-
 #[tauri::command]
 pub fn get_cpu_info() -> ApiResponse<CpuInfo> {
     let cpu0_path = "/sys/devices/system/cpu/cpu0/cpufreq";
 
     let read_file = |file: &str| -> String {
-        fs::read_to_string(format!("{}/{}", cpu0_path, file))
+        crate::hardware_root::read_to_string(&format!("{}/{}", cpu0_path, file))
             .unwrap_or_default()
             .trim()
             .to_string()
@@ -45,26 +38,24 @@ pub fn get_cpu_info() -> ApiResponse<CpuInfo> {
         .map(String::from)
         .collect();
 
-    ApiResponse {
-        success: true,
-        data: Some(CpuInfo {
-            governor,
-            min_freq: read_freq("scaling_min_freq"),
-            max_freq: read_freq("scaling_max_freq"),
-            current_freq: read_freq("scaling_cur_freq"),
-            available_governors,
-        }),
-        error: None,
-    }
+    ApiResponse::ok(CpuInfo {
+        governor,
+        min_freq: read_freq("scaling_min_freq"),
+        max_freq: read_freq("scaling_max_freq"),
+        current_freq: read_freq("scaling_cur_freq"),
+        available_governors,
+    })
 }
 
 /// Governors the kernel reports as available on this machine.
 fn read_available_governors() -> Vec<String> {
-    fs::read_to_string("/sys/devices/system/cpu/cpu0/cpufreq/scaling_available_governors")
-        .unwrap_or_default()
-        .split_whitespace()
-        .map(String::from)
-        .collect()
+    crate::hardware_root::read_to_string(
+        "/sys/devices/system/cpu/cpu0/cpufreq/scaling_available_governors",
+    )
+    .unwrap_or_default()
+    .split_whitespace()
+    .map(String::from)
+    .collect()
 }
 
 /// Validate a governor name before it is interpolated into a script run as root.
@@ -73,7 +64,7 @@ fn read_available_governors() -> Vec<String> {
 /// kernel must list it as available. The character whitelist is the load-bearing
 /// one — it holds even when sysfs can't be read, so an empty available-list can
 /// never widen what is accepted.
-fn validate_governor(governor: &str) -> Result<(), String> {
+pub(crate) fn validate_governor(governor: &str) -> Result<(), String> {
     if governor.is_empty() || governor.len() > 32 {
         return Err("Invalid governor name.".to_string());
     }
@@ -95,108 +86,173 @@ fn validate_governor(governor: &str) -> Result<(), String> {
     Ok(())
 }
 
-/// Where the per-CPU governor files live. A glob, not a count, so it is the
-/// root shell that decides which CPUs exist — see [`governor_script`].
-const CPU_GLOB: &str = "/sys/devices/system/cpu/cpu[0-9]*/cpufreq/scaling_governor";
+/// The turbo switch on intel_pstate machines. Inverted: 1 means turbo is off.
+const TURBO_INTEL_PSTATE: &str = "/sys/devices/system/cpu/intel_pstate/no_turbo";
+/// The turbo switch on acpi-cpufreq and amd-pstate machines.
+const TURBO_CPUFREQ_BOOST: &str = "/sys/devices/system/cpu/cpufreq/boost";
+
+/// The ACPI platform profile and the values this firmware offers for it.
+const PLATFORM_PROFILE: &str = "/sys/firmware/acpi/platform_profile";
+const PLATFORM_PROFILE_CHOICES: &str = "/sys/firmware/acpi/platform_profile_choices";
 
-/// Build the script that applies `governor` to every CPU exposing a governor file.
+/// How cpufreq answers a write, for a simulated hardware root. None for any
+/// path it does not own.
 ///
-/// The previous version counted `cpuN` directories and emitted one fixed
-/// redirect per index under `set -e`. That broke in two ways: the count includes
-/// CPUs that are present but OFFLINE, whose `cpufreq/` directory the kernel
-/// removes, and it assumes contiguous numbering. Offlining a core — disabling
-/// SMT, say — made that index's redirect fail with ENOENT, `set -e` aborted, and
-/// pkexec returned non-zero *after* the earlier cores had already been changed.
-/// The machine was left with mixed governors while the UI said nothing happened.
+/// A governor must be one the policy lists as available, the turbo switches
+/// take 0 or 1, and the platform profile takes one of its choices.
+pub(crate) fn simulate_write(path: &str, value: &str) -> Option<std::io::Result<()>> {
+    let accepted = if let Some(policy) = path.strip_suffix("/scaling_governor") {
+        crate::hardware_root::read_to_string(&format!("{}/scaling_available_governors", policy))
+            .unwrap_or_default()
+            .split_whitespace()
+            .any(|g| g == value)
+    } else if path == TURBO_INTEL_PSTATE || path == TURBO_CPUFREQ_BOOST {
+        value == "0" || value == "1"
+    } else if path == PLATFORM_PROFILE {
+        read_platform_profile_choices().iter().any(|p| p == value)
+    } else {
+        return None;
+    };
+
+    if !accepted {
+        return Some(Err(crate::hardware_root::invalid_value(format!(
+            "{} does not accept '{}'",
+            path, value
+        ))));
+    }
+    Some(std::fs::write(
+        crate::hardware_root::resolve(path),
+        format!("{}\n", value),
+    ))
+}
+
+/// Apply a governor to every CPU exposing a governor file, returning how many
+/// took it. Run by the privileged helper, and directly under a simulated
+/// profile.
 ///
-/// Globbing defers the decision to execution time, as root, so it sees whatever
-/// CPUs are online at that moment. Only a total failure is an error: one core
-/// going offline mid-run must not discard the ones that were set.
+/// The CPUs are listed when the write happens, not counted beforehand. An
+/// earlier version counted `cpuN` directories and wrote one fixed path per
+/// index under `set -e`, which broke twice over: the count includes CPUs that
+/// are present but OFFLINE, whose `cpufreq/` directory the kernel removes, and
+/// it assumed contiguous numbering. Offlining a core — disabling SMT, say —
+/// aborted the run after the earlier cores had already been changed, leaving
+/// mixed governors while the UI said nothing happened.
 ///
-/// `governor` is validated against the kernel's own list before it gets here,
-/// so interpolating it is safe.
-fn governor_script(governor: &str, glob: &str) -> String {
-    format!(
-        r#"#!/bin/bash
-set -u
-applied=0
-failed=0
-for f in {glob}; do
-  [ -e "$f" ] || continue
-  if echo {governor} > "$f" 2>/dev/null; then
-    applied=$((applied + 1))
-  else
-    failed=$((failed + 1))
-    echo "could not write $f" >&2
-  fi
-done
-echo "governor applied to $applied CPU(s), $failed refused"
-if [ "$applied" -eq 0 ]; then
-  echo "no CPU accepted the governor" >&2
-  exit 1
-fi
-exit 0
-"#
-    )
+/// So CPUs without a governor file are skipped, and only a total failure is an
+/// error: one core refusing must not discard the ones that were set.
+pub(crate) fn apply_governor(governor: &str) -> Result<usize, String> {
+    let entries = crate::hardware_root::read_dir("/sys/devices/system/cpu")
+        .map_err(|e| format!("Failed to list CPUs: {}", e))?;
+
+    let mut applied = 0;
+    let mut last_error = None;
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name
+            .strip_prefix("cpu")
+            .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+        {
+            continue;
+        }
+        // Rebuilt from the real directory: entry paths point into the profile.
+        let path = format!("/sys/devices/system/cpu/{}/cpufreq/scaling_governor", name);
+        if !crate::hardware_root::exists(&path) {
+            continue;
+        }
+        match crate::hardware_root::write(&path, governor) {
+            Ok(()) => applied += 1,
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    match (applied, last_error) {
+        (0, Some(e)) => Err(format!("No CPU accepted the governor: {}", e)),
+        (0, None) => Err("No CPU exposes a governor".to_string()),
+        (n, _) => Ok(n),
+    }
+}
+
+/// Switch turbo boost through whichever control the CPU driver exposes.
+/// Run by the privileged helper, and directly where the file is writable.
+pub(crate) fn write_turbo(enabled: bool) -> Result<(), String> {
+    let result = if crate::hardware_root::exists(TURBO_INTEL_PSTATE) {
+        // Inverted: no_turbo=1 turns turbo off.
+        crate::hardware_root::write(TURBO_INTEL_PSTATE, if enabled { "0" } else { "1" })
+    } else if crate::hardware_root::exists(TURBO_CPUFREQ_BOOST) {
+        crate::hardware_root::write(TURBO_CPUFREQ_BOOST, if enabled { "1" } else { "0" })
+    } else {
+        return Err("Turbo boost control not available".to_string());
+    };
+    result.map_err(|e| format!("Failed to set turbo boost: {}", e))
+}
+
+/// Platform profiles the firmware offers, e.g. `low-power balanced performance`.
+fn read_platform_profile_choices() -> Vec<String> {
+    crate::hardware_root::read_to_string(PLATFORM_PROFILE_CHOICES)
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(String::from)
+        .collect()
+}
+
+/// Validate a platform profile before the helper writes it.
+///
+/// Unlike governors there is no character layer to fall back on: a machine
+/// that does not list its choices has no platform profile to set.
+pub(crate) fn validate_platform_profile(profile: &str) -> Result<(), String> {
+    let choices = read_platform_profile_choices();
+    if choices.is_empty() {
+        return Err("This machine has no ACPI platform profile".to_string());
+    }
+    if !choices.iter().any(|c| c == profile) {
+        return Err(format!(
+            "Platform profile '{}' is not offered here. Available: {}",
+            profile,
+            choices.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Write the ACPI platform profile. Run by the privileged helper.
+pub(crate) fn write_platform_profile(profile: &str) -> Result<(), String> {
+    crate::hardware_root::write(PLATFORM_PROFILE, profile)
+        .map_err(|e| format!("Failed to set the platform profile: {}", e))
+}
+
+/// The platform profile matching a power-profiles-daemon profile name.
+fn platform_profile_for(power_profile: &str) -> &str {
+    match power_profile {
+        "power-saver" => "low-power",
+        other => other,
+    }
 }
 
 #[tauri::command]
 pub async fn set_cpu_governor(governor: String) -> ApiResponse<String> {
     println!("[Performance] Setting CPU governor to: {}", governor);
 
-    // This value reaches a root shell below — validate before anything else.
+    // This value is written as root below — validate before anything else.
     if let Err(e) = validate_governor(&governor) {
         println!("[Performance] ✗ Rejected governor: {}", e);
-        return ApiResponse {
-            success: false,
-            data: None,
-            error: Some(e),
-        };
+        return ApiResponse::err(Error::validation(e));
     }
 
-    let script_content = governor_script(&governor, CPU_GLOB);
-
-    println!("[Performance] Script content:\n{}", script_content);
-
-    println!("[Performance] Executing pkexec...");
+    let result = if crate::hardware_root::is_simulated() {
+        apply_governor(&governor)
+            .map(|_| ())
+            .map_err(|e| Error::simulated(format!("Simulated hardware rejected the change: {}", e)))
+    } else {
+        crate::helper::run(&crate::helper::Operation::Governor(governor.clone()))
+            .await
+            .map(|report| println!("[Performance] {}", report))
+    };
 
-    match crate::privileged::run_script(&script_content).await {
-        Ok(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let stderr = String::from_utf8_lossy(&output.stderr);
-
-            println!("[Performance] pkexec stdout: {}", stdout);
-            println!("[Performance] pkexec stderr: {}", stderr);
-            println!("[Performance] pkexec status: {}", output.status);
-
-            if output.status.success() {
-                println!("[Performance] Successfully set governor to: {}", governor);
-                ApiResponse {
-                    success: true,
-                    data: Some(format!("CPU governor set to: {}", governor)),
-                    error: None,
-                }
-            } else {
-                let error_msg = if !stderr.is_empty() {
-                    stderr.to_string()
-                } else {
-                    "Permission denied or operation failed".to_string()
-                };
-                println!("[Performance] Failed to set governor: {}", error_msg);
-                ApiResponse {
-                    success: false,
-                    data: None,
-                    error: Some(error_msg),
-                }
-            }
-        }
+    match result {
+        Ok(()) => ApiResponse::ok(format!("CPU governor set to: {}", governor)),
         Err(e) => {
-            println!("[Performance] Failed to execute pkexec: {}", e);
-            ApiResponse {
-                success: false,
-                data: None,
-                error: Some(format!("Failed to execute pkexec: {}", e)),
-            }
+            println!("[Performance] Failed to set governor: {}", e);
+            ApiResponse::err(e)
         }
     }
 }
@@ -238,15 +294,27 @@ pub fn get_power_profile() -> ApiResponse<PowerProfile> {
                 ],
             };
 
-            return ApiResponse {
-                success: true,
-                data: Some(PowerProfile { current, available }),
-                error: None,
-            };
+            return ApiResponse::ok(PowerProfile { current, available });
         }
         _ => {}
     }
 
+    // Then the firmware's own profile, named the way power-profiles-daemon
+    // names it so set_power_profile takes the same names back.
+    let choices = read_platform_profile_choices();
+    if let Ok(current) = crate::hardware_root::read_to_string(PLATFORM_PROFILE) {
+        if !choices.is_empty() {
+            let ppd_name = |p: &str| match p {
+                "low-power" => "power-saver".to_string(),
+                other => other.to_string(),
+            };
+            return ApiResponse::ok(PowerProfile {
+                current: ppd_name(current.trim()),
+                available: choices.iter().map(|c| ppd_name(c)).collect(),
+            });
+        }
+    }
+
     // Fallback to TLP if available
     match Command::new("tlp-stat").arg("-s").output() {
         Ok(output) if output.status.success() => {
@@ -257,25 +325,17 @@ pub fn get_power_profile() -> ApiResponse<PowerProfile> {
                 "power-saver".to_string()
             };
 
-            return ApiResponse {
-                success: true,
-                data: Some(PowerProfile {
-                    current,
-                    available: vec!["power-saver".to_string(), "performance".to_string()],
-                }),
-                error: None,
-            };
+            return ApiResponse::ok(PowerProfile {
+                current,
+                available: vec!["power-saver".to_string(), "performance".to_string()],
+            });
         }
         _ => {}
     }
 
-    ApiResponse {
-        success: false,
-        data: None,
-        error: Some(
-            "No power management tool found (install power-profiles-daemon or TLP)".to_string(),
-        ),
-    }
+    ApiResponse::err(Error::tool_missing(
+        "No power management tool found (install power-profiles-daemon or TLP)",
+    ))
 }
 
 #[tauri::command]
@@ -290,15 +350,32 @@ pub async fn set_power_profile(profile: String) -> ApiResponse<String> {
         .await
     {
         Ok(output) if output.status.success() => {
-            return ApiResponse {
-                success: true,
-                data: Some(format!("Power profile set to: {}", profile)),
-                error: None,
-            };
+            return ApiResponse::ok(format!("Power profile set to: {}", profile));
         }
         _ => {}
     }
 
+    // Then the firmware's own profile, where it has one
+    let platform_profile = platform_profile_for(&profile);
+    if validate_platform_profile(platform_profile).is_ok() {
+        let result = match write_platform_profile(platform_profile) {
+            Ok(()) => Ok(()),
+            Err(e) if crate::hardware_root::is_simulated() => Err(Error::simulated(format!(
+                "Simulated hardware rejected the change: {}",
+                e
+            ))),
+            Err(_) => crate::helper::run(&crate::helper::Operation::PlatformProfile(
+                platform_profile.to_string(),
+            ))
+            .await
+            .map(|_| ()),
+        };
+        return match result {
+            Ok(()) => ApiResponse::ok(format!("Platform profile set to: {}", platform_profile)),
+            Err(e) => ApiResponse::err(e),
+        };
+    }
+
     // Fallback to TLP
     let tlp_mode = match profile.as_str() {
         "power-saver" => "BAT",
@@ -312,279 +389,166 @@ pub async fn set_power_profile(profile: String) -> ApiResponse<String> {
         .output()
         .await
     {
-        Ok(output) if output.status.success() => ApiResponse {
-            success: true,
-            data: Some(format!("TLP mode set to: {}", tlp_mode)),
-            error: None,
-        },
-        _ => ApiResponse {
-            success: false,
-            data: None,
-            error: Some("Failed to set power profile".to_string()),
-        },
+        Ok(output) if output.status.success() => {
+            ApiResponse::ok(format!("TLP mode set to: {}", tlp_mode))
+        }
+        _ => ApiResponse::err("Failed to set power profile".to_string()),
     }
 }
 
 #[tauri::command]
 pub fn get_turbo_boost_status() -> ApiResponse<bool> {
-    let intel_pstate = "/sys/devices/system/cpu/intel_pstate/no_turbo";
-    let cpufreq_boost = "/sys/devices/system/cpu/cpufreq/boost";
+    let intel_pstate = TURBO_INTEL_PSTATE;
+    let cpufreq_boost = TURBO_CPUFREQ_BOOST;
 
     // Check Intel P-state
-    if let Ok(content) = fs::read_to_string(intel_pstate) {
+    if let Ok(content) = crate::hardware_root::read_to_string(intel_pstate) {
         let no_turbo = content.trim() == "1";
-        return ApiResponse {
-            success: true,
-            data: Some(!no_turbo), // Invert because file is "no_turbo"
-            error: None,
-        };
+        return ApiResponse::ok(!no_turbo); // Invert because file is "no_turbo"
     }
 
     // Check cpufreq boost
-    if let Ok(content) = fs::read_to_string(cpufreq_boost) {
+    if let Ok(content) = crate::hardware_root::read_to_string(cpufreq_boost) {
         let boost = content.trim() == "1";
-        return ApiResponse {
-            success: true,
-            data: Some(boost),
-            error: None,
-        };
+        return ApiResponse::ok(boost);
     }
 
-    ApiResponse {
-        success: false,
-        data: None,
-        error: Some("Turbo boost control not available".to_string()),
-    }
+    ApiResponse::err(Error::hardware_absent("Turbo boost control not available"))
 }
 
 #[tauri::command]
 pub async fn set_turbo_boost(enabled: bool) -> ApiResponse<String> {
-    let intel_pstate = "/sys/devices/system/cpu/intel_pstate/no_turbo";
-    let cpufreq_boost = "/sys/devices/system/cpu/cpufreq/boost";
-
-    let value = if enabled { "0" } else { "1" }; // Inverted for no_turbo
-    let boost_value = if enabled { "1" } else { "0" };
-
-    // Try Intel P-state first
-    if std::path::Path::new(intel_pstate).exists() {
-        let script_content = format!(
-            "#!/bin/bash\nset -e\necho {} > {}\nexit 0\n",
-            value, intel_pstate
-        );
-
-        {
-            if let Ok(output) = crate::privileged::run_script(&script_content).await {
-                if output.status.success() {
-                    return ApiResponse {
-                        success: true,
-                        data: Some(format!(
-                            "Turbo boost {}",
-                            if enabled { "enabled" } else { "disabled" }
-                        )),
-                        error: None,
-                    };
-                }
-            }
-        }
+    if !crate::hardware_root::exists(TURBO_INTEL_PSTATE)
+        && !crate::hardware_root::exists(TURBO_CPUFREQ_BOOST)
+    {
+        return ApiResponse::err(Error::hardware_absent("Turbo boost control not available"));
     }
 
-    // Try cpufreq boost
-    if std::path::Path::new(cpufreq_boost).exists() {
-        let script_content = format!(
-            "#!/bin/bash\nset -e\necho {} > {}\nexit 0\n",
-            boost_value, cpufreq_boost
-        );
-
-        {
-            if let Ok(output) = crate::privileged::run_script(&script_content).await {
-                if output.status.success() {
-                    return ApiResponse {
-                        success: true,
-                        data: Some(format!(
-                            "Turbo boost {}",
-                            if enabled { "enabled" } else { "disabled" }
-                        )),
-                        error: None,
-                    };
-                }
-            }
-        }
-    }
+    let result = match write_turbo(enabled) {
+        Ok(()) => Ok(()),
+        Err(e) if crate::hardware_root::is_simulated() => Err(Error::simulated(format!(
+            "Simulated hardware rejected the change: {}",
+            e
+        ))),
+        Err(_) => crate::helper::run(&crate::helper::Operation::Turbo(enabled))
+            .await
+            .map(|_| ()),
+    };
 
-    ApiResponse {
-        success: false,
-        data: None,
-        error: Some("Failed to set turbo boost".to_string()),
+    match result {
+        Ok(()) => ApiResponse::ok(format!(
+            "Turbo boost {}",
+            if enabled { "enabled" } else { "disabled" }
+        )),
+        Err(e) => ApiResponse::err(e),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
 
-    /// Build a fake CPU tree and actually run the generated script against it.
+    /// Lay out a CPU tree in a scratch copy of the P1 and apply a governor to it.
     ///
-    /// `online` lists the CPU indices that get a `cpufreq/scaling_governor`;
-    /// any index in `present` but not `online` gets a bare `cpuN` directory,
-    /// which is exactly what the kernel leaves behind for an offline CPU.
-    fn run_governor_script(present: &[u32], online: &[u32]) -> (bool, String, Vec<(u32, String)>) {
-        let root = std::env::temp_dir().join(format!(
-            "thinkutils_gov_{}_{}",
-            std::process::id(),
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_nanos()
-        ));
-
-        for cpu in present {
-            let dir = root.join(format!("cpu{}", cpu));
-            if online.contains(cpu) {
-                let freq = dir.join("cpufreq");
-                fs::create_dir_all(&freq).unwrap();
-                fs::write(freq.join("scaling_governor"), "powersave\n").unwrap();
-            } else {
+    /// `online` lists the CPU indices that get a `cpufreq/` directory (a copy
+    /// of the P1's cpu0); any index in `present` but not `online` gets a bare
+    /// `cpuN` directory, which is exactly what the kernel leaves behind for an
+    /// offline CPU. `refusing` CPUs lose their governor list, so the model
+    /// refuses the write the way the kernel refuses an unknown governor.
+    fn apply_to_cpus(
+        present: &[u32],
+        online: &[u32],
+        refusing: &[u32],
+    ) -> (Result<usize, String>, Vec<(u32, String)>) {
+        crate::hardware_root::testing::with_scratch_profile("thinkpad-p1-gen-4i", |root| {
+            let cpus = root.join("sys/devices/system/cpu");
+            let template = std::env::temp_dir().join(format!(
+                "thinkutils_cpufreq_{}_{}",
+                std::process::id(),
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap()
+                    .as_nanos()
+            ));
+            fs::rename(cpus.join("cpu0"), &template).unwrap();
+
+            for cpu in present {
+                let dir = cpus.join(format!("cpu{}", cpu));
                 fs::create_dir_all(&dir).unwrap();
+                if online.contains(cpu) {
+                    let freq = dir.join("cpufreq");
+                    fs::create_dir_all(&freq).unwrap();
+                    for file in ["scaling_governor", "scaling_available_governors"] {
+                        fs::copy(template.join("cpufreq").join(file), freq.join(file)).unwrap();
+                    }
+                    if refusing.contains(cpu) {
+                        fs::remove_file(freq.join("scaling_available_governors")).unwrap();
+                    }
+                }
             }
-        }
-
-        let glob = format!("{}/cpu[0-9]*/cpufreq/scaling_governor", root.display());
-        let script = governor_script("performance", &glob);
-
-        let out = std::process::Command::new("bash")
-            .arg("-c")
-            .arg(&script)
-            .output()
-            .expect("bash runs");
-
-        let mut written = Vec::new();
-        for cpu in online {
-            let p = root
-                .join(format!("cpu{}", cpu))
-                .join("cpufreq/scaling_governor");
-            if let Ok(v) = fs::read_to_string(&p) {
-                written.push((*cpu, v.trim().to_string()));
-            }
-        }
-
-        let combined = format!(
-            "{}{}",
-            String::from_utf8_lossy(&out.stdout),
-            String::from_utf8_lossy(&out.stderr)
-        );
-        let _ = fs::remove_dir_all(&root);
-        (out.status.success(), combined, written)
+            let _ = fs::remove_dir_all(&template);
+
+            let result = apply_governor("performance");
+            let written = online
+                .iter()
+                .map(|cpu| {
+                    let p = cpus.join(format!("cpu{}/cpufreq/scaling_governor", cpu));
+                    (*cpu, fs::read_to_string(p).unwrap().trim().to_string())
+                })
+                .collect();
+            (result, written)
+        })
     }
 
-    /// The regression: cpu5 offline used to abort the whole script under set -e,
+    /// The regression: cpu5 offline used to abort the whole run under set -e,
     /// after cpu0..cpu4 had already been changed — mixed governors, and a UI
     /// saying the operation failed.
     #[test]
     fn an_offline_cpu_does_not_abort_the_others() {
-        let (ok, output, written) = run_governor_script(&[0, 1, 2, 3, 4, 5], &[0, 1, 2, 3, 4]);
+        let (result, written) = apply_to_cpus(&[0, 1, 2, 3, 4, 5], &[0, 1, 2, 3, 4], &[]);
 
-        assert!(
-            ok,
-            "script should succeed despite cpu5 being offline:\n{output}"
-        );
-        assert_eq!(written.len(), 5, "every online CPU should be written");
+        assert_eq!(result, Ok(5), "every online CPU should be written");
         for (cpu, value) in &written {
             assert_eq!(value, "performance", "cpu{} kept the old governor", cpu);
         }
-        assert!(
-            output.contains("applied to 5 CPU(s)"),
-            "should report what it actually did:\n{output}"
-        );
     }
 
     /// Numbering is not guaranteed contiguous, and indexing 0..count assumed it.
     #[test]
     fn non_contiguous_cpu_numbering_is_handled() {
-        let (ok, output, written) = run_governor_script(&[0, 3, 7], &[0, 3, 7]);
-
-        assert!(ok, "gaps in numbering are normal:\n{output}");
-        assert_eq!(written.len(), 3, "cpu0, cpu3 and cpu7 should all be set");
+        let (result, _) = apply_to_cpus(&[0, 3, 7], &[0, 3, 7], &[]);
+        assert_eq!(result, Ok(3), "cpu0, cpu3 and cpu7 should all be set");
     }
 
-    /// An existing-but-unwritable governor file is the case `set -e` actually
-    /// aborted on, so exercise it directly: one refusal must not cost the rest.
-    #[cfg(unix)]
+    /// A CPU refusing the write is the case `set -e` actually aborted on, so
+    /// exercise it directly: one refusal must not cost the rest.
     #[test]
     fn one_refused_write_does_not_discard_the_successful_ones() {
-        use std::os::unix::fs::PermissionsExt;
-
-        let root = std::env::temp_dir().join(format!(
-            "thinkutils_gov_ro_{}_{}",
-            std::process::id(),
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_nanos()
-        ));
-
-        for cpu in 0..3u32 {
-            let freq = root.join(format!("cpu{}", cpu)).join("cpufreq");
-            fs::create_dir_all(&freq).unwrap();
-            let f = freq.join("scaling_governor");
-            fs::write(&f, "powersave\n").unwrap();
-            if cpu == 1 {
-                fs::set_permissions(&f, fs::Permissions::from_mode(0o444)).unwrap();
-            }
-        }
-
-        let glob = format!("{}/cpu[0-9]*/cpufreq/scaling_governor", root.display());
-        let out = std::process::Command::new("bash")
-            .arg("-c")
-            .arg(governor_script("performance", &glob))
-            .output()
-            .expect("bash runs");
-        let combined = format!(
-            "{}{}",
-            String::from_utf8_lossy(&out.stdout),
-            String::from_utf8_lossy(&out.stderr)
-        );
-
-        let read = |cpu: u32| {
-            fs::read_to_string(
-                root.join(format!("cpu{}", cpu))
-                    .join("cpufreq/scaling_governor"),
-            )
-            .unwrap()
-            .trim()
-            .to_string()
-        };
-
-        // Root can write through 0444, which would make this assert nothing.
-        if read(1) == "performance" {
-            let _ = fs::remove_dir_all(&root);
-            eprintln!("skipping: running as root, 0444 is not enforced");
-            return;
-        }
-
-        assert!(
-            out.status.success(),
-            "one unwritable CPU must not fail the whole operation:\n{combined}"
-        );
-        assert_eq!(read(0), "performance", "cpu0 should have been set");
-        assert_eq!(read(2), "performance", "cpu2 should have been set");
-        assert!(
-            combined.contains("applied to 2 CPU(s), 1 refused"),
-            "should report the partial result honestly:\n{combined}"
+        let (result, written) = apply_to_cpus(&[0, 1, 2], &[0, 1, 2], &[1]);
+
+        assert_eq!(result, Ok(2), "one refusing CPU must not fail the rest");
+        assert_eq!(
+            written,
+            [
+                (0, "performance".to_string()),
+                (1, "powersave".to_string()),
+                (2, "performance".to_string())
+            ]
         );
-
-        let _ = fs::remove_dir_all(&root);
     }
 
     /// Failing silently would be worse than failing loudly: the UI would report
     /// a governor change that never touched anything.
     #[test]
     fn no_writable_cpu_at_all_is_still_an_error() {
-        let (ok, output, _) = run_governor_script(&[0, 1], &[]);
+        let (result, _) = apply_to_cpus(&[0, 1], &[], &[]);
+        assert!(result.is_err(), "a total failure must surface");
 
-        assert!(!ok, "a total failure must surface:\n{output}");
+        let (result, _) = apply_to_cpus(&[0, 1], &[0, 1], &[0, 1]);
         assert!(
-            output.contains("no CPU accepted the governor"),
-            "should say why:\n{output}"
+            result.is_err_and(|e| e.contains("No CPU accepted the governor")),
+            "should say why"
         );
     }
 
@@ -605,7 +569,7 @@ mod tests {
         }
     }
 
-    /// The value reaches a root shell via pkexec, so shell metacharacters must
+    /// The value is written as root by the helper, so shell metacharacters must
     /// never survive validation.
     #[test]
     fn rejects_shell_injection_payloads() {
@@ -636,4 +600,59 @@ mod tests {
     fn rejects_overlong_names() {
         assert!(validate_governor(&"a".repeat(33)).is_err());
     }
+
+    // -- Against a captured P1 Gen 4i --
+
+    use crate::hardware_root::testing::with_profile;
+
+    #[test]
+    fn reads_the_captured_cpufreq_state() {
+        let info = with_profile("thinkpad-p1-gen-4i", get_cpu_info)
+            .data
+            .unwrap();
+        assert_eq!(info.governor, "powersave");
+        assert_eq!(info.available_governors, ["performance", "powersave"]);
+        assert_eq!((info.min_freq, info.max_freq), (800, 4900));
+
+        let turbo = with_profile("thinkpad-p1-gen-4i", get_turbo_boost_status);
+        assert_eq!(turbo.data, Some(true), "no_turbo=0 means turbo is on");
+    }
+
+    /// intel_pstate offers only two governors. The availability check has to
+    /// see the profile's list, not the host's, or this passes on any machine
+    /// that happens to have schedutil.
+    #[test]
+    fn governor_validation_uses_the_profiles_list() {
+        with_profile("thinkpad-p1-gen-4i", || {
+            assert!(validate_governor("performance").is_ok());
+            assert!(validate_governor("schedutil").is_err());
+        });
+    }
+
+    #[test]
+    fn simulated_turbo_switch_takes_only_zero_or_one() {
+        crate::hardware_root::testing::with_scratch_profile("thinkpad-p1-gen-4i", |_| {
+            assert!(crate::hardware_root::write(TURBO_INTEL_PSTATE, "2").is_err());
+            crate::hardware_root::write(TURBO_INTEL_PSTATE, "1").unwrap();
+            assert_eq!(get_turbo_boost_status().data, Some(false));
+        });
+    }
+
+    #[test]
+    fn the_platform_profile_takes_only_its_choices() {
+        crate::hardware_root::testing::with_scratch_profile("thinkpad-p1-gen-4i", |_| {
+            assert!(validate_platform_profile("low-power").is_ok());
+            assert!(validate_platform_profile("power-saver").is_err());
+            assert_eq!(platform_profile_for("power-saver"), "low-power");
+
+            assert!(write_platform_profile("quiet").is_err());
+            write_platform_profile("performance").unwrap();
+            assert_eq!(
+                crate::hardware_root::read_to_string(PLATFORM_PROFILE)
+                    .unwrap()
+                    .trim(),
+                "performance"
+            );
+        });
+    }
 }
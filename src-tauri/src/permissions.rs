@@ -1,19 +1,17 @@
+use crate::error::{ApiResponse, Error};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
 
-use crate::fan_control::{
-    helper_is_packaged, helper_path, polkit_rule, HELPER_SCRIPT, HELPER_SELF_INSTALL_PATH,
+use crate::hardware_service::{
+    activation_file, systemd_unit, ACTIVATION_FILE_PATH, BUS_POLICY, BUS_POLICY_PATH,
+    SELF_INSTALL_UNIT_PATH,
+};
+use crate::helper::{
+    bundled_helper, helper_is_packaged, helper_path, polkit_pkla, polkit_policy, polkit_rule,
+    HELPER_SELF_INSTALL_PATH, LEGACY_SCRIPT_HELPER_PATH, POLKIT_PKLA_PATH, POLKIT_POLICY_PATH,
     POLKIT_RULE_PATH,
 };
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ApiResponse<T> {
-    pub success: bool,
-    pub data: Option<T>,
-    pub error: Option<String>,
-}
-
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PermissionStatus {
     pub has_permissions: bool,
@@ -27,7 +25,9 @@ pub struct PermissionStatus {
 // standard kernel pair. Both exist on a ThinkPad and report the same value, but
 // they are separate sysfs files, so granting one never affected the other --
 // "Grant Permissions" silently never fixed battery thresholds. They come from
-// battery::threshold_paths() now, which is the single source of truth.
+// battery::all_threshold_paths() now, which is the single source of truth, and
+// cover every battery rather than only the first. Each battery's
+// charge_behaviour comes from battery.rs the same way.
 //
 // thinkpad_hwmon/pwm1 is also gone: that path does not exist. The real attribute
 // lives under .../thinkpad_hwmon/hwmon/hwmonN/pwm1, and the exists() guard below
@@ -46,17 +46,22 @@ const REQUIRED_FILES: &[&str] = &[
 /// Every sysfs file the app wants writable, resolved for this machine.
 fn required_files() -> Vec<String> {
     let mut files: Vec<String> = REQUIRED_FILES.iter().map(|s| s.to_string()).collect();
-    if let Some((start, stop)) = crate::battery::threshold_paths() {
-        files.push(start);
-        files.push(stop);
+    for paths in crate::battery::all_threshold_paths() {
+        files.push(paths.start);
+        files.push(paths.stop);
     }
+    files.extend(crate::battery::charge_behaviour_paths());
     // The thinkpad hwmon PWM lives under a numbered hwmon directory, so it has to
     // be discovered rather than hardcoded.
-    if let Ok(entries) = fs::read_dir("/sys/devices/platform/thinkpad_hwmon/hwmon") {
+    // The path is rebuilt from the entry's name rather than taken from the entry:
+    // under a hardware root the entry points into the fixture, and these paths
+    // are the ones chmod'ed.
+    let hwmon_dir = crate::fan_control::THINKPAD_HWMON_DIR;
+    if let Ok(entries) = crate::hardware_root::read_dir(hwmon_dir) {
         for entry in entries.flatten() {
-            let pwm = entry.path().join("pwm1");
-            if pwm.exists() {
-                files.push(pwm.to_string_lossy().to_string());
+            let pwm = format!("{}/{}/pwm1", hwmon_dir, entry.file_name().to_string_lossy());
+            if crate::hardware_root::exists(&pwm) {
+                files.push(pwm);
             }
         }
     }
@@ -68,7 +73,7 @@ pub async fn check_permissions_status() -> ApiResponse<PermissionStatus> {
     let mut missing_files = Vec::new();
 
     for file_path in required_files() {
-        if Path::new(&file_path).exists() {
+        if crate::hardware_root::exists(&file_path) {
             // Check if we can write to it
             match fs::OpenOptions::new().write(true).open(&file_path) {
                 Ok(_) => {
@@ -87,7 +92,7 @@ pub async fn check_permissions_status() -> ApiResponse<PermissionStatus> {
     if helper_path().is_none() {
         // Can we at least write to the fan file directly?
         let fan_path = "/proc/acpi/ibm/fan";
-        if Path::new(fan_path).exists()
+        if crate::hardware_root::exists(fan_path)
             && fs::OpenOptions::new().write(true).open(fan_path).is_err()
         {
             missing_files.push("Fan control helper (not installed)".to_string());
@@ -96,20 +101,24 @@ pub async fn check_permissions_status() -> ApiResponse<PermissionStatus> {
 
     let has_permissions = missing_files.is_empty();
 
-    ApiResponse {
-        success: true,
-        data: Some(PermissionStatus {
-            has_permissions,
-            missing_files,
-        }),
-        error: None,
-    }
+    ApiResponse::ok(PermissionStatus {
+        has_permissions,
+        missing_files,
+    })
 }
 
 #[tauri::command]
 pub async fn setup_permissions() -> ApiResponse<String> {
     println!("[Permissions] Setting up file permissions...");
 
+    // chmod on a captured profile would change nothing real, and a scratch copy
+    // is already writable.
+    if crate::hardware_root::is_simulated() {
+        return ApiResponse::err(Error::simulated(
+            "Running against a simulated hardware profile; there are no permissions to grant.",
+        ));
+    }
+
     let username = std::env::var("USER").unwrap_or_else(|_| "root".to_string());
 
     // Validate username to prevent command injection (only allow alphanumeric, dash, underscore)
@@ -117,11 +126,7 @@ pub async fn setup_permissions() -> ApiResponse<String> {
         .chars()
         .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
     {
-        return ApiResponse {
-            success: false,
-            data: None,
-            error: Some("Invalid username detected".to_string()),
-        };
+        return ApiResponse::err(Error::validation("Invalid username detected"));
     }
 
     // Create a script that sets up all permissions
@@ -133,7 +138,7 @@ pub async fn setup_permissions() -> ApiResponse<String> {
 
     // Add chmod commands for each file that exists
     for file_path in required_files() {
-        if Path::new(&file_path).exists() {
+        if crate::hardware_root::exists(&file_path) {
             script_lines.push(format!("if [ -f {} ]; then", file_path));
             script_lines.push(format!("  chmod 666 {} 2>/dev/null || true", file_path));
             script_lines.push(format!(
@@ -146,7 +151,7 @@ pub async fn setup_permissions() -> ApiResponse<String> {
 
     // Also set up the fan control file if it exists
     let fan_file = "/sys/devices/platform/thinkpad_hwmon/pwm1_enable";
-    if Path::new(fan_file).exists() {
+    if crate::hardware_root::exists(fan_file) {
         script_lines.push(format!("if [ -f {} ]; then", fan_file));
         script_lines.push(format!("  chmod 666 {} 2>/dev/null || true", fan_file));
         script_lines.push(format!(
@@ -156,26 +161,65 @@ pub async fn setup_permissions() -> ApiResponse<String> {
         script_lines.push("fi".to_string());
     }
 
-    // Install the fan helper and polkit rule -- but only when this app owns
-    // them. On a distro-packaged install both files belong to dpkg/rpm/pacman,
-    // and rewriting them would put the package database out of sync with the
-    // filesystem and leave orphans behind on uninstall.
+    // Install the privileged helper, its polkit actions and the rule -- but
+    // only when this app owns them. On a distro-packaged install those files
+    // belong to dpkg/rpm/pacman, and rewriting them would put the package
+    // database out of sync with the filesystem and leave orphans behind on
+    // uninstall.
     if helper_is_packaged() {
         println!("[Permissions] Packaged helper detected; only adjusting sysfs permissions");
     } else {
-        script_lines.push(format!(
-            "mkdir -p \"$(dirname {})\"",
-            HELPER_SELF_INSTALL_PATH
-        ));
-        script_lines.push(format!("cat > {} << 'HELPEREOF'", HELPER_SELF_INSTALL_PATH));
-        script_lines.push(HELPER_SCRIPT.trim().to_string());
-        script_lines.push("HELPEREOF".to_string());
-        script_lines.push(format!("chmod 755 {}", HELPER_SELF_INSTALL_PATH));
+        // The helper is a compiled binary shipped next to the app; there is no
+        // script to write out any more. The bash helper older releases
+        // installed would still answer to the old rule, so it goes.
+        match bundled_helper().and_then(|p| p.to_str().map(str::to_string)) {
+            Some(bundled) if !bundled.contains('\'') => {
+                script_lines.push(format!(
+                    "install -D -m 0755 '{}' {}",
+                    bundled, HELPER_SELF_INSTALL_PATH
+                ));
+            }
+            _ => println!("[Permissions] No bundled helper next to the app; skipping its install"),
+        }
+        script_lines.push(format!("rm -f {}", LEGACY_SCRIPT_HELPER_PATH));
+
+        // The rule grants by action ID, so the actions have to exist -- and
+        // name the path the helper was just installed at.
+        script_lines.push(format!("mkdir -p \"$(dirname {})\"", POLKIT_POLICY_PATH));
+        script_lines.push(format!("cat > {} << 'POLICYEOF'", POLKIT_POLICY_PATH));
+        script_lines.push(polkit_policy(HELPER_SELF_INSTALL_PATH).trim().to_string());
+        script_lines.push("POLICYEOF".to_string());
+
+        // The helper's bus service, activated on the first call.
+        for (path, content, marker) in [
+            (BUS_POLICY_PATH, BUS_POLICY.to_string(), "BUSEOF"),
+            (
+                ACTIVATION_FILE_PATH,
+                activation_file(HELPER_SELF_INSTALL_PATH),
+                "ACTIVATIONEOF",
+            ),
+            (
+                SELF_INSTALL_UNIT_PATH,
+                systemd_unit(HELPER_SELF_INSTALL_PATH),
+                "UNITEOF",
+            ),
+        ] {
+            script_lines.push(format!("mkdir -p \"$(dirname {})\"", path));
+            script_lines.push(format!("cat > {} << '{}'", path, marker));
+            script_lines.push(content.trim().to_string());
+            script_lines.push(marker.to_string());
+        }
+        script_lines.push("systemctl daemon-reload 2>/dev/null || true".to_string());
+        script_lines.push("systemctl reload dbus 2>/dev/null || true".to_string());
 
         script_lines.push("mkdir -p /etc/polkit-1/rules.d".to_string());
         script_lines.push(format!("cat > {} << 'RULEEOF'", POLKIT_RULE_PATH));
         script_lines.push(polkit_rule().trim().to_string());
         script_lines.push("RULEEOF".to_string());
+        script_lines.push(format!("mkdir -p \"$(dirname {})\"", POLKIT_PKLA_PATH));
+        script_lines.push(format!("cat > {} << 'PKLAEOF'", POLKIT_PKLA_PATH));
+        script_lines.push(polkit_pkla().trim().to_string());
+        script_lines.push("PKLAEOF".to_string());
         script_lines.push(
             "systemctl reload polkit 2>/dev/null || killall -HUP polkitd 2>/dev/null || true"
                 .to_string(),
@@ -207,32 +251,20 @@ pub async fn setup_permissions() -> ApiResponse<String> {
         {
             Ok(f) => f,
             Err(e) => {
-                return ApiResponse {
-                    success: false,
-                    data: None,
-                    error: Some(format!("Failed to create setup script: {}", e)),
-                };
+                return ApiResponse::err(format!("Failed to create setup script: {}", e));
             }
         };
 
         if let Err(e) = file.write_all(script_content.as_bytes()) {
             let _ = fs::remove_file(&temp_script);
-            return ApiResponse {
-                success: false,
-                data: None,
-                error: Some(format!("Failed to write setup script: {}", e)),
-            };
+            return ApiResponse::err(format!("Failed to write setup script: {}", e));
         }
     }
 
     #[cfg(not(unix))]
     {
         if let Err(e) = fs::write(&temp_script, &script_content) {
-            return ApiResponse {
-                success: false,
-                data: None,
-                error: Some(format!("Failed to create setup script: {}", e)),
-            };
+            return ApiResponse::err(format!("Failed to create setup script: {}", e));
         }
     }
 
@@ -248,29 +280,47 @@ pub async fn setup_permissions() -> ApiResponse<String> {
 
             if output.status.success() {
                 println!("[Permissions] ✓ Permissions setup successful");
-                ApiResponse {
-                    success: true,
-                    data: Some("Permissions configured successfully".to_string()),
-                    error: None,
-                }
+                ApiResponse::ok("Permissions configured successfully".to_string())
             } else {
                 let stderr = String::from_utf8_lossy(&output.stderr);
                 println!("[Permissions] ✗ Setup failed: {}", stderr);
-                ApiResponse {
-                    success: false,
-                    data: None,
-                    error: Some(format!("Permission setup failed: {}", stderr)),
-                }
+                ApiResponse::err(format!("Permission setup failed: {}", stderr))
             }
         }
         Err(e) => {
             let _ = fs::remove_file(&temp_script);
             println!("[Permissions] ✗ Failed to execute pkexec: {}", e);
-            ApiResponse {
-                success: false,
-                data: None,
-                error: Some(format!("Failed to setup permissions: {}", e)),
-            }
+            ApiResponse::err(format!("Failed to setup permissions: {}", e))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware_root::testing::with_profile;
+
+    /// The PWM sits under a numbered hwmon directory that has to be discovered.
+    /// Discovery runs in the profile, but the path reported is the real one,
+    /// because it is what gets chmod'ed.
+    #[test]
+    fn discovers_the_pwm_and_thresholds_in_the_profile() {
+        let files = with_profile("thinkpad-p1-gen-4i", required_files);
+        assert!(
+            files.contains(&"/sys/devices/platform/thinkpad_hwmon/hwmon/hwmon6/pwm1".to_string()),
+            "{:?}",
+            files
+        );
+        let (start, stop) = with_profile("thinkpad-p1-gen-4i", crate::battery::threshold_paths)
+            .expect("fixture has thresholds");
+        assert!(files.contains(&start) && files.contains(&stop));
+        assert!(files.contains(&"/sys/class/power_supply/BAT0/charge_behaviour".to_string()));
+        assert!(
+            files
+                .iter()
+                .all(|f| f.starts_with('/') && !f.contains("fixtures")),
+            "a fixture path leaked into the chmod list: {:?}",
+            files
+        );
+    }
+}
@@ -18,7 +18,9 @@
 //! window between writing and root executing it, so that user could swap its
 //! contents. That matters only where an administrator authenticates on behalf of
 //! a less-privileged user, and closing it properly means not passing a
-//! user-owned script to root at all — the shape the fan helper already uses.
+//! user-owned script to root at all — the shape `helper` uses. Hardware writes
+//! all go through that helper now, enabling fan control in thinkpad_acpi
+//! included; what is left here is the one-off authentication prompt.
 
 use std::process::Output;
 
@@ -1,3 +1,4 @@
+use crate::error::{ApiResponse, Error, ErrorCode};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::process::Command;
@@ -28,113 +29,77 @@ pub struct ThreatInfo {
     pub threat_name: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Response<T> {
-    pub success: bool,
-    pub data: Option<T>,
-    pub error: Option<String>,
-}
-
 #[derive(Debug, Serialize, Deserialize)]
 pub struct InstallResponse {
     pub success: bool,
     pub message: String,
     pub logs: Vec<String>,
     pub error: Option<String>,
+    /// `tool_missing` when this distribution has to install ClamAV by hand,
+    /// with the instructions in `error`.
+    pub code: Option<ErrorCode>,
 }
 
 // Check if ClamAV is installed and get status
 #[tauri::command]
-pub async fn get_security_status() -> Response<SecurityStatus> {
+pub async fn get_security_status() -> ApiResponse<SecurityStatus> {
     let clamav_installed = check_clamav_installed();
 
     if !clamav_installed {
-        return Response {
-            success: true,
-            data: Some(SecurityStatus {
-                clamav_installed: false,
-                clamav_running: false,
-                database_version: "N/A".to_string(),
-                last_update: "N/A".to_string(),
-                definitions_count: "N/A".to_string(),
-            }),
-            error: None,
-        };
+        return ApiResponse::ok(SecurityStatus {
+            clamav_installed: false,
+            clamav_running: false,
+            database_version: "N/A".to_string(),
+            last_update: "N/A".to_string(),
+            definitions_count: "N/A".to_string(),
+        });
     }
 
     let clamav_running = check_clamd_running();
     let (db_version, last_update, def_count) = get_database_info();
 
-    Response {
-        success: true,
-        data: Some(SecurityStatus {
-            clamav_installed,
-            clamav_running,
-            database_version: db_version,
-            last_update,
-            definitions_count: def_count,
-        }),
-        error: None,
-    }
+    ApiResponse::ok(SecurityStatus {
+        clamav_installed,
+        clamav_running,
+        database_version: db_version,
+        last_update,
+        definitions_count: def_count,
+    })
 }
 
 // Update ClamAV virus definitions
 #[tauri::command]
-pub async fn update_virus_definitions() -> Response<String> {
+pub async fn update_virus_definitions() -> ApiResponse<String> {
     if !check_clamav_installed() {
-        return Response {
-            success: false,
-            data: None,
-            error: Some("ClamAV is not installed".to_string()),
-        };
+        return ApiResponse::err(Error::tool_missing("ClamAV is not installed"));
     }
 
     match Command::new("pkexec").args(["freshclam"]).output() {
         Ok(output) => {
             if output.status.success() {
-                Response {
-                    success: true,
-                    data: Some("Virus definitions updated successfully".to_string()),
-                    error: None,
-                }
+                ApiResponse::ok("Virus definitions updated successfully".to_string())
             } else {
                 let error_msg = String::from_utf8_lossy(&output.stderr).to_string();
-                Response {
-                    success: false,
-                    data: None,
-                    error: Some(format!("Update failed: {}", error_msg)),
-                }
+                ApiResponse::err(format!("Update failed: {}", error_msg))
             }
         }
-        Err(e) => Response {
-            success: false,
-            data: None,
-            error: Some(format!("Failed to run freshclam: {}", e)),
-        },
+        Err(e) => ApiResponse::err(format!("Failed to run freshclam: {}", e)),
     }
 }
 
 // Scan a file or directory
 #[tauri::command]
-pub async fn scan_path(path: String) -> Response<ScanResult> {
+pub async fn scan_path(path: String) -> ApiResponse<ScanResult> {
     let mut logs = Vec::new();
 
     if !check_clamav_installed() {
         logs.push("✗ ClamAV is not installed".to_string());
-        return Response {
-            success: false,
-            data: None,
-            error: Some("ClamAV is not installed".to_string()),
-        };
+        return ApiResponse::err(Error::tool_missing("ClamAV is not installed"));
     }
 
     if !Path::new(&path).exists() {
         logs.push(format!("✗ Path does not exist: {}", path));
-        return Response {
-            success: false,
-            data: None,
-            error: Some("Path does not exist".to_string()),
-        };
+        return ApiResponse::err(Error::validation("Path does not exist"));
     }
 
     logs.push(format!("Starting scan of: {}", path));
@@ -180,23 +145,19 @@ pub async fn scan_path(path: String) -> Response<ScanResult> {
             }
             logs.push(format!("Scan completed in {}", scan_time));
 
-            Response {
+            ApiResponse::ok(ScanResult {
                 success: true,
-                data: Some(ScanResult {
-                    success: true,
-                    scanned_files: scanned,
-                    infected_files: infected,
-                    scan_time,
-                    threats,
-                    logs,
-                    error: None,
-                }),
+                scanned_files: scanned,
+                infected_files: infected,
+                scan_time,
+                threats,
+                logs,
                 error: None,
-            }
+            })
         }
         Err(e) => {
             logs.push(format!("✗ Scan failed: {}", e));
-            Response {
+            ApiResponse {
                 success: false,
                 data: Some(ScanResult {
                     success: false,
@@ -208,6 +169,7 @@ pub async fn scan_path(path: String) -> Response<ScanResult> {
                     error: Some(format!("Scan failed: {}", e)),
                 }),
                 error: Some(format!("Scan failed: {}", e)),
+                code: Some(ErrorCode::Failed),
             }
         }
     }
@@ -215,16 +177,12 @@ pub async fn scan_path(path: String) -> Response<ScanResult> {
 
 // Quick scan of common directories
 #[tauri::command]
-pub async fn quick_scan() -> Response<ScanResult> {
+pub async fn quick_scan() -> ApiResponse<ScanResult> {
     let mut logs = Vec::new();
 
     if !check_clamav_installed() {
         logs.push("✗ ClamAV is not installed".to_string());
-        return Response {
-            success: false,
-            data: None,
-            error: Some("ClamAV is not installed".to_string()),
-        };
+        return ApiResponse::err(Error::tool_missing("ClamAV is not installed"));
     }
 
     let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/home".to_string());
@@ -285,19 +243,15 @@ pub async fn quick_scan() -> Response<ScanResult> {
     }
     logs.push(format!("Total scan time: {}", scan_time));
 
-    Response {
+    ApiResponse::ok(ScanResult {
         success: true,
-        data: Some(ScanResult {
-            success: true,
-            scanned_files: total_scanned,
-            infected_files: total_infected,
-            scan_time,
-            threats: all_threats,
-            logs,
-            error: None,
-        }),
+        scanned_files: total_scanned,
+        infected_files: total_infected,
+        scan_time,
+        threats: all_threats,
+        logs,
         error: None,
-    }
+    })
 }
 
 // Install ClamAV
@@ -329,10 +283,8 @@ pub async fn install_clamav() -> InstallResponse {
                 success: false,
                 message: String::new(),
                 logs,
-                error: Some(format!(
-                    "MANUAL_INSTALL:{}",
-                    get_manual_install_instructions(&distro)
-                )),
+                error: Some(get_manual_install_instructions(&distro)),
+                code: Some(ErrorCode::ToolMissing),
             }
         }
     }
@@ -412,6 +364,7 @@ fn install_with_apt(mut logs: Vec<String>) -> InstallResponse {
                     message: "ClamAV installed successfully".to_string(),
                     logs,
                     error: None,
+                    code: None,
                 }
             } else {
                 for line in stderr.lines() {
@@ -426,6 +379,7 @@ fn install_with_apt(mut logs: Vec<String>) -> InstallResponse {
                     message: String::new(),
                     logs,
                     error: Some("Installation failed. Check logs for details.".to_string()),
+                    code: Some(ErrorCode::Failed),
                 }
             }
         }
@@ -436,6 +390,7 @@ fn install_with_apt(mut logs: Vec<String>) -> InstallResponse {
                 message: String::new(),
                 logs,
                 error: Some(format!("Failed to install: {}", e)),
+                code: Some(ErrorCode::Failed),
             }
         }
     }
@@ -486,6 +441,7 @@ fn install_with_dnf(mut logs: Vec<String>) -> InstallResponse {
                     message: "ClamAV installed successfully".to_string(),
                     logs,
                     error: None,
+                    code: None,
                 }
             } else {
                 for line in stderr.lines() {
@@ -500,6 +456,7 @@ fn install_with_dnf(mut logs: Vec<String>) -> InstallResponse {
                     message: String::new(),
                     logs,
                     error: Some("Installation failed. Check logs for details.".to_string()),
+                    code: Some(ErrorCode::Failed),
                 }
             }
         }
@@ -510,6 +467,7 @@ fn install_with_dnf(mut logs: Vec<String>) -> InstallResponse {
                 message: String::new(),
                 logs,
                 error: Some(format!("Failed to install: {}", e)),
+                code: Some(ErrorCode::Failed),
             }
         }
     }
@@ -560,6 +518,7 @@ fn install_with_pacman(mut logs: Vec<String>) -> InstallResponse {
                     message: "ClamAV installed successfully".to_string(),
                     logs,
                     error: None,
+                    code: None,
                 }
             } else {
                 for line in stderr.lines() {
@@ -574,6 +533,7 @@ fn install_with_pacman(mut logs: Vec<String>) -> InstallResponse {
                     message: String::new(),
                     logs,
                     error: Some("Installation failed. Check logs for details.".to_string()),
+                    code: Some(ErrorCode::Failed),
                 }
             }
         }
@@ -584,6 +544,7 @@ fn install_with_pacman(mut logs: Vec<String>) -> InstallResponse {
                 message: String::new(),
                 logs,
                 error: Some(format!("Failed to install: {}", e)),
+                code: Some(ErrorCode::Failed),
             }
         }
     }
@@ -634,6 +595,7 @@ fn install_with_zypper(mut logs: Vec<String>) -> InstallResponse {
                     message: "ClamAV installed successfully".to_string(),
                     logs,
                     error: None,
+                    code: None,
                 }
             } else {
                 for line in stderr.lines() {
@@ -648,6 +610,7 @@ fn install_with_zypper(mut logs: Vec<String>) -> InstallResponse {
                     message: String::new(),
                     logs,
                     error: Some("Installation failed. Check logs for details.".to_string()),
+                    code: Some(ErrorCode::Failed),
                 }
             }
         }
@@ -658,6 +621,7 @@ fn install_with_zypper(mut logs: Vec<String>) -> InstallResponse {
                 message: String::new(),
                 logs,
                 error: Some(format!("Failed to install: {}", e)),
+                code: Some(ErrorCode::Failed),
             }
         }
     }
@@ -1,13 +1,8 @@
+use crate::fan_curve::{check_curve, CurvePoint};
 use serde::{Deserialize, Serialize};
 use tauri::AppHandle;
 use tauri_plugin_store::StoreExt;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CurvePoint {
-    pub temp: i32,
-    pub level: i32,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppSettings {
     // Fan Control
@@ -19,6 +14,10 @@ pub struct AppSettings {
     // Battery
     pub battery_start_threshold: i32,
     pub battery_stop_threshold: i32,
+    /// Health, in percent, at which the wear trend projects a replacement.
+    /// Defaulted so settings saved before it existed still load.
+    #[serde(default = "default_replacement_threshold")]
+    pub battery_replacement_threshold: u8,
 
     // Performance
     pub cpu_governor: String,
@@ -39,16 +38,42 @@ impl Default for AppSettings {
             fan_level: 0,
             fan_curve_enabled: false,
             fan_curve_points: vec![
-                CurvePoint { temp: 40, level: 0 },
-                CurvePoint { temp: 50, level: 1 },
-                CurvePoint { temp: 60, level: 3 },
-                CurvePoint { temp: 70, level: 5 },
-                CurvePoint { temp: 80, level: 7 },
+                CurvePoint {
+                    temp: 40,
+                    level: 0,
+                    fall_temp: None,
+                    rpm: None,
+                },
+                CurvePoint {
+                    temp: 50,
+                    level: 1,
+                    fall_temp: None,
+                    rpm: None,
+                },
+                CurvePoint {
+                    temp: 60,
+                    level: 3,
+                    fall_temp: None,
+                    rpm: None,
+                },
+                CurvePoint {
+                    temp: 70,
+                    level: 5,
+                    fall_temp: None,
+                    rpm: None,
+                },
+                CurvePoint {
+                    temp: 80,
+                    level: 7,
+                    fall_temp: None,
+                    rpm: None,
+                },
             ],
 
             // Battery defaults
             battery_start_threshold: 40,
             battery_stop_threshold: 80,
+            battery_replacement_threshold: default_replacement_threshold(),
 
             // Performance defaults
             cpu_governor: "powersave".to_string(),
@@ -63,12 +88,20 @@ impl Default for AppSettings {
     }
 }
 
+fn default_replacement_threshold() -> u8 {
+    crate::battery_history::DEFAULT_REPLACEMENT_THRESHOLD
+}
+
 const STORE_FILE: &str = "settings.json";
 const SETTINGS_KEY: &str = "app_settings";
 
 /// Save all app settings to persistent storage
 #[tauri::command]
 pub async fn save_app_settings(app: AppHandle, settings: AppSettings) -> Result<(), String> {
+    // The same rules the fan curve itself is held to, so a curve restored from
+    // settings can always be applied.
+    check_curve(&settings.fan_curve_points)?;
+
     let store = app
         .store(STORE_FILE)
         .map_err(|e| format!("Failed to get store: {}", e))?;
@@ -133,6 +166,12 @@ pub async fn update_setting(
             settings.fan_curve_enabled =
                 value.as_bool().ok_or("Invalid fan_curve_enabled value")?;
         }
+        "fan_curve_points" => {
+            let points: Vec<CurvePoint> = serde_json::from_value(value)
+                .map_err(|e| format!("Invalid fan_curve_points value: {}", e))?;
+            check_curve(&points)?;
+            settings.fan_curve_points = points;
+        }
         "battery_start_threshold" => {
             settings.battery_start_threshold = value
                 .as_i64()
@@ -145,6 +184,14 @@ pub async fn update_setting(
                 .ok_or("Invalid battery_stop_threshold value")?
                 as i32;
         }
+        "battery_replacement_threshold" => {
+            let threshold = value
+                .as_u64()
+                .and_then(|v| u8::try_from(v).ok())
+                .ok_or("Invalid battery_replacement_threshold value")?;
+            crate::battery_history::validate_replacement_threshold(threshold)?;
+            settings.battery_replacement_threshold = threshold;
+        }
         "cpu_governor" => {
             settings.cpu_governor = value
                 .as_str()
@@ -1,3 +1,4 @@
+use crate::error::ApiResponse;
 use chrono::Utc;
 use oauth2::basic::BasicClient;
 use oauth2::{
@@ -73,13 +74,6 @@ pub struct SyncState {
     pub refresh_token: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ApiResponse<T> {
-    pub success: bool,
-    pub data: Option<T>,
-    pub error: Option<String>,
-}
-
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AuthInitResponse {
     pub auth_url: String,
@@ -107,7 +101,7 @@ lazy_static::lazy_static! {
     static ref OAUTH_STATE: Arc<Mutex<HashMap<String, (String, String)>>> = Arc::new(Mutex::new(HashMap::new()));
 }
 
-fn get_config_dir() -> Result<PathBuf, String> {
+pub(crate) fn get_config_dir() -> Result<PathBuf, String> {
     let home = std::env::var("HOME")
         .or_else(|_| std::env::var("USERPROFILE"))
         .map_err(|_| "Could not find home directory".to_string())?;
@@ -237,13 +231,7 @@ pub async fn google_auth_init() -> ApiResponse<AuthInitResponse> {
 
     let client = match create_oauth_client() {
         Ok(c) => c,
-        Err(e) => {
-            return ApiResponse {
-                success: false,
-                data: None,
-                error: Some(format!("Failed to create OAuth client: {}", e)),
-            }
-        }
+        Err(e) => return ApiResponse::err(format!("Failed to create OAuth client: {}", e)),
     };
 
     let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
@@ -273,13 +261,9 @@ pub async fn google_auth_init() -> ApiResponse<AuthInitResponse> {
         }
     });
 
-    ApiResponse {
-        success: true,
-        data: Some(AuthInitResponse {
-            auth_url: auth_url.to_string(),
-        }),
-        error: None,
-    }
+    ApiResponse::ok(AuthInitResponse {
+        auth_url: auth_url.to_string(),
+    })
 }
 
 async fn start_callback_server() -> Result<(), String> {
@@ -379,16 +363,8 @@ async fn get_user_info(access_token: &str) -> Result<GoogleUserInfo, String> {
 #[tauri::command]
 pub async fn google_auth_status() -> ApiResponse<SyncState> {
     match load_sync_state() {
-        Ok(state) => ApiResponse {
-            success: true,
-            data: Some(state),
-            error: None,
-        },
-        Err(e) => ApiResponse {
-            success: false,
-            data: None,
-            error: Some(e),
-        },
+        Ok(state) => ApiResponse::ok(state),
+        Err(e) => ApiResponse::err(e),
     }
 }
 
@@ -399,21 +375,11 @@ pub async fn sync_to_cloud(settings: UserSettings) -> ApiResponse<String> {
 
     let mut state = match load_sync_state() {
         Ok(s) => s,
-        Err(e) => {
-            return ApiResponse {
-                success: false,
-                data: None,
-                error: Some(e),
-            }
-        }
+        Err(e) => return ApiResponse::err(e),
     };
 
     if !state.is_logged_in || state.access_token.is_none() {
-        return ApiResponse {
-            success: false,
-            data: None,
-            error: Some("Not logged in".to_string()),
-        };
+        return ApiResponse::err("Not logged in".to_string());
     }
 
     let access_token = state.access_token.as_ref().unwrap();
@@ -425,24 +391,12 @@ pub async fn sync_to_cloud(settings: UserSettings) -> ApiResponse<String> {
             state.last_sync = Some(Utc::now().format("%Y-%m-%d %H:%M:%S").to_string());
 
             if let Err(e) = save_sync_state(&state) {
-                return ApiResponse {
-                    success: false,
-                    data: None,
-                    error: Some(e),
-                };
+                return ApiResponse::err(e);
             }
 
-            ApiResponse {
-                success: true,
-                data: Some("Settings synced to Google Drive".to_string()),
-                error: None,
-            }
+            ApiResponse::ok("Settings synced to Google Drive".to_string())
         }
-        Err(e) => ApiResponse {
-            success: false,
-            data: None,
-            error: Some(format!("Failed to sync: {}", e)),
-        },
+        Err(e) => ApiResponse::err(format!("Failed to sync: {}", e)),
     }
 }
 
@@ -527,36 +481,18 @@ pub async fn sync_from_cloud() -> ApiResponse<UserSettings> {
 
     let state = match load_sync_state() {
         Ok(s) => s,
-        Err(e) => {
-            return ApiResponse {
-                success: false,
-                data: None,
-                error: Some(e),
-            }
-        }
+        Err(e) => return ApiResponse::err(e),
     };
 
     if !state.is_logged_in || state.access_token.is_none() {
-        return ApiResponse {
-            success: false,
-            data: None,
-            error: Some("Not logged in".to_string()),
-        };
+        return ApiResponse::err("Not logged in".to_string());
     }
 
     let access_token = state.access_token.as_ref().unwrap();
 
     match download_from_drive(access_token).await {
-        Ok(settings) => ApiResponse {
-            success: true,
-            data: Some(settings),
-            error: None,
-        },
-        Err(e) => ApiResponse {
-            success: false,
-            data: None,
-            error: Some(format!("Failed to download: {}", e)),
-        },
+        Ok(settings) => ApiResponse::ok(settings),
+        Err(e) => ApiResponse::err(format!("Failed to download: {}", e)),
     }
 }
 
@@ -599,23 +535,11 @@ pub async fn google_logout() -> ApiResponse<String> {
             state.refresh_token = None;
 
             match save_sync_state(&state) {
-                Ok(_) => ApiResponse {
-                    success: true,
-                    data: Some("Logged out successfully".to_string()),
-                    error: None,
-                },
-                Err(e) => ApiResponse {
-                    success: false,
-                    data: None,
-                    error: Some(e),
-                },
+                Ok(_) => ApiResponse::ok("Logged out successfully".to_string()),
+                Err(e) => ApiResponse::err(e),
             }
         }
-        Err(e) => ApiResponse {
-            success: false,
-            data: None,
-            error: Some(format!("Failed to load state: {}", e)),
-        },
+        Err(e) => ApiResponse::err(format!("Failed to load state: {}", e)),
     }
 }
 
@@ -623,16 +547,8 @@ pub async fn google_logout() -> ApiResponse<String> {
 #[tauri::command]
 pub fn get_settings() -> ApiResponse<UserSettings> {
     match load_sync_state() {
-        Ok(state) => ApiResponse {
-            success: true,
-            data: Some(state.settings),
-            error: None,
-        },
-        Err(e) => ApiResponse {
-            success: false,
-            data: None,
-            error: Some(format!("Failed to load settings: {}", e)),
-        },
+        Ok(state) => ApiResponse::ok(state.settings),
+        Err(e) => ApiResponse::err(format!("Failed to load settings: {}", e)),
     }
 }
 
@@ -645,23 +561,11 @@ pub fn save_settings(settings: UserSettings) -> ApiResponse<String> {
         Ok(mut state) => {
             state.settings = settings;
             match save_sync_state(&state) {
-                Ok(_) => ApiResponse {
-                    success: true,
-                    data: Some("Settings saved".to_string()),
-                    error: None,
-                },
-                Err(e) => ApiResponse {
-                    success: false,
-                    data: None,
-                    error: Some(e),
-                },
+                Ok(_) => ApiResponse::ok("Settings saved".to_string()),
+                Err(e) => ApiResponse::err(e),
             }
         }
-        Err(e) => ApiResponse {
-            success: false,
-            data: None,
-            error: Some(format!("Failed to load current state: {}", e)),
-        },
+        Err(e) => ApiResponse::err(format!("Failed to load current state: {}", e)),
     }
 }
 
@@ -1,3 +1,4 @@
+use crate::error::ApiResponse;
 use serde::{Deserialize, Serialize};
 use std::process::Command;
 
@@ -11,13 +12,6 @@ pub struct SystemInfo {
     pub memory: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ApiResponse<T> {
-    pub success: bool,
-    pub data: Option<T>,
-    pub error: Option<String>,
-}
-
 #[tauri::command]
 pub fn get_system_info() -> ApiResponse<SystemInfo> {
     let info = SystemInfo {
@@ -29,11 +23,7 @@ pub fn get_system_info() -> ApiResponse<SystemInfo> {
         memory: get_memory_info(),
     };
 
-    ApiResponse {
-        success: true,
-        data: Some(info),
-        error: None,
-    }
+    ApiResponse::ok(info)
 }
 
 fn get_hostname() -> String {
@@ -72,11 +62,11 @@ fn get_kernel_version() -> String {
 }
 
 fn get_model() -> String {
-    std::fs::read_to_string("/sys/class/dmi/id/product_name")
+    crate::hardware_root::read_to_string("/sys/class/dmi/id/product_name")
         .ok()
         .map(|s| s.trim().to_string())
         .or_else(|| {
-            std::fs::read_to_string("/sys/class/dmi/id/product_version")
+            crate::hardware_root::read_to_string("/sys/class/dmi/id/product_version")
                 .ok()
                 .map(|s| s.trim().to_string())
         })
@@ -84,7 +74,7 @@ fn get_model() -> String {
 }
 
 fn get_cpu_info() -> String {
-    std::fs::read_to_string("/proc/cpuinfo")
+    crate::hardware_root::read_to_string("/proc/cpuinfo")
         .ok()
         .and_then(|content| {
             content
@@ -97,7 +87,7 @@ fn get_cpu_info() -> String {
 }
 
 fn get_memory_info() -> String {
-    std::fs::read_to_string("/proc/meminfo")
+    crate::hardware_root::read_to_string("/proc/meminfo")
         .ok()
         .and_then(|content| {
             content
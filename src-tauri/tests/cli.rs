@@ -0,0 +1,166 @@
+//! Runs `thinkutils <command>` lines against a simulated ThinkPad.
+//!
+//! `simulated_hardware.rs` covers what the commands do to the hardware; this
+//! covers what a script sees: the output, and an exit status that tells a bad
+//! value from a missing interface.
+
+use std::path::{Path, PathBuf};
+use thinkutils_lib::cli;
+use thinkutils_lib::hardware_root::{self, HARDWARE_ROOT_ENV, HARDWARE_WRITABLE_ENV};
+
+const P1: &str = "thinkpad-p1-gen-4i";
+
+/// Both env vars are process-global and the tests in this file run in parallel.
+static ENV_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// A writable hardware root, active for as long as it is held. `None` leaves it
+/// empty: a machine with none of the interfaces.
+struct Scratch {
+    root: PathBuf,
+    _guard: tokio::sync::MutexGuard<'static, ()>,
+}
+
+impl Scratch {
+    async fn of(profile: Option<&str>) -> Self {
+        let guard = ENV_LOCK.lock().await;
+        let root = std::env::temp_dir().join(format!(
+            "thinkutils_cli_{}_{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        match profile {
+            Some(profile) => {
+                let source = Path::new(env!("CARGO_MANIFEST_DIR"))
+                    .join("tests/fixtures/hardware")
+                    .join(profile);
+                hardware_root::scratch_copy(&source, &root).expect("copy profile");
+            }
+            None => std::fs::create_dir_all(&root).unwrap(),
+        }
+
+        std::env::set_var(HARDWARE_ROOT_ENV, &root);
+        std::env::set_var(HARDWARE_WRITABLE_ENV, "1");
+        Scratch {
+            root,
+            _guard: guard,
+        }
+    }
+
+    fn fan_level(&self) -> String {
+        std::fs::read_to_string(self.root.join("proc/acpi/ibm/fan"))
+            .unwrap()
+            .lines()
+            .find_map(|l| l.strip_prefix("level:"))
+            .expect("level line")
+            .trim()
+            .to_string()
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        std::env::remove_var(HARDWARE_ROOT_ENV);
+        std::env::remove_var(HARDWARE_WRITABLE_ENV);
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+/// Exit status, stdout and stderr of one command line.
+async fn thinkutils(line: &str) -> (i32, String, String) {
+    let args: Vec<String> = line.split_whitespace().map(String::from).collect();
+    let (mut out, mut err) = (Vec::new(), Vec::new());
+    let code = cli::run(&args, &mut out, &mut err).await;
+    (
+        code,
+        String::from_utf8(out).unwrap(),
+        String::from_utf8(err).unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn fan_set_changes_the_level_and_reports_it() {
+    let machine = Scratch::of(Some(P1)).await;
+
+    let (code, out, _) = thinkutils("fan set 3").await;
+    assert_eq!(code, 0);
+    assert_eq!(out.trim(), "Fan speed set to: 3");
+    assert_eq!(machine.fan_level(), "3");
+
+    let (code, out, _) = thinkutils("fan --json").await;
+    assert_eq!(code, 0);
+    let status: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(status["success"], true);
+    assert_eq!(status["data"]["level"], "3");
+}
+
+#[tokio::test]
+async fn thresholds_round_trip_through_the_cli() {
+    let _machine = Scratch::of(Some(P1)).await;
+
+    let (code, _, err) = thinkutils("battery thresholds 40 60").await;
+    assert_eq!(code, 0, "{}", err);
+
+    let (code, out, _) = thinkutils("battery thresholds").await;
+    assert_eq!(code, 0);
+    assert_eq!(out.trim(), "40%-60%");
+
+    let (_, out, _) = thinkutils("status --json").await;
+    let status: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(
+        status["data"]["thresholds"],
+        serde_json::json!({ "start": 40, "stop": 60 })
+    );
+    assert_eq!(status["data"]["cpu"]["governor"], "powersave");
+}
+
+#[tokio::test]
+async fn a_rejected_value_exits_65_and_changes_nothing() {
+    let machine = Scratch::of(Some(P1)).await;
+
+    let (code, _, err) = thinkutils("fan set 9").await;
+    assert_eq!(code, 65);
+    assert!(err.contains("Invalid fan speed: 9"), "{}", err);
+    assert_eq!(machine.fan_level(), "auto");
+
+    let (code, out, _) = thinkutils("battery thresholds 80 40 --json").await;
+    assert_eq!(code, 65);
+    let failure: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(failure["success"], false);
+    assert_eq!(failure["kind"], "invalid");
+
+    // The CLI exits before a time limit could be enforced.
+    let (code, _, err) = thinkutils("fan set disengaged").await;
+    assert_eq!(code, 65);
+    assert!(err.contains("only available in the app"), "{}", err);
+    assert_eq!(machine.fan_level(), "auto");
+
+    let (code, _, err) = thinkutils("governor ondemand").await;
+    assert_eq!(code, 65);
+    assert!(err.contains("not available"), "{}", err);
+}
+
+#[tokio::test]
+async fn a_missing_interface_exits_69() {
+    let _machine = Scratch::of(None).await;
+
+    for line in ["fan set 3", "battery thresholds 40 80", "turbo on"] {
+        let (code, _, err) = thinkutils(line).await;
+        assert_eq!(code, 69, "{}: {}", line, err);
+    }
+
+    // A report, unlike a change, leaves out what is missing.
+    let (code, _, _) = thinkutils("status").await;
+    assert_eq!(code, 0);
+}
+
+#[tokio::test]
+async fn a_malformed_line_exits_64_with_usage() {
+    let (code, out, err) = thinkutils("fan spin").await;
+    assert_eq!(code, 64);
+    assert!(out.is_empty());
+    assert!(err.contains("Unknown command: fan spin"), "{}", err);
+    assert!(err.contains("Usage: thinkutils"), "{}", err);
+}
@@ -0,0 +1,327 @@
+//! Drives `thinkutils-daemon` over its socket against a simulated ThinkPad.
+//!
+//! The daemon is started in-process on a scratch socket and config file, with
+//! the hardware root pointed at a writable copy of a captured profile. The
+//! client side is the same blocking code the GUI uses, so these tests cover
+//! both ends of the protocol.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use thinkutils_lib::daemon::{self, Request};
+use thinkutils_lib::error::{Error, ErrorCode};
+use thinkutils_lib::fan_curve::{CurvePoint, FanCurveConfig};
+use thinkutils_lib::hardware_root::{self, HARDWARE_ROOT_ENV, HARDWARE_WRITABLE_ENV};
+
+const P1: &str = "thinkpad-p1-gen-4i";
+
+/// The hardware root is process-global and the tests in this file run in
+/// parallel.
+static ENV_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// A daemon on a scratch socket, over a scratch copy of a profile.
+struct Harness {
+    dir: PathBuf,
+    socket: PathBuf,
+    server: tokio::task::JoinHandle<std::io::Result<()>>,
+    _guard: tokio::sync::MutexGuard<'static, ()>,
+}
+
+impl Harness {
+    /// `config` is written as the daemon's config file before it starts.
+    async fn start(config: Option<serde_json::Value>) -> Self {
+        let guard = ENV_LOCK.lock().await;
+        let dir = std::env::temp_dir().join(format!(
+            "thinkutils_daemon_{}_{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        let root = dir.join("root");
+        let source = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/hardware")
+            .join(P1);
+        hardware_root::scratch_copy(&source, &root).expect("copy profile");
+        std::env::set_var(HARDWARE_ROOT_ENV, &root);
+        std::env::set_var(HARDWARE_WRITABLE_ENV, "1");
+
+        let config_path = dir.join("daemon.json");
+        if let Some(config) = config {
+            std::fs::write(&config_path, config.to_string()).unwrap();
+        }
+
+        let socket = dir.join("daemon.sock");
+        let server = tokio::spawn(daemon::serve(socket.clone(), config_path));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !socket.exists() {
+            assert!(Instant::now() < deadline, "daemon never bound its socket");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        Harness {
+            dir,
+            socket,
+            server,
+            _guard: guard,
+        }
+    }
+
+    /// A request from a client thread, as the GUI makes it.
+    async fn request(&self, request: Request) -> Result<serde_json::Value, Error> {
+        let socket = self.socket.clone();
+        tokio::task::spawn_blocking(move || daemon::request(&socket, &request))
+            .await
+            .unwrap()
+    }
+
+    fn config_file(&self) -> serde_json::Value {
+        let content = std::fs::read_to_string(self.dir.join("daemon.json")).unwrap();
+        serde_json::from_str(&content).unwrap()
+    }
+
+    fn fan_level(&self) -> String {
+        std::fs::read_to_string(self.dir.join("root/proc/acpi/ibm/fan"))
+            .unwrap()
+            .lines()
+            .find_map(|l| l.strip_prefix("level:"))
+            .expect("level line")
+            .trim()
+            .to_string()
+    }
+
+    async fn wait_for_fan_level(&self, level: &str) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while self.fan_level() != level {
+            assert!(
+                Instant::now() < deadline,
+                "fan stayed at {} instead of {}",
+                self.fan_level(),
+                level
+            );
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        self.server.abort();
+        std::env::remove_var(HARDWARE_ROOT_ENV);
+        std::env::remove_var(HARDWARE_WRITABLE_ENV);
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn flat_curve(level: i32) -> Vec<CurvePoint> {
+    [0, 120]
+        .into_iter()
+        .map(|temp| CurvePoint {
+            temp,
+            level,
+            fall_temp: None,
+            rpm: None,
+        })
+        .collect()
+}
+
+/// A config whose active curve holds `level` at every temperature, with the
+/// saved copy in step as the GUI keeps it.
+fn flat_config(level: i32, enabled: bool) -> FanCurveConfig {
+    let mut config = FanCurveConfig {
+        enabled,
+        points: flat_curve(level),
+        ..FanCurveConfig::default()
+    };
+    config
+        .curves
+        .insert(config.active_curve.clone(), flat_curve(level));
+    config
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn the_daemon_reads_the_curve_from_its_config_file() {
+    let stored = flat_config(2, false);
+    let daemon = Harness::start(Some(serde_json::json!({
+        "fan_curve": stored,
+        "app_settings": { "theme": "dark" },
+    })))
+    .await;
+
+    let status = daemon.request(Request::Status).await.unwrap();
+    assert_eq!(status["fan_curve_enabled"], false);
+    assert_eq!(status["battery_thresholds"], serde_json::json!({}));
+
+    let curve: FanCurveConfig =
+        serde_json::from_value(daemon.request(Request::GetFanCurve).await.unwrap()).unwrap();
+    assert_eq!(curve.points, flat_curve(2));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn an_invalid_curve_is_refused_and_not_stored() {
+    let daemon = Harness::start(None).await;
+
+    let config = flat_config(9, false);
+    let e = daemon
+        .request(Request::SetFanCurve {
+            config: Box::new(config),
+        })
+        .await
+        .unwrap_err();
+    assert!(e.message.contains("level 9"), "{}", e);
+    assert_eq!(e.code, ErrorCode::Validation);
+    assert!(!daemon.dir.join("daemon.json").exists());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn the_curve_runs_without_the_gui_and_reports_to_subscribers() {
+    let daemon = Harness::start(None).await;
+
+    let socket = daemon.socket.clone();
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = daemon::subscribe(&socket, |event| {
+            let _ = tx.send(event);
+        });
+    });
+
+    // Only `points` is set: the daemon saves it under the active name.
+    let config = FanCurveConfig {
+        enabled: true,
+        points: flat_curve(5),
+        ..FanCurveConfig::default()
+    };
+    daemon
+        .request(Request::SetFanCurve {
+            config: Box::new(config),
+        })
+        .await
+        .unwrap();
+    daemon.wait_for_fan_level("5").await;
+
+    // Persisted under the same key the GUI's store uses.
+    assert_eq!(daemon.config_file()["fan_curve"]["enabled"], true);
+
+    let update = rx
+        .recv_timeout(Duration::from_secs(10))
+        .expect("no event from the daemon");
+    assert_eq!(update.event, "fan-curve-update");
+    assert_eq!(update.payload["fan_level"], 5);
+
+    // Turning the curve off hands the fan back, as it does in the GUI.
+    daemon
+        .request(Request::EnableFanCurve { enabled: false })
+        .await
+        .unwrap();
+    daemon.wait_for_fan_level("auto").await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn thresholds_set_through_the_daemon_are_applied_and_kept() {
+    let daemon = Harness::start(None).await;
+
+    daemon
+        .request(Request::SetBatteryThresholds {
+            start: 40,
+            stop: 60,
+            battery: None,
+        })
+        .await
+        .unwrap();
+
+    let applied = thinkutils_lib::battery::get_battery_thresholds()
+        .data
+        .unwrap();
+    assert_eq!((applied.start, applied.stop), (40, 60));
+    assert_eq!(
+        daemon.config_file()["battery_thresholds"],
+        serde_json::json!({ "BAT0": { "start": 40, "stop": 60 } })
+    );
+
+    let status = daemon.request(Request::Status).await.unwrap();
+    assert_eq!(status["battery_thresholds"]["BAT0"]["stop"], 60);
+
+    // A named battery's write is kept for that battery.
+    daemon
+        .request(Request::SetBatteryThresholds {
+            start: 50,
+            stop: 70,
+            battery: Some("BAT0".to_string()),
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        daemon.config_file()["battery_thresholds"],
+        serde_json::json!({ "BAT0": { "start": 50, "stop": 70 } })
+    );
+}
+
+/// A refused value keeps its code across the socket, so the CLI exits with
+/// the same status whether or not the daemon is attached.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn refused_thresholds_come_back_as_validation_errors() {
+    let daemon = Harness::start(None).await;
+
+    for (start, stop, battery) in [(80, 40, None), (40, 101, None), (40, 80, Some("BAT9"))] {
+        let e = daemon
+            .request(Request::SetBatteryThresholds {
+                start,
+                stop,
+                battery: battery.map(String::from),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(
+            e.code,
+            ErrorCode::Validation,
+            "{}-{} {:?}",
+            start,
+            stop,
+            battery
+        );
+    }
+    assert!(
+        !daemon.dir.join("daemon.json").exists(),
+        "nothing refused is stored"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn a_second_daemon_does_not_take_the_socket() {
+    let daemon = Harness::start(None).await;
+
+    let second = daemon::serve(daemon.socket.clone(), daemon.dir.join("other.json")).await;
+    assert_eq!(second.unwrap_err().kind(), std::io::ErrorKind::AddrInUse);
+    assert!(daemon.request(Request::Status).await.is_ok());
+}
+
+/// The daemon runs as root and reads whatever a group member sends. A line
+/// that never ends is cut off and refused rather than buffered until memory
+/// runs out, and the daemon carries on serving.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn an_endless_request_is_refused() {
+    let daemon = Harness::start(None).await;
+
+    let socket = daemon.socket.clone();
+    let response = tokio::task::spawn_blocking(move || {
+        use std::io::{BufRead, Write};
+        let mut stream = std::os::unix::net::UnixStream::connect(&socket).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        // The daemon stops reading partway, so the tail may not go through.
+        let _ = stream.write_all(&vec![b'x'; 256 * 1024]);
+        let mut line = String::new();
+        std::io::BufReader::new(stream)
+            .read_line(&mut line)
+            .unwrap();
+        line
+    })
+    .await
+    .unwrap();
+
+    let response: thinkutils_lib::error::ApiResponse<serde_json::Value> =
+        serde_json::from_str(&response).unwrap();
+    assert_eq!(response.failure().unwrap().code, ErrorCode::Validation);
+    assert!(daemon.request(Request::Status).await.is_ok());
+}
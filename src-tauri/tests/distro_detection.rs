@@ -111,42 +111,47 @@ fn derivatives_resolve_through_id_like() {
 }
 
 /// The generated commands are shown to users verbatim, so assert on the exact
-/// strings — including the package name differences between distros.
+/// strings — including the package name differences between distros, and the
+/// joining when several packages go in one command.
 #[test]
 fn generated_commands_are_correct_per_distro() {
     let cases = [
         (
             "ubuntu-24.04",
-            "sudo apt install lm-sensors",
             "sudo apt install policykit-1",
+            "sudo apt install policykit-1 clamav",
         ),
         (
             "fedora-41",
-            "sudo dnf install lm_sensors",
             "sudo dnf install polkit",
+            "sudo dnf install polkit clamav",
+        ),
+        (
+            "arch",
+            "sudo pacman -S polkit",
+            "sudo pacman -S polkit clamav",
         ),
-        ("arch", "sudo pacman -S lm_sensors", "sudo pacman -S polkit"),
         (
             "opensuse-tumbleweed",
-            "sudo zypper install lm_sensors",
             "sudo zypper install polkit",
+            "sudo zypper install polkit clamav",
         ),
     ];
 
-    for (name, want_sensors, want_polkit) in cases {
+    for (name, want_polkit, want_both) in cases {
         let pm = detect_package_manager(&parse_os_release(&fixture(name)));
         assert_eq!(
-            pm.install_command(&[pm.package_name(Tool::Sensors)])
+            pm.install_command(&[pm.package_name(Tool::Polkit)])
                 .unwrap(),
-            want_sensors,
-            "sensors command for {}",
+            want_polkit,
+            "polkit command for {}",
             name
         );
         assert_eq!(
-            pm.install_command(&[pm.package_name(Tool::Polkit)])
+            pm.install_command(&[pm.package_name(Tool::Polkit), pm.package_name(Tool::Clamav)])
                 .unwrap(),
-            want_polkit,
-            "polkit command for {}",
+            want_both,
+            "combined command for {}",
             name
         );
     }
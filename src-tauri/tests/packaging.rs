@@ -1,7 +1,7 @@
 //! Keeps the packaging files in lockstep with the Rust source.
 //!
-//! Three package formats install the fan helper and the polkit rule, and the app
-//! looks for them at paths defined in `fan_control::HELPER_CANDIDATES`. If a
+//! Three package formats install the privileged helper and the polkit rule, and
+//! the app looks for them at paths defined in `helper::HELPER_CANDIDATES`. If a
 //! packaged copy drifts from the constants, the rule grants access to a path the
 //! helper is not installed at — and that fails *silently*: polkit denies, the app
 //! falls back to a password prompt, and it looks exactly like a permissions
@@ -14,8 +14,13 @@
 //! ```
 
 use std::path::PathBuf;
-use thinkutils_lib::fan_control::{
-    polkit_rule, HELPER_CANDIDATES, HELPER_SCRIPT, POLKIT_RULE_PACKAGED_PATH,
+use thinkutils_lib::daemon::{SYSTEMD_UNIT, SYSUSERS};
+use thinkutils_lib::hardware_service::{
+    activation_file, systemd_unit, BUS_POLICY, SYSTEMD_SERVICE,
+};
+use thinkutils_lib::helper::{
+    polkit_pkla, polkit_policy, polkit_rule, HELPER_CANDIDATES, PACKAGED_POLICIES,
+    POLKIT_PKLA_PATH, POLKIT_POLICY_PATH, POLKIT_RULE_PACKAGED_PATH,
 };
 
 fn repo_file(rel: &str) -> PathBuf {
@@ -51,20 +56,239 @@ fn committed_polkit_rule_matches_source() {
 }
 
 #[test]
-fn committed_helper_matches_source() {
+fn committed_polkit_pkla_matches_source() {
+    assert_eq!(
+        read("packaging/polkit/50-thinkutils.pkla"),
+        polkit_pkla(),
+        "packaging/polkit/50-thinkutils.pkla is stale - regenerate with:\n  \
+         cargo run --example gen-packaging -- ../packaging"
+    );
+}
+
+/// polkit 0.105, still Ubuntu 22.04's, ignores the rule; without the
+/// .pkla every fan change there asks for a password.
+#[test]
+fn the_debian_package_ships_the_pkla() {
+    let rules = read("packaging/ppa/debian/rules");
+    assert!(
+        directives(&rules).any(|l| l.contains("packaging/polkit/50-thinkutils.pkla")),
+        "debian/rules does not install the .pkla"
+    );
+    assert!(
+        rules.contains(POLKIT_PKLA_PATH.trim_start_matches('/')),
+        "debian/rules must install the .pkla to {}",
+        POLKIT_PKLA_PATH
+    );
+}
+
+#[test]
+fn committed_polkit_policies_match_source() {
+    for (dir, helper) in PACKAGED_POLICIES {
+        let rel = format!("packaging/polkit/{}/com.thinkutils.helper.policy", dir);
+        assert_eq!(
+            read(&rel),
+            polkit_policy(helper),
+            "{} is stale - regenerate with:\n  \
+             cargo run --example gen-packaging -- ../packaging",
+            rel
+        );
+    }
+}
+
+/// The rule grants by action ID, so a package shipping the rule without the
+/// actions grants nothing. Each must ship the policy generated for the path it
+/// installs the helper at; the other one names a helper that is not there.
+#[test]
+fn each_package_ships_the_policy_for_its_helper_path() {
+    let actions_dir = POLKIT_POLICY_PATH.trim_start_matches("/usr/share/");
+    for (f, dir) in [
+        ("packaging/aur/PKGBUILD", "lib"),
+        ("packaging/copr/thinkutils.spec", "libexec"),
+        ("packaging/ppa/debian/rules", "lib"),
+    ] {
+        let content = read(f);
+        assert!(
+            content.contains(&format!(
+                "packaging/polkit/{}/com.thinkutils.helper.policy",
+                dir
+            )),
+            "{} must install the {} policy",
+            f,
+            dir
+        );
+        assert!(
+            content.contains(actions_dir),
+            "{} must install the policy to {}",
+            f,
+            POLKIT_POLICY_PATH
+        );
+    }
+}
+
+#[test]
+fn committed_dbus_service_files_match_source() {
+    assert_eq!(
+        read("packaging/dbus/org.thinkutils.Hardware1.conf"),
+        BUS_POLICY,
+        "packaging/dbus/org.thinkutils.Hardware1.conf is stale - regenerate with:\n  \
+         cargo run --example gen-packaging -- ../packaging"
+    );
+    for (dir, helper) in PACKAGED_POLICIES {
+        for (name, expected) in [
+            ("org.thinkutils.Hardware1.service", activation_file(helper)),
+            (SYSTEMD_SERVICE, systemd_unit(helper)),
+        ] {
+            let rel = format!("packaging/dbus/{}/{}", dir, name);
+            assert_eq!(
+                read(&rel),
+                expected,
+                "{} is stale - regenerate with:\n  \
+                 cargo run --example gen-packaging -- ../packaging",
+                rel
+            );
+        }
+    }
+}
+
+/// Bus activation needs all three files: the bus policy lets root own the
+/// name, the activation file tells the bus what to start, and the unit is what
+/// dbus-broker actually starts. Each names the helper the package installs.
+#[test]
+fn each_package_ships_the_hardware_service() {
+    for (f, dir) in [
+        ("packaging/aur/PKGBUILD", "lib"),
+        ("packaging/copr/thinkutils.spec", "libexec"),
+        ("packaging/ppa/debian/rules", "lib"),
+    ] {
+        let content = read(f);
+        for source in [
+            "packaging/dbus/org.thinkutils.Hardware1.conf".to_string(),
+            format!("packaging/dbus/{}/org.thinkutils.Hardware1.service", dir),
+            format!("packaging/dbus/{}/{}", dir, SYSTEMD_SERVICE),
+        ] {
+            assert!(
+                content.contains(&source),
+                "{} does not install {}",
+                f,
+                source
+            );
+        }
+        assert!(
+            content.contains("dbus-1/system.d/org.thinkutils.Hardware1.conf")
+                && content.contains("dbus-1/system-services/org.thinkutils.Hardware1.service"),
+            "{} must install the bus files under /usr/share/dbus-1",
+            f
+        );
+    }
+}
+
+#[test]
+fn committed_systemd_unit_matches_source() {
     assert_eq!(
-        read("packaging/helper/thinkutils-fan-control"),
-        HELPER_SCRIPT,
-        "packaging/helper/thinkutils-fan-control is stale - regenerate with:\n  \
+        read("packaging/systemd/thinkutils-daemon.service"),
+        SYSTEMD_UNIT,
+        "packaging/systemd/thinkutils-daemon.service is stale - regenerate with:\n  \
          cargo run --example gen-packaging -- ../packaging"
     );
 }
 
+#[test]
+fn committed_sysusers_file_matches_source() {
+    assert_eq!(
+        read("packaging/systemd/thinkutils.sysusers"),
+        SYSUSERS,
+        "packaging/systemd/thinkutils.sysusers is stale - regenerate with:\n  \
+         cargo run --example gen-packaging -- ../packaging"
+    );
+}
+
+/// Without the group the daemon's socket is root-only, and the app cannot
+/// reach it at all.
+#[test]
+fn each_package_creates_the_socket_group() {
+    for f in [
+        "packaging/aur/PKGBUILD",
+        "packaging/copr/thinkutils.spec",
+        "packaging/ppa/debian/rules",
+    ] {
+        let content = read(f);
+        assert!(
+            content.contains("usr/lib/sysusers.d/thinkutils.conf")
+                || content.contains("%{_sysusersdir}/thinkutils.conf"),
+            "{} must install the sysusers.d file",
+            f
+        );
+    }
+    assert!(read("packaging/ppa/debian/thinkutils.postinst").contains("systemd-sysusers"));
+}
+
+/// Every package ships the daemon and its unit, and none enables it: the
+/// daemon takes the fan away from the firmware, which is the user's call.
+#[test]
+fn each_package_ships_the_daemon_disabled() {
+    for f in [
+        "packaging/aur/PKGBUILD",
+        "packaging/copr/thinkutils.spec",
+        "packaging/ppa/debian/rules",
+    ] {
+        let content = read(f);
+        assert!(
+            content.contains("target/release/thinkutils-daemon"),
+            "{} does not install the daemon",
+            f
+        );
+        assert!(
+            content.contains("usr/lib/systemd/system/thinkutils-daemon.service")
+                || content.contains("%{_unitdir}/thinkutils-daemon.service"),
+            "{} must install the unit under /usr/lib/systemd/system",
+            f
+        );
+        for line in directives(&content) {
+            assert!(
+                !line.contains("systemctl enable") && !line.contains("%systemd_post"),
+                "{} enables the daemon on install:\n  {}",
+                f,
+                line
+            );
+        }
+    }
+
+    let rules = read("packaging/ppa/debian/rules");
+    assert!(
+        rules.contains("dh_installsystemd --no-enable --no-start"),
+        "dh_installsystemd enables every unit it finds unless told not to"
+    );
+}
+
 /// Each package format has its own convention, and each must install to a path
 /// the app actually searches. A package installing to an unsearched path
 /// produces a working install whose fan control silently never works.
+///
+/// The helper is the compiled binary, straight out of the cargo build; the bash
+/// script it replaced must not come back through a stale recipe.
 #[test]
 fn each_package_installs_the_helper_where_the_app_looks() {
+    for f in [
+        "packaging/aur/PKGBUILD",
+        "packaging/copr/thinkutils.spec",
+        "packaging/ppa/debian/rules",
+    ] {
+        let content = read(f);
+        assert!(
+            content.contains("target/release/thinkutils-helper"),
+            "{} does not install the compiled helper",
+            f
+        );
+        for line in directives(&content) {
+            assert!(
+                !line.contains("thinkutils-fan-control"),
+                "{} still installs the retired script helper:\n  {}",
+                f,
+                line
+            );
+        }
+    }
+
     let deb_arch_path = HELPER_CANDIDATES[0]; // /usr/lib/thinkutils/...
     let fedora_path = HELPER_CANDIDATES[1]; // /usr/libexec/thinkutils/...
 
@@ -77,7 +301,7 @@ fn each_package_installs_the_helper_where_the_app_looks() {
 
     let spec = read("packaging/copr/thinkutils.spec");
     assert!(
-        spec.contains("%{_libexecdir}/thinkutils/thinkutils-fan-control"),
+        spec.contains("%{_libexecdir}/thinkutils/thinkutils-helper"),
         "spec must install the helper to {} via %{{_libexecdir}}",
         fedora_path
     );
@@ -0,0 +1,267 @@
+//! Drives the write commands end to end against a simulated ThinkPad.
+//!
+//! `hardware_profiles.rs` proves the app reads captured machines correctly; this
+//! proves it changes them correctly. Each test copies a profile into a scratch
+//! directory, points the hardware root at it with writes enabled, and calls the
+//! same commands the UI calls. The copy then shows what the kernel would have
+//! been asked to do, and the model behind `hardware_root::write` refuses what
+//! the kernel would refuse -- an unknown fan level, a threshold pair written in
+//! the wrong order.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use thinkutils_lib::error::ErrorCode;
+use thinkutils_lib::hardware_root::{self, HARDWARE_ROOT_ENV, HARDWARE_WRITABLE_ENV};
+use thinkutils_lib::{battery, fan_control, performance};
+
+const P1: &str = "thinkpad-p1-gen-4i";
+
+/// Both env vars are process-global and the tests in this file run in parallel.
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+/// A writable copy of a profile, active for as long as it is held.
+struct Scratch {
+    root: PathBuf,
+    _guard: std::sync::MutexGuard<'static, ()>,
+}
+
+impl Scratch {
+    fn of(profile: &str) -> Self {
+        let guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let root = std::env::temp_dir().join(format!(
+            "thinkutils_sim_{}_{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        let source = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/hardware")
+            .join(profile);
+        hardware_root::scratch_copy(&source, &root).expect("copy profile");
+
+        std::env::set_var(HARDWARE_ROOT_ENV, &root);
+        std::env::set_var(HARDWARE_WRITABLE_ENV, "1");
+        Scratch {
+            root,
+            _guard: guard,
+        }
+    }
+
+    fn read(&self, path: &str) -> String {
+        std::fs::read_to_string(self.root.join(path.trim_start_matches('/')))
+            .unwrap_or_else(|e| panic!("{} missing from the scratch profile: {}", path, e))
+    }
+
+    fn fan_level(&self) -> String {
+        self.read("/proc/acpi/ibm/fan")
+            .lines()
+            .find_map(|l| l.strip_prefix("level:"))
+            .expect("level line")
+            .trim()
+            .to_string()
+    }
+
+    fn thresholds(&self) -> (u8, u8) {
+        let t = battery::get_battery_thresholds().data.unwrap();
+        (t.start, t.stop)
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        std::env::remove_var(HARDWARE_ROOT_ENV);
+        std::env::remove_var(HARDWARE_WRITABLE_ENV);
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+#[tokio::test]
+async fn setting_the_fan_level_updates_procfs() {
+    let sim = Scratch::of(P1);
+    assert_eq!(sim.fan_level(), "auto");
+
+    let r = fan_control::set_fan_speed("5".to_string(), None, None).await;
+    assert!(r.success, "{:?}", r.error);
+    assert_eq!(sim.fan_level(), "5");
+
+    let r = fan_control::set_fan_speed("full-speed".to_string(), None, None).await;
+    assert!(r.success, "{:?}", r.error);
+    assert_eq!(sim.fan_level(), "full-speed");
+
+    // The UI reads back through the same root, so it sees its own change.
+    let data = fan_control::get_sensor_data().data.unwrap();
+    assert_eq!(data.level.as_deref(), Some("full-speed"));
+}
+
+#[tokio::test]
+async fn the_fan_keeps_its_level_when_a_command_is_refused() {
+    let sim = Scratch::of(P1);
+
+    // The P1 does not advertise a fan selector.
+    let r = fan_control::set_fan_speed("3".to_string(), Some(2), None).await;
+    assert_eq!(r.code, Some(ErrorCode::HardwareAbsent));
+    let r = fan_control::set_fan_speed("9".to_string(), None, None).await;
+    assert_eq!(r.code, Some(ErrorCode::Validation));
+    assert_eq!(sim.fan_level(), "auto");
+}
+
+/// Disengaged only runs for as long as it was asked to, then the fan is
+/// handed back without anyone touching it.
+#[tokio::test]
+async fn disengaged_mode_returns_to_auto_when_its_time_is_up() {
+    let sim = Scratch::of(P1);
+
+    let r = fan_control::set_fan_speed("disengaged".to_string(), None, None).await;
+    assert!(
+        !r.success,
+        "disengaged without a time limit must be refused"
+    );
+    assert_eq!(sim.fan_level(), "auto");
+
+    let r = fan_control::set_fan_speed("disengaged".to_string(), None, Some(1)).await;
+    assert!(r.success, "{:?}", r.error);
+    assert_eq!(sim.fan_level(), "disengaged");
+
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while sim.fan_level() != "auto" {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "fan is still disengaged past its time limit"
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+}
+
+/// Choosing something else stands the timer down: it must not later put the
+/// fan on auto over the user's new choice.
+#[tokio::test]
+async fn a_new_choice_overrides_the_disengaged_time_limit() {
+    let sim = Scratch::of(P1);
+
+    let r = fan_control::set_fan_speed("disengaged".to_string(), None, Some(1)).await;
+    assert!(r.success, "{:?}", r.error);
+    let r = fan_control::set_fan_speed("3".to_string(), None, None).await;
+    assert!(r.success, "{:?}", r.error);
+
+    tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+    assert_eq!(sim.fan_level(), "3");
+}
+
+#[tokio::test]
+async fn thresholds_move_in_both_directions() {
+    let sim = Scratch::of(P1);
+    assert_eq!(sim.thresholds(), (75, 80));
+
+    // Lowering both past the current start only works start-first.
+    let r = battery::set_battery_thresholds(40, 60, None).await;
+    assert!(r.success, "{:?}", r.error);
+    assert_eq!(sim.thresholds(), (40, 60));
+
+    // Raising both past the current stop only works stop-first.
+    let r = battery::set_battery_thresholds(85, 95, None).await;
+    assert!(r.success, "{:?}", r.error);
+    assert_eq!(sim.thresholds(), (85, 95));
+}
+
+/// The model is what gives the ordering tests teeth: writing a start at or
+/// above the current stop must fail, as it does on the firmware.
+#[test]
+fn the_model_rejects_an_out_of_order_threshold_write() {
+    let sim = Scratch::of(P1);
+    let (start_path, _) = battery::threshold_paths().expect("fixture has thresholds");
+
+    let err = hardware_root::write(&start_path, "90").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert_eq!(sim.thresholds(), (75, 80));
+}
+
+#[tokio::test]
+async fn the_governor_reaches_every_online_cpu() {
+    let sim = Scratch::of(P1);
+
+    let r = performance::set_cpu_governor("performance".to_string()).await;
+    assert!(r.success, "{:?}", r.error);
+    assert_eq!(
+        performance::get_cpu_info().data.unwrap().governor,
+        "performance"
+    );
+    assert_eq!(
+        sim.read("/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor")
+            .trim(),
+        "performance"
+    );
+}
+
+#[tokio::test]
+async fn a_governor_the_profile_does_not_list_is_refused() {
+    let sim = Scratch::of(P1);
+
+    let r = performance::set_cpu_governor("schedutil".to_string()).await;
+    assert!(!r.success);
+    assert_eq!(r.code, Some(ErrorCode::Validation));
+    assert_eq!(
+        sim.read("/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor")
+            .trim(),
+        "powersave"
+    );
+}
+
+#[tokio::test]
+async fn turbo_can_be_switched_off_and_on() {
+    let _sim = Scratch::of(P1);
+
+    let r = performance::set_turbo_boost(false).await;
+    assert!(r.success, "{:?}", r.error);
+    assert_eq!(performance::get_turbo_boost_status().data, Some(false));
+
+    let r = performance::set_turbo_boost(true).await;
+    assert!(r.success, "{:?}", r.error);
+    assert_eq!(performance::get_turbo_boost_status().data, Some(true));
+}
+
+/// The helper binary the polkit rule grants, run the way pkexec runs it. It
+/// inherits the simulated root, so what it writes lands in the scratch copy.
+fn run_helper(args: &[&str]) -> std::process::Output {
+    std::process::Command::new(env!("CARGO_BIN_EXE_thinkutils-helper"))
+        .args(args)
+        .output()
+        .expect("run thinkutils-helper")
+}
+
+#[test]
+fn the_helper_binary_applies_only_what_it_can_validate() {
+    let sim = Scratch::of(P1);
+
+    let out = run_helper(&["battery-thresholds", "60", "70"]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert_eq!(sim.thresholds(), (60, 70));
+
+    let out = run_helper(&["fan", "level 4"]);
+    assert!(out.status.success());
+    assert_eq!(sim.fan_level(), "4");
+
+    // Refused before anything is written: an unknown operation, a command
+    // the whitelist does not hold, a governor the profile does not list.
+    for args in [
+        &["chmod", "666", "/etc/shadow"][..],
+        &["fan", "level 4; rm -rf /"],
+        &["cpu", "governor", "schedutil"],
+        &["battery-thresholds", "80", "70"],
+    ] {
+        let out = run_helper(args);
+        assert_eq!(out.status.code(), Some(2), "{:?} was not refused", args);
+    }
+    assert_eq!(sim.fan_level(), "4");
+    assert_eq!(sim.thresholds(), (60, 70));
+    assert_eq!(
+        sim.read("/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor")
+            .trim(),
+        "powersave"
+    );
+}
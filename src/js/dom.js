@@ -12,6 +12,9 @@ export function initializeElements() {
   elements.slider = document.getElementById('fan-slider');
   elements.sliderValue = document.getElementById('slider-value');
   elements.sliderSection = document.getElementById('slider-section');
+  elements.disengagedSection = document.getElementById('disengaged-section');
+  elements.disengagedLimit = document.getElementById('disengaged-limit');
+  elements.btnDisengage = document.getElementById('btn-disengage');
   elements.btnAuto = document.getElementById('btn-auto');
   elements.btnManual = document.getElementById('btn-manual');
   elements.btnCurve = document.getElementById('btn-curve');
@@ -51,4 +54,14 @@ export function initializeElements() {
   elements.thresholdStartValue = document.getElementById('threshold-start-value');
   elements.thresholdStopValue = document.getElementById('threshold-stop-value');
   elements.btnApplyThresholds = document.getElementById('btn-apply-thresholds');
+  elements.chargeBehaviourCard = document.getElementById('charge-behaviour-card');
+  elements.chargeBehaviourCurrent = document.getElementById('charge-behaviour-current');
+  elements.chargeBehaviourMode = document.getElementById('charge-behaviour-mode');
+  elements.chargeBehaviourLimit = document.getElementById('charge-behaviour-limit');
+  elements.btnApplyBehaviour = document.getElementById('btn-apply-behaviour');
+  elements.batteryCalibrationCard = document.getElementById('battery-calibration-card');
+  elements.batteryCalibrationStatus = document.getElementById('battery-calibration-status');
+  elements.btnStartBatteryCalibration = document.getElementById('btn-start-battery-calibration');
+  elements.btnCancelBatteryCalibration = document.getElementById('btn-cancel-battery-calibration');
+  elements.batteryHealthHistory = document.getElementById('battery-health-history');
 }
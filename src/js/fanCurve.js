@@ -2,13 +2,14 @@
 const { invoke } = window.__TAURI__.core;
 import { showStatus } from './utils.js';
 
-// Default fan curve points: [temperature, fan_level]
+// Default fan curve points: [temperature, fan_level]. fall_temp is where the
+// point lets go again while cooling; see next_fan_level in fan_curve.rs.
 const DEFAULT_CURVE = [
-  { temp: 40, level: 0 },
-  { temp: 50, level: 1 },
-  { temp: 60, level: 3 },
-  { temp: 70, level: 5 },
-  { temp: 80, level: 7 }
+  { temp: 40, level: 0, fall_temp: 36 },
+  { temp: 50, level: 1, fall_temp: 46 },
+  { temp: 60, level: 3, fall_temp: 56 },
+  { temp: 70, level: 5, fall_temp: 66 },
+  { temp: 80, level: 7, fall_temp: 76 }
 ];
 
 let curvePoints = [...DEFAULT_CURVE];
@@ -44,12 +45,71 @@ export async function initFanCurve() {
 
     document.getElementById('btn-reset-curve')?.addEventListener('click', resetCurve);
     document.getElementById('btn-save-curve')?.addEventListener('click', saveCurve);
+    document.getElementById('btn-calibrate-fan')?.addEventListener('click', calibrateFan);
+    document
+      .getElementById('btn-cancel-calibration')
+      ?.addEventListener('click', () => invoke('cancel_fan_calibration'));
 
     canvas.dataset.initialized = 'true';
   }
 
   // Always draw the curve when initializing
   drawCurve();
+
+  try {
+    const calibration = await invoke('get_fan_calibration');
+    if (calibration) {
+      renderCalibration(calibration.points);
+    }
+  } catch (error) {
+    console.error('[Fan Curve] Failed to load calibration:', error);
+  }
+}
+
+// Steps the fan through every level and stores the measured RPM for this
+// machine. The backend hands the fan back to auto however it ends.
+async function calibrateFan() {
+  const calibrateBtn = document.getElementById('btn-calibrate-fan');
+  const cancelBtn = document.getElementById('btn-cancel-calibration');
+  const status = document.getElementById('calibration-status');
+  const measured = [];
+
+  const { listen } = window.__TAURI__.event;
+  const unlisten = await listen('fan-calibration-progress', (event) => {
+    const { level, rpm } = event.payload;
+    measured.push(event.payload);
+    status.textContent = `Measuring... level ${level} runs at ${rpm} RPM`;
+    renderCalibration(measured);
+  });
+
+  calibrateBtn.disabled = true;
+  cancelBtn.style.display = '';
+  status.textContent = 'Measuring...';
+  try {
+    const calibration = await invoke('start_fan_calibration');
+    renderCalibration(calibration.points);
+    status.textContent = `Calibrated ${calibration.machine}. Curve points can now target RPM.`;
+    showStatus('✓ Fan calibrated', 'success');
+  } catch (error) {
+    status.textContent = `Calibration stopped: ${error}`;
+    showStatus(`Calibration failed: ${error}`, 'error');
+  } finally {
+    unlisten();
+    calibrateBtn.disabled = false;
+    cancelBtn.style.display = 'none';
+  }
+}
+
+function renderCalibration(points) {
+  const table = document.getElementById('calibration-table');
+  if (!table) {
+    return;
+  }
+  table.querySelector('tbody').innerHTML = points
+    // `~` marks a level whose speed was still changing when it was measured.
+    .map((p) => `<tr><td>${p.level}</td><td>${p.settled ? '' : '~'}${p.rpm}</td></tr>`)
+    .join('');
+  table.style.display = points.length > 0 ? '' : 'none';
 }
 
 export async function startCurveMode() {
@@ -64,7 +124,7 @@ export async function startCurveMode() {
     const { listen } = window.__TAURI__.event;
     if (!window.fanCurveUnlisten) {
       window.fanCurveUnlisten = await listen('fan-curve-update', (event) => {
-        const { temperature, fan_level, controlling } = event.payload;
+        const { temperature, fan_level, controlling, override } = event.payload;
         currentTemp = temperature;
 
         // Update UI. `controlling` is false when the backend computed this level
@@ -72,8 +132,13 @@ export async function startCurveMode() {
         // did apply — the error toast fires once and is easy to miss.
         document.getElementById('curve-current-temp').textContent = `${temperature}°C`;
         const speedEl = document.getElementById('curve-target-speed');
-        speedEl.textContent =
-          controlling === false ? `Level ${fan_level} (not applied)` : `Level ${fan_level}`;
+        if (override) {
+          speedEl.textContent =
+            override === 'disengaged' ? 'Disengaged (override)' : 'Max (override)';
+        } else {
+          speedEl.textContent =
+            controlling === false ? `Level ${fan_level} (not applied)` : `Level ${fan_level}`;
+        }
         speedEl.classList.toggle('curve-not-applied', controlling === false);
 
         drawCurve();
@@ -89,6 +154,50 @@ export async function startCurveMode() {
       });
     }
 
+    // The backend switches curves on AC plug/unplug. Reload the points so the
+    // editor shows, and later saves, the curve that is actually active.
+    if (!window.fanCurveSwitchUnlisten) {
+      window.fanCurveSwitchUnlisten = await listen('fan-curve-switched', async (event) => {
+        const { curve } = event.payload;
+        await loadCurveFromBackend();
+        drawCurve();
+        showStatus(`Fan curve switched to ${curve}`, 'info');
+      });
+    }
+
+    // Past the critical temperature the backend runs the fan flat out,
+    // whatever the curve says, until the temperature falls below `release`.
+    if (!window.fanCurveOverrideUnlisten) {
+      window.fanCurveOverrideUnlisten = await listen('fan-thermal-override', (event) => {
+        const { active, temperature, critical, release, action } = event.payload;
+        if (!active) {
+          showStatus(`Temperature back under ${release}°C - fan curve resumed`, 'info');
+        } else if (action) {
+          console.warn(`[Fan Curve] Thermal override at ${temperature}°C (limit ${critical}°C)`);
+          showStatus(
+            `${temperature}°C reached the ${critical}°C limit - fan at maximum`,
+            'error'
+          );
+        } else {
+          showStatus(
+            `${temperature}°C reached the ${critical}°C limit - fan returned to auto`,
+            'error'
+          );
+        }
+      });
+    }
+
+    // The fan read 0 RPM at a level that should turn it. The backend has
+    // already acted; the curve stays paused until it is toggled off and on.
+    if (!window.fanCurveStallUnlisten) {
+      window.fanCurveStallUnlisten = await listen('fan-stall', (event) => {
+        const { fan, level, ticks, action } = event.payload;
+        const fallback = action === 'auto' ? 'auto' : 'full speed';
+        console.error(`[Fan Curve] Fan ${fan} stalled at level ${level} for ${ticks} ticks`);
+        showStatus(`Fan ${fan} not spinning at level ${level} - switched to ${fallback}`, 'error');
+      });
+    }
+
     console.log('[Fan Curve] Started - backend will handle temperature monitoring');
   } catch (error) {
     console.error('[Fan Curve] Failed to start:', error);
@@ -109,6 +218,18 @@ export async function stopCurveMode() {
       window.fanCurveErrorUnlisten();
       window.fanCurveErrorUnlisten = null;
     }
+    if (window.fanCurveSwitchUnlisten) {
+      window.fanCurveSwitchUnlisten();
+      window.fanCurveSwitchUnlisten = null;
+    }
+    if (window.fanCurveStallUnlisten) {
+      window.fanCurveStallUnlisten();
+      window.fanCurveStallUnlisten = null;
+    }
+    if (window.fanCurveOverrideUnlisten) {
+      window.fanCurveOverrideUnlisten();
+      window.fanCurveOverrideUnlisten = null;
+    }
 
     console.log('[Fan Curve] Stopped');
   } catch (error) {
@@ -321,7 +442,11 @@ function handleMouseMove(e) {
     newTemp = Math.max(TEMP_MIN, Math.min(TEMP_MAX, newTemp));
     newLevel = Math.max(LEVEL_MIN, Math.min(LEVEL_MAX, newLevel));
 
-    curvePoints[draggedPointIndex] = { temp: newTemp, level: newLevel };
+    // Keep the point's hysteresis band the same width as it moves. A dragged
+    // point is placed by level, so any RPM target it had is dropped.
+    const old = curvePoints[draggedPointIndex];
+    const band = old.temp - (old.fall_temp ?? old.temp);
+    curvePoints[draggedPointIndex] = { temp: newTemp, level: newLevel, fall_temp: newTemp - band };
     drawCurve();
   } else {
     // Check if hovering over a point
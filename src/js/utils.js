@@ -67,3 +67,16 @@ export function escapeHtml(text) {
   div.textContent = text ?? '';
   return div.innerHTML;
 }
+
+/**
+ * Format a hwmon sensor reading (`SensorReading` on the Rust side) for display.
+ * A null value means the input exists but could not be read.
+ */
+export function formatReading(reading) {
+  if (reading.value === null || reading.value === undefined) {
+    return '—';
+  }
+  return reading.unit === 'rpm'
+    ? `${Math.round(reading.value)} RPM`
+    : `${reading.value.toFixed(1)}°C`;
+}
@@ -17,13 +17,33 @@ export function setupBatteryHandlers() {
   if (elements.btnApplyThresholds) {
     elements.btnApplyThresholds.addEventListener('click', applyBatteryThresholds);
   }
+  if (elements.btnApplyBehaviour) {
+    elements.btnApplyBehaviour.addEventListener('click', applyChargeBehaviour);
+    // auto is what a time limit returns to, so it takes none.
+    elements.chargeBehaviourMode.addEventListener('change', () => {
+      elements.chargeBehaviourLimit.disabled = elements.chargeBehaviourMode.value === 'auto';
+    });
+  }
+  if (elements.btnStartBatteryCalibration) {
+    elements.btnStartBatteryCalibration.addEventListener('click', startBatteryCalibration);
+    elements.btnCancelBatteryCalibration.addEventListener('click', () =>
+      invoke('cancel_battery_calibration')
+    );
+    // A calibration outlives any one visit to this view, so this listens for
+    // as long as the app runs.
+    window.__TAURI__.event.listen('battery-calibration-progress', (event) =>
+      renderCalibration(event.payload)
+    );
+  }
 }
 
 export async function loadBatteryInfo() {
   try {
     const response = await invoke('get_battery_info');
     if (response.success && response.data) {
-      displayBatteries(response.data);
+      const summary = await invoke('get_battery_summary');
+      displayBatteries(response.data, summary.success ? summary.data : null);
+      displayPower(summary.success ? summary.data : null);
     }
 
     const thresholdResponse = await invoke('get_battery_thresholds');
@@ -33,16 +53,80 @@ export async function loadBatteryInfo() {
       elements.thresholdStartValue.textContent = thresholdResponse.data.start + '%';
       elements.thresholdStopValue.textContent = thresholdResponse.data.stop + '%';
     }
+
+    await loadChargeBehaviour();
+    await loadBatteryCalibration();
+    await loadHealthHistory();
   } catch (error) {
     console.error('[Battery] Load failed:', error);
   }
 }
 
-function displayBatteries(batteries) {
+/** "2h 15m" from seconds, or null when there is no estimate. */
+function formatDuration(seconds) {
+  if (seconds === null || seconds === undefined) {
+    return null;
+  }
+  const minutes = Math.round(seconds / 60);
+  const hours = Math.floor(minutes / 60);
+  return hours > 0 ? `${hours}h ${minutes % 60}m` : `${minutes}m`;
+}
+
+function detail(label, value) {
+  return `
+        <div class="battery-detail">
+          <span class="battery-detail-label">${label}</span>
+          <span class="battery-detail-value">${value}</span>
+        </div>`;
+}
+
+/** The time estimate for the machine as a whole, as a detail row. */
+function timeDetail(summary) {
+  if (!summary) {
+    return '';
+  }
+  const empty = formatDuration(summary.time_to_empty);
+  if (empty) {
+    return detail('Time left', empty);
+  }
+  const full = formatDuration(summary.time_to_full);
+  return full ? detail('Time to full', full) : '';
+}
+
+function summaryCard(summary) {
+  const energy = `${summary.energy_now.toFixed(1)} / ${summary.energy_full.toFixed(1)}Wh`;
+  const card = document.createElement('div');
+  card.className = 'battery-card';
+  card.innerHTML = `
+      <div class="battery-header">
+        <span class="battery-name">All batteries</span>
+        <span class="battery-status">${escapeHtml(summary.status)}</span>
+      </div>
+      <div class="battery-capacity">${summary.capacity}%</div>
+      <div class="battery-details">
+        ${detail('Health', `${summary.health}%`)}
+        ${detail('Energy', energy)}
+        ${detail('Power', `${summary.power.toFixed(1)}W`)}
+        ${timeDetail(summary)}
+      </div>
+    `;
+  return card;
+}
+
+function displayBatteries(batteries, summary) {
   const container = document.getElementById('battery-cards');
   container.innerHTML = '';
 
+  // With one battery its own card is the whole story; with more, the
+  // combined figures come first.
+  if (summary && batteries.length > 1) {
+    container.appendChild(summaryCard(summary));
+  }
+
   batteries.forEach((battery) => {
+    const thresholds = battery.thresholds
+      ? detail('Thresholds', `${battery.thresholds.start}%-${battery.thresholds.stop}%`)
+      : '';
     const card = document.createElement('div');
     card.className = 'battery-card';
     card.innerHTML = `
@@ -67,13 +151,26 @@ function displayBatteries(batteries) {
         <div class="battery-detail">
           <span class="battery-detail-label">Technology</span>
           <span class="battery-detail-value">${escapeHtml(battery.technology)}</span>
-        </div>
+        </div>${thresholds}${batteries.length === 1 ? timeDetail(summary) : ''}
       </div>
     `;
     container.appendChild(card);
   });
 }
 
+/** The Power Consumption card, from the smoothed rate. */
+function displayPower(summary) {
+  const draw = document.getElementById('power-draw');
+  const runtime = document.getElementById('runtime-estimate');
+  if (!draw || !runtime || !summary) {
+    return;
+  }
+  draw.textContent = `${summary.rate.toFixed(1)} W`;
+  const empty = formatDuration(summary.time_to_empty);
+  const full = formatDuration(summary.time_to_full);
+  runtime.textContent = empty || (full ? `Full in ${full}` : '-- h');
+}
+
 async function applyBatteryThresholds() {
   const start = parseInt(elements.thresholdStart.value);
   const stop = parseInt(elements.thresholdStop.value);
@@ -91,3 +188,162 @@ async function applyBatteryThresholds() {
     showStatus(`Error: ${error}`, 'error');
   }
 }
+
+const BEHAVIOUR_LABELS = {
+  auto: 'Auto',
+  'inhibit-charge': 'Inhibit charging',
+  'force-discharge': 'Force discharge',
+};
+
+/** Show the charge behaviour card, if any battery has the control. */
+async function loadChargeBehaviour() {
+  const response = await invoke('get_charge_behaviour');
+  if (!response.success || !response.data) {
+    elements.chargeBehaviourCard.style.display = 'none';
+    return;
+  }
+  const states = response.data;
+  elements.chargeBehaviourCard.style.display = 'block';
+
+  elements.chargeBehaviourCurrent.textContent = states
+    .map((state) => {
+      const current = BEHAVIOUR_LABELS[state.current] || 'Unknown';
+      const reverts = formatDuration(state.reverts_in);
+      return `${state.battery}: ${current}${reverts ? ` (auto in ${reverts})` : ''}`;
+    })
+    .join(' · ');
+
+  // Offer what every battery offers, since a change applies to them all.
+  const offered = Object.keys(BEHAVIOUR_LABELS).filter((mode) =>
+    states.every((state) => state.available.includes(mode))
+  );
+  const selected = elements.chargeBehaviourMode.value || states[0].current;
+  elements.chargeBehaviourMode.innerHTML = offered
+    .map((mode) => `<option value="${mode}">${BEHAVIOUR_LABELS[mode]}</option>`)
+    .join('');
+  if (offered.includes(selected)) {
+    elements.chargeBehaviourMode.value = selected;
+  }
+  elements.chargeBehaviourLimit.disabled = elements.chargeBehaviourMode.value === 'auto';
+}
+
+async function applyChargeBehaviour() {
+  const behaviour = elements.chargeBehaviourMode.value;
+  const limit = elements.chargeBehaviourLimit.value;
+  const limitSecs = behaviour !== 'auto' && limit ? parseInt(limit) : null;
+
+  try {
+    showStatus('Setting charge behaviour...', 'info');
+    const response = await invoke('set_charge_behaviour', { behaviour, limitSecs });
+    if (response.success) {
+      showStatus(`✓ ${response.data}`, 'success');
+      await loadChargeBehaviour();
+    } else {
+      showStatus(`Error: ${response.error}`, 'error');
+    }
+  } catch (error) {
+    showStatus(`Error: ${error}`, 'error');
+  }
+}
+
+const CALIBRATION_PHASES = {
+  discharging: 'Discharging',
+  charging: 'Charging to full',
+  complete: 'Complete',
+  cancelled: 'Cancelled',
+  failed: 'Failed',
+};
+
+/** Show the calibration card where a battery can be forced to discharge. */
+async function loadBatteryCalibration() {
+  const behaviour = await invoke('get_charge_behaviour');
+  const capable =
+    behaviour.success &&
+    behaviour.data.some((state) => state.available.includes('force-discharge'));
+  elements.batteryCalibrationCard.style.display = capable ? 'block' : 'none';
+
+  const response = await invoke('get_battery_calibration');
+  renderCalibration(response.success ? response.data : null);
+}
+
+function renderCalibration(calibration) {
+  const running =
+    calibration !== null && ['discharging', 'charging'].includes(calibration.phase);
+  elements.btnStartBatteryCalibration.style.display = running ? 'none' : '';
+  elements.btnCancelBatteryCalibration.style.display = running ? '' : 'none';
+  if (!calibration) {
+    elements.batteryCalibrationStatus.textContent = '';
+    return;
+  }
+  const phase = CALIBRATION_PHASES[calibration.phase] || calibration.phase;
+  const target = calibration.phase === 'discharging' ? ` to ${calibration.floor}%` : '';
+  const message = calibration.message ? ` · ${calibration.message}` : '';
+  elements.batteryCalibrationStatus.textContent =
+    `${calibration.battery}: ${phase}${target}, now at ${calibration.capacity}%${message}`;
+}
+
+async function startBatteryCalibration() {
+  try {
+    showStatus('Starting battery calibration...', 'info');
+    const response = await invoke('start_battery_calibration');
+    if (response.success) {
+      renderCalibration(response.data);
+      showStatus('✓ Battery calibration started', 'success');
+    } else {
+      showStatus(`Error: ${response.error}`, 'error');
+    }
+  } catch (error) {
+    showStatus(`Error: ${error}`, 'error');
+  }
+}
+
+/** A line through the health points, scaled to fill the box. */
+function sparkline(points) {
+  if (points.length < 2) {
+    return '';
+  }
+  const times = points.map((p) => p.timestamp);
+  const healths = points.map((p) => p.health);
+  const [t0, t1] = [Math.min(...times), Math.max(...times)];
+  const [h0, h1] = [Math.min(...healths) - 1, Math.max(...healths) + 1];
+  const coords = points
+    .map((p) => {
+      const x = ((p.timestamp - t0) / (t1 - t0 || 1)) * 100;
+      const y = 40 - ((p.health - h0) / (h1 - h0)) * 40;
+      return `${x.toFixed(1)},${y.toFixed(1)}`;
+    })
+    .join(' ');
+  return `<svg class="health-sparkline" viewBox="0 0 100 40" preserveAspectRatio="none">
+      <polyline points="${coords}" fill="none" stroke="currentColor" stroke-width="1"
+        vector-effect="non-scaling-stroke" />
+    </svg>`;
+}
+
+function historyEntry(history) {
+  const latest = history.points[history.points.length - 1];
+  const since = new Date(history.points[0].timestamp * 1000).toLocaleDateString();
+  let trend = 'Not enough history for a trend yet';
+  if (history.wear_per_year !== null) {
+    trend = `Losing ${history.wear_per_year.toFixed(1)}% a year`;
+    if (history.projected_replacement !== null) {
+      const when = new Date(history.projected_replacement * 1000);
+      const verb = when < new Date() ? 'reached' : 'reaches';
+      trend += ` · ${verb} ${history.replacement_threshold}% around ${when.toLocaleDateString()}`;
+    }
+  }
+  return `
+    <div class="health-history-entry">
+      ${detail(escapeHtml(history.battery), `${latest.health.toFixed(1)}% since ${since}`)}
+      <p class="card-description">${trend}</p>
+      ${sparkline(history.points)}
+    </div>`;
+}
+
+async function loadHealthHistory() {
+  const response = await invoke('get_battery_health_history', { maxPoints: 100 });
+  const histories = response.success ? response.data : [];
+  elements.batteryHealthHistory.innerHTML =
+    histories.length > 0
+      ? histories.map(historyEntry).join('')
+      : '<p class="card-description">No samples yet.</p>';
+}
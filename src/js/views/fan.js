@@ -2,7 +2,7 @@
 const { invoke } = window.__TAURI__.core;
 import { elements } from '../dom.js';
 import { setState, getState } from '../state.js';
-import { showStatus, escapeHtml } from '../utils.js';
+import { showStatus, escapeHtml, formatReading } from '../utils.js';
 import { initFanCurve, startCurveMode, stopCurveMode } from '../fanCurve.js';
 
 export function setupFanControl() {
@@ -18,6 +18,7 @@ export function setupFanControl() {
   elements.btnManual.addEventListener('click', () => setFanMode('manual', elements.slider.value));
   elements.btnCurve.addEventListener('click', () => setFanMode('curve'));
   elements.btnFull.addEventListener('click', () => setFanMode('full'));
+  elements.btnDisengage.addEventListener('click', disengageFan);
 
   elements.btnGrantPermissions.addEventListener('click', async () => {
     await tryUpdatePermissions();
@@ -46,73 +47,80 @@ export async function updateSensorData() {
 
     if (response.success && response.data) {
       updateTemperatureDisplay(response.data.temps);
-      updateFanDisplay(response.data.fans);
+      updateFanDisplay(response.data);
     }
   } catch (error) {
     console.error('[Sensors] Update failed:', error);
   }
 }
 
+function metricRow(label, value, className = 'metric-row') {
+  const row = document.createElement('div');
+  row.className = className;
+  row.innerHTML = `
+    <span class="metric-label">${escapeHtml(label)}</span>
+    <span class="metric-value">${escapeHtml(value)}</span>
+  `;
+  return row;
+}
+
 function updateTemperatureDisplay(temps) {
   elements.tempMetrics.innerHTML = '';
 
-  if (Object.keys(temps).length === 0) {
+  if (temps.length === 0) {
     elements.tempMetrics.innerHTML =
       '<div class="metric-row"><span class="metric-label">No data</span></div>';
     return;
   }
 
-  Object.entries(temps)
-    .sort((a, b) => a[0].localeCompare(b[0]))
-    .forEach(([label, value]) => {
-      const row = document.createElement('div');
-      row.className = 'metric-row';
-      row.innerHTML = `
-      <span class="metric-label">${escapeHtml(label)}</span>
-      <span class="metric-value">${escapeHtml(value)}</span>
-    `;
-      elements.tempMetrics.appendChild(row);
-    });
+  // Already ordered by chip, then index, on the Rust side.
+  temps.forEach((reading) => {
+    // Highlight anything past the limit its own driver publishes.
+    const limit = reading.max ?? reading.crit;
+    const hot = limit !== null && reading.value !== null && reading.value >= limit;
+    elements.tempMetrics.appendChild(
+      metricRow(
+        `${reading.chip} ${reading.label}`,
+        formatReading(reading),
+        hot ? 'metric-row highlight' : 'metric-row'
+      )
+    );
+  });
 }
 
-function updateFanDisplay(fans) {
+function updateFanDisplay(data) {
   elements.fanMetrics.innerHTML = '';
 
-  if (Object.keys(fans).length === 0) {
+  if (data.level && !getState('fanControlInProgress')) {
+    updateUIFromFanLevel(data.level);
+  }
+
+  const rows = data.fans.map((reading) => [
+    `${reading.chip} ${reading.label}`,
+    formatReading(reading)
+  ]);
+  // No hwmon tachometer: fall back to thinkpad_acpi's own reading.
+  if (rows.length === 0 && data.speed !== null) {
+    rows.push(['Fan1', `${data.speed} RPM`]);
+  }
+  if (data.status) {
+    rows.push(['status', data.status]);
+  }
+  if (data.level) {
+    rows.push(['level', data.level]);
+  }
+
+  if (rows.length === 0) {
     elements.fanMetrics.innerHTML =
       '<div class="metric-row"><span class="metric-label">No data</span></div>';
     return;
   }
 
-  if (fans.level && !getState('fanControlInProgress')) {
-    updateUIFromFanLevel(fans.level);
-  }
-
-  const sortOrder = ['Fan1', 'status', 'level'];
-  Object.entries(fans)
-    .sort((a, b) => {
-      const indexA = sortOrder.indexOf(a[0]);
-      const indexB = sortOrder.indexOf(b[0]);
-      if (indexA !== -1 && indexB !== -1) {
-        return indexA - indexB;
-      }
-      if (indexA !== -1) {
-        return -1;
-      }
-      if (indexB !== -1) {
-        return 1;
-      }
-      return a[0].localeCompare(b[0]);
-    })
-    .forEach(([label, value]) => {
-      const row = document.createElement('div');
-      row.className = label === 'Fan1' ? 'metric-row highlight' : 'metric-row';
-      row.innerHTML = `
-        <span class="metric-label">${escapeHtml(label)}</span>
-        <span class="metric-value">${escapeHtml(value)}</span>
-      `;
-      elements.fanMetrics.appendChild(row);
-    });
+  rows.forEach(([label, value], i) => {
+    elements.fanMetrics.appendChild(
+      metricRow(label, value, i === 0 ? 'metric-row highlight' : 'metric-row')
+    );
+  });
 }
 
 function updateUIFromFanLevel(level) {
@@ -162,6 +170,27 @@ function updateUIFromFanLevel(level) {
     elements.sliderSection.style.display = 'block';
     elements.curveSection.style.display = 'none';
   }
+  elements.disengagedSection.style.display =
+    getState('currentFanMode') === 'full' ? 'block' : 'none';
+}
+
+// Disengaged is offered from Full Speed mode only, and always with a time
+// limit: the backend hands the fan back to auto when it runs out.
+async function disengageFan() {
+  const limitSecs = parseInt(elements.disengagedLimit.value);
+  try {
+    const response = await invoke('set_fan_speed', { speed: 'disengaged', limitSecs });
+    if (response.success) {
+      showStatus(response.data, 'success');
+    } else {
+      showStatus(`Error: ${response.error}`, 'error');
+    }
+  } catch (error) {
+    showStatus(`Error: ${error}`, 'error');
+  }
+  // procfs reads "disengaged" either way, so pressing Full Speed again must
+  // still send full-speed.
+  setState('lastFanSpeedSet', null);
 }
 
 async function setFanMode(mode, level = null) {
@@ -177,6 +206,7 @@ async function setFanMode(mode, level = null) {
   }
 
   setState('currentFanMode', mode);
+  elements.disengagedSection.style.display = mode === 'full' ? 'block' : 'none';
 
   [elements.btnAuto, elements.btnManual, elements.btnCurve, elements.btnFull].forEach((btn) => {
     btn.classList.remove('active');
@@ -279,7 +309,7 @@ async function setFanMode(mode, level = null) {
       showStatus(`Error: ${response.error}`, 'error');
       setState('lastFanSpeedSet', null);
 
-      if (response.error && response.error.includes('Permission')) {
+      if (response.code === 'permission_denied') {
         elements.permissionHelper.style.display = 'flex';
       }
     }
@@ -7,38 +7,42 @@ import {
   bindOnce,
   isControlBusy
 } from '../hardwareControls.js';
+import { formatReading } from '../utils.js';
 
 export async function updateHomeView() {
   try {
     const response = await invoke('get_sensor_data');
 
     if (response.success && response.data) {
-      const cpuTemp = Object.entries(response.data.temps).find(
-        ([key]) => key.toLowerCase().includes('cpu') || key.toLowerCase().includes('core')
-      );
+      const { temps, fans, speed, status } = response.data;
+      const cpuTemp =
+        temps.find((t) => t.chip === 'coretemp' && t.label.startsWith('Package')) ||
+        temps.find((t) => t.label.toLowerCase().includes('cpu'));
       if (cpuTemp) {
         const cpuTempEl = document.getElementById('home-cpu-temp');
         if (cpuTempEl) {
-          cpuTempEl.textContent = cpuTemp[1];
+          cpuTempEl.textContent = formatReading(cpuTemp);
         }
       }
 
-      if (response.data.fans) {
-        const fanSpeed = response.data.fans.Fan1 || response.data.fans.fan1;
-        const fanStatus = response.data.fans.status;
-
-        if (fanSpeed) {
-          const fanSpeedEl = document.getElementById('home-fan-speed');
-          if (fanSpeedEl) {
-            fanSpeedEl.textContent = fanSpeed;
-          }
+      // thinkpad_acpi's procfs reading stands in when hwmon has no tachometer.
+      let fanSpeed = null;
+      if (fans.length > 0) {
+        fanSpeed = formatReading(fans[0]);
+      } else if (speed !== null) {
+        fanSpeed = `${speed} RPM`;
+      }
+      if (fanSpeed) {
+        const fanSpeedEl = document.getElementById('home-fan-speed');
+        if (fanSpeedEl) {
+          fanSpeedEl.textContent = fanSpeed;
         }
+      }
 
-        if (fanStatus) {
-          const fanModeEl = document.getElementById('home-fan-mode');
-          if (fanModeEl) {
-            fanModeEl.textContent = fanStatus;
-          }
+      if (status) {
+        const fanModeEl = document.getElementById('home-fan-mode');
+        if (fanModeEl) {
+          fanModeEl.textContent = status;
         }
       }
     }
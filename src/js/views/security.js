@@ -153,11 +153,10 @@ export function setupSecurityHandlers() {
             await loadSecurityStatus();
           }, 2000);
         } else {
-          // Check if it's a manual install error
-          if (response.error && response.error.startsWith('MANUAL_INSTALL:')) {
+          // This distribution has no automatic install; error holds the instructions
+          if (response.code === 'tool_missing') {
             hideInstallLogs();
-            const instructions = response.error.replace('MANUAL_INSTALL:', '');
-            showManualInstallDialog(instructions);
+            showManualInstallDialog(response.error);
           } else {
             completeInstallLogs(false);
             showNotification('Installation failed: ' + response.error, 'error');